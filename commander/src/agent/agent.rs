@@ -15,6 +15,7 @@ use crate::corefutures::turnstilefuture::TurnstileFuture;
 use crate::integration::reentering::ReenteringIntegration;
 use crate::task::runconfig::RunConfig;
 use crate::task::task::KillReason;
+use crate::task::taskgroup::TaskGroup;
 use crate::task::taskhandle::TaskHandle;
 use super::blockagent::BlockAgent;
 use super::finishagent::FinishAgent;
@@ -198,6 +199,29 @@ impl Agent {
         self.finish_agent().make_tidier(inner)
     }
 
+    /// Add this task to the given group.
+    /// 
+    /// Call before submitting the task. Agents later created by this task with `new_agent()` join the group too. If
+    /// the group has been cancelled, so is this task.
+    pub fn join_group(&self, group: &TaskGroup) {
+        let key = self.run_agent().join_group(group);
+        let weak = Arc::downgrade(&self.state);
+        group.add(key,move |reason| {
+            if let Some(state) = weak.upgrade() {
+                Agent { state }.finish(reason);
+            }
+        });
+    }
+
+    /* Tell our groups we've finished. Safe to call more than once. */
+    pub(crate) fn leave_groups(&self) {
+        let reason = self.finish_agent().kill_reason();
+        let (key,groups) = self.run_agent().take_groups();
+        for group in groups {
+            group.complete(key,reason.clone());
+        }
+    }
+
     pub(crate) fn lock(&self, lock: &Lock) -> impl Future<Output=LockGuard<'static>> {
         self.run_agent().lock(lock)
     }
@@ -265,6 +289,7 @@ impl Agent {
         cdr_set_agent(Some(self));
         self.run_agent().set_tick_index(tick_index);
         if self.finish_agent().finished() {
+            self.leave_groups();
             return true;
         }
         let waker = self.block_agent().root_block().make_waker();
@@ -280,6 +305,9 @@ impl Agent {
         self.run_agent().timing(slice_start,slice_end);
         self.finish_agent().check_tidiers();
         let out = self.finish_agent().finished();
+        if out {
+            self.leave_groups();
+        }
         cdr_set_agent(None);
         out
     }
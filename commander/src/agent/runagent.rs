@@ -1,4 +1,5 @@
 use std::future::Future;
+use peregrine_toolkit::identitynumber;
 use crate::corefutures::promisefuture::PromiseFuture;
 use crate::executor::lock::{ Lock, LockGuard };
 use crate::executor::action::Action;
@@ -7,10 +8,15 @@ use crate::executor::request::Request;
 use crate::executor::taskcontainerhandle::TaskContainerHandle;
 use crate::integration::reentering::ReenteringIntegration;
 use crate::task::runconfig::RunConfig;
+use crate::task::taskgroup::TaskGroup;
 use crate::task::taskhandle::TaskHandle;
 use super::agent::Agent;
 
-/* RunAgent is the Agent mixin responsible for various odds and ends around step execution */
+/* RunAgent is the Agent mixin responsible for various odds and ends around step execution,
+ * including which TaskGroups the task is in. group_key identifies the task to its groups.
+ */
+
+identitynumber!(GROUP_KEY);
 
 pub(crate) struct RunAgent {
     tick_index: u64,
@@ -22,7 +28,9 @@ pub(crate) struct RunAgent {
     start_time: Option<f64>,
     elapsed: f64,
     run: f64,
-    stats: bool
+    stats: bool,
+    group_key: u64,
+    groups: Vec<TaskGroup>
 }
 
 impl RunAgent {
@@ -38,7 +46,9 @@ impl RunAgent {
             start_time: None,
             elapsed: 0.,
             run: 0.,
-            stats: false
+            stats: false,
+            group_key: GROUP_KEY.next(),
+            groups: vec![]
         }
     }
 
@@ -57,7 +67,20 @@ impl RunAgent {
 
     pub(super) fn new_agent(&self, name: &str, rc: Option<RunConfig>) -> Agent {
         let rc = rc.unwrap_or(self.config.clone());
-        Agent::new(&rc,&self.task_action_link.get_link(),&self.task_request_link.get_link(),&self.integration,name)
+        let agent = Agent::new(&rc,&self.task_action_link.get_link(),&self.task_request_link.get_link(),&self.integration,name);
+        for group in &self.groups {
+            agent.join_group(group);
+        }
+        agent
+    }
+
+    pub(super) fn join_group(&mut self, group: &TaskGroup) -> u64 {
+        self.groups.push(group.clone());
+        self.group_key
+    }
+
+    pub(super) fn take_groups(&mut self) -> (u64,Vec<TaskGroup>) {
+        (self.group_key,self.groups.drain(..).collect())
     }
 
     pub(super) fn submit<R,T>(&self, mut agent2: Agent, future: T) -> TaskHandle<R> where T: Future<Output=R> + 'static, R: 'static {
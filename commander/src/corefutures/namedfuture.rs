@@ -2,6 +2,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{ Context, Poll };
 use crate::agent::agent::Agent;
+use crate::agent::taskrun::{ cdr_in_agent, cdr_named_wait };
 use peregrine_toolkit::{ identitynumber, hashable };
 
 
@@ -37,6 +38,17 @@ impl NamedWait {
     }
 }
 
+/* Wait on inner as a named wait if running in a task. Used by the sync primitives so that
+ * tasks queued on them show up in summaries.
+ */
+pub(crate) async fn maybe_named_wait<R,T>(inner: T, name: String) -> R where T: Future<Output=R> + 'static {
+    if cdr_in_agent() {
+        cdr_named_wait(inner,&name).await
+    } else {
+        inner.await
+    }
+}
+
 pub(crate) struct NamedFuture<R> {
     agent: Agent,
     inner: Pin<Box<dyn Future<Output=R> + 'static>>,
@@ -40,6 +40,9 @@ impl<T> PromiseFuture<T> {
         })))
     }
 
+    /* Only the caller holds this promise: nobody can ever take the value */
+    pub(crate) fn abandoned(&self) -> bool { Arc::strong_count(&self.0) == 1 }
+
     /// Satisfy promise.
     pub fn satisfy(&self, value: T) {
         let mut state = self.0.lock().unwrap();
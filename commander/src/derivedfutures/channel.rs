@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{ Arc, Mutex };
+use crate::corefutures::namedfuture::maybe_named_wait;
+use crate::corefutures::promisefuture::PromiseFuture;
+
+/* A bounded channel. Unlike CommanderStream, senders wait when it is full, so a fast producer
+ * is held back to the pace of its consumers. A waiting sender parks its value alongside its
+ * promise and the value moves into the buffer as room appears, so values are received in the
+ * order send() was called. Once closed, waiting and later senders get their value back and
+ * receivers drain what is buffered and then get None.
+ *
+ * Waiters whose tasks have gone away are skipped so values are not handed to nobody, though
+ * a parked value from a sender which has gone away is still delivered.
+ */
+
+struct ChannelState<T> {
+    capacity: usize,
+    data: VecDeque<T>,
+    senders: VecDeque<(T,PromiseFuture<Result<(),T>>)>,
+    receivers: VecDeque<PromiseFuture<Option<T>>>,
+    closed: bool
+}
+
+impl<T> ChannelState<T> {
+    fn next_receiver(&mut self) -> Option<PromiseFuture<Option<T>>> {
+        while let Some(receiver) = self.receivers.pop_front() {
+            if !receiver.abandoned() { return Some(receiver); }
+        }
+        None
+    }
+
+    /* Buffer has room: let a parked sender in */
+    fn unpark(&mut self) -> Option<PromiseFuture<Result<(),T>>> {
+        let (value,promise) = self.senders.pop_front()?;
+        self.data.push_back(value);
+        Some(promise)
+    }
+}
+
+/// A bounded, multi-producer, multi-consumer queue using futures.
+///
+/// Senders wait while the channel is full. Tasks waiting to send or receive show the channel's name among their named
+/// waits.
+pub struct CommanderChannel<T> {
+    state: Arc<Mutex<ChannelState<T>>>,
+    name: Arc<String>
+}
+
+// Rust bug means can't derive Clone on polymorphic types
+impl<T> Clone for CommanderChannel<T> {
+    fn clone(&self) -> Self {
+        CommanderChannel { state: self.state.clone(), name: self.name.clone() }
+    }
+}
+
+impl<T: 'static> CommanderChannel<T> {
+    /// Create a channel buffering at most `capacity` values (at least one).
+    pub fn new(name: &str, capacity: usize) -> CommanderChannel<T> {
+        CommanderChannel {
+            state: Arc::new(Mutex::new(ChannelState {
+                capacity: capacity.max(1),
+                data: VecDeque::new(),
+                senders: VecDeque::new(),
+                receivers: VecDeque::new(),
+                closed: false
+            })),
+            name: Arc::new(name.to_string())
+        }
+    }
+
+    /// Send without waiting. Returns the value if the channel is full or closed.
+    pub fn try_send(&self, value: T) -> Result<(),T> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed { return Err(value); }
+        if let Some(receiver) = state.next_receiver() {
+            drop(state);
+            receiver.satisfy(Some(value));
+            return Ok(());
+        }
+        if state.data.len() >= state.capacity || !state.senders.is_empty() { return Err(value); }
+        state.data.push_back(value);
+        Ok(())
+    }
+
+    /// Send, waiting while the channel is full. Resolves to the value if the channel is closed before it is sent.
+    pub fn send(&self, value: T) -> impl Future<Output=Result<(),T>> {
+        let promise = PromiseFuture::new();
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            promise.satisfy(Err(value));
+        } else if let Some(receiver) = state.next_receiver() {
+            receiver.satisfy(Some(value));
+            promise.satisfy(Ok(()));
+        } else if state.data.len() < state.capacity && state.senders.is_empty() {
+            state.data.push_back(value);
+            promise.satisfy(Ok(()));
+        } else {
+            state.senders.push_back((value,promise.clone()));
+        }
+        drop(state);
+        maybe_named_wait(promise,self.name.to_string())
+    }
+
+    /// Receive without waiting.
+    pub fn try_recv(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        let value = state.data.pop_front()?;
+        let sender = state.unpark();
+        drop(state);
+        if let Some(sender) = sender { sender.satisfy(Ok(())); }
+        Some(value)
+    }
+
+    /// Receive, waiting while the channel is empty. Resolves to None once the channel is closed and empty.
+    pub fn recv(&self) -> impl Future<Output=Option<T>> {
+        let promise = PromiseFuture::new();
+        let mut state = self.state.lock().unwrap();
+        if let Some(value) = state.data.pop_front() {
+            let sender = state.unpark();
+            drop(state);
+            if let Some(sender) = sender { sender.satisfy(Ok(())); }
+            promise.satisfy(Some(value));
+        } else if state.closed {
+            promise.satisfy(None);
+        } else {
+            state.receivers.push_back(promise.clone());
+        }
+        maybe_named_wait(promise,self.name.to_string())
+    }
+
+    /// Close the channel. Waiting senders get their values back and waiting receivers get None.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        let senders = state.senders.drain(..).collect::<Vec<_>>();
+        let receivers = state.receivers.drain(..).collect::<Vec<_>>();
+        drop(state);
+        for (value,promise) in senders {
+            promise.satisfy(Err(value));
+        }
+        for promise in receivers {
+            promise.satisfy(None);
+        }
+    }
+
+    /// Has the channel been closed?
+    pub fn closed(&self) -> bool { self.state.lock().unwrap().closed }
+
+    /// Number of values buffered.
+    pub fn len(&self) -> usize { self.state.lock().unwrap().data.len() }
+
+    /// Is nothing buffered?
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::agent::agent::Agent;
+    use crate::executor::executor::Executor;
+    use crate::integration::testintegration::TestIntegration;
+    use crate::task::runconfig::RunConfig;
+    use super::*;
+
+    async fn producer(agent: Agent, channel: CommanderChannel<u32>) -> Vec<u64> {
+        let mut sent = vec![];
+        for i in 0..5 {
+            channel.send(i).await.ok().unwrap();
+            sent.push(agent.get_tick_index());
+        }
+        channel.close();
+        sent
+    }
+
+    async fn consumer(agent: Agent, channel: CommanderChannel<u32>) -> Vec<u32> {
+        let mut out = vec![];
+        agent.tick(3).await;
+        while let Some(value) = channel.recv().await {
+            out.push(value);
+            agent.tick(1).await;
+        }
+        out
+    }
+
+    #[test]
+    pub fn test_channel_backpressure() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let channel = CommanderChannel::new("queue",2);
+        let agent = x.new_agent(&cfg,"producer");
+        let producer = x.add(producer(agent.clone(),channel.clone()),agent);
+        let agent = x.new_agent(&cfg,"consumer");
+        let consumer = x.add(consumer(agent.clone(),channel.clone()),agent);
+        x.tick(1.);
+        assert_eq!(2,channel.len());
+        assert_eq!(vec!["queue"],producer.get_waits());
+        for _ in 0..10 {
+            x.tick(1.);
+        }
+        assert_eq!(Some(vec![0,1,2,3,4]),consumer.take_result());
+        let sent = producer.take_result().unwrap();
+        assert_eq!(sent[0],sent[1]);
+        assert!(sent[2] >= sent[1]+3);
+        assert!(channel.closed());
+    }
+
+    #[test]
+    pub fn test_channel_nowait() {
+        let channel = CommanderChannel::new("queue",1);
+        assert_eq!(None,channel.try_recv());
+        assert_eq!(Ok(()),channel.try_send(1));
+        assert_eq!(Err(2),channel.try_send(2));
+        assert_eq!(Some(1),channel.try_recv());
+        assert!(channel.is_empty());
+        assert_eq!(Ok(()),channel.try_send(3));
+        channel.close();
+        assert_eq!(Err(4),channel.try_send(4));
+        assert_eq!(Some(3),channel.try_recv());
+    }
+
+    #[test]
+    pub fn test_channel_close() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let channel = CommanderChannel::new("queue",1);
+        channel.try_send(0).ok().unwrap();
+        let agent = x.new_agent(&cfg,"sender");
+        let channel2 = channel.clone();
+        let sender = x.add(async move { channel2.send(1).await },agent);
+        x.tick(1.);
+        channel.close();
+        x.tick(1.);
+        assert_eq!(Some(Err(1)),sender.take_result());
+        let agent = x.new_agent(&cfg,"receiver");
+        let channel2 = channel.clone();
+        let receiver = x.add(async move { (channel2.recv().await,channel2.recv().await) },agent);
+        x.tick(1.);
+        assert_eq!(Some((Some(0),None)),receiver.take_result());
+    }
+}
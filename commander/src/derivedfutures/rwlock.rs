@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{ Arc, Mutex };
+use crate::corefutures::namedfuture::maybe_named_wait;
+use crate::corefutures::promisefuture::PromiseFuture;
+
+/* A reader/writer lock. Like Lock it protects no data itself: holding a guard is the
+ * permission. Waiters are served in order, so a waiting writer stops new readers jumping
+ * ahead of it and cannot be starved; a run of readers at the head of the queue are let in
+ * together.
+ *
+ * As with Semaphore, guards are handed over by satisfying promises outside the state lock so
+ * that a guard dropped with an abandoned promise can release again.
+ */
+
+enum Waiter {
+    Read(PromiseFuture<ReadGuard>),
+    Write(PromiseFuture<WriteGuard>)
+}
+
+impl Waiter {
+    fn abandoned(&self) -> bool {
+        match self {
+            Waiter::Read(p) => p.abandoned(),
+            Waiter::Write(p) => p.abandoned()
+        }
+    }
+}
+
+struct ReadWriteLockState {
+    readers: usize,
+    writer: bool,
+    waiting: VecDeque<Waiter>
+}
+
+impl ReadWriteLockState {
+    fn ready(&mut self) -> Vec<Waiter> {
+        let mut out = vec![];
+        while let Some(waiter) = self.waiting.front() {
+            if waiter.abandoned() {
+                self.waiting.pop_front();
+                continue;
+            }
+            match waiter {
+                Waiter::Read(_) if !self.writer => { self.readers += 1; },
+                Waiter::Write(_) if !self.writer && self.readers == 0 => { self.writer = true; },
+                _ => { break; }
+            }
+            out.push(self.waiting.pop_front().unwrap());
+        }
+        out
+    }
+}
+
+/// A lock which can be held by many readers or one writer.
+///
+/// Tasks waiting for the lock show its name among their named waits.
+#[derive(Clone)]
+pub struct ReadWriteLock {
+    state: Arc<Mutex<ReadWriteLockState>>,
+    name: Arc<String>
+}
+
+/// A read hold on a `ReadWriteLock`, released when dropped.
+pub struct ReadGuard(ReadWriteLock);
+
+/// A write hold on a `ReadWriteLock`, released when dropped.
+pub struct WriteGuard(ReadWriteLock);
+
+impl Drop for ReadGuard {
+    fn drop(&mut self) {
+        self.0.release(|state| state.readers -= 1);
+    }
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        self.0.release(|state| state.writer = false);
+    }
+}
+
+impl ReadWriteLock {
+    pub fn new(name: &str) -> ReadWriteLock {
+        ReadWriteLock {
+            state: Arc::new(Mutex::new(ReadWriteLockState {
+                readers: 0,
+                writer: false,
+                waiting: VecDeque::new()
+            })),
+            name: Arc::new(name.to_string())
+        }
+    }
+
+    fn release<F>(&self, cb: F) where F: FnOnce(&mut ReadWriteLockState) {
+        let ready = {
+            let mut state = self.state.lock().unwrap();
+            cb(&mut state);
+            state.ready()
+        };
+        for waiter in ready {
+            match waiter {
+                Waiter::Read(p) => p.satisfy(ReadGuard(self.clone())),
+                Waiter::Write(p) => p.satisfy(WriteGuard(self.clone()))
+            }
+        }
+    }
+
+    /// Number of readers currently holding the lock.
+    pub fn readers(&self) -> usize { self.state.lock().unwrap().readers }
+
+    /// Is a writer currently holding the lock?
+    pub fn writing(&self) -> bool { self.state.lock().unwrap().writer }
+
+    /// Take a read hold now if possible.
+    pub fn try_read(&self) -> Option<ReadGuard> {
+        let mut state = self.state.lock().unwrap();
+        if state.writer || !state.waiting.is_empty() { return None; }
+        state.readers += 1;
+        drop(state);
+        Some(ReadGuard(self.clone()))
+    }
+
+    /// Take a write hold now if possible.
+    pub fn try_write(&self) -> Option<WriteGuard> {
+        let mut state = self.state.lock().unwrap();
+        if state.writer || state.readers > 0 || !state.waiting.is_empty() { return None; }
+        state.writer = true;
+        drop(state);
+        Some(WriteGuard(self.clone()))
+    }
+
+    /// Wait for a read hold.
+    pub fn read(&self) -> impl Future<Output=ReadGuard> {
+        let promise = PromiseFuture::new();
+        if let Some(guard) = self.try_read() {
+            promise.satisfy(guard);
+        } else {
+            self.state.lock().unwrap().waiting.push_back(Waiter::Read(promise.clone()));
+        }
+        maybe_named_wait(promise,self.name.to_string())
+    }
+
+    /// Wait for a write hold.
+    pub fn write(&self) -> impl Future<Output=WriteGuard> {
+        let promise = PromiseFuture::new();
+        if let Some(guard) = self.try_write() {
+            promise.satisfy(guard);
+        } else {
+            self.state.lock().unwrap().waiting.push_back(Waiter::Write(promise.clone()));
+        }
+        maybe_named_wait(promise,self.name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::agent::agent::Agent;
+    use crate::executor::executor::Executor;
+    use crate::integration::testintegration::TestIntegration;
+    use crate::task::runconfig::RunConfig;
+    use super::*;
+
+    fn record(report: &Arc<Mutex<Vec<String>>>, value: &str) {
+        report.lock().unwrap().push(value.to_string());
+    }
+
+    async fn reader(agent: Agent, lock: ReadWriteLock, report: Arc<Mutex<Vec<String>>>, name: &str) {
+        let _guard = lock.read().await;
+        record(&report,&format!("{}+",name));
+        agent.tick(2).await;
+        record(&report,&format!("{}-",name));
+    }
+
+    async fn writer(agent: Agent, lock: ReadWriteLock, report: Arc<Mutex<Vec<String>>>, name: &str) {
+        let _guard = lock.write().await;
+        record(&report,&format!("{}+",name));
+        agent.tick(1).await;
+        record(&report,&format!("{}-",name));
+    }
+
+    #[test]
+    pub fn test_rwlock_smoke() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let lock = ReadWriteLock::new("rw");
+        let report = Arc::new(Mutex::new(vec![]));
+        let agent = x.new_agent(&cfg,"r1");
+        x.add(reader(agent.clone(),lock.clone(),report.clone(),"r1"),agent);
+        let agent = x.new_agent(&cfg,"r2");
+        x.add(reader(agent.clone(),lock.clone(),report.clone(),"r2"),agent);
+        let agent = x.new_agent(&cfg,"w");
+        let w = x.add(writer(agent.clone(),lock.clone(),report.clone(),"w"),agent);
+        let agent = x.new_agent(&cfg,"r3");
+        x.add(reader(agent.clone(),lock.clone(),report.clone(),"r3"),agent);
+        x.tick(1.);
+        assert_eq!(2,lock.readers());
+        assert_eq!(vec!["rw"],w.get_waits());
+        for _ in 0..10 {
+            x.tick(1.);
+        }
+        /* r3 queues behind the writer rather than joining r1 and r2 */
+        assert_eq!(vec!["r1+","r2+","r1-","r2-","w+","w-","r3+","r3-"],*report.lock().unwrap());
+        assert!(lock.try_write().is_some());
+    }
+
+    #[test]
+    pub fn test_rwlock_try() {
+        let lock = ReadWriteLock::new("rw");
+        let r = lock.try_read().unwrap();
+        assert!(lock.try_read().is_some());
+        assert!(lock.try_write().is_none());
+        drop(r);
+        let w = lock.try_write().unwrap();
+        assert!(lock.writing());
+        assert!(lock.try_read().is_none());
+        drop(w);
+        assert!(!lock.writing());
+        assert_eq!(0,lock.readers());
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{ Arc, Mutex };
+use crate::corefutures::namedfuture::maybe_named_wait;
+use crate::corefutures::promisefuture::PromiseFuture;
+
+/* A counting semaphore. Waiters are served strictly in order: a large request at the head of
+ * the queue holds up smaller ones behind it, so it cannot be starved.
+ *
+ * Waiters are handed a permit by satisfying their promise. Waiters whose future has been
+ * dropped (a killed task whose handles have all gone) are skipped. If one goes away just
+ * as it is handed a permit, the permit is dropped with the promise and so released again.
+ * Promises are satisfied outside the state lock as that drop re-enters release().
+ */
+
+struct SemaphoreState {
+    available: usize,
+    waiting: VecDeque<(usize,PromiseFuture<SemaphorePermit>)>
+}
+
+impl SemaphoreState {
+    /* abandoned waiters must not hold up those behind them */
+    fn prune(&mut self) {
+        self.waiting.retain(|(_,promise)| !promise.abandoned());
+    }
+
+    /* only if nobody is ahead in the queue */
+    fn take(&mut self, count: usize) -> bool {
+        self.prune();
+        if self.waiting.is_empty() && self.available >= count {
+            self.available -= count;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ready(&mut self) -> Vec<(usize,PromiseFuture<SemaphorePermit>)> {
+        let mut out = vec![];
+        self.prune();
+        while let Some((count,_)) = self.waiting.front() {
+            if *count > self.available { break; }
+            let (count,promise) = self.waiting.pop_front().unwrap();
+            self.available -= count;
+            out.push((count,promise));
+        }
+        out
+    }
+}
+
+/// A counting semaphore for limiting concurrency among tasks.
+///
+/// Tasks waiting on `acquire()` show the semaphore's name among their named waits.
+#[derive(Clone)]
+pub struct Semaphore {
+    state: Arc<Mutex<SemaphoreState>>,
+    name: Arc<String>
+}
+
+/// Permits held from a `Semaphore`. They are returned when this is dropped.
+pub struct SemaphorePermit {
+    semaphore: Semaphore,
+    count: usize
+}
+
+impl SemaphorePermit {
+    /// Number of permits held.
+    pub fn count(&self) -> usize { self.count }
+}
+
+impl Drop for SemaphorePermit {
+    fn drop(&mut self) {
+        self.semaphore.release(self.count);
+    }
+}
+
+impl Semaphore {
+    /// Create a semaphore with the given number of permits.
+    pub fn new(name: &str, permits: usize) -> Semaphore {
+        Semaphore {
+            state: Arc::new(Mutex::new(SemaphoreState {
+                available: permits,
+                waiting: VecDeque::new()
+            })),
+            name: Arc::new(name.to_string())
+        }
+    }
+
+    fn permit(&self, count: usize) -> SemaphorePermit {
+        SemaphorePermit { semaphore: self.clone(), count }
+    }
+
+    fn release(&self, count: usize) {
+        let ready = {
+            let mut state = self.state.lock().unwrap();
+            state.available += count;
+            state.ready()
+        };
+        for (count,promise) in ready {
+            promise.satisfy(self.permit(count));
+        }
+    }
+
+    /// Number of permits not currently held.
+    pub fn available(&self) -> usize { self.state.lock().unwrap().available }
+
+    /// Add extra permits.
+    pub fn add_permits(&self, count: usize) { self.release(count); }
+
+    /// Take `count` permits if available now and nobody else is waiting.
+    pub fn try_acquire(&self, count: usize) -> Option<SemaphorePermit> {
+        let taken = self.state.lock().unwrap().take(count);
+        if taken { Some(self.permit(count)) } else { None }
+    }
+
+    /// Wait for `count` permits.
+    ///
+    /// Waiters are served in order. Waiting for more permits than will ever be available waits forever.
+    pub fn acquire(&self, count: usize) -> impl Future<Output=SemaphorePermit> {
+        let promise = PromiseFuture::new();
+        /* check and enqueue under one lock lest a release() in between goes unseen */
+        let taken = {
+            let mut state = self.state.lock().unwrap();
+            let taken = state.take(count);
+            if !taken {
+                state.waiting.push_back((count,promise.clone()));
+            }
+            taken
+        };
+        if taken {
+            promise.satisfy(self.permit(count));
+        }
+        maybe_named_wait(promise,self.name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::executor::executor::Executor;
+    use crate::integration::testintegration::TestIntegration;
+    use crate::task::runconfig::RunConfig;
+    use crate::task::taskhandle::ExecutorTaskHandle;
+    use crate::task::task::KillReason;
+    use super::*;
+
+    #[test]
+    pub fn test_semaphore_limit() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let semaphore = Semaphore::new("backend",2);
+        let running = Arc::new(Mutex::new((0,0)));
+        let mut handles = vec![];
+        for _ in 0..5 {
+            let agent = x.new_agent(&cfg,"test");
+            let agent2 = agent.clone();
+            let semaphore2 = semaphore.clone();
+            let running2 = running.clone();
+            handles.push(x.add(async move {
+                let _permit = semaphore2.acquire(1).await;
+                {
+                    let mut running = running2.lock().unwrap();
+                    running.0 += 1;
+                    running.1 = running.1.max(running.0);
+                }
+                agent2.tick(2).await;
+                running2.lock().unwrap().0 -= 1;
+            },agent));
+        }
+        x.tick(1.);
+        assert_eq!(0,semaphore.available());
+        assert_eq!(vec!["backend"],handles[4].get_waits());
+        for _ in 0..10 {
+            x.tick(1.);
+        }
+        assert_eq!((0,2),*running.lock().unwrap());
+        assert_eq!(2,semaphore.available());
+    }
+
+    #[test]
+    pub fn test_semaphore_order() {
+        let semaphore = Semaphore::new("test",3);
+        let a = semaphore.try_acquire(2).unwrap();
+        assert!(semaphore.try_acquire(2).is_none());
+        assert_eq!(2,a.count());
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let agent = x.new_agent(&cfg,"big");
+        let semaphore2 = semaphore.clone();
+        let big = x.add(async move { semaphore2.acquire(3).await.count() },agent);
+        x.tick(1.);
+        /* one is free but the big request is first in line */
+        assert!(semaphore.try_acquire(1).is_none());
+        drop(a);
+        x.tick(1.);
+        assert_eq!(Some(3),big.take_result());
+        assert_eq!(3,semaphore.available());
+    }
+
+    #[test]
+    pub fn test_semaphore_killed_waiter() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let semaphore = Semaphore::new("test",1);
+        let held = semaphore.try_acquire(1).unwrap();
+        let agent = x.new_agent(&cfg,"doomed");
+        let semaphore2 = semaphore.clone();
+        let doomed = x.add(async move { let _permit = semaphore2.acquire(1).await; },agent);
+        x.tick(1.);
+        doomed.kill(KillReason::Cancelled);
+        x.tick(1.);
+        /* the future, and so its interest in the permit, goes with the last handle */
+        drop(doomed);
+        drop(held);
+        assert_eq!(1,semaphore.available());
+    }
+
+    #[test]
+    pub fn test_semaphore_killed_head() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let semaphore = Semaphore::new("test",2);
+        let held = semaphore.try_acquire(1).unwrap();
+        let agent = x.new_agent(&cfg,"greedy");
+        let semaphore2 = semaphore.clone();
+        let greedy = x.add(async move { let _permit = semaphore2.acquire(2).await; },agent);
+        let agent = x.new_agent(&cfg,"modest");
+        let semaphore2 = semaphore.clone();
+        let modest = x.add(async move { semaphore2.acquire(1).await.count() },agent);
+        x.tick(1.);
+        assert!(modest.take_result().is_none());
+        greedy.kill(KillReason::Cancelled);
+        x.tick(1.);
+        drop(greedy);
+        /* the abandoned head is skipped rather than blocking the waiter behind */
+        semaphore.add_permits(0);
+        x.tick(1.);
+        assert_eq!(Some(1),modest.take_result());
+        assert!(semaphore.try_acquire(1).is_some());
+        drop(held);
+    }
+}
@@ -6,6 +6,7 @@ use crate::integration::integration::{ Integration, SleepQuantity };
 use crate::integration::reentering::ReenteringIntegration;
 use crate::task::runconfig::RunConfig;
 use crate::task::slot::RunSlot;
+use crate::task::taskgroup::TaskGroup;
 use crate::task::task::{ KillReason, TaskSummary };
 use crate::task::taskhandle::{ ExecutorTaskHandle, TaskHandle };
 use super::action::Action;
@@ -15,6 +16,7 @@ use super::request::Request;
 use super::exetasks::ExecutorTasks;
 use super::taskcontainerhandle::TaskContainerHandle;
 use super::timings::ExecutorTimings;
+use super::tracer::Tracer;
 
 lazy_static! {
     static ref NEXT_IDENTITY : Arc<Mutex<u64>> = Arc::new(Mutex::new(0));
@@ -29,7 +31,10 @@ pub struct Executor {
     tasks: ExecutorTasks,
     integration: ReenteringIntegration,
     actions: Link<Action>,
-    requests: Link<Request>
+    requests: Link<Request>,
+    tracer: Option<Tracer>,
+    expiry: f64,
+    starvation_ticks: Option<u64>
 }
 
 impl Executor {
@@ -43,15 +48,22 @@ impl Executor {
             *id += 1;
             *id
         };
+        let mut tasks = ExecutorTasks::new();
+        if let Some(seed) = integration.scheduling_seed() {
+            tasks.set_scheduling_seed(seed);
+        }
         Executor {
             identity,
             next_task_id: 0,
             locks: LockManager::new(),
             timings: ExecutorTimings::new(&integration),
             requests: Link::new(),
-            tasks: ExecutorTasks::new(),
+            tasks,
             integration,
-            actions: Link::new()
+            actions: Link::new(),
+            tracer: None,
+            expiry: f64::INFINITY,
+            starvation_ticks: None
         }
     }
 
@@ -69,11 +81,56 @@ impl Executor {
     /// task evicts the old. If `push` is false, the new submission fails.
     pub fn new_slot(&self, push: bool) -> RunSlot { RunSlot::new(push) }
 
+    /// Create new `TaskGroup`.
+    /// 
+    /// Tasks join with `Agent::join_group()` and can then be cancelled together with `TaskGroup::cancel()`.
+    pub fn new_group(&self) -> TaskGroup { TaskGroup::new() }
+
     /// Return `TaskSummary` objects for all tasks currently running on this executor.
     pub fn summarize_all(&self) -> Vec<TaskSummary> {
         self.get_tasks().summarize_all()
     }
 
+    /// Give a priority class a budget within each tick.
+    /// 
+    /// `guaranteed` and `limit` are fractions of the `slice` passed to `tick()`. While it has runnable tasks, the class
+    /// is guaranteed to run for at least `guaranteed` of each tick: once only that much of the tick remains, it runs
+    /// ahead of higher priorities. It never runs for more than `limit` of a tick, even if nothing else is runnable, in
+    /// which case the rest of the tick is given back to the integration. Classes without a budget have a guarantee
+    /// of zero and a limit of one. As with slices, this relies on tasks yielding regularly.
+    pub fn set_priority_budget(&mut self, priority: u8, guaranteed: f64, limit: f64) {
+        self.get_tasks_mut().set_budget(priority,guaranteed,limit);
+    }
+
+    /// Report priority classes which have been runnable but not run for `ticks` ticks in a row.
+    /// 
+    /// Reports are made via `Integration::starved()` after `ticks` ticks, then every further `ticks` ticks while the
+    /// starvation continues. `None` (the default) turns off reporting.
+    pub fn set_starvation_ticks(&mut self, ticks: Option<u64>) {
+        self.starvation_ticks = ticks.filter(|ticks| *ticks > 0);
+    }
+
+    /// Start recording a trace of task activity for export with `trace_json()`.
+    /// 
+    /// `us_per_unit` is the number of microseconds in one time unit of your integration (eg 1000 for milliseconds).
+    /// Only the most recent `max_events` events are kept. Any existing trace is discarded.
+    pub fn start_trace(&mut self, us_per_unit: f64, max_events: usize) {
+        self.tracer = Some(Tracer::new(&self.integration,self.identity,us_per_unit,max_events));
+    }
+
+    /// Stop recording a trace and discard it.
+    pub fn stop_trace(&mut self) {
+        self.tracer = None;
+    }
+
+    /// Export the trace recorded since `start_trace()` in the Chrome trace-event JSON format.
+    /// 
+    /// The result can be loaded into chrome://tracing, Perfetto, etc. Each task appears as a thread of a process
+    /// representing this executor. Returns `None` if not tracing.
+    pub fn trace_json(&self) -> Option<String> {
+        self.tracer.as_ref().map(|tracer| tracer.to_json())
+    }
+
     /// Create a new `Agent` to pass to add and, if you wish, pass into your future for access to executor
     /// functionality.
     pub fn new_agent(&self, run_config: &RunConfig, name: &str) -> Agent {
@@ -93,6 +150,7 @@ impl Executor {
     }
     
     pub fn add_timer(&mut self, timeout: f64, callback: Box<dyn FnOnce() + 'static>) {
+        let callback = self.trace_timer(0,callback);
         self.get_timings_mut().add_standalone_timer(timeout,callback);
         self.integration.cause_reentry();
     }
@@ -100,11 +158,17 @@ impl Executor {
     fn try_add_task(&mut self, task: Box<dyn ExecutorTaskHandle>, agent: Agent) {
         self.next_task_id += 1;
         let id = (self.identity,self.next_task_id);
-        let tasks = self.get_tasks_mut(); // shared to avoid race to slot
+        let tasks = &mut self.tasks; // shared to avoid race to slot
         if tasks.check_slot(&agent) {
             let container_handle = tasks.create_handle(&agent,task,id);
             tasks.use_slot(&agent,&container_handle);
-            if !agent.finish_agent().finished() {
+            if let (Some(tracer),Some(summary)) = (&self.tracer,tasks.summarize(&container_handle)) {
+                tracer.spawn(&summary,agent.get_config().get_priority());
+            }
+            let finished = agent.finish_agent().finished();
+            if finished {
+                agent.leave_groups();
+            } else {
                 tasks.start_task(&container_handle);
                 self.integration.cause_reentry();
                 if let Some(timeout) = agent.get_config().get_timeout() {
@@ -113,7 +177,11 @@ impl Executor {
                 }
             }
         } else {
+            if let Some(tracer) = &self.tracer {
+                tracer.refuse(&agent.get_name());
+            }
             task.kill(KillReason::NotNeeded);
+            agent.leave_groups();
         }
     }
 
@@ -129,12 +197,50 @@ impl Executor {
 
     pub fn make_lock(&mut self) -> Lock { self.locks.make_lock() }
 
+    fn trace_timer(&self, tid: u64, callback: Box<dyn FnOnce() + 'static>) -> Box<dyn FnOnce() + 'static> {
+        if let Some(tracer) = self.tracer.clone() {
+            Box::new(move || { tracer.timer(tid); callback(); })
+        } else {
+            callback
+        }
+    }
+
+    fn trace_lock(&self, tid: u64, lock: &Lock, callback: Box<dyn FnOnce() + 'static>) -> Box<dyn FnOnce() + 'static> {
+        if let Some(tracer) = self.tracer.clone() {
+            let id = lock.id();
+            let start = tracer.now();
+            Box::new(move || { tracer.lock(tid,id,start); callback(); })
+        } else {
+            callback
+        }
+    }
+
+    fn trace_action(&self, handle: &TaskContainerHandle, action: &Action) {
+        if let Some(tracer) = &self.tracer {
+            match action {
+                Action::BlockTask() => {
+                    if let Some(summary) = self.get_tasks().summarize(handle) {
+                        tracer.block(&summary);
+                    }
+                },
+                Action::UnblockTask() => {
+                    tracer.unblock(handle.identity());
+                },
+                Action::Done() => {
+                    tracer.finish(handle.identity(),self.get_tasks().kill_reason(handle));
+                },
+                Action::Unblock(_) => {}
+            }
+        }
+    }
+
     pub(crate) fn service(&mut self) {
         loop {
             let actions = self.actions.drain();
             let requests = self.requests.drain();
             if actions.len() == 0 && requests.len() == 0 { break; }
             for mut action in actions {
+                self.trace_action(&action.0,&action.1);
                 match action {
                     (ref handle,Action::BlockTask()) => {
                         self.get_tasks_mut().block_task(&handle);
@@ -156,15 +262,20 @@ impl Executor {
                         self.try_add_task(task,agent);
                     },
                     (handle,Request::Timer(timeout,callback)) => {
+                        let callback = self.trace_timer(handle.identity(),callback);
                         self.get_timings_mut().add_timer(&handle,timeout,callback);
                     },
                     (handle,Request::Tick(tick,callback)) => {
                         self.get_timings_mut().add_tick(&handle,tick,callback);
                     },
                     (handle,Request::Lock(lock,callback)) => {
+                        let callback = self.trace_lock(handle.identity(),&lock,callback);
                         self.locks.lock(&handle,lock,callback);
                     },
                     (_handle,Request::Unlock(lock)) => {
+                        if let Some(tracer) = &self.tracer {
+                            tracer.unlock(lock.id());
+                        }
                         self.locks.unlock(lock);
                     },
                 }
@@ -176,15 +287,37 @@ impl Executor {
         self.get_tasks().run_timers(self.get_timings());
         self.service();
         let tick = self.get_timings().get_tick_index();
-        let out = self.get_tasks_mut().execute(tick);
+        let tracer = self.tracer.clone();
+        let out = if self.get_tasks().budgeted() {
+            let start = self.integration.current_time();
+            let remaining = self.expiry-start;
+            let out = self.get_tasks_mut().execute(tick,remaining,tracer.as_ref());
+            let elapsed = self.integration.current_time()-start;
+            self.get_tasks_mut().charge(elapsed);
+            out
+        } else {
+            self.get_tasks_mut().execute(tick,f64::INFINITY,tracer.as_ref())
+        };
         self.service();
         out
     }
 
+    fn start_tick(&mut self, slice: f64) {
+        let starved = self.get_tasks_mut().start_tick(slice);
+        if let Some(limit) = self.starvation_ticks {
+            for (priority,ticks) in starved {
+                if ticks % limit == 0 {
+                    self.integration.starved(priority,ticks);
+                }
+            }
+        }
+    }
+
     fn run_one_tick(&mut self, slice: f64) -> f64 {
         self.integration.reentering();
         let mut now = self.integration.current_time();
         let expiry = now+slice;
+        self.expiry = expiry;
         self.get_tasks().run_ticks(self.get_timings());
         loop {
             self.integration.reentering();
@@ -192,6 +325,7 @@ impl Executor {
             now = self.integration.current_time();
             if now >= expiry { break; }
         }
+        self.expiry = f64::INFINITY;
         now
     }
 
@@ -212,6 +346,7 @@ impl Executor {
     /// environment is non-preemptive, this relies on co-operation from the tasks (yielding regularly).
     pub fn tick(&mut self, slice: f64) {
         self.get_timings_mut().advance_tick();
+        self.start_tick(slice);
         let now = self.run_one_tick(slice);
         self.integration.sleep(self.calculate_sleep(now));
     }
@@ -221,6 +356,7 @@ impl Executor {
 mod test {
     use futures::future;
     use std::collections::HashSet;
+    use std::sync::{ Arc, Mutex };
     use crate::corefutures::promisefuture::PromiseFuture;
     use crate::integration::integration::SleepQuantity;
     use crate::integration::testintegration::{ TestIntegration, tick_helper };
@@ -699,6 +835,69 @@ mod test {
         }
     }
 
+    /* each poll takes one time unit */
+    async fn busy(agent: Agent, mut integration: TestIntegration, runs: Arc<Mutex<u32>>) {
+        loop {
+            *runs.lock().unwrap() += 1;
+            let now = integration.get_time();
+            integration.set_time(now+1.);
+            agent.tick(0).await;
+        }
+    }
+
+    fn add_busy(x: &mut Executor, integration: &TestIntegration, priority: u8) -> Arc<Mutex<u32>> {
+        let runs = Arc::new(Mutex::new(0));
+        let agent = x.new_agent(&RunConfig::new(None,priority,None),"busy");
+        x.add(busy(agent.clone(),integration.clone(),runs.clone()),agent);
+        runs
+    }
+
+    #[test]
+    pub fn test_starvation() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        x.set_starvation_ticks(Some(2));
+        let high = add_busy(&mut x,&integration,1);
+        let low = add_busy(&mut x,&integration,2);
+        for _ in 0..5 {
+            x.tick(4.);
+        }
+        assert_eq!(20,*high.lock().unwrap());
+        assert_eq!(0,*low.lock().unwrap());
+        assert_eq!(vec![(2,2),(2,4)],*integration.get_starvations());
+    }
+
+    #[test]
+    pub fn test_priority_budget() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        x.set_starvation_ticks(Some(2));
+        x.set_priority_budget(2,0.25,1.);
+        let high = add_busy(&mut x,&integration,1);
+        let low = add_busy(&mut x,&integration,2);
+        for _ in 0..5 {
+            x.tick(8.);
+        }
+        assert_eq!(30,*high.lock().unwrap());
+        assert_eq!(10,*low.lock().unwrap());
+        assert_eq!(0,integration.get_starvations().len());
+    }
+
+    #[test]
+    pub fn test_priority_limit() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        x.set_priority_budget(2,0.,0.5);
+        let low = add_busy(&mut x,&integration,2);
+        x.tick(8.);
+        assert_eq!(4,*low.lock().unwrap());
+        assert_eq!(4.,integration.get_time());
+        assert_ne!(Some(&SleepQuantity::Forever),integration.get_sleeps().last());
+        x.tick(8.);
+        assert_eq!(8,*low.lock().unwrap());
+        assert_eq!(8.,integration.get_time());
+    }
+
     #[test]
     pub fn test_kill() {
         let integration = TestIntegration::new();
@@ -1,12 +1,13 @@
 use hashbrown::HashMap;
 use crate::agent::agent::Agent;
 use crate::task::slot::RunSlot;
-use crate::task::task::TaskSummary;
+use crate::task::task::{ KillReason, TaskSummary };
 use crate::task::taskhandle::ExecutorTaskHandle;
 use super::runnable::Runnable;
 use super::taskcontainer::TaskContainer;
 use super::taskcontainerhandle::TaskContainerHandle;
 use super::timings::ExecutorTimings;
+use super::tracer::Tracer;
 
 #[cfg(debug_unregister)]
 use peregrine_toolkit::log;
@@ -124,14 +125,27 @@ impl ExecutorTasks {
         self.tasks.remove(&handle);
     }
 
-    pub(crate) fn execute(&mut self, tick: u64) -> bool {
-        self.runnable.run(&mut self.tasks,tick)
+    pub(crate) fn execute(&mut self, tick: u64, remaining: f64, tracer: Option<&Tracer>) -> bool {
+        self.runnable.run(&mut self.tasks,tick,remaining,tracer)
     }
 
+    pub(crate) fn set_budget(&mut self, priority: u8, guaranteed: f64, limit: f64) {
+        self.runnable.set_budget(priority as usize,guaranteed,limit);
+    }
+
+    pub(crate) fn set_scheduling_seed(&mut self, seed: u64) { self.runnable.set_seed(seed); }
+    pub(crate) fn budgeted(&self) -> bool { self.runnable.budgeted() }
+    pub(crate) fn start_tick(&mut self, slice: f64) -> Vec<(u8,u64)> { self.runnable.start_tick(slice) }
+    pub(crate) fn charge(&mut self, elapsed: f64) { self.runnable.charge(elapsed); }
+
     pub(crate) fn summarize(&self, handle: &TaskContainerHandle) -> Option<TaskSummary> {
         self.tasks.get(handle).and_then(|x| x.summarize())
     }
 
+    pub(crate) fn kill_reason(&self, handle: &TaskContainerHandle) -> Option<KillReason> {
+        self.tasks.get(handle).and_then(|x| x.kill_reason())
+    }
+
     pub(crate) fn create_handle(&mut self, agent: &Agent, handle: Box<dyn ExecutorTaskHandle>, id: (u64,u64)) -> TaskContainerHandle {
         let container_handle = self.tasks.allocate();
         agent.run_agent().register(&container_handle,id);
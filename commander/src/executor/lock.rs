@@ -7,6 +7,8 @@ use super::taskcontainerhandle::TaskContainerHandle;
 pub struct Lock(u64);
 
 impl Lock {
+    pub(crate) fn id(&self) -> u64 { self.0 }
+
     pub(crate) fn make_guard<'t,F>(&self, cb: F) -> LockGuard<'t> where F: FnOnce(&Lock) + 't { LockGuard(self.clone(),Some(Box::new(cb))) }
 }
 
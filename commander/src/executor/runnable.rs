@@ -1,33 +1,98 @@
 use crate::executor::taskcontainer::TaskContainer;
 use crate::executor::taskcontainerhandle::TaskContainerHandle;
 use super::runqueue::RunQueue;
+use super::tracer::Tracer;
 
 /* VERY HOT CODE PATH: BE EFFICIENT NOT PRETTY */
 
 /* A Runnable contains a group of RunQueues. Each RunQueue has a different priority.
  * When asked to run a task, Runnable diverts the call to the RunQueue with the
  * highest priority.
+ *
+ * If any priority class has a budget, the choice is more involved. Once the time
+ * remaining in the tick is no more than the time still owed to classes with a
+ * guarantee, the highest priority of those classes runs. Otherwise the highest
+ * priority class not over its limit runs. If every runnable class is over its
+ * limit, nothing runs and the rest of the tick is given up.
  */
 
 pub(super) struct Runnable {
     range: Option<(usize,usize)>, // ends are both INCLUSIVE
-    queues: Vec<Option<RunQueue>>
+    queues: Vec<Option<RunQueue>>,
+    budgeted: bool,
+    last_run: Option<usize>,
+    seed: Option<u64>
 }
 
 impl Runnable {
     pub(super) fn new() -> Runnable {
         Runnable {
             range: None,
-            queues: vec![]
+            queues: vec![],
+            budgeted: false,
+            last_run: None,
+            seed: None
         }
     }
 
+    pub(super) fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        for (index,queue) in self.queues.iter_mut().enumerate() {
+            if let Some(queue) = queue {
+                queue.set_shuffle(seed.wrapping_add(index as u64));
+            }
+        }
+    }
+
+    pub(super) fn set_budget(&mut self, index: usize, guaranteed: f64, limit: f64) {
+        self.ensure(index);
+        self.queues[index].as_mut().unwrap().set_budget(guaranteed,limit);
+        self.budgeted = true;
+    }
+
+    pub(super) fn budgeted(&self) -> bool { self.budgeted }
+
+    /* returns (priority,ticks) for each starved class */
+    pub(super) fn start_tick(&mut self, slice: f64) -> Vec<(u8,u64)> {
+        let mut starved = vec![];
+        for (index,queue) in self.queues.iter_mut().enumerate() {
+            if let Some(queue) = queue {
+                let ticks = queue.start_tick(slice);
+                if ticks > 0 { starved.push((index as u8,ticks)); }
+            }
+        }
+        starved
+    }
+
+    /* charge time to the class which ran last */
+    pub(super) fn charge(&mut self, elapsed: f64) {
+        if let Some(index) = self.last_run {
+            self.queues[index].as_mut().unwrap().charge(elapsed);
+        }
+    }
+
+    fn choose(&self, min: usize, max: usize, remaining: f64) -> Option<usize> {
+        if !self.budgeted { return Some(min); }
+        let runnable = || (min..(max+1)).filter_map(|i| {
+            self.queues[i].as_ref().filter(|q| !q.empty()).map(|q| (i,q))
+        });
+        let owed = runnable().map(|(_,q)| q.owed()).sum::<f64>();
+        if owed > 0. && remaining <= owed {
+            return runnable().find(|(_,q)| q.owed() > 0.).map(|(i,_)| i);
+        }
+        runnable().find(|(_,q)| !q.over_limit()).map(|(i,_)| i)
+    }
+
     fn ensure(&mut self, index: usize) {
         if self.queues.len() <= index {
             self.queues.resize_with(index+1,Default::default)
         }
         if self.queues[index].is_none() {
-            self.queues[index] = Some(RunQueue::new());
+            let mut queue = RunQueue::new();
+            if let Some(seed) = self.seed {
+                queue.set_shuffle(seed.wrapping_add(index as u64));
+            }
+            self.queues[index] = Some(queue);
         }
     }
 
@@ -115,13 +180,16 @@ impl Runnable {
         self.range.is_some()
     }
 
-    pub(super) fn run(&mut self, tasks: &mut TaskContainer, tick_index: u64) -> bool {
-        if let Some((index,_)) = self.range {
-            self.queues[index].as_mut().unwrap().run(tasks,tick_index);
-            true
-        } else {
-            false
+    pub(super) fn run(&mut self, tasks: &mut TaskContainer, tick_index: u64, remaining: f64, tracer: Option<&Tracer>) -> bool {
+        self.last_run = None;
+        if let Some((min,max)) = self.range {
+            if let Some(index) = self.choose(min,max,remaining) {
+                self.queues[index].as_mut().unwrap().run(tasks,tick_index,tracer);
+                self.last_run = Some(index);
+                return true;
+            }
         }
+        false
     }
 }
 
@@ -152,25 +220,25 @@ mod test {
         r.add(&mut tasks,&h2);
         r.add(&mut tasks,&h3);
         r.add(&mut tasks,&h4);
-        r.run(&mut tasks,0);
-        r.run(&mut tasks,0);
-        r.run(&mut tasks,0);
+        r.run(&mut tasks,0,f64::INFINITY,None);
+        r.run(&mut tasks,0,f64::INFINITY,None);
+        r.run(&mut tasks,0,f64::INFINITY,None);
         assert_eq!(2,t1.run_count());
         assert_eq!(1,t2.run_count());
         assert_eq!(0,t3.run_count());
         assert_eq!(0,t4.run_count());
         /* remove h1 and check h2 just runs */
         r.remove(&mut tasks,&h1);
-        r.run(&mut tasks,0);
-        r.run(&mut tasks,0);
+        r.run(&mut tasks,0,f64::INFINITY,None);
+        r.run(&mut tasks,0,f64::INFINITY,None);
         assert_eq!(2,t1.run_count());
         assert_eq!(3,t2.run_count());
         assert_eq!(0,t3.run_count());
         assert_eq!(0,t4.run_count());
         /* remove h2 and check just h3 runs */
         r.remove(&mut tasks,&h2);
-        r.run(&mut tasks,0);
-        assert!(r.run(&mut tasks,0));
+        r.run(&mut tasks,0,f64::INFINITY,None);
+        assert!(r.run(&mut tasks,0,f64::INFINITY,None));
         assert_eq!(2,t1.run_count());
         assert_eq!(3,t2.run_count());
         assert_eq!(2,t3.run_count());
@@ -178,6 +246,49 @@ mod test {
         /* remove h3 and h4 and check run returns false */
         r.remove(&mut tasks,&h3);
         r.remove(&mut tasks,&h4);
-        assert!(!r.run(&mut tasks,0));
+        assert!(!r.run(&mut tasks,0,f64::INFINITY,None));
+    }
+
+    #[test]
+    pub fn test_runnable_budget() {
+        let mut tasks = TaskContainer::new();
+        let mut r = Runnable::new();
+        /* 1: h1 (unbudgeted); 2: h2 (guaranteed 1/4, limited to 1/2) */
+        let h1 = tasks.allocate();
+        let t1 = FakeTask::new(1);
+        tasks.set(&h1,Box::new(t1.clone()));
+        let h2 = tasks.allocate();
+        let t2 = FakeTask::new(2);
+        tasks.set(&h2,Box::new(t2.clone()));
+        r.set_budget(2,0.25,0.5);
+        r.add(&mut tasks,&h1);
+        r.add(&mut tasks,&h2);
+        r.start_tick(8.);
+        /* early in the tick, priority wins */
+        r.run(&mut tasks,0,8.,None);
+        r.charge(3.);
+        r.run(&mut tasks,0,5.,None);
+        r.charge(2.);
+        assert_eq!(2,t1.run_count());
+        assert_eq!(0,t2.run_count());
+        /* when only the guarantee remains, it is paid */
+        r.run(&mut tasks,0,2.,None);
+        r.charge(2.);
+        assert_eq!(2,t1.run_count());
+        assert_eq!(1,t2.run_count());
+        r.run(&mut tasks,0,0.5,None);
+        assert_eq!(3,t1.run_count());
+        /* with priority 1 gone, priority 2 runs until its limit */
+        r.remove(&mut tasks,&h1);
+        let starved = r.start_tick(8.);
+        assert_eq!(0,starved.len());
+        assert!(r.run(&mut tasks,0,8.,None));
+        r.charge(4.);
+        assert!(!r.run(&mut tasks,0,4.,None));
+        assert_eq!(2,t2.run_count());
+        /* blocked out for a tick, it is reported as starved */
+        r.start_tick(8.);
+        let starved = r.start_tick(8.);
+        assert_eq!(vec![(2,1)],starved);
     }
 }
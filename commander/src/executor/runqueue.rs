@@ -1,12 +1,35 @@
 use crate::executor::taskcontainer::TaskContainer;
 use crate::executor::taskcontainerhandle::{ TaskContainerHandle, TaskContainerHandleData };
+use super::tracer::Tracer;
 
 /* A RunQueue contains a list of runnable tasks of the same priority. They are
  * run in order, once per call to run() with this struct remembering the next
  * task to run. It sits inside a Runnable which contains all the RunQueues of
  * different priorities. This, in turn, sits inside the excutor.
+ *
+ * A RunQueue also keeps the accounts for its priority class's budget: the
+ * fraction of each tick it is guaranteed and the fraction it is limited to,
+ * how much time it has used this tick, and how many ticks in a row it has been
+ * runnable but not run (ie starved). Runnable uses these to choose a queue.
+ * Budgets only apply to ticks with a finite slice.
+ *
+ * If shuffled, the next task is chosen pseudo-randomly from a seed rather
+ * than round-robin, for deterministic simulation of different orderings.
  */
 
+/* splitmix64: tiny, and good enough to vary an ordering */
+struct ShuffleRng(u64);
+
+impl ShuffleRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
 struct RunQueueMember {
     blocked: bool
 }
@@ -15,7 +38,14 @@ pub(super) struct RunQueue {
     present: TaskContainerHandleData<RunQueueMember>,
     tasks: Vec<TaskContainerHandle>,
     next_task: usize,
-    num_unblocked: usize
+    num_unblocked: usize,
+    guaranteed: f64,
+    limit: f64,
+    slice: f64,
+    used: f64,
+    ran: bool,
+    starved: u64,
+    shuffle: Option<ShuffleRng>
 }
 
 impl RunQueue {
@@ -24,12 +54,49 @@ impl RunQueue {
             present: TaskContainerHandleData::new(),
             tasks: Vec::new(),
             next_task: 0,
-            num_unblocked: 0
+            num_unblocked: 0,
+            guaranteed: 0.,
+            limit: 1.,
+            slice: f64::INFINITY,
+            used: 0.,
+            ran: false,
+            starved: 0,
+            shuffle: None
         }
     }
 
+    pub(super) fn set_shuffle(&mut self, seed: u64) {
+        self.shuffle = Some(ShuffleRng(seed));
+    }
+
     pub(super) fn empty(&self) -> bool { self.num_unblocked == 0 }
 
+    pub(super) fn set_budget(&mut self, guaranteed: f64, limit: f64) {
+        self.guaranteed = guaranteed;
+        self.limit = limit;
+    }
+
+    /* returns the number of ticks this queue has been starved for */
+    pub(super) fn start_tick(&mut self, slice: f64) -> u64 {
+        if self.empty() || self.ran { self.starved = 0; } else { self.starved += 1; }
+        self.ran = false;
+        self.used = 0.;
+        self.slice = slice;
+        self.starved
+    }
+
+    pub(super) fn charge(&mut self, elapsed: f64) { self.used += elapsed; }
+
+    /* time still due from the guarantee this tick */
+    pub(super) fn owed(&self) -> f64 {
+        if self.empty() || !self.slice.is_finite() { return 0.; }
+        (self.guaranteed*self.slice-self.used).max(0.)
+    }
+
+    pub(super) fn over_limit(&self) -> bool {
+        self.slice.is_finite() && self.used >= self.limit*self.slice
+    }
+
     pub(super) fn block(&mut self, handle: &TaskContainerHandle) {
         if let Some(member) = self.present.get_mut(handle) {
             if !member.blocked {
@@ -70,7 +137,24 @@ impl RunQueue {
         }
     }
 
+    fn shuffled_task(&mut self) -> Option<&TaskContainerHandle> {
+        let rng = self.shuffle.as_mut()?;
+        let mut choice = (rng.next() % (self.num_unblocked as u64)) as usize;
+        for handle in &self.tasks {
+            if let Some(member) = self.present.get(handle) {
+                if !member.blocked {
+                    if choice == 0 { return Some(handle); }
+                    choice -= 1;
+                }
+            }
+        }
+        None
+    }
+
     fn next_task(&mut self) -> &TaskContainerHandle {
+        if self.shuffle.is_some() {
+            return self.shuffled_task().unwrap();
+        }
         loop {
             if self.next_task >= self.tasks.len() {
                 self.next_task = 0;
@@ -85,9 +169,18 @@ impl RunQueue {
         }
     }
 
-    pub(super) fn run(&mut self, tasks: &mut TaskContainer, tick_index: u64) {
+    pub(super) fn run(&mut self, tasks: &mut TaskContainer, tick_index: u64, tracer: Option<&Tracer>) {
+        self.ran = true;
         if let Some(task) = tasks.get_mut(self.next_task()) {
-            task.run(tick_index);
+            if let Some(tracer) = tracer {
+                let start = tracer.now();
+                task.run(tick_index);
+                if let Some(summary) = task.summarize() {
+                    tracer.poll(&summary,start);
+                }
+            } else {
+                task.run(tick_index);
+            }
         }
     }
 }
@@ -111,9 +204,9 @@ mod test {
         q.add(&h1);
         assert!(!q.empty());
         assert_eq!(0,t1.run_count());
-        q.run(&mut tasks,0);
+        q.run(&mut tasks,0,None);
         assert_eq!(1,t1.run_count());
-        q.run(&mut tasks,0);
+        q.run(&mut tasks,0,None);
         assert_eq!(2,t1.run_count());
         /* add second and third task and check run fairly */
         let h2 = tasks.allocate();
@@ -124,22 +217,80 @@ mod test {
         tasks.set(&h3,Box::new(t3.clone()));
         q.add(&h2);
         q.add(&h3);
-        q.run(&mut tasks,0);
-        q.run(&mut tasks,0);
+        q.run(&mut tasks,0,None);
+        q.run(&mut tasks,0,None);
         assert_eq!(1,t2.run_count());
         assert_eq!(1,t3.run_count());
-        q.run(&mut tasks,0);
+        q.run(&mut tasks,0,None);
         assert_eq!(3,t1.run_count());
         /* remove first and check for queue rewind */
         q.remove(&h1);
-        q.run(&mut tasks,0);
+        q.run(&mut tasks,0,None);
         assert_eq!(2,t2.run_count());
         /* remove three and check for end-skip and no rewind */
         q.remove(&h3);
-        q.run(&mut tasks,0);
+        q.run(&mut tasks,0,None);
         assert_eq!(3,t2.run_count());
         /* remove two to check for emptying */
         q.remove(&h2);
         assert!(q.empty());
     }
+
+    #[test]
+    pub fn test_runqueue_shuffle() {
+        let order = |seed| {
+            let mut tasks = TaskContainer::new();
+            let mut q = RunQueue::new();
+            q.set_shuffle(seed);
+            let fakes = (0..4).map(|_| {
+                let h = tasks.allocate();
+                let t = FakeTask::new(0);
+                tasks.set(&h,Box::new(t.clone()));
+                q.add(&h);
+                t
+            }).collect::<Vec<_>>();
+            let mut order = vec![];
+            for _ in 0..8 {
+                q.run(&mut tasks,0,None);
+                let counts = fakes.iter().map(|t| t.run_count()).collect::<Vec<_>>();
+                order.push(counts.iter().sum::<u32>());
+                order.push(counts.iter().enumerate().map(|(i,c)| (i as u32+1)*c).sum::<u32>());
+            }
+            order
+        };
+        assert_eq!(order(1),order(1));
+        assert!((2..10).any(|seed| order(seed) != order(1)));
+    }
+
+    #[test]
+    pub fn test_runqueue_budget() {
+        let mut tasks = TaskContainer::new();
+        let mut q = RunQueue::new();
+        q.set_budget(0.25,0.5);
+        let h1 = tasks.allocate();
+        tasks.set(&h1,Box::new(FakeTask::new(0)));
+        /* empty queues are never owed or starved */
+        assert_eq!(0,q.start_tick(8.));
+        assert_eq!(0.,q.owed());
+        q.add(&h1);
+        assert_eq!(2.,q.owed());
+        q.run(&mut tasks,0,None);
+        q.charge(1.5);
+        assert_eq!(0.5,q.owed());
+        assert!(!q.over_limit());
+        q.charge(2.5);
+        assert_eq!(0.,q.owed());
+        assert!(q.over_limit());
+        /* ran, so not starved; next tick resets accounts */
+        assert_eq!(0,q.start_tick(8.));
+        assert_eq!(2.,q.owed());
+        assert!(!q.over_limit());
+        assert_eq!(1,q.start_tick(8.));
+        assert_eq!(2,q.start_tick(8.));
+        /* no budgets in unbounded ticks */
+        q.start_tick(f64::INFINITY);
+        q.charge(100.);
+        assert_eq!(0.,q.owed());
+        assert!(!q.over_limit());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,303 @@
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::sync::{ Arc, Mutex };
+use crate::integration::reentering::ReenteringIntegration;
+use crate::task::task::{ KillReason, TaskSummary };
+
+/* A Tracer records what the executor did and when, for export in the Chrome trace-event JSON
+ * format which can be loaded into chrome://tracing, Perfetto, etc. Each executor is a process and
+ * each task a thread (with the task identity as tid) so that a task's polls, blocks, etc, line up
+ * on one row. Events not belonging to any task (standalone timers, tasks refused a slot) go on
+ * tid 0.
+ *
+ * Events are:
+ *   poll     (complete)  one call into the task's future;
+ *   blocked  (complete)  from a task blocking until it is unblocked, with any named waits;
+ *   lock     (complete)  from requesting a lock until acquiring it;
+ *   locked   (complete)  from acquiring a lock until releasing it;
+ *   spawn    (instant)   task added to the executor;
+ *   timer    (instant)   a timer fired;
+ *   finish   (instant)   task completed normally;
+ *   kill     (instant)   task completed via a signal, with the KillReason.
+ *
+ * Integration time units are multiplied by us_per_unit to get the microseconds the format expects.
+ * Only the most recent max_events events are kept so that tracing can be left on for a long session
+ * and the interesting part exported when something goes slow. A task's name is kept only while some
+ * kept event is on its tid.
+ */
+
+struct TraceEvent {
+    name: String,
+    phase: char,
+    start: f64,
+    duration: f64,
+    tid: u64,
+    args: Vec<(String,String)>
+}
+
+struct TracerState {
+    integration: ReenteringIntegration,
+    pid: u64,
+    us_per_unit: f64,
+    max_events: usize,
+    events: VecDeque<TraceEvent>,
+    names: HashMap<u64,String>,
+    tid_events: HashMap<u64,usize>,
+    blocked: HashMap<u64,(f64,Vec<String>)>,
+    locked: HashMap<u64,(u64,f64)>
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}",c as u32)),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_args(args: &[(String,String)]) -> String {
+    let args = args.iter().map(|(k,v)| format!("{}:{}",json_string(k),json_string(v))).collect::<Vec<_>>();
+    format!("{{{}}}",args.join(","))
+}
+
+impl TracerState {
+    fn now(&self) -> f64 { self.integration.current_time() * self.us_per_unit }
+
+    fn add(&mut self, tid: u64, name: &str, phase: char, start: f64, args: Vec<(String,String)>) {
+        let duration = if phase == 'X' { self.now() - start } else { 0. };
+        if self.events.len() >= self.max_events {
+            if let Some(event) = self.events.pop_front() {
+                self.forget(event.tid);
+            }
+        }
+        if self.max_events > 0 {
+            self.events.push_back(TraceEvent { name: name.to_string(), phase, start, duration, tid, args });
+            *self.tid_events.entry(tid).or_insert(0) += 1;
+        } else {
+            self.names.remove(&tid);
+        }
+    }
+
+    fn forget(&mut self, tid: u64) {
+        let count = self.tid_events.get_mut(&tid).map(|count| { *count -= 1; *count });
+        if count == Some(0) {
+            self.tid_events.remove(&tid);
+            self.names.remove(&tid);
+        }
+    }
+
+    fn instant(&mut self, tid: u64, name: &str, args: Vec<(String,String)>) {
+        let now = self.now();
+        self.add(tid,name,'i',now,args);
+    }
+
+    fn end_block(&mut self, tid: u64) {
+        if let Some((start,waits)) = self.blocked.remove(&tid) {
+            let args = if !waits.is_empty() { vec![("waits".to_string(),waits.join(","))] } else { vec![] };
+            self.add(tid,"blocked",'X',start,args);
+        }
+    }
+
+    fn event_json(&self, event: &TraceEvent) -> String {
+        let timing = match event.phase {
+            'X' => format!("\"ts\":{},\"dur\":{}",event.start,event.duration),
+            _ => format!("\"ts\":{},\"s\":\"t\"",event.start)
+        };
+        format!("{{\"name\":{},\"cat\":\"commander\",\"ph\":\"{}\",{},\"pid\":{},\"tid\":{},\"args\":{}}}",
+            json_string(&event.name),event.phase,timing,self.pid,event.tid,json_args(&event.args))
+    }
+
+    fn name_json(&self, tid: u64, kind: &str, name: &str) -> String {
+        format!("{{\"name\":\"{}\",\"ph\":\"M\",\"pid\":{},\"tid\":{},\"args\":{{\"name\":{}}}}}",
+            kind,self.pid,tid,json_string(name))
+    }
+
+    fn to_json(&self) -> String {
+        let mut out = vec![
+            self.name_json(0,"process_name",&format!("commander executor {}",self.pid)),
+            self.name_json(0,"thread_name","executor")
+        ];
+        let mut names = self.names.iter().collect::<Vec<_>>();
+        names.sort();
+        for (tid,name) in names {
+            out.push(self.name_json(*tid,"thread_name",name));
+        }
+        for event in &self.events {
+            out.push(self.event_json(event));
+        }
+        format!("{{\"traceEvents\":[{}],\"displayTimeUnit\":\"ms\"}}",out.join(",\n"))
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Tracer(Arc<Mutex<TracerState>>);
+
+impl Tracer {
+    pub(crate) fn new(integration: &ReenteringIntegration, pid: u64, us_per_unit: f64, max_events: usize) -> Tracer {
+        Tracer(Arc::new(Mutex::new(TracerState {
+            integration: integration.clone(),
+            pid, us_per_unit, max_events,
+            events: VecDeque::new(),
+            names: HashMap::new(),
+            tid_events: HashMap::new(),
+            blocked: HashMap::new(),
+            locked: HashMap::new()
+        })))
+    }
+
+    pub(crate) fn now(&self) -> f64 { self.0.lock().unwrap().now() }
+
+    pub(crate) fn spawn(&self, summary: &TaskSummary, priority: u8) {
+        let mut state = self.0.lock().unwrap();
+        state.names.insert(summary.identity(),summary.get_name().to_string());
+        state.instant(summary.identity(),"spawn",vec![("priority".to_string(),priority.to_string())]);
+    }
+
+    /* Slot was occupied, so the task never got an identity */
+    pub(crate) fn refuse(&self, name: &str) {
+        let args = vec![
+            ("task".to_string(),name.to_string()),
+            ("reason".to_string(),KillReason::NotNeeded.to_string())
+        ];
+        self.0.lock().unwrap().instant(0,"kill",args);
+    }
+
+    pub(crate) fn poll(&self, summary: &TaskSummary, start: f64) {
+        let mut state = self.0.lock().unwrap();
+        state.names.insert(summary.identity(),summary.get_name().to_string());
+        state.add(summary.identity(),"poll",'X',start,vec![]);
+    }
+
+    pub(crate) fn block(&self, summary: &TaskSummary) {
+        let mut state = self.0.lock().unwrap();
+        let now = state.now();
+        state.blocked.entry(summary.identity()).or_insert_with(|| (now,summary.get_waits().clone()));
+    }
+
+    pub(crate) fn unblock(&self, tid: u64) {
+        self.0.lock().unwrap().end_block(tid);
+    }
+
+    pub(crate) fn timer(&self, tid: u64) {
+        self.0.lock().unwrap().instant(tid,"timer",vec![]);
+    }
+
+    pub(crate) fn lock(&self, tid: u64, lock: u64, start: f64) {
+        let mut state = self.0.lock().unwrap();
+        let now = state.now();
+        state.locked.insert(lock,(tid,now));
+        state.add(tid,"lock",'X',start,vec![("lock".to_string(),lock.to_string())]);
+    }
+
+    pub(crate) fn unlock(&self, lock: u64) {
+        let mut state = self.0.lock().unwrap();
+        if let Some((tid,start)) = state.locked.remove(&lock) {
+            state.add(tid,"locked",'X',start,vec![("lock".to_string(),lock.to_string())]);
+        }
+    }
+
+    pub(crate) fn finish(&self, tid: u64, reason: Option<KillReason>) {
+        let mut state = self.0.lock().unwrap();
+        state.end_block(tid);
+        match reason {
+            Some(reason) => state.instant(tid,"kill",vec![("reason".to_string(),reason.to_string())]),
+            None => state.instant(tid,"finish",vec![])
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String { self.0.lock().unwrap().to_json() }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::executor::executor::Executor;
+    use crate::integration::testintegration::TestIntegration;
+    use crate::task::runconfig::RunConfig;
+    use crate::task::task::KillReason;
+    use crate::task::taskhandle::ExecutorTaskHandle;
+    use super::*;
+
+    #[test]
+    pub fn test_json_string() {
+        assert_eq!("\"a\\\"b\\\\c\\nd\\u0001\"",json_string("a\"b\\c\nd\u{1}"));
+    }
+
+    #[test]
+    pub fn test_trace() {
+        let mut integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        x.start_trace(1000.,100);
+        let cfg = RunConfig::new(None,3,None);
+        let agent = x.new_agent(&cfg,"traced \"task\"");
+        let agent2 = agent.clone();
+        let step = async move {
+            let agent3 = agent2.clone();
+            agent2.named_wait(async move {
+                agent3.timer(1.).await;
+            },"sleepy").await;
+        };
+        let handle = x.add(step,agent);
+        let agent = x.new_agent(&cfg,"doomed");
+        let agent2 = agent.clone();
+        let doomed = x.add(async move { agent2.tick(10).await; },agent);
+        x.tick(1.);
+        doomed.kill(KillReason::Cancelled);
+        integration.set_time(2.);
+        x.tick(1.);
+        x.tick(1.);
+        assert!(handle.take_result().is_some());
+        let json = x.trace_json().unwrap();
+        assert!(json.starts_with("{\"traceEvents\":["));
+        assert!(json.contains("\"args\":{\"name\":\"traced \\\"task\\\"\"}"));
+        assert!(json.contains("\"name\":\"blocked\",\"cat\":\"commander\",\"ph\":\"X\",\"ts\":0,\"dur\":2000"));
+        assert!(json.contains("\"args\":{\"waits\":\"sleepy\"}"));
+        assert!(json.contains("\"name\":\"timer\""));
+        assert!(json.contains("\"name\":\"finish\""));
+        assert!(json.contains("\"args\":{\"reason\":\"cancelled\"}"));
+        x.stop_trace();
+        assert!(x.trace_json().is_none());
+    }
+
+    #[test]
+    pub fn test_trace_limit() {
+        let integration = TestIntegration::new();
+        let tracer = Tracer::new(&ReenteringIntegration::new(integration),1,1.,2);
+        tracer.timer(1);
+        tracer.timer(2);
+        tracer.timer(3);
+        let json = tracer.to_json();
+        assert!(!json.contains("\"tid\":1,"));
+        assert!(json.contains("\"tid\":2,"));
+        assert!(json.contains("\"tid\":3,"));
+    }
+
+    #[test]
+    pub fn test_trace_names_pruned() {
+        let mut integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        x.start_trace(1.,4);
+        let cfg = RunConfig::new(None,3,None);
+        for i in 0..5 {
+            let agent = x.new_agent(&cfg,&format!("task{}",i));
+            x.add(async {},agent);
+            integration.set_time(i as f64);
+            x.tick(1.);
+        }
+        let json = x.trace_json().unwrap();
+        assert!(!json.contains("\"task0\""));
+        assert!(!json.contains("\"task2\""));
+        /* task4's spawn, poll and finish and task3's finish */
+        assert!(json.contains("\"task3\""));
+        assert!(json.contains("\"task4\""));
+        assert_eq!(3,json.matches("\"thread_name\"").count());
+    }
+}
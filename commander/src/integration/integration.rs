@@ -33,4 +33,13 @@ pub trait Integration : Send {
     /// 
     /// See `SleepQuantity` for details.
     fn sleep(&self, amount: SleepQuantity);
+    /// A priority class has had runnable tasks but has not run for the given number of ticks.
+    /// 
+    /// Only called if enabled with `Executor::set_starvation_ticks()`. Does nothing by default.
+    fn starved(&self, _priority: u8, _ticks: u64) {}
+    /// Seed for choosing which of the runnable tasks of equal priority runs next.
+    /// 
+    /// If `None` (the default) tasks of equal priority run round-robin. If given, they run in a pseudo-random order
+    /// determined entirely by the seed, for use in simulations. Only called when the executor is created.
+    fn scheduling_seed(&self) -> Option<u64> { None }
 }
@@ -0,0 +1,203 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{ Arc, Condvar, Mutex, MutexGuard };
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant };
+use crate::executor::executor::Executor;
+use crate::integration::integration::{ Integration, SleepQuantity };
+
+/* NativeIntegration and NativeExecutor run an executor natively on a dedicated thread, really
+ * sleeping between ticks, rather than being polled by a browser.
+ *
+ * Tasks are not Send, so the Executor and everything which adds tasks to it directly must live
+ * on that thread. Other threads get at it by sending closures with run() or call() which are
+ * run on the executor thread between ticks. Once running, tasks can be woken from any thread
+ * (eg by satisfying a PromiseFuture from a WorkerPool) as wakes reach us as calls to sleep()
+ * which interrupt any wait.
+ *
+ * Time is in milliseconds since the integration was created, as in the browser.
+ */
+
+type Command = Box<dyn FnOnce(&Rc<RefCell<Executor>>) + Send>;
+
+struct NativeState {
+    sleep: SleepQuantity,
+    since: Instant,
+    commands: VecDeque<Command>,
+    stopping: bool
+}
+
+/// Integration for running an executor natively in its own thread.
+///
+/// Usually used via `NativeExecutor`, which owns the thread. Time is in milliseconds.
+#[derive(Clone)]
+pub struct NativeIntegration {
+    state: Arc<(Mutex<NativeState>,Condvar)>,
+    start: Instant
+}
+
+impl NativeIntegration {
+    pub fn new() -> NativeIntegration {
+        let now = Instant::now();
+        NativeIntegration {
+            state: Arc::new((Mutex::new(NativeState {
+                sleep: SleepQuantity::None,
+                since: now,
+                commands: VecDeque::new(),
+                stopping: false
+            }),Condvar::new())),
+            start: now
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_,NativeState> { self.state.0.lock().unwrap() }
+    fn notify(&self) { self.state.1.notify_all(); }
+
+    fn add_command(&self, command: Command) {
+        self.lock().commands.push_back(command);
+        self.notify();
+    }
+
+    fn take_commands(&self) -> Vec<Command> { self.lock().commands.drain(..).collect() }
+
+    fn stop(&self) {
+        self.lock().stopping = true;
+        self.notify();
+    }
+
+    /// Block until the executor wants to be ticked, there are commands to run, or we are stopping.
+    ///
+    /// Returns false if stopping.
+    pub fn wait(&self) -> bool {
+        let mut state = self.lock();
+        loop {
+            if state.stopping { return false; }
+            if !state.commands.is_empty() { return true; }
+            state = match state.sleep {
+                SleepQuantity::None | SleepQuantity::Yesterday => { return true; },
+                SleepQuantity::Forever => self.state.1.wait(state).unwrap(),
+                SleepQuantity::Time(t) => {
+                    let deadline = state.since + Duration::from_secs_f64(t.max(0.) / 1000.);
+                    let now = Instant::now();
+                    if now >= deadline { return true; }
+                    self.state.1.wait_timeout(state,deadline-now).unwrap().0
+                }
+            };
+        }
+    }
+}
+
+impl Default for NativeIntegration {
+    fn default() -> Self { Self::new() }
+}
+
+impl Integration for NativeIntegration {
+    fn current_time(&self) -> f64 { self.start.elapsed().as_secs_f64() * 1000. }
+
+    fn sleep(&self, amount: SleepQuantity) {
+        let mut state = self.lock();
+        state.sleep = amount;
+        state.since = Instant::now();
+        drop(state);
+        self.notify();
+    }
+}
+
+/// An executor ticked by its own thread which sleeps when there is nothing to do.
+///
+/// As tasks are not `Send`, anything which needs the `Executor` itself is passed as a closure to `run()` or `call()`
+/// to be run on the executor thread. The thread stops when this is dropped, abandoning any unfinished tasks.
+pub struct NativeExecutor {
+    integration: NativeIntegration,
+    thread: Option<JoinHandle<()>>
+}
+
+impl NativeExecutor {
+    /// Start an executor thread with ticks of at most `slice` milliseconds.
+    ///
+    /// `setup` is run on the thread to configure the new executor (priority budgets, etc) before the first tick.
+    pub fn new<F>(slice: f64, setup: F) -> NativeExecutor where F: FnOnce(&mut Executor) + Send + 'static {
+        let integration = NativeIntegration::new();
+        let integration2 = integration.clone();
+        let thread = thread::spawn(move || {
+            let mut executor = Executor::new(integration2.clone());
+            setup(&mut executor);
+            let executor = Rc::new(RefCell::new(executor));
+            while integration2.wait() {
+                for command in integration2.take_commands() {
+                    command(&executor);
+                }
+                executor.borrow_mut().tick(slice);
+            }
+        });
+        NativeExecutor { integration, thread: Some(thread) }
+    }
+
+    /// Run a closure on the executor thread between ticks, eg to add tasks.
+    pub fn run<F>(&self, cb: F) where F: FnOnce(&Rc<RefCell<Executor>>) + Send + 'static {
+        self.integration.add_command(Box::new(cb));
+    }
+
+    /// Run a closure on the executor thread between ticks and wait for its result.
+    ///
+    /// Never call from the executor thread itself (eg from within a task) as it would wait forever.
+    pub fn call<F,R>(&self, cb: F) -> R where F: FnOnce(&Rc<RefCell<Executor>>) -> R + Send + 'static, R: Send + 'static {
+        let result = Arc::new((Mutex::new(None),Condvar::new()));
+        let result2 = result.clone();
+        self.run(move |executor| {
+            *result2.0.lock().unwrap() = Some(cb(executor));
+            result2.1.notify_all();
+        });
+        let mut value = result.0.lock().unwrap();
+        loop {
+            if let Some(value) = value.take() { return value; }
+            value = result.1.wait(value).unwrap();
+        }
+    }
+
+    pub fn integration(&self) -> &NativeIntegration { &self.integration }
+}
+
+impl Drop for NativeExecutor {
+    fn drop(&mut self) {
+        self.integration.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc::channel;
+    use crate::agent::taskrun::cdr_current_time;
+    use crate::task::runconfig::RunConfig;
+    use super::*;
+
+    #[test]
+    pub fn test_native_timer() {
+        let native = NativeExecutor::new(10.,|_| {});
+        let (tx,rx) = channel();
+        native.run(move |executor| {
+            let mut executor = executor.borrow_mut();
+            let agent = executor.new_agent(&RunConfig::new(None,3,None),"sleepy");
+            let agent2 = agent.clone();
+            executor.add(async move {
+                let start = cdr_current_time();
+                agent2.timer(20.).await;
+                tx.send(cdr_current_time()-start).ok();
+            },agent);
+        });
+        let elapsed = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(elapsed >= 20.);
+    }
+
+    #[test]
+    pub fn test_native_call() {
+        let native = NativeExecutor::new(10.,|executor| { executor.set_starvation_ticks(None); });
+        let identity = native.call(|executor| executor.borrow().identity());
+        assert_eq!(identity,native.call(|executor| executor.borrow().identity()));
+        drop(native);
+    }
+}
@@ -28,6 +28,14 @@ impl ReenteringIntegration {
         self.integration.current_time()
     }
 
+    pub(crate) fn scheduling_seed(&self) -> Option<u64> {
+        self.integration.scheduling_seed()
+    }
+
+    pub(crate) fn starved(&self, priority: u8, ticks: u64) {
+        self.integration.starved(priority,ticks);
+    }
+
     pub(crate) fn sleep(&self, amount: SleepQuantity) {
         let yesterday = self.yesterday.lock().unwrap();
         if !*yesterday {
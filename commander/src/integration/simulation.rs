@@ -0,0 +1,193 @@
+use std::any::Any;
+use std::ops::Range;
+use std::panic::{ catch_unwind, AssertUnwindSafe };
+use std::sync::{ Arc, Mutex };
+use crate::executor::executor::Executor;
+use crate::integration::integration::{ Integration, SleepQuantity };
+
+/* SimulationIntegration and Simulation run an executor deterministically, for
+ * reproducing and hunting ordering bugs. Time is virtual: it only moves when
+ * the simulation moves it, between ticks. Tasks of equal priority run in an
+ * order chosen from the seed rather than round-robin, so different seeds
+ * explore different interleavings and the same seed always gives the same one.
+ *
+ * After each tick the clock moves on as the executor asks: by the sleep time
+ * if it gave one (so timers fire without spinning through ticks) or by a fixed
+ * tick length if it wants to be called again. Because time does not pass within
+ * a tick, slices never run out and a task which never yields or blocks will
+ * never return control; tasks must tick() or await something.
+ *
+ * simulate_seeds() runs one scenario under many seeds and collects those which
+ * fail, with why.
+ */
+
+struct SimulationState {
+    time: f64,
+    sleep: SleepQuantity
+}
+
+/// An integration with a virtual clock and a seeded task order, for deterministic simulation.
+///
+/// Usually used via `Simulation`, which advances the clock.
+#[derive(Clone)]
+pub struct SimulationIntegration {
+    state: Arc<Mutex<SimulationState>>,
+    seed: u64
+}
+
+impl SimulationIntegration {
+    pub fn new(seed: u64) -> SimulationIntegration {
+        SimulationIntegration {
+            state: Arc::new(Mutex::new(SimulationState { time: 0., sleep: SleepQuantity::None })),
+            seed
+        }
+    }
+
+    pub fn seed(&self) -> u64 { self.seed }
+    pub fn get_time(&self) -> f64 { self.state.lock().unwrap().time }
+    pub fn set_time(&self, time: f64) { self.state.lock().unwrap().time = time; }
+    pub fn advance(&self, amount: f64) { self.state.lock().unwrap().time += amount; }
+
+    /// Most recent sleep requested by the executor.
+    pub fn get_sleep(&self) -> SleepQuantity { self.state.lock().unwrap().sleep.clone() }
+}
+
+impl Integration for SimulationIntegration {
+    fn current_time(&self) -> f64 { self.state.lock().unwrap().time }
+    fn sleep(&self, quantity: SleepQuantity) { self.state.lock().unwrap().sleep = quantity; }
+    fn scheduling_seed(&self) -> Option<u64> { Some(self.seed) }
+}
+
+/// An executor running in virtual time with a seeded task order.
+pub struct Simulation {
+    executor: Executor,
+    integration: SimulationIntegration,
+    tick_length: f64
+}
+
+impl Simulation {
+    /// Create a simulation where a tick with nothing to wait for takes one unit of virtual time.
+    pub fn new(seed: u64) -> Simulation {
+        let integration = SimulationIntegration::new(seed);
+        Simulation {
+            executor: Executor::new(integration.clone()),
+            integration,
+            tick_length: 1.
+        }
+    }
+
+    /// Virtual time which passes for a tick after which the executor wants to be called again.
+    pub fn set_tick_length(&mut self, tick_length: f64) { self.tick_length = tick_length; }
+
+    pub fn executor(&mut self) -> &mut Executor { &mut self.executor }
+    pub fn integration(&self) -> &SimulationIntegration { &self.integration }
+    pub fn seed(&self) -> u64 { self.integration.seed() }
+
+    /// Run one tick and advance the clock. Returns false if the executor is idle.
+    pub fn tick(&mut self) -> bool {
+        self.executor.tick(f64::INFINITY);
+        match self.integration.get_sleep() {
+            SleepQuantity::Forever => false,
+            SleepQuantity::Time(t) => { self.integration.advance(if t > 0. { t } else { self.tick_length }); true },
+            SleepQuantity::None | SleepQuantity::Yesterday => { self.integration.advance(self.tick_length); true }
+        }
+    }
+
+    /// Tick until the executor is idle or `max_ticks` have run. Returns true if idle.
+    pub fn run(&mut self, max_ticks: u64) -> bool {
+        for _ in 0..max_ticks {
+            if !self.tick() { return true; }
+        }
+        false
+    }
+}
+
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panic".to_string()
+    }
+}
+
+/// Run a scenario in a fresh `Simulation` for each seed, returning the seeds for which it failed.
+///
+/// A scenario fails by returning an error or by panicking (eg with a failed assertion), either of which is reported
+/// along with the seed. Rerun a failing seed with `Simulation::new(seed)` to reproduce it.
+pub fn simulate_seeds<F>(seeds: Range<u64>, scenario: F) -> Vec<(u64,String)>
+        where F: Fn(&mut Simulation) -> Result<(),String> {
+    let mut failures = vec![];
+    for seed in seeds {
+        let mut simulation = Simulation::new(seed);
+        match catch_unwind(AssertUnwindSafe(|| scenario(&mut simulation))) {
+            Ok(Ok(())) => {},
+            Ok(Err(e)) => { failures.push((seed,e)); },
+            Err(payload) => { failures.push((seed,panic_message(payload))); }
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod test {
+    use crate::task::runconfig::RunConfig;
+    use crate::task::task::TaskResult;
+    use super::*;
+
+    fn run_order(simulation: &mut Simulation) -> Vec<u32> {
+        let order = Arc::new(Mutex::new(vec![]));
+        let cfg = RunConfig::new(None,3,None);
+        for i in 0..4 {
+            let agent = simulation.executor().new_agent(&cfg,"test");
+            let agent2 = agent.clone();
+            let order2 = order.clone();
+            simulation.executor().add(async move {
+                for _ in 0..3 {
+                    order2.lock().unwrap().push(i);
+                    agent2.tick(0).await;
+                }
+            },agent);
+        }
+        assert!(simulation.run(100));
+        let out = order.lock().unwrap().clone();
+        out
+    }
+
+    #[test]
+    pub fn test_simulation_order() {
+        let first = run_order(&mut Simulation::new(7));
+        assert_eq!(12,first.len());
+        assert_eq!(first,run_order(&mut Simulation::new(7)));
+        assert!((0..10).any(|seed| run_order(&mut Simulation::new(seed)) != first));
+    }
+
+    #[test]
+    pub fn test_simulation_clock() {
+        let mut simulation = Simulation::new(0);
+        let cfg = RunConfig::new(None,3,None);
+        let agent = simulation.executor().new_agent(&cfg,"test");
+        let agent2 = agent.clone();
+        let handle = simulation.executor().add(async move { agent2.timer(1000.).await; },agent);
+        assert!(simulation.run(5));
+        assert_eq!(TaskResult::Done,handle.task_state());
+        assert!(simulation.integration().get_time() >= 1000.);
+    }
+
+    #[test]
+    pub fn test_simulate_seeds() {
+        let failures = simulate_seeds(0..20,|simulation| {
+            let order = run_order(simulation);
+            if order[0] == 0 { Ok(()) } else { Err(format!("{} ran first",order[0])) }
+        });
+        assert!(!failures.is_empty());
+        assert!(failures.len() < 20);
+        for (seed,message) in &failures {
+            assert_ne!(0,run_order(&mut Simulation::new(*seed))[0]);
+            assert!(message.ends_with("ran first"));
+        }
+        let failures = simulate_seeds(0..3,|_| { panic!("oops") });
+        assert_eq!(vec![(0,"oops".to_string()),(1,"oops".to_string()),(2,"oops".to_string())],failures);
+    }
+}
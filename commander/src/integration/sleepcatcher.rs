@@ -28,6 +28,14 @@ impl SleepCatcherIntegration {
         self.integration.lock().unwrap().current_time()
     }
 
+    pub(super) fn scheduling_seed(&self) -> Option<u64> {
+        self.integration.lock().unwrap().scheduling_seed()
+    }
+
+    pub(super) fn starved(&self, priority: u8, ticks: u64) {
+        self.integration.lock().unwrap().starved(priority,ticks);
+    }
+
     pub(super) fn sleep(&self, amount: SleepQuantity) {
         let mut prev_sleep = self.prev_sleep.lock().unwrap();
         match amount {
@@ -1,10 +1,13 @@
 use std::sync::{ Arc, Mutex, MutexGuard };
+#[cfg(test)]
 use crate::cdr_tick;
-use crate::agent::agent::Agent;
 use crate::integration::integration::{ Integration, SleepQuantity };
 
 /* TestIntegration is the integration used in unit tests. The time can be set
- * and retrieved, and the current sleep setting retrieved.
+ * and retrieved, and the current sleep setting and any starvation reports
+ * retrieved. It is also exported
+ * for running tasks natively outside any real environment (eg test harnesses
+ * in other crates).
  * 
  * Additional odds-and-ends are also here such as a method to schedule a
  * range of ticks.
@@ -13,29 +16,38 @@ use crate::integration::integration::{ Integration, SleepQuantity };
 #[derive(Clone)]
 pub struct TestIntegration {
     timer: Arc<Mutex<f64>>,
-    sleeps: Arc<Mutex<Vec<SleepQuantity>>>
+    sleeps: Arc<Mutex<Vec<SleepQuantity>>>,
+    starvations: Arc<Mutex<Vec<(u8,u64)>>>
 }
 
 impl TestIntegration {
-    pub(crate) fn new() -> TestIntegration {
+    pub fn new() -> TestIntegration {
         TestIntegration {
             timer: Arc::new(Mutex::new(0.)),
-            sleeps: Arc::new(Mutex::new(vec![]))
+            sleeps: Arc::new(Mutex::new(vec![])),
+            starvations: Arc::new(Mutex::new(vec![]))
         }
     }
 
-    pub(crate) fn get_time(&self) -> f64 { *self.timer.lock().unwrap() }
-    pub(crate) fn set_time(&mut self, t: f64) { *self.timer.lock().unwrap() = t; }
+    pub fn get_time(&self) -> f64 { *self.timer.lock().unwrap() }
+    pub fn set_time(&mut self, t: f64) { *self.timer.lock().unwrap() = t; }
+
+    pub fn get_sleeps(&self) -> MutexGuard<'_,Vec<SleepQuantity>> { self.sleeps.lock().unwrap() }
+    pub fn get_starvations(&self) -> MutexGuard<'_,Vec<(u8,u64)>> { self.starvations.lock().unwrap() }
+}
 
-    pub(crate) fn get_sleeps(&self) -> MutexGuard<Vec<SleepQuantity>> { self.sleeps.lock().unwrap() }
+impl Default for TestIntegration {
+    fn default() -> Self { Self::new() }
 }
 
 impl Integration for TestIntegration {
     fn current_time(&self) -> f64 {*self.timer.lock().unwrap() }
     fn sleep(&self, quantity: SleepQuantity) { self.sleeps.lock().unwrap().push(quantity); }
+    fn starved(&self, priority: u8, ticks: u64) { self.starvations.lock().unwrap().push((priority,ticks)); }
 }
 
-pub async fn tick_helper(ticks: &[u64]) {
+#[cfg(test)]
+pub(crate) async fn tick_helper(ticks: &[u64]) {
     for tick in ticks {
         cdr_tick(*tick).await;
     }
@@ -47,6 +59,7 @@ mod test {
     use crate::executor::executor::Executor;
     use crate::task::runconfig::RunConfig;
     use crate::task::task::TaskResult;
+    use crate::agent::agent::Agent;
     use super::*;
 
     async fn tick_future(ctx: Agent,x: u32, finished: Option<Arc<Mutex<bool>>>, set: bool) -> u32 {
@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::{ catch_unwind, AssertUnwindSafe };
+use std::sync::{ Arc, Condvar, Mutex };
+use std::thread::{ self, JoinHandle };
+use crate::corefutures::promisefuture::PromiseFuture;
+use crate::integration::simulation::panic_message;
+
+/* A WorkerPool runs CPU-heavy closures on a fixed set of threads and returns their results as
+ * futures which tasks can await. Results come back by satisfying a PromiseFuture from the worker
+ * thread, which wakes the awaiting task through the executor's integration like any other
+ * wake. Only makes sense natively (eg alongside NativeExecutor).
+ *
+ * A closure which panics resolves to Err with the panic message rather than taking down its
+ * worker. On drop, queued jobs are finished before the workers are joined.
+ */
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct WorkerPoolState {
+    jobs: VecDeque<Job>,
+    stopping: bool
+}
+
+/// A pool of threads for running closures off the executor thread.
+pub struct WorkerPool {
+    state: Arc<(Mutex<WorkerPoolState>,Condvar)>,
+    threads: Vec<JoinHandle<()>>
+}
+
+fn worker(state: Arc<(Mutex<WorkerPoolState>,Condvar)>) {
+    loop {
+        let mut guard = state.0.lock().unwrap();
+        let job = loop {
+            if let Some(job) = guard.jobs.pop_front() { break job; }
+            if guard.stopping { return; }
+            guard = state.1.wait(guard).unwrap();
+        };
+        drop(guard);
+        job();
+    }
+}
+
+impl WorkerPool {
+    /// Create a pool with the given number of threads (at least one).
+    pub fn new(threads: usize) -> WorkerPool {
+        let state = Arc::new((Mutex::new(WorkerPoolState { jobs: VecDeque::new(), stopping: false }),Condvar::new()));
+        let threads = (0..threads.max(1)).map(|_| {
+            let state = state.clone();
+            thread::spawn(move || worker(state))
+        }).collect();
+        WorkerPool { state, threads }
+    }
+
+    /// Run `cb` on a worker thread, returning a future for its result.
+    ///
+    /// The result is `Err` with the panic message if `cb` panics.
+    pub fn run<F,T>(&self, cb: F) -> impl Future<Output=Result<T,String>> where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+        let promise = PromiseFuture::new();
+        let promise2 = promise.clone();
+        let job = Box::new(move || {
+            promise2.satisfy(catch_unwind(AssertUnwindSafe(cb)).map_err(panic_message));
+        });
+        self.state.0.lock().unwrap().jobs.push_back(job);
+        self.state.1.notify_one();
+        promise
+    }
+
+    /// Number of jobs waiting for a worker.
+    pub fn queued(&self) -> usize { self.state.0.lock().unwrap().jobs.len() }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.state.0.lock().unwrap().stopping = true;
+        self.state.1.notify_all();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+    use crate::integration::native::NativeExecutor;
+    use crate::task::runconfig::RunConfig;
+    use super::*;
+
+    #[test]
+    pub fn test_worker_pool() {
+        let pool = Arc::new(WorkerPool::new(2));
+        let native = NativeExecutor::new(10.,|_| {});
+        let (tx,rx) = channel();
+        let pool2 = pool.clone();
+        native.run(move |executor| {
+            let mut executor = executor.borrow_mut();
+            let agent = executor.new_agent(&RunConfig::new(None,3,None),"heavy");
+            executor.add(async move {
+                let sums = (0..4_u64).map(|i| pool2.run(move || (0..1000*i).sum::<u64>())).collect::<Vec<_>>();
+                let mut out = vec![];
+                for sum in sums {
+                    out.push(sum.await.unwrap());
+                }
+                let panicked = pool2.run(|| -> u64 { panic!("too heavy") }).await;
+                tx.send((out,panicked)).ok();
+            },agent);
+        });
+        let (sums,panicked) = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(vec![0,499500,1999000,4498500],sums);
+        assert_eq!(Err("too heavy".to_string()),panicked);
+    }
+}
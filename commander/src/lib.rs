@@ -27,6 +27,10 @@ representing time in whatever units are convenient for the integration. When the
 is also given as an argument. Through use of the integrated callback, the tick progresses futures until that time bound
 is busted.
 
+Outside the browser, `NativeExecutor` gives an executor its own thread which ticks it and really sleeps when the
+executor allows. Because tasks need not be `Send`, other threads hand it closures to run on that thread, to add tasks,
+etc. CPU-heavy work can be sent from tasks to a `WorkerPool` and the result awaited like any other future.
+
 ## Executor-Future Communication
 
 Furst futures allow only one interaction with a future: wakoing on sleep. Commander adds a much larger API through the
@@ -55,6 +59,10 @@ Finalisers ("tidiers") are auxilliary futures which may be created within some o
 future is waited upon and the tidier is transparent. However, after signal delivery, any unfinished tidiers are waited
 upon to completion before the future is discarded.
 
+Related tasks can be put in a `TaskGroup` to be signalled together. Cancelling a group sends `KillReason::Cancelled` to
+all its tasks, including those created from within them via `Agent::new_agent()`, and to its subgroups. The group also
+summarises the outcome of its tasks.
+
 ## Yielding, Ticks, and Timers
 
 There are two time-oriented concepts in commander: ticks and timers. For each, and Agent can provide a future to wait
@@ -72,6 +80,12 @@ Tasks have a single, fixed priority. Within a tick the tasks with the current hi
 Priorities are intended to separate broad classes of task -- real-time, interactive batch, etc -- not be a means of
 managing scheduler execution order or "fair share".
 
+Strict priority can starve low-priority classes when higher ones are busy, and a busy low-priority class can use up
+ticks which would be better handed back to the environment (eg for smooth rendering). To help, a priority class can be
+given a budget with `Executor::set_priority_budget()`: a fraction of each tick it is guaranteed and a fraction it is
+limited to. Classes which have been runnable but starved for a number of ticks can be reported to the integration via
+`Executor::set_starvation_ticks()`.
+
 ## Slots
 
 Tasks may be placed in slots. A slot is an object which may be created arbitrarily and passed to the executor at
@@ -109,6 +123,27 @@ Because some tasks may spend a long time waiting on some external resource, it c
 summaries. A future exist which wraps an internal, potentially slow future and takes a name. When a task is pending on a
 future inside this wrapper, the name of this wait is included in summary information, for diagnostic purposes.
 
+## Synchronisation
+
+As well as executor-managed `Lock`s and the unbounded `CommanderStream`, there are a bounded `CommanderChannel` whose
+senders wait while it is full, a counting `Semaphore` for capping concurrency (eg requests in flight to a backend), and
+a `ReadWriteLock`. Each is given a name on creation and a task waiting on one has that name among its named waits, so
+queueing shows up in summaries. Waiters are served in the order they arrived.
+
+## Tracing
+
+Summaries only say what is happening now. For a timeline, an executor can record a trace: task spawns, polls, blocks
+(with any named waits), lock acquisition, timers firing, and kills. `Executor::trace_json()` exports this in the Chrome
+trace-event format for loading into a trace viewer, where each task appears as a thread. Tracing is off unless started
+with `Executor::start_trace()` and only a fixed number of the most recent events are kept.
+
+## Simulation
+
+Bugs which depend on the order tasks happen to run in are hard to reproduce. A `Simulation` runs an executor with a
+`SimulationIntegration`, which has a virtual clock and a seed. Tasks of equal priority run in an order chosen from the
+seed, and between ticks the clock jumps straight to the next timer. The same seed always gives the same run.
+`simulate_seeds()` runs a scenario under many seeds and reports which failed, so that one can be rerun and debugged.
+
 # Usage
 
 ## Creating an Executor
@@ -228,6 +263,7 @@ mod executor {
   mod runqueue;
   mod timerset;
   mod timings;
+  mod tracer;
 }
 
 mod corefutures {
@@ -238,18 +274,24 @@ mod corefutures {
 }
 
 mod derivedfutures {
+  pub(crate) mod channel;
   pub(crate) mod commanderstream;
   pub(crate) mod fuse;
+  pub(crate) mod rwlock;
+  pub(crate) mod semaphore;
   pub(crate) mod sendfuse;
 }
 
 mod integration {
     pub(crate) mod integration;
+    #[cfg(not(target_arch="wasm32"))]
+    pub(crate) mod native;
     pub(crate) mod reentering;
+    pub(crate) mod simulation;
     mod sleepcatcher;
-
-    #[cfg(test)]
     pub(crate) mod testintegration;
+    #[cfg(not(target_arch="wasm32"))]
+    pub(crate) mod workerpool;
 }
 
 mod task {
@@ -257,6 +299,7 @@ mod task {
     pub(crate) mod runconfig;
     pub(crate) mod slot;
     pub(crate) mod task;
+    pub(crate) mod taskgroup;
     pub(crate) mod taskhandle;
 
     #[cfg(test)]
@@ -287,11 +330,21 @@ pub use crate::agent::taskrun::{
 pub use crate::executor::executor::Executor;
 pub use crate::executor::lock::{ Lock, LockGuard };
 pub use crate::corefutures::promisefuture::PromiseFuture;
+pub use crate::derivedfutures::channel::CommanderChannel;
 pub use crate::derivedfutures::commanderstream::CommanderStream;
 pub use crate::derivedfutures::fuse::FusePromise;
+pub use crate::derivedfutures::rwlock::{ ReadWriteLock, ReadGuard, WriteGuard };
+pub use crate::derivedfutures::semaphore::{ Semaphore, SemaphorePermit };
 pub use crate::derivedfutures::sendfuse::SendFusePromise;
 pub use crate::integration::integration::{ Integration, SleepQuantity };
+#[cfg(not(target_arch="wasm32"))]
+pub use crate::integration::native::{ NativeExecutor, NativeIntegration };
+pub use crate::integration::simulation::{ Simulation, SimulationIntegration, simulate_seeds };
+pub use crate::integration::testintegration::TestIntegration;
+#[cfg(not(target_arch="wasm32"))]
+pub use crate::integration::workerpool::WorkerPool;
 pub use crate::task::runconfig::RunConfig;
 pub use crate::task::slot::RunSlot;
 pub use crate::task::task::{ KillReason, TaskResult, TaskSummary };
+pub use crate::task::taskgroup::TaskGroup;
 pub use crate::task::taskhandle::TaskHandle;
@@ -29,6 +29,7 @@ impl ExecutorTaskHandle for FakeTask {
     fn summarize(&self) -> Option<TaskSummary> { None }
     fn evict(&self) {}
     fn kill(&self, _reason: KillReason) {}
+    fn kill_reason(&self) -> Option<KillReason> { None }
     fn set_identity(&self, _identity: u64) {}
     fn identity(&self) -> u64 { 0 }
 }
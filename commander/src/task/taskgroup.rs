@@ -0,0 +1,218 @@
+use hashbrown::HashMap;
+use std::future::Future;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::corefutures::promisefuture::PromiseFuture;
+use super::task::{ KillReason, TaskResult };
+
+/* A TaskGroup collects related tasks so that they can be cancelled together and their outcomes
+ * examined together. Tasks join with Agent::join_group() before submission. Agents created by a
+ * member with new_agent() join all of its groups too, so a group contains all the descendants of
+ * the tasks which joined it. Subgroups are cancelled with their parent but not vice versa.
+ *
+ * The group holds a callback for each ongoing member to finish it on cancellation. These hold
+ * the agent weakly so that there is no cycle between the group and its members. Members report
+ * back when they finish (see Agent::leave_groups()).
+ */
+
+type Finisher = Box<dyn Fn(KillReason)>;
+
+struct TaskGroupState {
+    members: HashMap<u64,Finisher>,
+    children: Vec<TaskGroup>,
+    cancelled: bool,
+    done: usize,
+    killed: Vec<KillReason>,
+    waiting: Vec<PromiseFuture<()>>
+}
+
+/// A group of tasks which can be cancelled together.
+///
+/// Tasks join a group with `Agent::join_group()` and any tasks which they create with `Agent::new_agent()` are members
+/// too. See the crate-level documentation for details.
+#[derive(Clone)]
+pub struct TaskGroup(Rc<RefCell<TaskGroupState>>);
+
+impl TaskGroup {
+    pub(crate) fn new() -> TaskGroup {
+        TaskGroup(Rc::new(RefCell::new(TaskGroupState {
+            members: HashMap::new(),
+            children: vec![],
+            cancelled: false,
+            done: 0,
+            killed: vec![],
+            waiting: vec![]
+        })))
+    }
+
+    /// Create a subgroup of this group. Cancelling this group also cancels the subgroup.
+    pub fn new_group(&self) -> TaskGroup {
+        let child = TaskGroup::new();
+        let mut state = self.0.borrow_mut();
+        if state.cancelled {
+            child.cancel();
+        }
+        state.children.push(child.clone());
+        child
+    }
+
+    pub(crate) fn add<F>(&self, key: u64, finisher: F) where F: Fn(KillReason) + 'static {
+        let mut state = self.0.borrow_mut();
+        if state.cancelled {
+            finisher(KillReason::Cancelled);
+        }
+        state.members.insert(key,Box::new(finisher));
+    }
+
+    pub(crate) fn complete(&self, key: u64, reason: Option<KillReason>) {
+        let mut state = self.0.borrow_mut();
+        if state.members.remove(&key).is_none() { return; }
+        if let Some(reason) = reason {
+            state.killed.push(reason);
+        } else {
+            state.done += 1;
+        }
+        if state.members.is_empty() {
+            for promise in state.waiting.drain(..) {
+                promise.satisfy(());
+            }
+        }
+    }
+
+    /// Cancel all ongoing tasks in this group and its subgroups and any which later join.
+    ///
+    /// Each task is sent `KillReason::Cancelled`.
+    pub fn cancel(&self) {
+        let mut state = self.0.borrow_mut();
+        if state.cancelled { return; }
+        state.cancelled = true;
+        for finisher in state.members.values() {
+            finisher(KillReason::Cancelled);
+        }
+        let children = state.children.clone();
+        drop(state);
+        for child in children {
+            child.cancel();
+        }
+    }
+
+    /// Has this group been cancelled?
+    pub fn cancelled(&self) -> bool { self.0.borrow().cancelled }
+
+    /// Number of tasks in this group which have not yet finished.
+    pub fn ongoing(&self) -> usize { self.0.borrow().members.len() }
+
+    /// Number of tasks in this group which finished normally.
+    pub fn done(&self) -> usize { self.0.borrow().done }
+
+    /// Reasons for each task in this group which was killed.
+    pub fn killed(&self) -> Vec<KillReason> { self.0.borrow().killed.clone() }
+
+    /// Overall state of the tasks in this group.
+    ///
+    /// `Ongoing` while any task is unfinished, otherwise `Killed` with the reason of the first killed task, if any,
+    /// otherwise `Done`. Only direct members count, not those of subgroups.
+    pub fn result(&self) -> TaskResult {
+        let state = self.0.borrow();
+        if !state.members.is_empty() {
+            TaskResult::Ongoing
+        } else if let Some(reason) = state.killed.first() {
+            TaskResult::Killed(reason.clone())
+        } else {
+            TaskResult::Done
+        }
+    }
+
+    /// Returns a future which will be ready when no task in this group is ongoing.
+    pub fn finish_future(&self) -> impl Future<Output=()> {
+        let promise = PromiseFuture::new();
+        let mut state = self.0.borrow_mut();
+        if state.members.is_empty() {
+            promise.satisfy(());
+        } else {
+            state.waiting.push(promise.clone());
+        }
+        promise
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::executor::executor::Executor;
+    use crate::integration::testintegration::TestIntegration;
+    use crate::task::runconfig::RunConfig;
+    use crate::task::task::{ KillReason, TaskResult };
+
+    #[test]
+    pub fn test_group_done() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let group = x.new_group();
+        for i in 0..3 {
+            let agent = x.new_agent(&cfg,"test");
+            agent.join_group(&group);
+            let agent2 = agent.clone();
+            x.add(async move { agent2.tick(i).await; },agent);
+        }
+        let agent = x.new_agent(&cfg,"waiter");
+        let group2 = group.clone();
+        let waiter = x.add(async move { group2.finish_future().await; },agent);
+        assert_eq!(3,group.ongoing());
+        x.tick(1.);
+        assert_eq!(2,group.ongoing());
+        assert_eq!(TaskResult::Ongoing,group.result());
+        x.tick(1.);
+        x.tick(1.);
+        assert_eq!(TaskResult::Done,group.result());
+        assert_eq!(3,group.done());
+        x.tick(1.);
+        assert_eq!(TaskResult::Done,waiter.task_state());
+    }
+
+    #[test]
+    pub fn test_group_cancel() {
+        let integration = TestIntegration::new();
+        let mut x = Executor::new(integration.clone());
+        let cfg = RunConfig::new(None,3,None);
+        let group = x.new_group();
+        let subgroup = group.new_group();
+        let spawned = Rc::new(RefCell::new(None));
+        let spawned2 = spawned.clone();
+        let agent = x.new_agent(&cfg,"parent");
+        agent.join_group(&group);
+        let agent2 = agent.clone();
+        let parent = x.add(async move {
+            let child = agent2.new_agent(None,"child");
+            let child2 = child.clone();
+            *spawned2.borrow_mut() = Some(agent2.add(async move { child2.tick(10).await; },child));
+            agent2.tick(10).await;
+        },agent);
+        let other = x.new_agent(&cfg,"other");
+        other.join_group(&subgroup);
+        let other2 = other.clone();
+        let other = x.add(async move { other2.tick(10).await; },other);
+        x.tick(1.);
+        assert_eq!(2,group.ongoing());
+        group.cancel();
+        x.tick(1.);
+        x.tick(1.);
+        let child = spawned.borrow_mut().take().unwrap();
+        assert_eq!(TaskResult::Killed(KillReason::Cancelled),parent.task_state());
+        assert_eq!(TaskResult::Killed(KillReason::Cancelled),child.task_state());
+        assert_eq!(TaskResult::Killed(KillReason::Cancelled),other.task_state());
+        assert_eq!(TaskResult::Killed(KillReason::Cancelled),group.result());
+        assert_eq!(vec![KillReason::Cancelled,KillReason::Cancelled],group.killed());
+        assert_eq!(TaskResult::Killed(KillReason::Cancelled),subgroup.result());
+        /* late joiners are cancelled too */
+        let late = x.new_agent(&cfg,"late");
+        late.join_group(&subgroup);
+        let late2 = late.clone();
+        let late = x.add(async move { late2.tick(1).await; },late);
+        assert_eq!(TaskResult::Killed(KillReason::Cancelled),late.task_state());
+        assert_eq!(0,subgroup.ongoing());
+        assert_eq!(2,subgroup.killed().len());
+    }
+}
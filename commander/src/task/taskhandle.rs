@@ -28,6 +28,7 @@ pub(crate) trait ExecutorTaskHandle {
     fn get_priority(&self) -> u8;
     fn summarize(&self) -> Option<TaskSummary>;
     fn kill(&self, reason: KillReason);
+    fn kill_reason(&self) -> Option<KillReason>;
     fn set_identity(&self, identity: u64);
     fn identity(&self) -> u64;
 }
@@ -188,6 +189,10 @@ impl<R> ExecutorTaskHandle for TaskHandle<R> where R: 'static {
         self.get_agent().finish(reason);
     }
 
+    fn kill_reason(&self) -> Option<KillReason> {
+        self.get_agent().finish_agent().kill_reason()
+    }
+
     fn set_identity(&self, identity: u64) {
         self.0.lock().unwrap().identity = Some(identity);
     }
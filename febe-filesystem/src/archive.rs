@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::sync::{ Arc, Mutex };
+use peregrine_data::{ BackendNamespace, MaxiRequest };
+use peregrine_toolkit::error::Error;
+use peregrine_toolkit::lock;
+use serde_cbor::Value;
+use peregrine_mock_backend::FixtureSource;
+use crate::filechannel::key;
+use crate::fixturepath::{ archive_key, archive_value_key };
+
+/* A session archive holds everything needed to replay a session's backend traffic: which channel
+ * names were claimed (and with what backend namespace) and a fixture for each request answered. The
+ * fixtures have the same format as those used by the filesystem channel and are keyed by backend
+ * namespace and then the fixture path.
+ *
+ * As it's all in memory it works in the browser. to_bytes() gives a single CBOR blob to be sent
+ * with a bug report, from_bytes() recreates the archive for replay.
+ */
+
+struct SessionArchiveData {
+    channels: BTreeMap<String,Option<BackendNamespace>>,
+    fixtures: BTreeMap<String,Vec<u8>>
+}
+
+#[derive(Clone)]
+pub struct SessionArchive(Arc<Mutex<SessionArchiveData>>);
+
+fn list(packet: &BTreeMap<Value,Value>, name: &str) -> Option<Value> {
+    match packet.get(&key(name)) {
+        Some(Value::Array(values)) if values.len() > 0 => Some(Value::Array(values.clone())),
+        _ => None
+    }
+}
+
+impl SessionArchive {
+    pub fn new() -> SessionArchive {
+        SessionArchive(Arc::new(Mutex::new(SessionArchiveData {
+            channels: BTreeMap::new(),
+            fixtures: BTreeMap::new()
+        })))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SessionArchive,Error> {
+        let (channels,fixtures) : (BTreeMap<String,Option<BackendNamespace>>,BTreeMap<String,Value>) =
+            Error::oper_r(serde_cbor::from_slice(bytes),"corrupt session archive")?;
+        let fixtures = fixtures.into_iter().map(|(k,v)| {
+            match v {
+                Value::Bytes(bytes) => Ok((k,bytes)),
+                _ => Err(Error::operr("corrupt session archive: fixture not bytes"))
+            }
+        }).collect::<Result<_,_>>()?;
+        Ok(SessionArchive(Arc::new(Mutex::new(SessionArchiveData { channels, fixtures }))))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>,Error> {
+        let data = lock!(self.0);
+        let fixtures = data.fixtures.iter().map(|(k,v)| (k.clone(),Value::Bytes(v.clone()))).collect::<BTreeMap<_,_>>();
+        Error::oper_r(serde_cbor::to_vec(&(&data.channels,fixtures)),"cannot serialize session archive")
+    }
+
+    pub(crate) fn add_channel(&self, name: &str, namespace: &Option<BackendNamespace>) {
+        lock!(self.0).channels.insert(name.to_string(),namespace.clone());
+    }
+
+    /* None if the channel was never claimed in the recorded session */
+    pub(crate) fn channel(&self, name: &str) -> Option<Option<BackendNamespace>> {
+        lock!(self.0).channels.get(name).cloned()
+    }
+
+    /* Splits a raw response packet into per-request fixtures. Each fixture carries all the programs,
+     * etc, of its packet as we cannot know which request they were sent in response to.
+     */
+    pub(crate) fn record(&self, request: &MaxiRequest, packet: &[u8]) -> Result<(),Error> {
+        let packet = match Error::oper_r(serde_cbor::from_slice(packet),"corrupt packet")? {
+            Value::Map(map) => map,
+            _ => { return Err(Error::operr("corrupt packet: not a map")); }
+        };
+        let responses = match packet.get(&key("responses")) {
+            Some(Value::Array(responses)) => responses,
+            _ => { return Err(Error::operr("corrupt packet: no responses")); }
+        };
+        let extras = ["programs","eardos","tracks-packed"].iter().filter_map(|name| {
+            list(&packet,name).map(|values| (key(name),values))
+        }).collect::<Vec<_>>();
+        let mut data = lock!(self.0);
+        for response in responses {
+            let (msgid,response) = match response {
+                Value::Array(parts) if parts.len() == 2 => (&parts[0],&parts[1]),
+                _ => { return Err(Error::operr("corrupt packet: bad response")); }
+            };
+            let attempt = request.requests().iter().find(|attempt| {
+                msgid == &Value::Integer(attempt.msgid() as i128)
+            });
+            let attempt = if let Some(attempt) = attempt { attempt } else { continue; };
+            let mut fixture = BTreeMap::new();
+            fixture.insert(key("response"),response.clone());
+            fixture.extend(extras.iter().cloned());
+            let bytes = Error::oper_r(serde_cbor::to_vec(&Value::Map(fixture)),"cannot serialize fixture")?;
+            data.fixtures.insert(archive_key(request.channel(),attempt.request())?,bytes);
+        }
+        Ok(())
+    }
+}
+
+/* The fixtures recorded for one backend namespace, to be served by a MockBackend */
+pub(crate) struct ArchiveSource {
+    archive: SessionArchive,
+    channel: BackendNamespace
+}
+
+impl ArchiveSource {
+    pub(crate) fn new(archive: &SessionArchive, channel: &BackendNamespace) -> ArchiveSource {
+        ArchiveSource { archive: archive.clone(), channel: channel.clone() }
+    }
+}
+
+impl FixtureSource for ArchiveSource {
+    fn fixture(&self, opcode: u8, request: &Value) -> Result<Vec<u8>,String> {
+        let key = archive_value_key(&self.channel,opcode,request).map_err(|e| e.message)?;
+        lock!(self.archive.0).fixtures.get(&key).cloned().ok_or_else(|| format!("not in archive: {}",key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip() {
+        let archive = SessionArchive::new();
+        archive.add_channel("https://example.com/api",&Some(BackendNamespace::new("ensembl","main")));
+        archive.add_channel("jsapi",&None);
+        lock!(archive.0).fixtures.insert("a/b/boot.cbor".to_string(),vec![1,2,3]);
+        let archive = SessionArchive::from_bytes(&archive.to_bytes().unwrap()).unwrap();
+        assert_eq!(Some(Some(BackendNamespace::new("ensembl","main"))),archive.channel("https://example.com/api"));
+        assert_eq!(Some(None),archive.channel("jsapi"));
+        assert_eq!(None,archive.channel("https://example.com/other"));
+        assert_eq!(Some(&vec![1,2,3]),lock!(archive.0).fixtures.get("a/b/boot.cbor"));
+    }
+}
@@ -0,0 +1,84 @@
+use peregrine_data::{ ChannelIntegration, ChannelSender, BackendNamespace, ChannelMessageDecoder, MaxiResponse, null_payload, DataAlgorithm };
+use peregrine_mock_backend::MockBackend;
+use peregrine_toolkit::error::Error;
+use serde_cbor::{ Deserializer, Value };
+use serde::de::{ DeserializeSeed };
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{ Arc };
+use inflate::inflate_bytes_zlib;
+use crate::mockchannel::MockChannelSender;
+
+/* Filesystem channel names are "file:" followed by the root directory of the fixtures. As with
+ * network channels, after the fragment identifier is an optional backend namespace formatted as two
+ * colon-separated strings.
+ *
+ * eg file:/data/fixtures/ensembl#ensembl:main
+ *
+ * Packets are answered from the fixtures by a MockBackend, which assembles the response packet as
+ * a backend would (see peregrine-mock-backend's backend.rs for the fixture format). Requests
+ * without a fixture fail as if the backend had failed them. See fixturepath.rs for where fixtures
+ * are found.
+ */
+
+pub(crate) fn decode_packet(packet: &[u8], decoder: ChannelMessageDecoder) -> Result<MaxiResponse,Error> {
+    let mut deserializer = Deserializer::from_slice(packet);
+    let deserialize = decoder.serde_deserialize_maxi(null_payload());
+    let response = Error::oper_r(deserialize.deserialize(&mut deserializer),"packet error/C")?;
+    Error::oper_r(deserializer.end(),"packet error/D")?;
+    Ok(response)
+}
+
+/* Fixtures may have been copied from a real backend, in which case they are compressed. */
+pub(crate) fn deserialize_fixture_data(bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> {
+    let bytes = inflate_bytes_zlib(&bytes).unwrap_or(bytes);
+    Ok(Some(serde_cbor::from_slice(&bytes).map_err(|e| format!("corrupt payload/A: {}",e))?))
+}
+
+pub(crate) fn key(name: &str) -> Value { Value::Text(name.to_string()) }
+
+pub struct FilesystemChannel;
+
+impl FilesystemChannel {
+    pub fn new() -> FilesystemChannel {
+        FilesystemChannel
+    }
+}
+
+pub(crate) fn parse_backend_namespace(name: &str) -> BackendNamespace {
+    let mut authority = "";
+    let mut name = name;
+    if let Some(first_colon) = name.find(":") {
+        (authority,name) = name.split_at(first_colon);
+        name = &name[1..];
+    }
+    BackendNamespace::new(authority,name)
+}
+
+impl ChannelIntegration for FilesystemChannel {
+    fn make_channel(&self, name: &str) -> Option<(Arc<dyn ChannelSender>,Option<BackendNamespace>)> {
+        let name = name.trim().strip_prefix("file:")?;
+        let (root,namespace) = if let Some(hash_pos) = name.find("#") {
+            (&name[..hash_pos],Some(parse_backend_namespace(&name[hash_pos+1..])))
+        } else {
+            (name,None)
+        };
+        let sender = MockChannelSender::new(MockBackend::from_directory(Path::new(root)));
+        Some((Arc::new(sender),namespace))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_make_channel() {
+        let channel = FilesystemChannel::new();
+        assert!(channel.make_channel("https://example.com/api").is_none());
+        let (_,namespace) = channel.make_channel("file:/tmp/fixtures#ensembl:main").unwrap();
+        assert_eq!(Some(BackendNamespace::new("ensembl","main")),namespace);
+        let (_,namespace) = channel.make_channel("file:/tmp/fixtures").unwrap();
+        assert_eq!(None,namespace);
+    }
+}
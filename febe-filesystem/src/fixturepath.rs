@@ -0,0 +1,44 @@
+use std::path::{ Path, PathBuf };
+use peregrine_data::{ BackendNamespace, MiniRequest };
+use peregrine_mock_backend::{ fixture_segments as value_segments, flatten };
+use peregrine_toolkit::error::Error;
+use serde_cbor::Value;
+
+/* Fixtures are stored one file per mini-request, at a path derived from the serialized form of the
+ * request. The layout is shared with peregrine-mock-backend (see its fixture.rs) so that the same
+ * fixtures can be served by either. For example,
+ *
+ * boot.cbor
+ * program/ensembl-webteam/core/gene/1.cbor
+ * data/ensembl/main/gene/homo_sapiens_GCA_000001405_28:1/12/345/release.cbor
+ *
+ * Session archives hold fixtures for many backends and so prefix the same path with the two parts
+ * of the backend namespace.
+ */
+
+fn fixture_segments(request: &MiniRequest) -> Result<Vec<String>,Error> {
+    let value = Error::oper_r(serde_cbor::value::to_value(request),"cannot serialize request")?;
+    Ok(value_segments(request.as_mini().opcode(),&value))
+}
+
+pub fn fixture_path(root: &Path, request: &MiniRequest) -> Result<PathBuf,Error> {
+    let mut path = root.to_path_buf();
+    for segment in fixture_segments(request)? {
+        path.push(segment);
+    }
+    Ok(path)
+}
+
+/* request is as found in a request packet, see peregrine-mock-backend's fixture_segments */
+pub(crate) fn archive_value_key(channel: &BackendNamespace, opcode: u8, request: &Value) -> Result<String,Error> {
+    let value = Error::oper_r(serde_cbor::value::to_value(channel),"cannot serialize channel")?;
+    let mut segments = vec![];
+    flatten(&value,&mut segments);
+    segments.append(&mut value_segments(opcode,request));
+    Ok(segments.join("/"))
+}
+
+pub(crate) fn archive_key(channel: &BackendNamespace, request: &MiniRequest) -> Result<String,Error> {
+    let value = Error::oper_r(serde_cbor::value::to_value(request),"cannot serialize request")?;
+    archive_value_key(channel,request.as_mini().opcode(),&value)
+}
@@ -0,0 +1,14 @@
+mod archive;
+mod filechannel;
+mod fixturepath;
+mod mockchannel;
+mod recorder;
+mod replay;
+
+pub use archive::SessionArchive;
+pub use filechannel::FilesystemChannel;
+pub use fixturepath::fixture_path;
+pub use mockchannel::MockChannel;
+pub use peregrine_mock_backend::opcode_name;
+pub use recorder::RecordingChannel;
+pub use replay::ReplayChannel;
@@ -0,0 +1,88 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use peregrine_data::{ BackendNamespace, ChannelIntegration, ChannelMessageDecoder, ChannelSender, DataAlgorithm, MaxiRequest, MaxiResponse, PacketPriority };
+use peregrine_mock_backend::MockBackend;
+use peregrine_toolkit::error::Error;
+use crate::filechannel::{ decode_packet, deserialize_fixture_data, parse_backend_namespace };
+
+/* Mock channel names are "mock:" followed by the root directory of the fixtures and, as with the
+ * filesystem channel, an optional backend namespace after a fragment identifier.
+ *
+ * eg mock:/data/fixtures/ensembl#ensembl:main
+ *
+ * Each packet is serialized and answered by a MockBackend exactly as the network channel's are by
+ * a real backend, so the whole wire format is exercised. The filesystem and replay channels answer
+ * through the same sender. A MockChannel made with_backend uses the given backend for every mock
+ * channel, ignoring the root.
+ */
+
+pub(crate) struct MockChannelSender {
+    backend: MockBackend
+}
+
+impl MockChannelSender {
+    pub(crate) fn new(backend: MockBackend) -> MockChannelSender {
+        MockChannelSender { backend }
+    }
+}
+
+impl ChannelSender for MockChannelSender {
+    fn get_sender(&self, _prio: &PacketPriority, data: MaxiRequest, decoder: ChannelMessageDecoder) -> Pin<Box<dyn Future<Output=Result<MaxiResponse,Error>>>> {
+        let backend = self.backend.clone();
+        Box::pin(async move {
+            let request = Error::oper_r(serde_cbor::to_vec(&data),"packet error/B")?;
+            let response = backend.respond(&request).map_err(|e| Error::operr(&e))?;
+            decode_packet(&response,decoder)
+        })
+    }
+
+    fn deserialize_data(&self, _payload: &dyn Any, bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> {
+        deserialize_fixture_data(bytes)
+    }
+
+    fn backoff(&self) -> bool { false }
+}
+
+pub struct MockChannel {
+    backend: Option<MockBackend>
+}
+
+impl MockChannel {
+    pub fn new() -> MockChannel {
+        MockChannel { backend: None }
+    }
+
+    pub fn with_backend(backend: &MockBackend) -> MockChannel {
+        MockChannel { backend: Some(backend.clone()) }
+    }
+}
+
+impl ChannelIntegration for MockChannel {
+    fn make_channel(&self, name: &str) -> Option<(Arc<dyn ChannelSender>,Option<BackendNamespace>)> {
+        let name = name.trim().strip_prefix("mock:")?;
+        let (root,namespace) = if let Some(hash_pos) = name.find("#") {
+            (&name[..hash_pos],Some(parse_backend_namespace(&name[hash_pos+1..])))
+        } else {
+            (name,None)
+        };
+        let backend = self.backend.clone().unwrap_or_else(|| MockBackend::from_directory(Path::new(root)));
+        Some((Arc::new(MockChannelSender { backend }),namespace))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_make_channel() {
+        let channel = MockChannel::new();
+        assert!(channel.make_channel("file:/tmp/fixtures").is_none());
+        let (_,namespace) = channel.make_channel("mock:/tmp/fixtures#ensembl:main").unwrap();
+        assert_eq!(Some(BackendNamespace::new("ensembl","main")),namespace);
+    }
+}
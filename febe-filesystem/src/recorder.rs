@@ -0,0 +1,231 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use peregrine_data::{ BackendNamespace, ChannelIntegration, ChannelMessageDecoder, ChannelSender, DataAlgorithm, MaxiRequest, MaxiResponse, PacketPriority };
+use peregrine_toolkit::error::Error;
+use peregrine_toolkit::warn;
+use crate::archive::SessionArchive;
+use crate::filechannel::decode_packet;
+
+/* A RecordingChannel wraps another channel integration and records all the traffic of the channels
+ * it creates into a SessionArchive, which can later be served by a ReplayChannel.
+ *
+ * Senders which can supply their raw packets (see ChannelSender::get_raw_sender) are recorded
+ * byte-for-byte. Every other sender, such as the javascript channel, is recorded by serializing
+ * its decoded response back into a packet. Packets carrying something which can't be serialized
+ * (programs or tracks supplied alongside the responses) go unrecorded, with a warning.
+ */
+
+struct RecordingChannelSender {
+    inner: Arc<dyn ChannelSender>,
+    archive: SessionArchive,
+    warned: Arc<AtomicBool>
+}
+
+fn record(archive: &SessionArchive, request: &MaxiRequest, packet: &[u8]) {
+    if let Err(e) = archive.record(request,packet) {
+        warn!("cannot record packet: {}",e.message);
+    }
+}
+
+impl ChannelSender for RecordingChannelSender {
+    fn get_sender(&self, prio: &PacketPriority, data: MaxiRequest, decoder: ChannelMessageDecoder) -> Pin<Box<dyn Future<Output=Result<MaxiResponse,Error>>>> {
+        if let Some(raw) = self.get_raw_sender(prio,data.clone()) {
+            return Box::pin(async move {
+                decode_packet(&raw.await?,decoder)
+            });
+        }
+        let archive = self.archive.clone();
+        let sender = self.inner.get_sender(prio,data.clone(),decoder);
+        let warned = self.warned.clone();
+        Box::pin(async move {
+            let response = sender.await?;
+            match serde_cbor::to_vec(&response) {
+                Ok(packet) => { record(&archive,&data,&packet); },
+                Err(e) => {
+                    if !warned.swap(true,Ordering::Relaxed) {
+                        warn!("cannot record packet from channel {}: {}",data.channel(),e);
+                    }
+                }
+            }
+            Ok(response)
+        })
+    }
+
+    fn get_raw_sender(&self, prio: &PacketPriority, data: MaxiRequest) -> Option<Pin<Box<dyn Future<Output=Result<Vec<u8>,Error>>>>> {
+        let raw = self.inner.get_raw_sender(prio,data.clone())?;
+        let archive = self.archive.clone();
+        Some(Box::pin(async move {
+            let packet = raw.await?;
+            record(&archive,&data,&packet);
+            Ok(packet)
+        }))
+    }
+
+    fn deserialize_data(&self, payload: &dyn Any, bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> {
+        self.inner.deserialize_data(payload,bytes)
+    }
+
+    fn deserialize_index(&self, payload: &dyn Any, index: usize) -> Result<Option<Vec<u8>>,String> {
+        self.inner.deserialize_index(payload,index)
+    }
+
+    fn backoff(&self) -> bool { self.inner.backoff() }
+}
+
+pub struct RecordingChannel {
+    inner: Rc<dyn ChannelIntegration>,
+    archive: SessionArchive
+}
+
+impl RecordingChannel {
+    pub fn new(inner: Rc<dyn ChannelIntegration>, archive: &SessionArchive) -> RecordingChannel {
+        RecordingChannel {
+            inner,
+            archive: archive.clone()
+        }
+    }
+
+    pub fn archive(&self) -> &SessionArchive { &self.archive }
+}
+
+impl ChannelIntegration for RecordingChannel {
+    fn make_channel(&self, name: &str) -> Option<(Arc<dyn ChannelSender>,Option<BackendNamespace>)> {
+        let (inner,namespace) = self.inner.make_channel(name)?;
+        self.archive.add_channel(name,&namespace);
+        let sender = RecordingChannelSender {
+            inner,
+            archive: self.archive.clone(),
+            warned: Arc::new(AtomicBool::new(false))
+        };
+        Some((Arc::new(sender),namespace))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use futures::executor::block_on;
+    use peregrine_data::{ JumpReq, MiniRequest };
+    use peregrine_mock_backend::{ MemorySource, MockBackend };
+    use serde_cbor::Value;
+    use crate::filechannel::key;
+    use crate::replay::ReplayChannel;
+    use super::*;
+
+    /* Answers from a MockBackend, as a network channel would, supplying the raw packets if asked */
+    struct TestSender {
+        backend: MockBackend,
+        raw: bool
+    }
+
+    impl TestSender {
+        fn respond(&self, data: &MaxiRequest) -> Result<Vec<u8>,Error> {
+            let request = Error::oper_r(serde_cbor::to_vec(data),"packet error/B")?;
+            self.backend.respond(&request).map_err(|e| Error::operr(&e))
+        }
+    }
+
+    impl ChannelSender for TestSender {
+        fn get_sender(&self, _prio: &PacketPriority, data: MaxiRequest, decoder: ChannelMessageDecoder) -> Pin<Box<dyn Future<Output=Result<MaxiResponse,Error>>>> {
+            let packet = self.respond(&data);
+            Box::pin(async move { decode_packet(&packet?,decoder) })
+        }
+
+        fn get_raw_sender(&self, _prio: &PacketPriority, data: MaxiRequest) -> Option<Pin<Box<dyn Future<Output=Result<Vec<u8>,Error>>>>> {
+            if !self.raw { return None; }
+            let packet = self.respond(&data);
+            Some(Box::pin(async move { packet }))
+        }
+
+        fn backoff(&self) -> bool { false }
+    }
+
+    struct TestChannel(MockBackend);
+
+    impl ChannelIntegration for TestChannel {
+        fn make_channel(&self, name: &str) -> Option<(Arc<dyn ChannelSender>,Option<BackendNamespace>)> {
+            let sender = TestSender { backend: self.0.clone(), raw: name == "raw" };
+            Some((Arc::new(sender),Some(BackendNamespace::new("test",name))))
+        }
+    }
+
+    fn jump_fixture(left: i128) -> Vec<u8> {
+        let mut location = BTreeMap::new();
+        location.insert(key("stick"),key("13"));
+        location.insert(key("left"),Value::Integer(left));
+        location.insert(key("right"),Value::Integer(left+10));
+        let mut fixture = BTreeMap::new();
+        fixture.insert(key("response"),Value::Array(vec![Value::Integer(6),Value::Map(location)]));
+        serde_cbor::to_vec(&Value::Map(fixture)).unwrap()
+    }
+
+    fn backend() -> MockBackend {
+        let mut source = MemorySource::new();
+        for (i,location) in ["gene:BRCA2","gene:TP53"].iter().enumerate() {
+            let request = JumpReq::new(location);
+            let value = serde_cbor::value::to_value(&request).unwrap();
+            source.insert(request.as_mini().opcode(),&value,jump_fixture(i as i128*100));
+        }
+        MockBackend::new(source)
+    }
+
+    fn jumps(namespace: &Option<BackendNamespace>) -> MaxiRequest {
+        let requests = ["gene:BRCA2","gene:TP53"].iter().map(|x| JumpReq::new(x)).collect::<Vec<MiniRequest>>();
+        MaxiRequest::new_fixed(namespace.as_ref().unwrap(),requests)
+    }
+
+    fn send(sender: &Arc<dyn ChannelSender>, data: MaxiRequest) -> Value {
+        let decoder = ChannelMessageDecoder::new_fixed(sender);
+        let response = block_on(sender.get_sender(&PacketPriority::RealTime,data,decoder)).unwrap();
+        responses(&serde_cbor::to_vec(&response).unwrap())
+    }
+
+    /* the responses of a packet, by msgid, with the packet decoded for comparison */
+    fn responses(packet: &[u8]) -> Value {
+        match serde_cbor::from_slice(packet).unwrap() {
+            Value::Map(mut map) => map.remove(&key("responses")).unwrap(),
+            _ => panic!("packet not a map")
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let archive = SessionArchive::new();
+        let recorder = RecordingChannel::new(Rc::new(TestChannel(backend())),&archive);
+        /* raw via get_sender, raw directly and re-encoded */
+        let (raw,raw_namespace) = recorder.make_channel("raw").unwrap();
+        let (cooked,cooked_namespace) = recorder.make_channel("cooked").unwrap();
+        let mut live = vec![];
+        live.push(send(&raw,jumps(&raw_namespace)));
+        let direct = raw.get_raw_sender(&PacketPriority::RealTime,jumps(&raw_namespace)).unwrap();
+        live.push(responses(&block_on(direct).unwrap()));
+        live.push(send(&cooked,jumps(&cooked_namespace)));
+        assert!(matches!(&live[0],Value::Array(x) if x.len() == 2));
+        assert_eq!(live[0],live[1]);
+        assert_eq!(live[0],live[2]);
+        let replay = ReplayChannel::new(&SessionArchive::from_bytes(&archive.to_bytes().unwrap()).unwrap());
+        assert!(replay.make_channel("other").is_none());
+        for name in ["raw","cooked"] {
+            let (sender,namespace) = replay.make_channel(name).unwrap();
+            assert_eq!(Some(BackendNamespace::new("test",name)),namespace);
+            assert_eq!(live[0],send(&sender,jumps(&namespace)));
+        }
+    }
+
+    #[test]
+    fn test_record_raw_only() {
+        let archive = SessionArchive::new();
+        let recorder = RecordingChannel::new(Rc::new(TestChannel(backend())),&archive);
+        let (raw,namespace) = recorder.make_channel("raw").unwrap();
+        let (cooked,_) = recorder.make_channel("cooked").unwrap();
+        assert!(cooked.get_raw_sender(&PacketPriority::RealTime,jumps(&namespace)).is_none());
+        let packet = block_on(raw.get_raw_sender(&PacketPriority::RealTime,jumps(&namespace)).unwrap()).unwrap();
+        let (replayed,_) = ReplayChannel::new(&archive).make_channel("raw").unwrap();
+        assert_eq!(responses(&packet),send(&replayed,jumps(&namespace)));
+    }
+}
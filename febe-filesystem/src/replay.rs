@@ -0,0 +1,55 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use peregrine_data::{ BackendNamespace, ChannelIntegration, ChannelMessageDecoder, ChannelSender, DataAlgorithm, MaxiRequest, MaxiResponse, PacketPriority };
+use peregrine_mock_backend::MockBackend;
+use peregrine_toolkit::error::Error;
+use crate::archive::{ ArchiveSource, SessionArchive };
+use crate::filechannel::deserialize_fixture_data;
+use crate::mockchannel::MockChannelSender;
+
+/* A ReplayChannel claims exactly those channel names which were claimed during the recorded
+ * session, with the same backend namespaces, and answers every request from the archive. Requests
+ * which weren't made during the session fail. There's no network and no timing involved so a replay
+ * always produces the same responses.
+ */
+
+/* Fixtures are archived by backend namespace, so each packet is answered from its own channel's */
+struct ReplayChannelSender {
+    archive: SessionArchive
+}
+
+impl ChannelSender for ReplayChannelSender {
+    fn get_sender(&self, prio: &PacketPriority, data: MaxiRequest, decoder: ChannelMessageDecoder) -> Pin<Box<dyn Future<Output=Result<MaxiResponse,Error>>>> {
+        let backend = MockBackend::new(ArchiveSource::new(&self.archive,data.channel()));
+        MockChannelSender::new(backend).get_sender(prio,data,decoder)
+    }
+
+    fn deserialize_data(&self, _payload: &dyn Any, bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> {
+        deserialize_fixture_data(bytes)
+    }
+
+    fn backoff(&self) -> bool { false }
+}
+
+pub struct ReplayChannel {
+    archive: SessionArchive
+}
+
+impl ReplayChannel {
+    pub fn new(archive: &SessionArchive) -> ReplayChannel {
+        ReplayChannel {
+            archive: archive.clone()
+        }
+    }
+}
+
+impl ChannelIntegration for ReplayChannel {
+    fn make_channel(&self, name: &str) -> Option<(Arc<dyn ChannelSender>,Option<BackendNamespace>)> {
+        let namespace = self.archive.channel(name)?;
+        let sender = ReplayChannelSender { archive: self.archive.clone() };
+        Some((Arc::new(sender),namespace))
+    }
+}
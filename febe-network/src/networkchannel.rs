@@ -26,13 +26,20 @@ pub struct NetworkChannelSender {
     url_lo: String
 }
 
+impl NetworkChannelSender {
+    fn send_raw(&self, prio: &PacketPriority, data: MaxiRequest) -> impl Future<Output=Result<Vec<u8>,Error>> {
+        let url = if prio.is_high() { &self.url_hi } else { &self.url_lo };
+        send_raw(url.clone(),prio.clone(),data,Some(30.),self.cache_buster.clone())
+    }
+}
+
 impl ChannelSender for NetworkChannelSender {
     fn get_sender(&self, prio: &PacketPriority, data: MaxiRequest, decoder: ChannelMessageDecoder) -> Pin<Box<dyn Future<Output=Result<MaxiResponse,Error>>>> {
-        let url = match prio {
-            PacketPriority::RealTime => &self.url_hi,
-            PacketPriority::Batch => &self.url_lo
-        };
-        Box::pin(send_wrap(url.clone(),prio.clone(),data,Some(30.),self.cache_buster.clone(),decoder))
+        Box::pin(decode(self.send_raw(prio,data),decoder))
+    }
+
+    fn get_raw_sender(&self, prio: &PacketPriority, data: MaxiRequest) -> Option<Pin<Box<dyn Future<Output=Result<Vec<u8>,Error>>>>> {
+        Some(Box::pin(self.send_raw(prio,data)))
     }
 
     fn deserialize_data(&self, _payload: &dyn Any, bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> {
@@ -108,10 +115,7 @@ impl ChannelIntegration for NetworkChannel {
 }
 
 fn add_priority(a: &Url, prio: &PacketPriority, cache_buster: &str) -> Url {
-    let z = a.add_path_segment(match prio {
-        PacketPriority::RealTime => "hi",
-        PacketPriority::Batch => "lo"
-    });
+    let z = a.add_path_segment(if prio.is_high() { "hi" } else { "lo" });
     z.add_query_parameter(&format!("stamp={}",cache_buster))
 }
 
@@ -124,10 +128,14 @@ async fn send(url: &Url, prio: PacketPriority, data: Vec<u8>, timeout: Option<f6
     ajax.get_cbor().await
 }
 
-async fn send_wrap(url_str: String, prio: PacketPriority, packet: MaxiRequest, timeout: Option<f64>, cache_buster: String, decoder: ChannelMessageDecoder) -> Result<MaxiResponse,Error> {
+async fn send_raw(url_str: String, prio: PacketPriority, packet: MaxiRequest, timeout: Option<f64>, cache_buster: String) -> Result<Vec<u8>,Error> {
     let url = Error::oper_r(Url::parse(&url_str),&format!("bad_url {}",url_str))?;
     let data = Error::oper_r(serde_cbor::to_vec(&packet),"packet error/B")?;
-    let data = send(&url,prio,data,timeout,&cache_buster).await?;
+    send(&url,prio,data,timeout,&cache_buster).await
+}
+
+async fn decode(raw: impl Future<Output=Result<Vec<u8>,Error>>, decoder: ChannelMessageDecoder) -> Result<MaxiResponse,Error> {
+    let data = raw.await?;
     let mut deserializer = Deserializer::from_slice(&data);
     let deserialize = decoder.serde_deserialize_maxi(null_payload());
     let response = Error::oper_r(deserialize.deserialize(&mut deserializer),"packet error/C")?;
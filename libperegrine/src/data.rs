@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex};
 use eard_interp::{GlobalBuildContext, GlobalContext, HandleStore, Value, Return, AsyncReturn };
-use peregrine_data::{DataRequest, PacketPriority, DataStore, DataResponse, LoadMode, RunReport, ShapeRequest, AccessorResolver, BackendNamespace, SmallValuesStore };
+use peregrine_data::{DataRequest, PacketPriority, DataStore, DataResponse, LoadMode, RunReport, ShapeRequest, AccessorResolver, BackendNamespace, SmallValuesStore, ReceivedData };
 
 async fn resolve(resolver: AccessorResolver, accessor: String) -> Result<BackendNamespace,String> {
     resolver.resolve(&accessor).await.map_err(|e| e.message.to_string())
@@ -71,7 +71,7 @@ pub(crate) fn op_get_data(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut G
         let requests = ctx.context.get(&requests);
         let req = requests.get(h)?.clone();
         let mode = ctx.context.get(&mode);
-        let priority = if mode.high_priority() { PacketPriority::RealTime } else { PacketPriority::Batch };
+        let priority = mode.priority();
         let responses = responses.clone();
         let report = report.clone();
         let data_store = ctx.context.get(&data_store).clone();
@@ -143,6 +143,78 @@ pub(crate) fn op_data_string(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mu
     }))
 }
 
+pub(crate) fn op_data_length(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let responses = gctx.patterns.lookup::<HandleStore<DataResponse>>("responses")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let stream = ctx.force_string(regs[2])?;
+        let responses = ctx.context.get(&responses);
+        let res = responses.get(h)?.clone();
+        let len = res.len(stream).map_err(|e|
+            format!("cannot get data stream {}: {}",stream,e)
+        )?;
+        ctx.set(regs[0],Value::Number(len as f64))?;
+        Ok(Return::Sync)
+    }))
+}
+
+/* The range variants take a start (inclusive) and end (exclusive) index into the stream, which is
+ * then only decoded as far as needed.
+ */
+fn data_range(ctx: &GlobalContext, regs: &[usize], res: &DataResponse) -> Result<ReceivedData,String> {
+    let stream = ctx.force_string(regs[2])?;
+    let start = ctx.force_number(regs[3])?.max(0.) as usize;
+    let end = ctx.force_number(regs[4])?.max(0.) as usize;
+    res.get_range(stream,start,end).map_err(|e|
+        format!("cannot get data stream {}: {}",stream,e)
+    )
+}
+
+pub(crate) fn op_data_boolean_range(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let responses = gctx.patterns.lookup::<HandleStore<DataResponse>>("responses")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let responses = ctx.context.get(&responses);
+        let res = responses.get(h)?.clone();
+        let value = data_range(ctx,regs,&res)?;
+        let value = value.data_as_booleans().map_err(|_|
+            "data stream was not booleans".to_string()
+        )?;
+        ctx.set(regs[0],Value::FiniteBoolean(value.as_ref().to_vec()))?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_data_number_range(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let responses = gctx.patterns.lookup::<HandleStore<DataResponse>>("responses")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let responses = ctx.context.get(&responses);
+        let res = responses.get(h)?.clone();
+        let value = data_range(ctx,regs,&res)?;
+        let value = value.data_as_numbers().map_err(|_|
+            "data stream was not numbers".to_string()
+        )?;
+        ctx.set(regs[0],Value::FiniteNumber(value.as_ref().to_vec()))?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_data_string_range(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let responses = gctx.patterns.lookup::<HandleStore<DataResponse>>("responses")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let responses = ctx.context.get(&responses);
+        let res = responses.get(h)?.clone();
+        let value = data_range(ctx,regs,&res)?;
+        let value = value.data_as_strings().map_err(|_|
+            "data stream was not strings".to_string()
+        )?;
+        ctx.set(regs[0],Value::FiniteString(value.as_ref().to_vec()))?;
+        Ok(Return::Sync)
+    }))
+}
+
 pub(crate) fn op_bp_range(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
     let shape_request = gctx.patterns.lookup::<ShapeRequest>("shape-request")?;
     Ok(Box::new(move |ctx,regs| {
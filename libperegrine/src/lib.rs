@@ -3,6 +3,7 @@ mod coord;
 mod data;
 mod leaf;
 mod paint;
+mod registry;
 mod shape;
 mod setting;
 mod style;
@@ -10,3 +11,4 @@ mod util;
 mod zmenu;
 
 pub use crate::context::{ prepare_libperegrine, build_libperegrine, LibPeregrineBuilder };
+pub use crate::registry::{ ArgType, Register, OpSpec, libperegrine_operations, libperegrine_operation, libperegrine_version, check_operations, LIBPEREGRINE_OPCODES };
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use eachorevery::{EachOrEvery, eoestruct::{StructTemplate, StructValue, StructBuilt}};
 use eard_interp::{GlobalBuildContext, GlobalContext, HandleStore, Value, Return, ContextItem};
-use peregrine_data::{Colour, DirectColour, Patina, DrawnType, Plotter, Pen, AttachmentPoint, HotspotPatina, ShapeRequest, DataRequest};
+use peregrine_data::{Colour, DirectColour, PlainColour, Patina, DrawnType, BlendMode, Plotter, Pen, AttachmentPoint, HotspotPatina, ShapeRequest, DataRequest};
 use crate::{util::eoe_from_handle};
 
 fn to_u8(v: f64) -> u8 { v as u8 }
@@ -21,6 +21,18 @@ pub(crate) fn op_colour(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut Glo
     }))
 }
 
+/* A named colour, looked up in the palette of the current theme when drawn */
+pub(crate) fn op_palette_colour(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let colours = gctx.patterns.lookup::<HandleStore<Colour>>("colours")?;
+    Ok(Box::new(move |ctx,regs| {
+        let name = ctx.force_string(regs[1])?.to_string();
+        let colours = ctx.context.get_mut(&colours);
+        let h = colours.push(Colour::Palette(name));
+        ctx.set(regs[0],Value::Number(h as f64))?;
+        Ok(Return::Sync)
+    }))
+}
+
 pub(crate) fn op_paint_solid(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
     let colours = gctx.patterns.lookup::<HandleStore<Colour>>("colours")?;
     let paints = gctx.patterns.lookup::<HandleStore<Patina>>("paint")?;
@@ -28,7 +40,7 @@ pub(crate) fn op_paint_solid(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mu
         let h = ctx.force_number(regs[1])? as usize;
         let colours = ctx.context.get_mut(&colours);
         let colour = colours.get(h)?.clone();
-        let paint = Patina::Drawn(DrawnType::Fill,EachOrEvery::every(colour));
+        let paint = Patina::Drawn(DrawnType::Fill,EachOrEvery::every(colour),BlendMode::Normal);
         let paints = ctx.context.get_mut(&paints);
         let h = paints.push(paint);
         ctx.set(regs[0],Value::Number(h as f64))?;
@@ -44,7 +56,7 @@ pub(crate) fn op_paint_hollow(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&m
         let h = ctx.force_number(regs[1])? as usize;
         let colours = ctx.context.get_mut(&colours);
         let colour = colours.get(h)?.clone();
-        let paint = Patina::Drawn(DrawnType::Stroke(width),EachOrEvery::every(colour));
+        let paint = Patina::Drawn(DrawnType::Stroke(width),EachOrEvery::every(colour),BlendMode::Normal);
         let paints = ctx.context.get_mut(&paints);
         let h = paints.push(paint);
         ctx.set(regs[0],Value::Number(h as f64))?;
@@ -69,7 +81,7 @@ pub(crate) fn op_paint_solid_s(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&
             let colour = colours.get(h)?.clone();
             EachOrEvery::every(colour)
         };
-        let paint = Patina::Drawn(DrawnType::Fill,value);
+        let paint = Patina::Drawn(DrawnType::Fill,value,BlendMode::Normal);
         let paints = ctx.context.get_mut(&paints);
         let h = paints.push(paint);
         ctx.set(regs[0],Value::Number(h as f64))?;
@@ -95,7 +107,7 @@ pub(crate) fn op_paint_hollow_s(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(
             EachOrEvery::every(colour)
         };
         let width = ctx.force_number(regs[2])?;
-        let paint = Patina::Drawn(DrawnType::Stroke(width),value);
+        let paint = Patina::Drawn(DrawnType::Stroke(width),value,BlendMode::Normal);
         let paints = ctx.context.get_mut(&paints);
         let h = paints.push(paint);
         ctx.set(regs[0],Value::Number(h as f64))?;
@@ -116,15 +128,17 @@ pub(crate) fn op_paint_special(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&
     }))
 }
 
-fn to_direct(colour: &Colour) -> Result<&DirectColour,String> {
+/* pens, dotted lines and graphs can also take palette colours, resolved when drawn */
+fn to_plain(colour: &Colour) -> Result<PlainColour,String> {
     match colour {
-        Colour::Direct(c) => Ok(c),
-        _ => Err(format!("graph colour must be simple colour"))
+        Colour::Direct(c) => Ok(PlainColour::Direct(c.clone())),
+        Colour::Palette(name) => Ok(PlainColour::Palette(name.clone())),
+        _ => Err(format!("pen, dotted and graph colours must be simple colours"))
     }
 }
 
-fn to_direct_seq(ctx: &GlobalContext, input: &HandleStore<Colour>, reg: usize) -> Result<EachOrEvery<DirectColour>,String> {
-    Ok(eoe_from_handle(ctx,input,reg)?.map_results(|c| to_direct(c).cloned())?)
+fn to_plain_seq(ctx: &GlobalContext, input: &HandleStore<Colour>, reg: usize) -> Result<EachOrEvery<PlainColour>,String> {
+    Ok(eoe_from_handle(ctx,input,reg)?.map_results(|c| to_plain(c))?)
 }
 
 pub(crate) fn op_graph_type(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
@@ -134,8 +148,8 @@ pub(crate) fn op_graph_type(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut
         let height = ctx.force_number(regs[1])?;
         let colour_handle = ctx.force_number(regs[2])? as usize;
         let colours = ctx.context.get(&colours);
-        let colour = to_direct(colours.get(colour_handle)?)?.clone();
-        let graph_type = Plotter(height, colour);
+        let colour = to_plain(colours.get(colour_handle)?)?;
+        let graph_type = Plotter(height, colour, BlendMode::Normal);
         let graph_types = ctx.context.get_mut(&graph_types);
         let h = graph_types.push(graph_type);
         ctx.set(regs[0],Value::Number(h as f64))?;
@@ -143,6 +157,40 @@ pub(crate) fn op_graph_type(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut
     }))
 }
 
+fn to_blend_mode(name: &str) -> Result<BlendMode,String> {
+    BlendMode::from_name(name).ok_or_else(|| format!("unknown blend mode '{}'",name))
+}
+
+/* A copy of a drawn paint which blends with what's beneath it by the named mode */
+pub(crate) fn op_paint_blend(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let paints = gctx.patterns.lookup::<HandleStore<Patina>>("paint")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let blend = to_blend_mode(ctx.force_string(regs[2])?)?;
+        let paints = ctx.context.get_mut(&paints);
+        let paint = match paints.get(h)? {
+            Patina::Drawn(drawn_type,colours,_) => Patina::Drawn(drawn_type.clone(),colours.clone(),blend),
+            _ => { return Err(format!("can only blend drawn paint")); }
+        };
+        let h = paints.push(paint);
+        ctx.set(regs[0],Value::Number(h as f64))?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_graph_type_blend(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let graph_types = gctx.patterns.lookup::<HandleStore<Plotter>>("graph-types")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let blend = to_blend_mode(ctx.force_string(regs[2])?)?;
+        let graph_types = ctx.context.get_mut(&graph_types);
+        let Plotter(height,colour,_) = graph_types.get(h)?.clone();
+        let h = graph_types.push(Plotter(height,colour,blend));
+        ctx.set(regs[0],Value::Number(h as f64))?;
+        Ok(Return::Sync)
+    }))
+}
+
 pub(crate) fn op_pen(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
     let colours = gctx.patterns.lookup::<HandleStore<Colour>>("colours")?;
     let pens = gctx.patterns.lookup::<HandleStore<Pen>>("pens")?;
@@ -155,8 +203,8 @@ pub(crate) fn op_pen(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut Global
             (size,AttachmentPoint::Left)
         };
         let colours = ctx.context.get(&colours);
-        let fgd = to_direct_seq(ctx,colours,regs[3])?.clone();
-        let bgd = to_direct_seq(ctx,colours,regs[4])?.clone();
+        let fgd = to_plain_seq(ctx,colours,regs[3])?.clone();
+        let bgd = to_plain_seq(ctx,colours,regs[4])?.clone();
         let pen = Pen::new(&font,size as u32,&fgd,&bgd,&attachment);
         let pens = ctx.context.get_mut(&pens);
         let h = pens.push(pen);
@@ -190,12 +238,12 @@ pub(crate) fn op_paint_dotted(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&m
         let length = ctx.force_number(regs[3])?;
         let width = ctx.force_number(regs[4])?;
         let prop = ctx.force_number(regs[5])?;
-        let colour_a = to_direct_seq(ctx,colours,regs[1])?;
-        let colour_b= to_direct_seq(ctx,colours,regs[2])?;
+        let colour_a = to_plain_seq(ctx,colours,regs[1])?;
+        let colour_b= to_plain_seq(ctx,colours,regs[2])?;
         let colour = colour_a.zip(&colour_b,|a,b| {
             Colour::Bar(a.clone(),b.clone(),(length as u32,length as u32),prop)
         });
-        let paint = Patina::Drawn(DrawnType::Stroke(width),colour);
+        let paint = Patina::Drawn(DrawnType::Stroke(width),colour,BlendMode::Normal);
         let paints = ctx.context.get_mut(&paints);
         let h = paints.push(paint);
         ctx.set(regs[0],Value::Number(h as f64))?;
@@ -0,0 +1,240 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+use eard_interp::{ GlobalBuildContext, GlobalContext, InterpreterBuilder, Operation, Return };
+
+/* Every operation in libperegrine is described here: its opcode, its name, the library version in
+ * which it first appeared and what it expects in each register. build_libperegrine registers the
+ * operations from this table and the library's version is the latest "since" in it, so a new
+ * operation must bump its "since" past the current version for programs using it to be refused
+ * by older clients.
+ *
+ * Handles are indexes into one of the stores in context.rs, named as there.
+ */
+
+type OpFactory = fn(&GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String>;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ArgType {
+    Number,
+    String,
+    Boolean,
+    Handle(&'static str),
+    /* finite sequences */
+    NumberSeq,
+    StringSeq,
+    BooleanSeq,
+    HandleSeq(&'static str),
+    /* an atom or any sequence, finite or infinite */
+    Numbers,
+    Strings,
+    Booleans,
+    Handles(&'static str)
+}
+
+impl fmt::Display for ArgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgType::Number => write!(f,"number"),
+            ArgType::String => write!(f,"string"),
+            ArgType::Boolean => write!(f,"boolean"),
+            ArgType::Handle(store) => write!(f,"handle({})",store),
+            ArgType::NumberSeq => write!(f,"seq(number)"),
+            ArgType::StringSeq => write!(f,"seq(string)"),
+            ArgType::BooleanSeq => write!(f,"seq(boolean)"),
+            ArgType::HandleSeq(store) => write!(f,"seq(handle({}))",store),
+            ArgType::Numbers => write!(f,"?number"),
+            ArgType::Strings => write!(f,"?string"),
+            ArgType::Booleans => write!(f,"?boolean"),
+            ArgType::Handles(store) => write!(f,"?handle({})",store)
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Register {
+    In(ArgType),
+    Out(ArgType)
+}
+
+pub struct OpSpec {
+    pub opcode: u64,
+    pub name: &'static str,
+    pub since: u32,
+    pub registers: &'static [Register],
+    factory: OpFactory
+}
+
+impl OpSpec {
+    pub fn arity(&self) -> usize { self.registers.len() }
+}
+
+/* eg "data_number(handle(responses),string) -> seq(number)" */
+impl fmt::Display for OpSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filter = |out: bool| {
+            self.registers.iter().filter_map(move |r| match (r,out) {
+                (Register::In(t),false) | (Register::Out(t),true) => Some(t.to_string()),
+                _ => None
+            }).collect::<Vec<_>>()
+        };
+        write!(f,"{}({})",self.name,filter(false).join(","))?;
+        let outs = filter(true);
+        if outs.len() > 0 {
+            write!(f," -> {}",outs.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/* Separate so that the variant names don't hide the types */
+mod table {
+    use super::{ OpSpec, ArgType::*, Register::{ In, Out } };
+    use crate::{leaf::{op_leaf, op_leaf_s}, style::op_style, paint::{op_colour, op_palette_colour, op_paint_blend, op_graph_type_blend, op_paint_solid, op_paint_solid_s, op_graph_type, op_pen, op_paint_hollow, op_paint_hollow_s, op_paint_special, op_zmenu, op_paint_dotted, op_paint_metadata, op_paint_setting}, coord::op_coord, shape::{op_rectangle, op_wiggle, op_text, op_image, op_running_text, op_empty, op_running_rectangle, op_rectangle_join, op_polygon}, data::{op_get_data, op_request, op_scope, op_data_boolean, op_data_number, op_data_string, op_bp_range, op_scope_s, op_small_value, op_only_warm, op_stick, op_data_length, op_data_boolean_range, op_data_number_range, op_data_string_range}, zmenu::{op_zmenu_new, op_zmenu_block, op_zmenu_link, op_zmenu_pair, op_zmenu_paint}, setting::{op_setting_boolean, op_setting_string, op_setting_number_seq, op_setting_number, op_setting_string_seq, op_setting_boolean_seq, op_setting_boolean_keys, op_setting_number_keys, op_setting_string_keys}};
+
+    macro_rules! op {
+        ($opcode:expr, $name:expr, $since:expr, $factory:expr, [$($reg:expr),*]) => {
+            OpSpec { opcode: $opcode, name: $name, since: $since, factory: $factory, registers: &[$($reg),*] }
+        };
+    }
+
+    pub(super) static OPERATIONS : &[OpSpec] = &[
+        op!(256,"leaf",0,op_leaf,[Out(Handle("leaf")),In(String)]),
+        op!(257,"leaf_s",0,op_leaf_s,[Out(HandleSeq("leaf")),In(StringSeq)]),
+        op!(258,"style",0,op_style,[In(String),In(StringSeq),In(StringSeq)]),
+        op!(259,"colour",0,op_colour,[Out(Handle("colours")),In(Number),In(Number),In(Number),In(Number)]),
+        op!(260,"paint_solid",0,op_paint_solid,[Out(Handle("paint")),In(Handle("colours"))]),
+        op!(261,"paint_solid_s",0,op_paint_solid_s,[Out(Handle("paint")),In(Handles("colours"))]),
+        op!(262,"coord",0,op_coord,[Out(Handle("coords")),In(Numbers),In(Numbers),In(Numbers)]),
+        op!(263,"rectangle",0,op_rectangle,[In(Handle("coords")),In(Handle("coords")),In(Handle("paint")),In(Handles("leaf"))]),
+        op!(264,"request",0,op_request,[Out(Handle("requests")),In(String),In(String)]),
+        op!(265,"scope",0,op_scope,[In(Handle("requests")),In(String),In(String)]),
+        op!(266,"get_data",0,op_get_data,[Out(Handle("responses")),In(Handle("requests"))]),
+        op!(267,"data_boolean",0,op_data_boolean,[Out(BooleanSeq),In(Handle("responses")),In(String)]),
+        op!(268,"data_number",0,op_data_number,[Out(NumberSeq),In(Handle("responses")),In(String)]),
+        op!(269,"data_string",0,op_data_string,[Out(StringSeq),In(Handle("responses")),In(String)]),
+        op!(270,"graph_type",0,op_graph_type,[Out(Handle("graph-types")),In(Number),In(Handle("colours"))]),
+        op!(271,"wiggle",0,op_wiggle,[In(Number),In(Number),In(Handle("graph-types")),In(NumberSeq),In(Booleans),In(Handle("leaf"))]),
+        op!(272,"setting_boolean",0,op_setting_boolean,[Out(Boolean),In(String),In(StringSeq)]),
+        op!(273,"setting_number",0,op_setting_number,[Out(Number),In(String),In(StringSeq)]),
+        op!(274,"setting_string",0,op_setting_string,[Out(String),In(String),In(StringSeq)]),
+        op!(275,"setting_boolean_seq",0,op_setting_boolean_seq,[Out(BooleanSeq),In(String),In(StringSeq)]),
+        op!(276,"setting_number_seq",0,op_setting_number_seq,[Out(NumberSeq),In(String),In(StringSeq)]),
+        op!(277,"setting_string_seq",0,op_setting_string_seq,[Out(StringSeq),In(String),In(StringSeq)]),
+        op!(278,"pen",0,op_pen,[Out(Handle("pens")),In(String),In(Number),In(Handles("colours")),In(Handles("colours"))]),
+        op!(279,"text",0,op_text,[In(Handle("coords")),In(Handle("pens")),In(Strings),In(Handles("leaf"))]),
+        op!(280,"paint_hollow",0,op_paint_hollow,[Out(Handle("paint")),In(Handle("colours")),In(Number)]),
+        op!(281,"paint_hollow_s",0,op_paint_hollow_s,[Out(Handle("paint")),In(Handles("colours")),In(Number)]),
+        op!(282,"bp_range",0,op_bp_range,[Out(Number),Out(Number)]),
+        op!(283,"paint_special",0,op_paint_special,[Out(Handle("paint")),In(String),In(Boolean)]),
+        op!(284,"image",0,op_image,[In(Handle("coords")),In(Strings),In(Handles("leaf"))]),
+        op!(285,"running_text",0,op_running_text,[In(Handle("coords")),In(Handle("coords")),In(Handle("pens")),In(Strings),In(Handles("leaf"))]),
+        op!(286,"zmenu",0,op_zmenu,[Out(Handle("paint")),In(Handle("eoetemplates")),In(Handle("eoetemplates")),In(Boolean)]),
+        op!(287,"paint_dotted",0,op_paint_dotted,[Out(Handle("paint")),In(Handles("colours")),In(Handles("colours")),In(Number),In(Number),In(Number)]),
+        op!(288,"empty",0,op_empty,[In(Handle("coords")),In(Handle("coords")),In(Handles("leaf"))]),
+        op!(289,"paint_metadata",0,op_paint_metadata,[Out(Handle("paint")),In(String),In(StringSeq),In(HandleSeq("eoetemplates"))]),
+        op!(290,"paint_setting",0,op_paint_setting,[Out(Handle("paint")),In(String),In(StringSeq),In(HandleSeq("eoetemplates")),In(Boolean)]),
+        op!(291,"setting_boolean_keys",0,op_setting_boolean_keys,[Out(BooleanSeq),In(String),In(StringSeq),In(StringSeq)]),
+        op!(292,"setting_number_keys",0,op_setting_number_keys,[Out(NumberSeq),In(String),In(StringSeq),In(StringSeq)]),
+        op!(293,"setting_string_keys",0,op_setting_string_keys,[Out(StringSeq),In(String),In(StringSeq),In(StringSeq)]),
+        op!(294,"scope_s",0,op_scope_s,[In(Handle("requests")),In(String),In(StringSeq)]),
+        op!(295,"running_rectangle",0,op_running_rectangle,[In(Handle("coords")),In(Handle("coords")),In(Numbers),In(Handle("paint")),In(Handles("leaf"))]),
+        op!(296,"small_value",0,op_small_value,[Out(StringSeq),In(String),In(String),In(StringSeq)]),
+        op!(297,"only_warm",0,op_only_warm,[Out(Boolean)]),
+        op!(298,"stick",0,op_stick,[Out(String)]),
+        op!(299,"rectangle_join",0,op_rectangle_join,[In(Handle("coords")),In(Handle("coords")),In(Handle("paint")),In(Handles("leaf")),In(Handles("leaf"))]),
+        op!(300,"polygon",0,op_polygon,[In(Handle("coords")),In(Numbers),In(Number),In(Number),In(Handle("paint")),In(Handles("leaf"))]),
+        op!(301,"data_length",1,op_data_length,[Out(Number),In(Handle("responses")),In(String)]),
+        op!(302,"data_boolean_range",1,op_data_boolean_range,[Out(BooleanSeq),In(Handle("responses")),In(String),In(Number),In(Number)]),
+        op!(303,"data_number_range",1,op_data_number_range,[Out(NumberSeq),In(Handle("responses")),In(String),In(Number),In(Number)]),
+        op!(304,"data_string_range",1,op_data_string_range,[Out(StringSeq),In(Handle("responses")),In(String),In(Number),In(Number)]),
+        op!(305,"zmenu_new",2,op_zmenu_new,[Out(Handle("zmenus")),In(String),In(StringSeq)]),
+        op!(306,"zmenu_block",2,op_zmenu_block,[In(Handle("zmenus")),In(String),In(Strings)]),
+        op!(307,"zmenu_link",2,op_zmenu_link,[In(Handle("zmenus")),In(Strings),In(Strings)]),
+        op!(308,"zmenu_pair",2,op_zmenu_pair,[In(Handle("zmenus")),In(String),In(Strings)]),
+        op!(309,"zmenu_paint",2,op_zmenu_paint,[Out(Handle("paint")),In(Handle("zmenus")),In(Boolean)]),
+        op!(310,"palette_colour",3,op_palette_colour,[Out(Handle("colours")),In(String)]),
+        op!(311,"paint_blend",4,op_paint_blend,[Out(Handle("paint")),In(Handle("paint")),In(String)]),
+        op!(312,"graph_type_blend",4,op_graph_type_blend,[Out(Handle("graph-types")),In(Handle("graph-types")),In(String)])
+    ];
+}
+
+pub fn libperegrine_operations() -> &'static [OpSpec] { table::OPERATIONS }
+
+pub fn libperegrine_operation(opcode: u64) -> Option<&'static OpSpec> {
+    table::OPERATIONS.iter().find(|op| op.opcode == opcode)
+}
+
+pub fn libperegrine_version() -> (u32,u32) {
+    (0,table::OPERATIONS.iter().map(|op| op.since).max().unwrap_or(0))
+}
+
+/* Opcodes in this range are libperegrine's, whether or not this client has them. Others belong to
+ * other libraries and are not checked here.
+ */
+pub const LIBPEREGRINE_OPCODES : Range<u64> = 256..512;
+
+/* Checks the libperegrine operations used by a program, given as (opcode,number of registers),
+ * against the table. The error lists every opcode missing from this client and every use with
+ * the wrong number of registers.
+ */
+pub fn check_operations(uses: &[(u64,usize)]) -> Result<(),String> {
+    let mut missing = BTreeSet::new();
+    let mut bad_arity = BTreeSet::new();
+    for (opcode,arity) in uses.iter().filter(|(opcode,_)| LIBPEREGRINE_OPCODES.contains(opcode)) {
+        match libperegrine_operation(*opcode) {
+            Some(op) if op.arity() != *arity => {
+                bad_arity.insert(format!("{} ({}) used with {} registers, not {}",op.opcode,op,arity,op.arity()));
+            },
+            Some(_) => {},
+            None => { missing.insert(opcode.to_string()); }
+        }
+    }
+    let mut problems = vec![];
+    if missing.len() > 0 {
+        let (major,minor) = libperegrine_version();
+        problems.push(format!("operations missing from libperegrine {}.{}: {}",major,minor,missing.into_iter().collect::<Vec<_>>().join(", ")));
+    }
+    problems.extend(bad_arity.into_iter());
+    if problems.len() > 0 { Err(problems.join("; ")) } else { Ok(()) }
+}
+
+pub(crate) fn add_operations(builder: &mut InterpreterBuilder) {
+    let (major,minor) = libperegrine_version();
+    builder.add_version("libperegrine",(major as _,minor as _));
+    for op in table::OPERATIONS {
+        builder.add_operation(op.opcode as _,Operation::new(op.factory));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opcodes_unique() {
+        let mut seen = BTreeSet::new();
+        for op in libperegrine_operations() {
+            assert!(seen.insert(op.opcode),"duplicate opcode {}",op.opcode);
+            assert!(LIBPEREGRINE_OPCODES.contains(&op.opcode),"opcode {} out of range",op.opcode);
+        }
+        let names = libperegrine_operations().iter().map(|op| op.name).collect::<BTreeSet<_>>();
+        assert_eq!(libperegrine_operations().len(),names.len());
+    }
+
+    #[test]
+    fn test_check_operations() {
+        assert!(check_operations(&[(256,2),(268,3),(5,1),(600,9)]).is_ok());
+        let error = check_operations(&[(256,2),(500,1),(499,0),(500,2),(268,2)]).err().unwrap();
+        assert!(error.contains(": 499, 500;"),"{}",error);
+        assert!(error.contains("268 (data_number(handle(responses),string) -> seq(number)) used with 2 registers, not 3"),"{}",error);
+    }
+
+    #[test]
+    fn test_version() {
+        let (_,minor) = libperegrine_version();
+        assert!(libperegrine_operations().iter().any(|op| op.since == minor));
+        assert_eq!(Some("paint_blend"),libperegrine_operation(311).map(|op| op.name));
+        assert!(libperegrine_operation(255).is_none());
+    }
+}
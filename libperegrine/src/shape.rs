@@ -3,21 +3,7 @@ use eachorevery::EachOrEvery;
 use eard_interp::{GlobalContext, GlobalBuildContext, Return, HandleStore, AsyncReturn };
 use peregrine_data::{ProgramShapesBuilder, SpaceBaseArea, PartialSpaceBase, SpaceBase, LeafRequest, Patina, Plotter, Pen, AccessorResolver, BackendNamespace};
 use peregrine_toolkit::{lock};
-use crate::util::{eoe_from_handle, eoe_from_number};
-
-fn eoe_from_string_reg(ctx: &GlobalContext, reg: usize) -> Result<EachOrEvery<String>,String> {
-    Ok(if !ctx.is_finite(reg)? {
-        EachOrEvery::every(ctx.force_infinite_string(reg)?.to_string())
-    } else if ctx.is_atomic(reg)? {
-        EachOrEvery::every(ctx.force_string(reg)?.to_string())
-    } else {
-        EachOrEvery::each(
-            ctx.force_finite_string(reg)?.iter().map(|h| {
-                h.to_string()
-            }).collect::<Vec<_>>()
-        )
-    })
-}
+use crate::util::{eoe_from_handle, eoe_from_number, eoe_from_string_reg};
 
 fn rectangle(gctx: &GlobalBuildContext, run: bool, join: bool) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
     let run1 = if run { 1 } else { 0 };
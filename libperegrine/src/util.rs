@@ -26,3 +26,17 @@ pub(crate) fn eoe_from_number(ctx: &GlobalContext, reg: usize) -> Result<EachOrE
         )
     })
 }
+
+pub(crate) fn eoe_from_string_reg(ctx: &GlobalContext, reg: usize) -> Result<EachOrEvery<String>,String> {
+    Ok(if !ctx.is_finite(reg)? {
+        EachOrEvery::every(ctx.force_infinite_string(reg)?.to_string())
+    } else if ctx.is_atomic(reg)? {
+        EachOrEvery::every(ctx.force_string(reg)?.to_string())
+    } else {
+        EachOrEvery::each(
+            ctx.force_finite_string(reg)?.iter().map(|h| {
+                h.to_string()
+            }).collect::<Vec<_>>()
+        )
+    })
+}
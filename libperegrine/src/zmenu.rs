@@ -0,0 +1,274 @@
+use std::sync::Arc;
+use eachorevery::EachOrEvery;
+use eachorevery::eoestruct::{ StructPair, StructTemplate, StructVar, StructVarGroup };
+use eard_interp::{ GlobalBuildContext, GlobalContext, HandleStore, Return, Value };
+use peregrine_data::{ HotspotPatina, Patina };
+use crate::util::eoe_from_string_reg;
+
+/* Structured zmenus are built from typed parts rather than a template string so that the front-end
+ * needn't parse anything. Each feature (ie each shape painted) gets the content
+ *
+ * { "feature": <id>, "lines": [ <line>, ... ] }
+ *
+ * where each line is one of
+ *
+ * { "type": "block", "kind": <kind>, "text": <text> }
+ * { "type": "link", "text": <text>, "href": <href> }
+ * { "type": "pair", "key": <key>, "value": <value> }
+ *
+ * and the variety is { "type": <zmenu type>, "schema": "zmenu/1" }. Shapes with the same feature
+ * id belong to the same feature and the front-end groups their lines into one zmenu. Apart from
+ * the ids, which are one per feature, each value may be one per feature or one for all of them.
+ */
+
+const SCHEMA : &str = "zmenu/1";
+
+#[derive(Clone)]
+enum ZMenuLine {
+    Block(String,EachOrEvery<String>),
+    Link(EachOrEvery<String>,EachOrEvery<String>),
+    Pair(String,EachOrEvery<String>)
+}
+
+#[derive(Clone)]
+pub(crate) struct ZMenuBuilder {
+    variety: String,
+    features: Vec<String>,
+    lines: Vec<ZMenuLine>
+}
+
+/* kinds and types are identifiers for the front-end to switch on */
+fn check_name(what: &str, name: &str) -> Result<(),String> {
+    let valid = name.len() > 0 && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid { Ok(()) } else { Err(format!("bad zmenu {} '{}'",what,name)) }
+}
+
+fn constant(value: &str) -> StructTemplate {
+    StructTemplate::new_string(value.to_string())
+}
+
+fn variable(group: &mut StructVarGroup, values: &EachOrEvery<String>) -> StructTemplate {
+    StructTemplate::new_var(&StructVar::new_string(group,values.clone()))
+}
+
+fn object(pairs: Vec<(&str,StructTemplate)>) -> StructTemplate {
+    StructTemplate::new_object(pairs.into_iter().map(|(k,v)| StructPair::new(k,v)).collect())
+}
+
+impl ZMenuBuilder {
+    fn new(variety: &str, features: &[String]) -> Result<ZMenuBuilder,String> {
+        check_name("type",variety)?;
+        Ok(ZMenuBuilder {
+            variety: variety.to_string(),
+            features: features.to_vec(),
+            lines: vec![]
+        })
+    }
+
+    fn check_len(&self, values: &EachOrEvery<String>) -> Result<(),String> {
+        if values.compatible(self.features.len()) {
+            Ok(())
+        } else {
+            Err(format!("zmenu has {} features but line has a different number of values",self.features.len()))
+        }
+    }
+
+    fn add_block(&mut self, kind: &str, text: EachOrEvery<String>) -> Result<(),String> {
+        check_name("block kind",kind)?;
+        self.check_len(&text)?;
+        self.lines.push(ZMenuLine::Block(kind.to_string(),text));
+        Ok(())
+    }
+
+    fn add_link(&mut self, text: EachOrEvery<String>, href: EachOrEvery<String>) -> Result<(),String> {
+        self.check_len(&text)?;
+        self.check_len(&href)?;
+        self.lines.push(ZMenuLine::Link(text,href));
+        Ok(())
+    }
+
+    fn add_pair(&mut self, key: &str, value: EachOrEvery<String>) -> Result<(),String> {
+        if key.len() == 0 { return Err("empty zmenu key".to_string()); }
+        self.check_len(&value)?;
+        self.lines.push(ZMenuLine::Pair(key.to_string(),value));
+        Ok(())
+    }
+
+    fn line(&self, group: &mut StructVarGroup, line: &ZMenuLine) -> StructTemplate {
+        match line {
+            ZMenuLine::Block(kind,text) => object(vec![
+                ("type",constant("block")),
+                ("kind",constant(kind)),
+                ("text",variable(group,text))
+            ]),
+            ZMenuLine::Link(text,href) => object(vec![
+                ("type",constant("link")),
+                ("text",variable(group,text)),
+                ("href",variable(group,href))
+            ]),
+            ZMenuLine::Pair(key,value) => object(vec![
+                ("type",constant("pair")),
+                ("key",constant(key)),
+                ("value",variable(group,value))
+            ])
+        }
+    }
+
+    fn build(&self) -> Result<HotspotPatina,String> {
+        if self.features.len() == 0 {
+            return Err("zmenu has no features".to_string());
+        }
+        let variety = object(vec![
+            ("type",constant(&self.variety)),
+            ("schema",constant(SCHEMA))
+        ]);
+        let mut group = StructVarGroup::new();
+        let features = EachOrEvery::each(self.features.clone());
+        let lines = self.lines.iter().map(|line| self.line(&mut group,line)).collect::<Vec<_>>();
+        let content = object(vec![
+            ("feature",variable(&mut group,&features)),
+            ("lines",StructTemplate::new_array(lines))
+        ]);
+        let content = StructTemplate::new_all(&mut group,content);
+        Ok(HotspotPatina::Click(Arc::new(variety),Arc::new(content)))
+    }
+}
+
+pub(crate) fn op_zmenu_new(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let zmenus = gctx.patterns.lookup::<HandleStore<ZMenuBuilder>>("zmenus")?;
+    Ok(Box::new(move |ctx,regs| {
+        let variety = ctx.force_string(regs[1])?;
+        let features = ctx.force_finite_string(regs[2])?;
+        let zmenu = ZMenuBuilder::new(variety,features)?;
+        let zmenus = ctx.context.get_mut(&zmenus);
+        let h = zmenus.push(zmenu);
+        ctx.set(regs[0],Value::Number(h as f64))?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_zmenu_block(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let zmenus = gctx.patterns.lookup::<HandleStore<ZMenuBuilder>>("zmenus")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[0])? as usize;
+        let kind = ctx.force_string(regs[1])?.to_string();
+        let text = eoe_from_string_reg(ctx,regs[2])?;
+        let zmenus = ctx.context.get_mut(&zmenus);
+        zmenus.get_mut(h)?.add_block(&kind,text)?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_zmenu_link(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let zmenus = gctx.patterns.lookup::<HandleStore<ZMenuBuilder>>("zmenus")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[0])? as usize;
+        let text = eoe_from_string_reg(ctx,regs[1])?;
+        let href = eoe_from_string_reg(ctx,regs[2])?;
+        let zmenus = ctx.context.get_mut(&zmenus);
+        zmenus.get_mut(h)?.add_link(text,href)?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_zmenu_pair(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let zmenus = gctx.patterns.lookup::<HandleStore<ZMenuBuilder>>("zmenus")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[0])? as usize;
+        let key = ctx.force_string(regs[1])?.to_string();
+        let value = eoe_from_string_reg(ctx,regs[2])?;
+        let zmenus = ctx.context.get_mut(&zmenus);
+        zmenus.get_mut(h)?.add_pair(&key,value)?;
+        Ok(Return::Sync)
+    }))
+}
+
+pub(crate) fn op_zmenu_paint(gctx: &GlobalBuildContext) -> Result<Box<dyn Fn(&mut GlobalContext,&[usize]) -> Result<Return,String>>,String> {
+    let zmenus = gctx.patterns.lookup::<HandleStore<ZMenuBuilder>>("zmenus")?;
+    let paints = gctx.patterns.lookup::<HandleStore<Patina>>("paint")?;
+    Ok(Box::new(move |ctx,regs| {
+        let h = ctx.force_number(regs[1])? as usize;
+        let hover = ctx.force_boolean(regs[2])?;
+        let zmenus = ctx.context.get(&zmenus);
+        let hotspot = zmenus.get(h)?.build()?;
+        let paints = ctx.context.get_mut(&paints);
+        let h = paints.push(Patina::Hotspot(hotspot,hover));
+        ctx.set(regs[0],Value::Number(h as f64))?;
+        Ok(Return::Sync)
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use eachorevery::eoestruct::StructValue;
+    use serde_json::{ json, Value as JsonValue };
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|x| x.to_string()).collect()
+    }
+
+    /* as the front-end sees a click on the index-th feature */
+    fn expand(zmenu: &ZMenuBuilder, index: usize) -> (JsonValue,JsonValue) {
+        let (variety,content) = match zmenu.build().unwrap() {
+            HotspotPatina::Click(variety,content) => (variety,content),
+            _ => panic!("not a click")
+        };
+        let variety = StructValue::new_expand(&variety.build().ok().unwrap(),None).ok().unwrap();
+        let content = content.set_index(&[],index).ok().unwrap().build().ok().unwrap();
+        let content = match StructValue::new_expand(&content,None).ok().unwrap() {
+            StructValue::Array(a) => a.get(0).cloned().unwrap(),
+            _ => panic!("not an array")
+        };
+        (variety.to_json_value(),content.to_json_value())
+    }
+
+    #[test]
+    fn test_zmenu_lines() {
+        let mut zmenu = ZMenuBuilder::new("gene",&strings(&["g1","g2"])).unwrap();
+        zmenu.add_block("title",EachOrEvery::each(strings(&["BRCA1","BRCA2"]))).unwrap();
+        zmenu.add_link(EachOrEvery::every("more".to_string()),EachOrEvery::each(strings(&["/g1","/g2"]))).unwrap();
+        zmenu.add_pair("Strand",EachOrEvery::every("forward".to_string())).unwrap();
+        let (variety,first) = expand(&zmenu,0);
+        assert_eq!(json!({ "type": "gene", "schema": "zmenu/1" }),variety);
+        assert_eq!(json!({
+            "feature": "g1",
+            "lines": [
+                { "type": "block", "kind": "title", "text": "BRCA1" },
+                { "type": "link", "text": "more", "href": "/g1" },
+                { "type": "pair", "key": "Strand", "value": "forward" }
+            ]
+        }),first);
+        let (_,second) = expand(&zmenu,1);
+        assert_eq!(json!({
+            "feature": "g2",
+            "lines": [
+                { "type": "block", "kind": "title", "text": "BRCA2" },
+                { "type": "link", "text": "more", "href": "/g2" },
+                { "type": "pair", "key": "Strand", "value": "forward" }
+            ]
+        }),second);
+    }
+
+    #[test]
+    fn test_zmenu_empty() {
+        let zmenu = ZMenuBuilder::new("transcript",&strings(&["t1"])).unwrap();
+        let (variety,content) = expand(&zmenu,0);
+        assert_eq!(json!({ "type": "transcript", "schema": "zmenu/1" }),variety);
+        assert_eq!(json!({ "feature": "t1", "lines": [] }),content);
+        assert!(ZMenuBuilder::new("gene",&[]).unwrap().build().is_err());
+    }
+
+    #[test]
+    fn test_zmenu_bad() {
+        assert!(ZMenuBuilder::new("Gene",&strings(&["g1"])).is_err());
+        assert!(ZMenuBuilder::new("",&strings(&["g1"])).is_err());
+        let mut zmenu = ZMenuBuilder::new("gene",&strings(&["g1","g2"])).unwrap();
+        assert!(zmenu.add_block("a title",EachOrEvery::every("x".to_string())).is_err());
+        assert!(zmenu.add_block("title",EachOrEvery::each(strings(&["x"]))).is_err());
+        assert!(zmenu.add_link(EachOrEvery::every("x".to_string()),EachOrEvery::each(strings(&["a","b","c"]))).is_err());
+        assert!(zmenu.add_pair("",EachOrEvery::every("x".to_string())).is_err());
+        assert!(zmenu.add_pair("key",EachOrEvery::each(strings(&["x"]))).is_err());
+        assert_eq!(0,zmenu.lines.len());
+    }
+}
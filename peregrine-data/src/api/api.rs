@@ -5,7 +5,9 @@ use crate::train::drawing::drawingcarriage::DrawingCarriage;
 use crate::DataMessage;
 use crate::core::Viewport;
 use crate::core::Assets;
+use crate::util::persistentcache::PersistentStore;
 use peregrine_toolkit::identitynumber;
+use std::sync::Arc;
 
 #[derive(Debug,Clone,PartialEq,Eq,Hash)]
 pub enum CarriageSpeed {
@@ -40,4 +42,7 @@ pub trait PeregrineIntegration {
     fn notify_viewport(&mut self, viewport: &Viewport);
     fn notify_allotment_metadata(&mut self, metadata: &GlobalAllotmentMetadata);
     fn set_playing_field(&mut self, playing_field: PlayingField);
+
+    /* Somewhere to cache data between sessions, if the platform has one */
+    fn persistent_store(&self) -> Option<Arc<dyn PersistentStore>> { None }
 }
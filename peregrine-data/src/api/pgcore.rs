@@ -25,6 +25,7 @@ use crate::api::AgentStore;
 use crate::core::{ StickId };
 use crate::train::graphics::Graphics;
 use crate::util::message::DataMessage;
+use crate::util::persistentcache::PersistentCache;
 use crate::switch::switches::Switches;
 
 #[derive(Clone)]
@@ -58,6 +59,7 @@ pub struct PeregrineCoreBase {
     pub integration: Arc<Mutex<Box<dyn PeregrineIntegration>>>,
     pub assets: Arc<Mutex<Assets>>,
     pub version: VersionMetadata,
+    pub persistent: Option<PersistentCache>,
     pub redraw_needed: Needed,
     pub shutdown: OneShot
 }
@@ -75,6 +77,7 @@ impl PeregrineCore {
     pub fn new<M,F>(integration: Box<dyn PeregrineIntegration>, commander: M, messages: F, queue: &PeregrineApiQueue, redraw_needed: &Needed, mut channel_integrations: Vec<Rc<dyn ChannelIntegration>>) -> Result<PeregrineCore,DataMessage> 
                 where M: Commander + 'static, F: FnMut(Error) + 'static + Send {
         let shutdown = OneShot::new();
+        let persistent_store = integration.persistent_store();
         let integration = Arc::new(Mutex::new(integration));
         let graphics = Graphics::new(&integration);
         let commander = PgCommander::new(Box::new(commander));
@@ -90,12 +93,14 @@ impl PeregrineCore {
         let dauphin = PgDauphin::new(&dauphin_queue,&channel_registry,&booted).map_err(|e| DataMessage::XXXTransitional(Error::fatal(&format!("could not create: {}",e))))?;
         let mut switches = Switches::new(&dauphin);
         let version = VersionMetadata::new();
+        let persistent = persistent_store.map(|store| PersistentCache::new(store,&version));
         let sidecars = RequestSidecars::new(&dauphin,&switches,&queue);
         let low_manager = LowLevelRequestManager::new(&sidecars,&commander,&shutdown,&messages,&version);
         let manager = RequestManager::new(&low_manager,&channel_registry);
         let all_backends = AllBackends::new(&manager,&metrics);
         switches.set_all_backends(&all_backends);
         dauphin.set_all_backends(&all_backends);
+        dauphin.set_persistent(persistent.as_ref());
         let base = PeregrineCoreBase {
             answer_allocator: Arc::new(Mutex::new(AnswerAllocator::new())),
             channel_registry,
@@ -113,6 +118,7 @@ impl PeregrineCore {
             identity: Arc::new(Mutex::new(0)),
             assets: Arc::new(Mutex::new(Assets::empty())),
             version,
+            persistent,
             redraw_needed: redraw_needed.clone(),
             shutdown
         };
@@ -5,6 +5,7 @@ use crate::request::core::minirequest::MiniRequest;
 use crate::request::minirequests::metricreq::MetricReport;
 use crate::run::{add_task};
 use crate::shapeload::carriagebuilder::CarriageBuilder;
+use crate::shapeload::loadshapes::LoadMode;
 use crate::train::main::datatasks::{load_stick, load_carriage};
 use crate::train::main::train::StickData;
 use crate::train::model::trainextent::TrainExtent;
@@ -74,7 +75,7 @@ use super::pgcore::PeregrineCore;
     CarriageLoaded(DrawingCarriage),
     Invalidate,
     LoadStick(TrainExtent,Arc<Mutex<StickData>>),
-    LoadCarriage(CarriageBuilder),
+    LoadCarriage(CarriageBuilder,LoadMode),
     Shutdown
 }
 
@@ -106,8 +107,8 @@ impl ApiQueueCampaign {
             ApiMessage::LoadStick(extent,output) => {
                 load_stick(&mut data.base,&data.agent_store.stick_store,&extent,&output);
             },
-            ApiMessage::LoadCarriage(builder) => {
-                load_carriage(&mut data.base, &data.agent_store.lane_store,&builder);
+            ApiMessage::LoadCarriage(builder,mode) => {
+                load_carriage(&mut data.base, &data.agent_store.lane_store,&builder,&mode);
             }
             ApiMessage::TransitionComplete => {
                 data.train_set.transition_complete();
@@ -252,6 +253,10 @@ impl PeregrineApiQueue {
         self.push(ApiMessage::CarriageLoaded(drawing_carriage.clone()));
     }
 
+    pub fn transition_complete(&self) {
+        self.push(ApiMessage::TransitionComplete);
+    }
+
     pub(crate) fn regenerate_track_config(&self) {
         self.push(ApiMessage::RegenerateTrackConfig);
     }
@@ -264,8 +269,8 @@ impl PeregrineApiQueue {
         self.push(ApiMessage::LoadStick(extent.clone(),output.clone()));
     }
 
-    pub(crate) fn load_carriage(&self, builder: &CarriageBuilder) {
-        self.push(ApiMessage::LoadCarriage(builder.clone()));
+    pub(crate) fn load_carriage(&self, builder: &CarriageBuilder, mode: LoadMode) {
+        self.push(ApiMessage::LoadCarriage(builder.clone(),mode));
     }
 
     pub fn shutdown(&self) {
@@ -35,6 +35,8 @@ impl Assets {
         Assets { assets: Arc::new(Mutex::new(HashMap::new())) }
     }
 
+    pub(crate) fn is_empty(&self) -> bool { lock!(self.assets).is_empty() }
+
     pub fn add(&mut self, assets: &Assets) {
         let mut self_assets = lock!(self.assets);
         for (key,value) in lock!(assets.assets).iter() {
@@ -11,7 +11,7 @@ fn matches_source(accessor: &str) -> bool {
 
 #[derive(Clone)]
 pub struct AccessorResolver {
-    registry: ChannelRegistry,
+    registry: Option<ChannelRegistry>,
     program_base: BackendNamespace,
     track_base: BackendNamespace
 }
@@ -19,7 +19,16 @@ pub struct AccessorResolver {
 impl AccessorResolver {
     pub fn new(registry: &ChannelRegistry, program_base: &BackendNamespace, track_base: &BackendNamespace) -> AccessorResolver {
         AccessorResolver {
-            registry: registry.clone(),
+            registry: Some(registry.clone()),
+            program_base: program_base.clone(),
+            track_base: track_base.clone()
+        }
+    }
+
+    /* Without a registry only self() and source() can be resolved, as when running natively */
+    pub fn new_fixed(program_base: &BackendNamespace, track_base: &BackendNamespace) -> AccessorResolver {
+        AccessorResolver {
+            registry: None,
             program_base: program_base.clone(),
             track_base: track_base.clone()
         }
@@ -30,12 +39,14 @@ impl AccessorResolver {
             Ok(self.track_base.clone())
         } else if matches_source(accessor) {
             Ok(self.program_base.clone())
+        } else if let Some(registry) = &self.registry {
+            registry.spec_to_name(accessor).await
         } else {
-            self.registry.spec_to_name(accessor).await
+            Err(Error::operr(&format!("cannot resolve {} without channels",accessor)))
         }
     }
 
     pub fn all(&self) -> Vec<BackendNamespace> {
-        self.registry.all()
+        self.registry.as_ref().map(|r| r.all()).unwrap_or_else(|| vec![])
     }
 }
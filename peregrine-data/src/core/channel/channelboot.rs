@@ -25,6 +25,7 @@ pub(super) async fn boot_channel(base: &PeregrineCoreBase, name: &BackendNamespa
     let response = base.manager.submit_direct(sender,&PacketPriority::RealTime,&Some(name.clone()),request, |v| {
         v.into_variety().into_boot_channel()
     }).await?;
+    base.manager.set_backend_features(name,response.features());
     finish_bootstrap(&response,base)
 }
 
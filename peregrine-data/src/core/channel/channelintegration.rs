@@ -28,6 +28,11 @@ impl ChannelMessageDecoder {
         ChannelMessageDecoder { sender: sender.clone() }
     }
 
+    /* For decoding a channel's packets natively, without a RequestManager (eg in tests) */
+    pub fn new_fixed(sender: &Arc<dyn ChannelSender>) -> ChannelMessageDecoder {
+        ChannelMessageDecoder { sender: WrappedChannelSender::new(sender.clone()) }
+    }
+
     pub fn serde_deserialize_maxi(&self, payload: Arc<dyn Any>) -> MaxiResponseDeserialize {
         MaxiResponseDeserialize(self.sender.clone(),payload)
     }
@@ -37,6 +42,8 @@ pub fn null_payload() -> Arc<dyn Any> { Arc::new(()) }
 
 pub trait ChannelSender {
     fn get_sender(&self, prio: &PacketPriority, data: MaxiRequest, decoder: ChannelMessageDecoder) -> Pin<Box<dyn Future<Output=Result<MaxiResponse,Error>>>>;
+    /* Senders which receive CBOR packets over the wire can supply them undecoded, eg for recording */
+    fn get_raw_sender(&self, _prio: &PacketPriority, _data: MaxiRequest) -> Option<Pin<Box<dyn Future<Output=Result<Vec<u8>,Error>>>>> { None }
     fn deserialize_data(&self, _payload: &dyn Any, _bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> { Ok(None) }
     fn deserialize_index(&self, _payload: &dyn Any, _index: usize) -> Result<Option<Vec<u8>>,String> { Ok(None) }
     fn backoff(&self) -> bool;
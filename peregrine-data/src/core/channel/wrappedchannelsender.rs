@@ -36,6 +36,10 @@ impl ChannelSender for WrappedChannelSender {
         self.sender.get_sender(prio,data,decoder)
     }
 
+    fn get_raw_sender(&self, prio: &PacketPriority, data: MaxiRequest) -> Option<Pin<Box<dyn Future<Output=Result<Vec<u8>,Error>>>>> {
+        self.sender.get_raw_sender(prio,data)
+    }
+
     fn deserialize_data(&self, payload: &dyn Any, bytes: Vec<u8>) -> Result<Option<HashMap<String,DataAlgorithm>>,String> {
         self.sender.deserialize_data(payload,bytes)
     }
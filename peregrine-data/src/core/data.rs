@@ -1,4 +1,5 @@
 use std::{sync::Arc};
+use serde::{Serialize, Serializer, ser::{SerializeSeq, Error as _}};
 
 #[derive(Debug)]
 pub enum ReceivedDataType { Bytes, Booleans, Numbers, Strings, Empty }
@@ -57,6 +58,19 @@ impl ReceivedData {
         }
     }
 
+    /* Range is clamped to the data */
+    pub fn slice(&self, start: usize, end: usize) -> ReceivedData {
+        let end = end.min(self.len());
+        let start = start.min(end);
+        match self {
+            ReceivedData::Bytes(x) => ReceivedData::new_bytes(x[start..end].to_vec()),
+            ReceivedData::Booleans(x) => ReceivedData::new_booleans(x[start..end].to_vec()),
+            ReceivedData::Numbers(x) => ReceivedData::new_numbers(x[start..end].to_vec()),
+            ReceivedData::Strings(x) => ReceivedData::new_strings(x[start..end].to_vec()),
+            ReceivedData::Empty => ReceivedData::Empty
+        }
+    }
+
     pub fn data_as_bytes(&self) -> Result<Arc<Vec<u8>>,()> {
         match self {
             ReceivedData::Bytes(x) => Ok(x.clone()),
@@ -89,3 +103,36 @@ impl ReceivedData {
         }
     }
 }
+
+impl ReceivedData {
+    /* Code of the simplest DataAlgorithm which decodes to this data, taking this data as its only
+     * stream (or no stream, if empty). There's no algorithm for a stream of bytes.
+     */
+    pub(crate) fn array_code(&self) -> Result<&'static str,()> {
+        match self {
+            ReceivedData::Bytes(_) => Err(()),
+            ReceivedData::Booleans(_) => Ok("BA"),
+            ReceivedData::Numbers(_) => Ok("NRA"),
+            ReceivedData::Strings(_) => Ok("SA"),
+            ReceivedData::Empty => Ok("E")
+        }
+    }
+}
+
+/* As the simplest DataAlgorithm which decodes to this data, for data responses which must be sent
+ * again (eg when recorded).
+ */
+impl Serialize for ReceivedData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let code = self.array_code().map_err(|_| S::Error::custom("cannot send a stream of bytes"))?;
+        let mut seq = serializer.serialize_seq(None)?;
+        seq.serialize_element(code)?;
+        match self {
+            ReceivedData::Booleans(x) => { seq.serialize_element(x.as_slice())?; },
+            ReceivedData::Numbers(x) => { seq.serialize_element(x.as_slice())?; },
+            ReceivedData::Strings(x) => { seq.serialize_element(x.as_slice())?; },
+            _ => {}
+        }
+        seq.end()
+    }
+}
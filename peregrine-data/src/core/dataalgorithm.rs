@@ -1,5 +1,5 @@
 use std::{str::{Chars, from_utf8}, sync::{Arc}, fmt};
-use peregrine_toolkit::{lesqlite2::lesqlite2_decode, serdetools::{st_field, ByteData}, log};
+use peregrine_toolkit::{lesqlite2::{lesqlite2_count, lesqlite2_decode, Lesqlite2Iter}, serdetools::{st_field, ByteData}, log};
 use serde::{Deserialize, Deserializer, de::{Visitor, self, IgnoredAny}};
 
 use super::data::{ReceivedData, ReceivedDataType};
@@ -8,6 +8,23 @@ fn pop(data: &mut Vec<ReceivedData>) -> Result<ReceivedData,()> {
     data.pop().ok_or(())
 }
 
+/* Values can be decoded one at a time so that a slice can be taken without decoding into a
+ * vector the size of the whole column. Iterators own their data so they can outlive the algorithm.
+ */
+type ValueIter<T> = Box<dyn Iterator<Item=Result<T,()>>>;
+type NumberIter = ValueIter<f64>;
+
+struct ArcBytes(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for ArcBytes {
+    fn as_ref(&self) -> &[u8] { &self.0 }
+}
+
+fn unzigzag(v: f64) -> f64 {
+    let v = v as i64;
+    (if v%2 == 1 { -((v+1)/2) } else { v/2 }) as f64
+}
+
 #[cfg_attr(debug_assertions,derive(Debug))]
 pub enum NumberSourceAlgorithm {
     Array(ReceivedData),
@@ -23,6 +40,13 @@ impl NumberSourceAlgorithm {
         })
     }
 
+    fn raw(&self, code: &mut String, data: &mut Vec<ReceivedData>) {
+        match self {
+            NumberSourceAlgorithm::Array(x) => { code.push('A'); data.push(x.clone()); },
+            NumberSourceAlgorithm::Lesqlite2(x) => { code.push('L'); data.push(x.clone()); }
+        }
+    }
+
     fn specify<'a>(code: &mut Chars<'a>, spec: &mut Vec<ReceivedDataType>) -> Result<(),()> {
          match code.next() {
             Some('A') => { spec.push(ReceivedDataType::Numbers); },
@@ -42,13 +66,53 @@ impl NumberSourceAlgorithm {
             }
         }
     }
+
+    fn iter(&self) -> Result<NumberIter,()> {
+        Ok(match self {
+            NumberSourceAlgorithm::Array(data) => {
+                let data = data.data_as_numbers()?;
+                Box::new((0..data.len()).map(move |i| Ok(data[i])))
+            },
+            NumberSourceAlgorithm::Lesqlite2(data) => {
+                Box::new(Lesqlite2Iter::new(ArcBytes(data.data_as_bytes()?)))
+            }
+        })
+    }
+
+    fn len(&self) -> Result<usize,()> {
+        match self {
+            NumberSourceAlgorithm::Array(data) => Ok(data.data_as_numbers()?.len()),
+            NumberSourceAlgorithm::Lesqlite2(data) => lesqlite2_count(&data.data_as_bytes()?)
+        }
+    }
+
+    fn validate(&self) -> Result<(),()> {
+        match self {
+            NumberSourceAlgorithm::Array(data) => { data.data_as_numbers()?; },
+            NumberSourceAlgorithm::Lesqlite2(data) => { data.data_as_bytes()?; }
+        }
+        Ok(())
+    }
 }
 
+/* RunLength is values then run lengths: each value is repeated its length times. Then comes the
+ * declared total number of values, which the runs must add up to exactly. Run lengths are never
+ * trusted beyond it, so a corrupt stream can't demand a huge allocation.
+ */
 #[cfg_attr(debug_assertions,derive(Debug))]
 pub enum NumberAlgorithm {
     Raw(NumberSourceAlgorithm),
     Zigzag(Box<NumberAlgorithm>),
-    Delta(Box<NumberAlgorithm>)
+    Delta(Box<NumberAlgorithm>),
+    RunLength(Box<NumberAlgorithm>,Box<NumberAlgorithm>,usize)
+}
+
+fn run_total(data: ReceivedData) -> Result<usize,()> {
+    let total = data.data_as_numbers()?;
+    match total.as_slice() {
+        [total] if total.is_finite() && *total >= 0. => Ok(*total as usize),
+        _ => Err(())
+    }
 }
 
 impl NumberAlgorithm {
@@ -57,15 +121,40 @@ impl NumberAlgorithm {
             Some('R') => NumberAlgorithm::Raw(NumberSourceAlgorithm::new(code,data)?),
             Some('Z') => NumberAlgorithm::Zigzag(Box::new(NumberAlgorithm::new(code,data)?)),
             Some('D') => NumberAlgorithm::Delta(Box::new(NumberAlgorithm::new(code,data)?)),
+            Some('U') => {
+                let values = NumberAlgorithm::new(code,data)?;
+                let lengths = NumberAlgorithm::new(code,data)?;
+                let total = run_total(pop(data)?)?;
+                NumberAlgorithm::RunLength(Box::new(values),Box::new(lengths),total)
+            },
             _ => { return Err(()); }
         })
     }
 
+    fn raw(&self, code: &mut String, data: &mut Vec<ReceivedData>) {
+        match self {
+            NumberAlgorithm::Raw(inner) => { code.push('R'); inner.raw(code,data); },
+            NumberAlgorithm::Zigzag(inner) => { code.push('Z'); inner.raw(code,data); },
+            NumberAlgorithm::Delta(inner) => { code.push('D'); inner.raw(code,data); },
+            NumberAlgorithm::RunLength(values,lengths,total) => {
+                code.push('U');
+                values.raw(code,data);
+                lengths.raw(code,data);
+                data.push(ReceivedData::new_numbers(vec![*total as f64]));
+            }
+        }
+    }
+
     fn specify<'a>(code: &mut Chars<'a>, spec: &mut Vec<ReceivedDataType>) -> Result<(),()> {
         Ok(match code.next() {
             Some('R') => { NumberSourceAlgorithm::specify(code,spec)?; },
             Some('Z') => { NumberAlgorithm::specify(code,spec)?; },
             Some('D') => { NumberAlgorithm::specify(code,spec)?; },
+            Some('U') => {
+                NumberAlgorithm::specify(code,spec)?;
+                NumberAlgorithm::specify(code,spec)?;
+                spec.push(ReceivedDataType::Numbers);
+            },
             _ => { return Err(()); }
         })
     }
@@ -76,10 +165,7 @@ impl NumberAlgorithm {
                 inner.make()
             },
             NumberAlgorithm::Zigzag(data) => {
-                Ok(Arc::new(data.make()?.iter().map(|v| {
-                    let v = *v as i64;
-                    (if v%2 == 1 { -((v+1)/2) } else { v/2 }) as f64
-                }).collect()))
+                Ok(Arc::new(data.make()?.iter().map(|v| unzigzag(*v)).collect()))
             },
             NumberAlgorithm::Delta(data) => {
                 let mut prev = 0.;
@@ -87,6 +173,78 @@ impl NumberAlgorithm {
                     prev += v;
                     prev
                 }).collect()))
+            },
+            NumberAlgorithm::RunLength(values,lengths,total) => {
+                let values = values.make()?;
+                let lengths = lengths.make()?;
+                if values.len() != lengths.len() { return Err(()); }
+                let mut out = vec![];
+                for (value,length) in values.iter().zip(lengths.iter()) {
+                    if length.is_nan() || *length < 0. || *length > (*total - out.len()) as f64 { return Err(()); }
+                    out.extend(std::iter::repeat(*value).take(*length as usize));
+                }
+                if out.len() != *total { return Err(()); }
+                Ok(Arc::new(out))
+            }
+        }
+    }
+
+    fn iter(&self) -> Result<NumberIter,()> {
+        Ok(match self {
+            NumberAlgorithm::Raw(inner) => inner.iter()?,
+            NumberAlgorithm::Zigzag(inner) => {
+                Box::new(inner.iter()?.map(|v| v.map(unzigzag)))
+            },
+            NumberAlgorithm::Delta(inner) => {
+                let mut prev = 0.;
+                Box::new(inner.iter()?.map(move |v| v.map(|v| {
+                    prev += v;
+                    prev
+                })))
+            },
+            NumberAlgorithm::RunLength(values,lengths,total) => {
+                if values.len()? != lengths.len()? { return Err(()); }
+                let mut remaining = *total;
+                let runs = values.iter()?.zip(lengths.iter()?).flat_map(move |(value,length)| -> NumberIter {
+                    match (value,length) {
+                        (Ok(value),Ok(length)) if length >= 0. && length <= remaining as f64 => {
+                            remaining -= length as usize;
+                            Box::new(std::iter::repeat(Ok(value)).take(length as usize))
+                        },
+                        _ => Box::new(std::iter::once(Err(())))
+                    }
+                });
+                /* runs which fall short of the total are an error too */
+                let mut seen = 0;
+                let total = *total;
+                Box::new(runs.map(Some).chain(std::iter::once(None)).filter_map(move |value| {
+                    match value {
+                        Some(value) => { seen += 1; Some(value) },
+                        None if seen < total => Some(Err(())),
+                        None => None
+                    }
+                }))
+            }
+        })
+    }
+
+    fn len(&self) -> Result<usize,()> {
+        match self {
+            NumberAlgorithm::Raw(inner) => inner.len(),
+            NumberAlgorithm::Zigzag(inner) => inner.len(),
+            NumberAlgorithm::Delta(inner) => inner.len(),
+            NumberAlgorithm::RunLength(_,_,total) => Ok(*total)
+        }
+    }
+
+    fn validate(&self) -> Result<(),()> {
+        match self {
+            NumberAlgorithm::Raw(inner) => inner.validate(),
+            NumberAlgorithm::Zigzag(inner) => inner.validate(),
+            NumberAlgorithm::Delta(inner) => inner.validate(),
+            NumberAlgorithm::RunLength(values,lengths,_) => {
+                values.validate()?;
+                lengths.validate()
             }
         }
     }
@@ -115,6 +273,19 @@ impl StringAlgorithm {
         })
     }
 
+    fn raw(&self, code: &mut String, data: &mut Vec<ReceivedData>) {
+        match self {
+            StringAlgorithm::Array(x) => { code.push('A'); data.push(x.clone()); },
+            StringAlgorithm::CharacterSplit(x) => { code.push('C'); data.push(x.clone()); },
+            StringAlgorithm::ZeroSplit(x) => { code.push('Z'); data.push(x.clone()); },
+            StringAlgorithm::Classify(index,values) => {
+                code.push('Y');
+                index.raw(code,data);
+                values.raw(code,data);
+            }
+        }
+    }
+
     fn specify<'a>(code: &mut Chars<'a>, spec: &mut Vec<ReceivedDataType>) -> Result<(),()> {
         match code.next() {
             Some('A') => { spec.push(ReceivedDataType::Strings); },
@@ -157,12 +328,81 @@ impl StringAlgorithm {
             }
         }
     }
+
+    fn iter(&self) -> Result<ValueIter<String>,()> {
+        Ok(match self {
+            StringAlgorithm::Array(data) => {
+                let data = data.data_as_strings()?;
+                Box::new((0..data.len()).map(move |i| Ok(data[i].clone())))
+            },
+            StringAlgorithm::CharacterSplit(data) => {
+                let bytes = data.data_as_bytes()?;
+                let mut i = 0;
+                Box::new(std::iter::from_fn(move || {
+                    let end = (i+utf8_width(*bytes.get(i)?)).min(bytes.len());
+                    let value = from_utf8(&bytes[i..end]).map(|x| x.to_string()).map_err(|_| ());
+                    i = end;
+                    Some(value)
+                }))
+            },
+            StringAlgorithm::ZeroSplit(data) => {
+                let bytes = data.data_as_bytes()?;
+                let mut i = 0;
+                Box::new(std::iter::from_fn(move || {
+                    let len = bytes[i..].iter().position(|b| *b == 0)?;
+                    let value = from_utf8(&bytes[i..(i+len)]).map(|x| x.to_string()).map_err(|_| ());
+                    i += len+1;
+                    Some(value)
+                }))
+            },
+            StringAlgorithm::Classify(index,values) => {
+                let values = values.make()?;
+                Box::new(index.iter()?.map(move |p| {
+                    p.and_then(|p| values.get(p as usize).cloned().ok_or(()))
+                }))
+            }
+        })
+    }
+
+    fn len(&self) -> Result<usize,()> {
+        match self {
+            StringAlgorithm::Array(data) => Ok(data.data_as_strings()?.len()),
+            StringAlgorithm::CharacterSplit(data) => {
+                /* count everything but continuation bytes: validate() checks the encoding */
+                Ok(data.data_as_bytes()?.iter().filter(|b| (**b & 0xC0) != 0x80).count())
+            },
+            StringAlgorithm::ZeroSplit(data) => Ok(data.data_as_bytes()?.iter().filter(|b| **b == 0).count()),
+            StringAlgorithm::Classify(index,_) => index.len()
+        }
+    }
+
+    fn validate(&self) -> Result<(),()> {
+        match self {
+            StringAlgorithm::Array(data) => { data.data_as_strings()?; },
+            StringAlgorithm::CharacterSplit(data) | StringAlgorithm::ZeroSplit(data) => {
+                from_utf8(&data.data_as_bytes()?).map_err(|_| ())?;
+            },
+            StringAlgorithm::Classify(index,values) => {
+                index.validate()?;
+                values.validate()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn utf8_width(lead: u8) -> usize {
+    if lead < 0x80 { 1 } else if lead < 0xE0 { 2 } else if lead < 0xF0 { 3 } else { 4 }
 }
 
+/* Packed is a bitset, least significant bit first, after an initial byte giving the number of
+ * unused bits at the end of the last byte.
+ */
 #[cfg_attr(debug_assertions,derive(Debug))]
 pub enum BooleanAlgorithm {
     Array(ReceivedData),
-    Bytes(ReceivedData)
+    Bytes(ReceivedData),
+    Packed(ReceivedData)
 }
 
 impl BooleanAlgorithm {
@@ -170,14 +410,24 @@ impl BooleanAlgorithm {
         Ok(match code.next() {
             Some('A') => BooleanAlgorithm::Array(pop(data)?),
             Some('B') => BooleanAlgorithm::Bytes(pop(data)?),
+            Some('P') => BooleanAlgorithm::Packed(pop(data)?),
             _ => { return Err(()); }
         })
     }
 
+    fn raw(&self, code: &mut String, data: &mut Vec<ReceivedData>) {
+        match self {
+            BooleanAlgorithm::Array(x) => { code.push('A'); data.push(x.clone()); },
+            BooleanAlgorithm::Bytes(x) => { code.push('B'); data.push(x.clone()); },
+            BooleanAlgorithm::Packed(x) => { code.push('P'); data.push(x.clone()); }
+        }
+    }
+
     fn specify<'a>(code: &mut Chars<'a>, spec: &mut Vec<ReceivedDataType>) -> Result<(),()> {
         match code.next() {
             Some('A') => { spec.push(ReceivedDataType::Booleans); },
             Some('B') => { spec.push(ReceivedDataType::Bytes); },
+            Some('P') => { spec.push(ReceivedDataType::Bytes); },
             _ => { return Err(()); }
         }
         Ok(())
@@ -190,9 +440,57 @@ impl BooleanAlgorithm {
             },
             BooleanAlgorithm::Bytes(data) => {
                 Ok(Arc::new(data.data_as_bytes()?.iter().map(|x| *x!=0).collect()))
+            },
+            BooleanAlgorithm::Packed(data) => {
+                let bytes = data.data_as_bytes()?;
+                let len = packed_len(&bytes)?;
+                Ok(Arc::new((0..len).map(|i| bytes[i/8+1] & (1<<(i%8)) != 0).collect()))
             }
         }
     }
+
+    fn iter(&self) -> Result<ValueIter<bool>,()> {
+        Ok(match self {
+            BooleanAlgorithm::Array(data) => {
+                let data = data.data_as_booleans()?;
+                Box::new((0..data.len()).map(move |i| Ok(data[i])))
+            },
+            BooleanAlgorithm::Bytes(data) => {
+                let data = data.data_as_bytes()?;
+                Box::new((0..data.len()).map(move |i| Ok(data[i] != 0)))
+            },
+            BooleanAlgorithm::Packed(data) => {
+                let bytes = data.data_as_bytes()?;
+                let len = packed_len(&bytes)?;
+                Box::new((0..len).map(move |i| Ok(bytes[i/8+1] & (1<<(i%8)) != 0)))
+            }
+        })
+    }
+
+    fn len(&self) -> Result<usize,()> {
+        match self {
+            BooleanAlgorithm::Array(data) => Ok(data.data_as_booleans()?.len()),
+            BooleanAlgorithm::Bytes(data) => Ok(data.data_as_bytes()?.len()),
+            BooleanAlgorithm::Packed(data) => packed_len(&data.data_as_bytes()?)
+        }
+    }
+
+    fn validate(&self) -> Result<(),()> {
+        match self {
+            BooleanAlgorithm::Array(data) => { data.data_as_booleans()?; },
+            BooleanAlgorithm::Bytes(data) => { data.data_as_bytes()?; },
+            BooleanAlgorithm::Packed(data) => { packed_len(&data.data_as_bytes()?)?; }
+        }
+        Ok(())
+    }
+}
+
+/* Checks the header of a packed bitset */
+fn packed_len(bytes: &[u8]) -> Result<usize,()> {
+    let (unused,bits) = bytes.split_first().ok_or(())?;
+    let unused = *unused as usize;
+    if unused > 7 || (bits.is_empty() && unused != 0) { return Err(()); }
+    Ok(bits.len()*8 - unused)
 }
 
 #[cfg_attr(debug_assertions,derive(Debug))]
@@ -204,7 +502,7 @@ pub enum DataAlgorithm {
 }
 
 impl DataAlgorithm {
-    fn new(code: &str, data: &mut Vec<ReceivedData>) -> Result<DataAlgorithm,()> {
+    pub(crate) fn new(code: &str, data: &mut Vec<ReceivedData>) -> Result<DataAlgorithm,()> {
         let mut code = code.chars();
         data.reverse();
         Ok(match code.next() {
@@ -216,6 +514,21 @@ impl DataAlgorithm {
         })
     }
 
+    /* The code and streams this algorithm was made from, as passed to new(), so that it can be
+     * stored still encoded.
+     */
+    pub(crate) fn raw(&self) -> (String,Vec<ReceivedData>) {
+        let mut code = String::new();
+        let mut data = vec![];
+        match self {
+            DataAlgorithm::Numbers(n) => { code.push('N'); n.raw(&mut code,&mut data); },
+            DataAlgorithm::Strings(s) => { code.push('S'); s.raw(&mut code,&mut data); },
+            DataAlgorithm::Booleans(b) => { code.push('B'); b.raw(&mut code,&mut data); },
+            DataAlgorithm::Empty => { code.push('E'); }
+        }
+        (code,data)
+    }
+
     fn specify(code: &str) -> Result<Vec<ReceivedDataType>,()> {
         let mut code = code.chars();
         let mut spec = vec![];
@@ -245,6 +558,82 @@ impl DataAlgorithm {
             }
         }
     }
+
+    /* Number of values without (where possible) decoding them */
+    pub(crate) fn len(&self) -> Result<usize,()> {
+        match self {
+            DataAlgorithm::Numbers(n) => n.len(),
+            DataAlgorithm::Strings(s) => s.len(),
+            DataAlgorithm::Booleans(b) => b.len(),
+            DataAlgorithm::Empty => Ok(0)
+        }
+    }
+
+    /* Checks types and headers, which is cheap, so that corrupt data is rejected on receipt
+     * rather than when it is first used. Values themselves are only checked as they are decoded.
+     */
+    pub(crate) fn validate(&self) -> Result<(),()> {
+        match self {
+            DataAlgorithm::Numbers(n) => n.validate(),
+            DataAlgorithm::Strings(s) => s.validate(),
+            DataAlgorithm::Booleans(b) => b.validate(),
+            DataAlgorithm::Empty => Ok(())
+        }
+    }
+
+    pub(crate) fn cursor(&self) -> Result<DataCursor,()> {
+        let iter = match self {
+            DataAlgorithm::Numbers(n) => DataIter::Numbers(n.iter()?),
+            DataAlgorithm::Strings(s) => DataIter::Strings(s.iter()?),
+            DataAlgorithm::Booleans(b) => DataIter::Booleans(b.iter()?),
+            DataAlgorithm::Empty => DataIter::Empty
+        };
+        Ok(DataCursor { iter, position: 0 })
+    }
+}
+
+enum DataIter {
+    Numbers(ValueIter<f64>),
+    Strings(ValueIter<String>),
+    Booleans(ValueIter<bool>),
+    Empty
+}
+
+/* Reads a stream front to back, a range at a time, so that reading a stream in consecutive
+ * chunks costs no more in total than decoding it once.
+ */
+pub(crate) struct DataCursor {
+    iter: DataIter,
+    position: usize
+}
+
+fn read_values<T>(iter: &mut ValueIter<T>, position: &mut usize, start: usize, end: usize) -> Result<Vec<T>,()> {
+    let mut out = vec![];
+    while *position < end {
+        let value = match iter.next() {
+            Some(value) => value?,
+            None => { break; }
+        };
+        if *position >= start { out.push(value); }
+        *position += 1;
+    }
+    Ok(out)
+}
+
+impl DataCursor {
+    pub(crate) fn position(&self) -> usize { self.position }
+
+    /* Range is clamped to the data. Ranges before the current position are gone. */
+    pub(crate) fn read(&mut self, start: usize, end: usize) -> Result<ReceivedData,()> {
+        if start < self.position { return Err(()); }
+        let position = &mut self.position;
+        Ok(match &mut self.iter {
+            DataIter::Numbers(iter) => ReceivedData::new_numbers(read_values(iter,position,start,end)?),
+            DataIter::Strings(iter) => ReceivedData::new_strings(read_values(iter,position,start,end)?),
+            DataIter::Booleans(iter) => ReceivedData::new_booleans(read_values(iter,position,start,end)?),
+            DataIter::Empty => ReceivedData::new_empty()
+        })
+    }
 }
 
 struct DataAlgorithmVisitor;
@@ -294,3 +683,170 @@ impl<'de> Deserialize<'de> for DataAlgorithm {
         deserializer.deserialize_seq(DataAlgorithmVisitor)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(code: &str, mut data: Vec<ReceivedData>) -> Result<ReceivedData,()> {
+        let spec = DataAlgorithm::specify(code)?;
+        assert_eq!(spec.len(),data.len());
+        DataAlgorithm::new(code,&mut data)?.to_received_data()
+    }
+
+    #[test]
+    fn test_run_length() {
+        let runs = |total: f64| vec![
+            ReceivedData::new_numbers(vec![5.,0.,-2.]),
+            ReceivedData::new_numbers(vec![3.,1.,2.]),
+            ReceivedData::new_numbers(vec![total])
+        ];
+        let out = decode("NURARA",runs(6.)).unwrap();
+        assert_eq!(vec![5.,5.,5.,0.,-2.,-2.],*out.data_as_numbers().unwrap());
+        assert!(decode("NURARA",runs(5.)).is_err());
+        assert!(decode("NURARA",runs(7.)).is_err());
+        let data = vec![
+            ReceivedData::new_numbers(vec![5.]),
+            ReceivedData::new_numbers(vec![3.,1.]),
+            ReceivedData::new_numbers(vec![4.])
+        ];
+        assert!(decode("NURARA",data).is_err());
+    }
+
+    #[test]
+    fn test_packed_booleans() {
+        let out = decode("BP",vec![ReceivedData::new_bytes(vec![6,0b101,0b10])]).unwrap();
+        assert_eq!(vec![true,false,true,false,false,false,false,false,false,true],*out.data_as_booleans().unwrap());
+        let out = decode("BP",vec![ReceivedData::new_bytes(vec![0])]).unwrap();
+        assert_eq!(0,out.data_as_booleans().unwrap().len());
+        assert!(decode("BP",vec![ReceivedData::new_bytes(vec![])]).is_err());
+        assert!(decode("BP",vec![ReceivedData::new_bytes(vec![8,0])]).is_err());
+    }
+
+    fn algorithm(code: &str, mut data: Vec<ReceivedData>) -> DataAlgorithm {
+        DataAlgorithm::new(code,&mut data).unwrap()
+    }
+
+    #[test]
+    fn test_number_range() {
+        let values = ReceivedData::new_numbers(vec![2.,4.,1.,3.]);
+        let lengths = ReceivedData::new_bytes(vec![2,1,3,1]);
+        let total = ReceivedData::new_numbers(vec![7.]);
+        let alg = algorithm("NDURARL",vec![values,lengths,total]);
+        let all = alg.to_received_data().unwrap().data_as_numbers().unwrap();
+        assert_eq!(vec![2.,4.,8.,9.,10.,11.,14.],*all);
+        assert_eq!(7,alg.len().unwrap());
+        for start in 0..8 {
+            for end in start..9 {
+                let part = alg.cursor().unwrap().read(start,end).unwrap();
+                let want = all.iter().skip(start).take(end-start).cloned().collect::<Vec<_>>();
+                assert_eq!(want,*part.data_as_numbers().unwrap());
+            }
+        }
+    }
+
+    /* every stream type gives the same values in chunks as it does decoded whole */
+    fn check_chunks(alg: &DataAlgorithm) {
+        let all = alg.to_received_data().unwrap();
+        assert_eq!(all.len(),alg.len().unwrap());
+        for chunk in 1..4 {
+            let mut cursor = alg.cursor().unwrap();
+            let mut start = 0;
+            while start <= all.len() {
+                let part = cursor.read(start,start+chunk).unwrap();
+                assert_eq!(format!("{:?}",all.slice(start,start+chunk)),format!("{:?}",part));
+                assert_eq!((start+chunk).min(all.len()),cursor.position());
+                start += chunk;
+            }
+        }
+    }
+
+    #[test]
+    fn test_cursor() {
+        check_chunks(&algorithm("NRL",vec![ReceivedData::new_bytes(vec![1,178,3,250,0,0,1,7])]));
+        check_chunks(&algorithm("SZ",vec![ReceivedData::new_bytes(b"a\0bc\0\0".to_vec())]));
+        check_chunks(&algorithm("SC",vec![ReceivedData::new_bytes("añ€😀b".as_bytes().to_vec())]));
+        check_chunks(&algorithm("SA",vec![ReceivedData::new_strings(vec!["x".to_string(),"y".to_string()])]));
+        check_chunks(&algorithm("SYRAA",vec![
+            ReceivedData::new_numbers(vec![1.,0.,1.]),
+            ReceivedData::new_strings(vec!["x".to_string(),"y".to_string()])
+        ]));
+        check_chunks(&algorithm("BP",vec![ReceivedData::new_bytes(vec![6,0b101,0b10])]));
+        check_chunks(&algorithm("BB",vec![ReceivedData::new_bytes(vec![0,2,0])]));
+        check_chunks(&algorithm("BA",vec![ReceivedData::new_booleans(vec![true,false])]));
+        check_chunks(&algorithm("E",vec![]));
+        let mut cursor = algorithm("SZ",vec![ReceivedData::new_bytes(b"a\0bc\0".to_vec())]).cursor().unwrap();
+        cursor.read(1,2).unwrap();
+        assert!(cursor.read(0,1).is_err());
+    }
+
+    #[test]
+    fn test_len() {
+        let alg = algorithm("SZ",vec![ReceivedData::new_bytes(b"a\0bc\0\0".to_vec())]);
+        assert_eq!(3,alg.len().unwrap());
+        let alg = algorithm("SC",vec![ReceivedData::new_bytes("añ€😀".as_bytes().to_vec())]);
+        assert_eq!(4,alg.len().unwrap());
+        let alg = algorithm("BP",vec![ReceivedData::new_bytes(vec![6,0b101,0b10])]);
+        assert_eq!(10,alg.len().unwrap());
+        let alg = algorithm("NRL",vec![ReceivedData::new_bytes(vec![1,178,3,250,0,0])]);
+        assert!(alg.len().is_err());
+    }
+
+    #[test]
+    fn test_resend() {
+        let all = vec![
+            ReceivedData::new_numbers(vec![1.,-2.5]),
+            ReceivedData::new_strings(vec!["a".to_string(),"".to_string()]),
+            ReceivedData::new_booleans(vec![true,false]),
+            ReceivedData::new_empty()
+        ];
+        for data in all {
+            let alg : DataAlgorithm = serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+            assert_eq!(format!("{:?}",data),format!("{:?}",alg.to_received_data().unwrap()));
+        }
+        assert!(serde_json::to_string(&ReceivedData::new_bytes(vec![1])).is_err());
+    }
+
+    #[test]
+    fn test_raw() {
+        let all = vec![
+            algorithm("NDURARL",vec![
+                ReceivedData::new_numbers(vec![2.,4.,1.,3.]),
+                ReceivedData::new_bytes(vec![2,1,3,1]),
+                ReceivedData::new_numbers(vec![7.])
+            ]),
+            algorithm("NZRL",vec![ReceivedData::new_bytes(vec![1,2,3])]),
+            algorithm("SYRAC",vec![
+                ReceivedData::new_numbers(vec![1.,0.,1.]),
+                ReceivedData::new_bytes(b"xy".to_vec())
+            ]),
+            algorithm("SZ",vec![ReceivedData::new_bytes(b"a\0bc\0".to_vec())]),
+            algorithm("BP",vec![ReceivedData::new_bytes(vec![6,0b101,0b10])]),
+            algorithm("BB",vec![ReceivedData::new_bytes(vec![0,2,0])]),
+            algorithm("E",vec![])
+        ];
+        for alg in all {
+            let (code,mut data) = alg.raw();
+            let again = DataAlgorithm::new(&code,&mut data).unwrap();
+            assert_eq!(format!("{:?}",alg.to_received_data()),format!("{:?}",again.to_received_data()));
+            assert_eq!(alg.raw().0,again.raw().0);
+        }
+        let (code,data) = algorithm("NRL",vec![ReceivedData::new_bytes(vec![1,2,3])]).raw();
+        assert_eq!("NRL",code);
+        assert_eq!(format!("{:?}",vec![ReceivedData::new_bytes(vec![1,2,3])]),format!("{:?}",data));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(algorithm("BP",vec![ReceivedData::new_bytes(vec![6,0b101,0b10])]).validate().is_ok());
+        assert!(algorithm("BP",vec![ReceivedData::new_bytes(vec![])]).validate().is_err());
+        assert!(algorithm("BP",vec![ReceivedData::new_bytes(vec![3])]).validate().is_err());
+        assert!(algorithm("SC",vec![ReceivedData::new_bytes(vec![b'a',0xFF])]).validate().is_err());
+        assert!(algorithm("SZ",vec![ReceivedData::new_bytes(vec![0xC3,0])]).validate().is_err());
+        assert!(algorithm("NRA",vec![ReceivedData::new_bytes(vec![1])]).validate().is_err());
+        assert!(algorithm("SYRAA",vec![
+            ReceivedData::new_numbers(vec![0.]),
+            ReceivedData::new_numbers(vec![1.])
+        ]).validate().is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Mutex;
+use peregrine_toolkit::lock;
+use super::data::ReceivedData;
+use super::dataalgorithm::{DataAlgorithm, DataCursor};
+
+/* Data streams arrive encoded and are only decoded when a program asks for them. A stream is
+ * decoded in full at most once, after which the encoded form is dropped. Until then, a range of
+ * values can be taken without decoding the whole stream (see DataAlgorithm), which avoids huge
+ * transient allocations for dense whole-chromosome tracks. The cursor from the last range is kept
+ * so that reading a stream in order, a chunk at a time, decodes each value only once. Headers are
+ * checked on receipt, but the length is only worked out when first asked for.
+ */
+
+enum LazyState {
+    Encoded(DataAlgorithm,Option<DataCursor>),
+    Decoded(ReceivedData)
+}
+
+pub(crate) struct LazyReceivedData {
+    state: Mutex<LazyState>,
+    len: Mutex<Option<usize>>
+}
+
+impl LazyReceivedData {
+    pub(crate) fn new_encoded(algorithm: DataAlgorithm) -> Result<LazyReceivedData,()> {
+        algorithm.validate()?;
+        Ok(LazyReceivedData {
+            len: Mutex::new(None),
+            state: Mutex::new(LazyState::Encoded(algorithm,None))
+        })
+    }
+
+    pub(crate) fn new_decoded(data: ReceivedData) -> LazyReceivedData {
+        LazyReceivedData {
+            len: Mutex::new(Some(data.len())),
+            state: Mutex::new(LazyState::Decoded(data))
+        }
+    }
+
+    pub(crate) fn len(&self) -> Result<usize,()> {
+        let mut len = lock!(self.len);
+        if let Some(len) = *len { return Ok(len); }
+        let value = match &*lock!(self.state) {
+            LazyState::Decoded(data) => data.len(),
+            LazyState::Encoded(algorithm,_) => algorithm.len()?
+        };
+        *len = Some(value);
+        Ok(value)
+    }
+
+    pub(crate) fn get(&self) -> Result<ReceivedData,()> {
+        let mut state = lock!(self.state);
+        let data = match &*state {
+            LazyState::Decoded(data) => { return Ok(data.clone()); },
+            LazyState::Encoded(algorithm,_) => algorithm.to_received_data()?
+        };
+        *state = LazyState::Decoded(data.clone());
+        Ok(data)
+    }
+
+    /* Code and streams of a DataAlgorithm for this data, still encoded if it hasn't been decoded */
+    pub(crate) fn raw(&self) -> Result<(String,Vec<ReceivedData>),()> {
+        match &*lock!(self.state) {
+            LazyState::Encoded(algorithm,_) => Ok(algorithm.raw()),
+            LazyState::Decoded(ReceivedData::Empty) => Ok(("E".to_string(),vec![])),
+            LazyState::Decoded(data) => Ok((data.array_code()?.to_string(),vec![data.clone()]))
+        }
+    }
+
+    pub(crate) fn get_range(&self, start: usize, end: usize) -> Result<ReceivedData,()> {
+        let mut state = lock!(self.state);
+        match &mut *state {
+            LazyState::Decoded(data) => Ok(data.slice(start,end)),
+            LazyState::Encoded(algorithm,cursor) => {
+                /* only go back to the start when asked for something already passed */
+                let mut current = match cursor.take() {
+                    Some(current) if current.position() <= start => current,
+                    _ => algorithm.cursor()?
+                };
+                let out = current.read(start,end)?;
+                *cursor = Some(current);
+                Ok(out)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(data: &ReceivedData) -> Vec<String> {
+        data.data_as_strings().unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_lazy_strings() {
+        let stream = ReceivedData::new_bytes(b"a\0bc\0d\0e\0".to_vec());
+        let algorithm = DataAlgorithm::new("SZ",&mut vec![stream]).unwrap();
+        let lazy = LazyReceivedData::new_encoded(algorithm).unwrap();
+        assert_eq!(Ok(4),lazy.len());
+        assert_eq!(vec!["bc","d"],strings(&lazy.get_range(1,3).unwrap()));
+        assert_eq!(vec!["e"],strings(&lazy.get_range(3,9).unwrap()));
+        assert_eq!(vec!["a"],strings(&lazy.get_range(0,1).unwrap()));
+        assert_eq!(vec!["a","bc","d","e"],strings(&lazy.get().unwrap()));
+        assert_eq!(vec!["d"],strings(&lazy.get_range(2,3).unwrap()));
+    }
+
+    #[test]
+    fn test_lazy_raw() {
+        let stream = ReceivedData::new_bytes(b"a\0bc\0".to_vec());
+        let lazy = LazyReceivedData::new_encoded(DataAlgorithm::new("SZ",&mut vec![stream]).unwrap()).unwrap();
+        let (code,data) = lazy.raw().unwrap();
+        assert_eq!("SZ",code);
+        assert!(matches!(data[0],ReceivedData::Bytes(_)));
+        lazy.get().unwrap();
+        let (code,mut data) = lazy.raw().unwrap();
+        assert_eq!("SA",code);
+        let again = DataAlgorithm::new(&code,&mut data).unwrap().to_received_data().unwrap();
+        assert_eq!(vec!["a","bc"],strings(&again));
+        assert!(LazyReceivedData::new_decoded(ReceivedData::new_bytes(vec![1])).raw().is_err());
+    }
+
+    #[test]
+    fn test_lazy_corrupt() {
+        let stream = ReceivedData::new_bytes(vec![9,0]);
+        let algorithm = DataAlgorithm::new("BP",&mut vec![stream]).unwrap();
+        assert!(LazyReceivedData::new_encoded(algorithm).is_err());
+    }
+}
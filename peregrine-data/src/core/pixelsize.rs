@@ -23,7 +23,7 @@ fn round_up(input: u32) -> u32 { round(input,1) }
 pub struct PixelSize(u32,u32);
 
 impl PixelSize {
-    pub(crate) fn new(px: u32) -> PixelSize {
+    pub fn new(px: u32) -> PixelSize {
         PixelSize(round_down(px),round_up(px))
     }
 
@@ -88,6 +88,15 @@ impl ProgramModel {
         )
     }
 
+    /* For running a program natively, where there's no bundle to describe it */
+    pub fn new_fixed(name: &ProgramName, settings: &[ProgramSetting]) -> ProgramModel {
+        let mut builder = ProgramModelBuilder::new(name,"*anon*");
+        for setting in settings {
+            builder.add_setting(&setting.name,setting.clone());
+        }
+        ProgramModel::new(builder)
+    }
+
     pub fn name(&self) -> &ProgramName { &self.0.name }
 
     pub fn in_bundle_name(&self) -> &str { &self.0.in_bundle_name }
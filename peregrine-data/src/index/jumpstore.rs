@@ -3,7 +3,8 @@ use commander::PromiseFuture;
 use peregrine_toolkit::error::Error;
 
 use crate::{PgCommanderTaskSpec, add_task, async_complete_task, core::{stick::{ StickId }, channel::channelregistry::ChannelRegistry}, AllBackends};
-use crate::util::memoized::{ Memoized, MemoizedType };
+use crate::util::memoized::{ LOOKUP_FAILURES, Memoized, MemoizedType };
+use crate::util::persistentcache::{ PersistentCache, DATA_TTL };
 use crate::api::{ PeregrineCoreBase };
 
 async fn get_jump(all_backends: &AllBackends, channel_registry: &ChannelRegistry, location: &str) -> Result<Option<(String,u64,u64)>,Error> {
@@ -16,21 +17,34 @@ async fn get_jump(all_backends: &AllBackends, channel_registry: &ChannelRegistry
     Ok(None)
 }
 
-async fn query_jump(all_backends: &AllBackends, channel_registry: &ChannelRegistry, location: &str) -> Result<Arc<(String,u64,u64)>,Error> {
+const PERSISTENT_NAMESPACE : &str = "jump";
+
+async fn query_jump(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>, location: &str) -> Result<Arc<(String,u64,u64)>,Error> {
+    if let Some(persistent) = persistent {
+        let cached = persistent.get(PERSISTENT_NAMESPACE,location).await;
+        if let Some(jump) = cached.and_then(|bytes| serde_json::from_slice(&bytes).ok()) {
+            return Ok(Arc::new(jump));
+        }
+    }
     let jump = get_jump(all_backends,channel_registry,location).await?.ok_or_else(||
-        Error::operr(&format!("no such jump: {}",location))
+        Error::nosuch(&format!("no such jump: {}",location))
     )?;
+    if let (Some(persistent),Ok(bytes)) = (persistent,serde_json::to_vec(&jump)) {
+        persistent.put(PERSISTENT_NAMESPACE,location,&bytes,Some(DATA_TTL)).await;
+    }
     Ok(Arc::new(jump))
 }
 
-fn make_jump_cache(all_backends: &AllBackends, channel_registry: &ChannelRegistry) -> Memoized<String,Result<Arc<(String,u64,u64)>,Error>> {
+fn make_jump_cache(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>) -> Memoized<String,Result<Arc<(String,u64,u64)>,Error>> {
     let all_backends = all_backends.clone();
     let channel_registry = channel_registry.clone();
-    Memoized::new(MemoizedType::Cache(128),move |_,location: &String| {
+    let persistent = persistent.clone();
+    Memoized::new_with_failures(MemoizedType::Cache(128),&LOOKUP_FAILURES,move |_,location: &String| {
         let all_backends = all_backends.clone();
         let channel_registry = channel_registry.clone();
+        let persistent = persistent.clone();
         let location = location.clone();
-        Box::pin(async move { query_jump(&all_backends,&channel_registry,&location).await })
+        Box::pin(async move { query_jump(&all_backends,&channel_registry,&persistent,&location).await })
     })   
 }
 
@@ -39,7 +53,7 @@ pub struct JumpStore(Memoized<String,Result<Arc<(String,u64,u64)>,Error>>,Peregr
 
 impl JumpStore {
     pub fn new(base: &PeregrineCoreBase) -> JumpStore {
-        JumpStore(make_jump_cache(&base.all_backends,&base.channel_registry),base.clone())
+        JumpStore(make_jump_cache(&base.all_backends,&base.channel_registry,&base.persistent),base.clone())
     }
 
     pub async fn get(&self, location: &String) -> Result<Arc<(String,u64,u64)>,Error> {
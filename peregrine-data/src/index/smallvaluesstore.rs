@@ -1,6 +1,20 @@
 use std::{sync::Arc, collections::HashMap};
 use peregrine_toolkit::{error::Error, error, log};
-use crate::{util::memoized::{Memoized, MemoizedType}, PeregrineCoreBase, AllBackends, core::channel::channelregistry::ChannelRegistry};
+use crate::{util::memoized::{Memoized, MemoizedFailures, MemoizedType}, PeregrineCoreBase, AllBackends, CountingPromise, core::channel::channelregistry::ChannelRegistry};
+use crate::util::persistentcache::{ PersistentCache, DATA_TTL };
+
+const PERSISTENT_NAMESPACE : &str = "small-values";
+
+/* Failures fail the program asking, so don't remember them for long */
+const SMALL_VALUES_FAILURES : MemoizedFailures = MemoizedFailures {
+    attempts: 3,
+    retry_delay: 1000.,
+    max_retry_delay: 4000.,
+    transient_ttl: 2000.,
+    permanent_ttl: Some(10000.)
+};
+
+type SmallValues = Result<Arc<HashMap<String,String>>,Error>;
 
 async fn get_small_values(all_backends: &AllBackends, channel_registry: &ChannelRegistry, namespace: &str, column: &str) -> Result<HashMap<String,String>,Error> {
     let mut out = HashMap::new();
@@ -12,46 +26,73 @@ async fn get_small_values(all_backends: &AllBackends, channel_registry: &Channel
     Ok(out)
 }
 
-async fn query_small_values(all_backends: &AllBackends, channel_registry: &ChannelRegistry, namespace: &str, column: &str) -> Arc<HashMap<String,String>> {
-    match get_small_values(all_backends,channel_registry,namespace,column).await {
-        Ok(v) => Arc::new(v),
-        Err(e) => {
-            error!("{:?}",e);
-            Arc::new(HashMap::new())
+async fn query_small_values(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>, namespace: &str, column: &str) -> SmallValues {
+    let key = format!("{}/{}",namespace,column);
+    if let Some(persistent) = persistent {
+        let cached = persistent.get(PERSISTENT_NAMESPACE,&key).await;
+        if let Some(v) = cached.and_then(|bytes| serde_json::from_slice(&bytes).ok()) {
+            return Ok(Arc::new(v));
         }
     }
+    let v = get_small_values(all_backends,channel_registry,namespace,column).await.map_err(|e| {
+        error!("{:?}",e);
+        e
+    })?;
+    if let (Some(persistent),Ok(bytes)) = (persistent,serde_json::to_vec(&v)) {
+        persistent.put(PERSISTENT_NAMESPACE,&key,&bytes,Some(DATA_TTL)).await;
+    }
+    Ok(Arc::new(v))
 }
 
-fn make_small_values_cache(all_backends: &AllBackends, channel_registry: &ChannelRegistry) -> Memoized<(String,String),Arc<HashMap<String,String>>> {
+fn make_small_values_cache(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>) -> Memoized<(String,String),SmallValues> {
     let all_backends = all_backends.clone();
     let channel_registry = channel_registry.clone();
-    Memoized::new(MemoizedType::Cache(100),move |_: &Memoized<(String,String),Arc<HashMap<String,String>>>,(namespace,column)| {
+    let persistent = persistent.clone();
+    Memoized::new_with_failures(MemoizedType::Cache(100),&SMALL_VALUES_FAILURES,move |_: &Memoized<(String,String),SmallValues>,(namespace,column)| {
         let all_backends = all_backends.clone();
         let channel_registry = channel_registry.clone();    
+        let persistent = persistent.clone();
         let namespace = namespace.to_string();
         let column = column.clone();
-        Box::pin(async move { query_small_values(&all_backends,&channel_registry,&namespace,&column).await })
+        Box::pin(async move { query_small_values(&all_backends,&channel_registry,&persistent,&namespace,&column).await })
     })   
 }
 
 
 #[derive(Clone)]
 pub struct SmallValuesStore {
-    values: Memoized<(String,String),Arc<HashMap<String,String>>>,
-    base: PeregrineCoreBase
+    values: Memoized<(String,String),SmallValues>,
+    booted: CountingPromise
 }
 
 impl SmallValuesStore {
     pub fn new(base: &PeregrineCoreBase) -> SmallValuesStore {
         SmallValuesStore {
-            values: make_small_values_cache(&base.all_backends,&base.channel_registry),
-            base: base.clone()
+            values: make_small_values_cache(&base.all_backends,&base.channel_registry,&base.persistent),
+            booted: base.booted.clone()
+        }
+    }
+
+    /* For running programs natively: values keyed by (namespace,column) and already "booted" */
+    pub fn new_fixed(values: HashMap<(String,String),HashMap<String,String>>) -> SmallValuesStore {
+        let values = Arc::new(values);
+        let booted = CountingPromise::new();
+        booted.unlock();
+        SmallValuesStore {
+            values: Memoized::new(MemoizedType::None,move |_,key: &(String,String)| {
+                let value = Ok(Arc::new(values.get(key).cloned().unwrap_or_else(|| HashMap::new())));
+                Box::pin(async move { value })
+            }),
+            booted
         }
     }
 
     pub async fn get(&self, namespace: &str, column: &str, row: &str) -> Result<Option<String>,Error> {
-        self.base.booted.wait().await;
-        let value_set = self.values.get(&(namespace.to_string(),column.to_string())).await.as_ref().clone();
-        Ok(value_set.get(row).cloned())
+        self.booted.wait().await;
+        /* a missing row is fine, as values are optional, but a failed lookup is not */
+        match self.values.get(&(namespace.to_string(),column.to_string())).await.as_ref() {
+            Ok(value_set) => Ok(value_set.get(row).cloned()),
+            Err(e) => Err(e.clone())
+        }
     }
 }
@@ -1,26 +1,51 @@
 use std::sync::{ Arc };
 use peregrine_toolkit::error::Error;
 
-use crate::{ core::{stick::{ Stick, StickId }, channel::channelregistry::ChannelRegistry}, AllBackends};
-use crate::util::memoized::{ Memoized, MemoizedType };
+use crate::{ core::{stick::{ Stick, StickId, StickTopology }, channel::channelregistry::ChannelRegistry}, AllBackends};
+use crate::util::memoized::{ LOOKUP_FAILURES, Memoized, MemoizedType };
+use crate::util::persistentcache::{ PersistentCache, DATA_TTL };
 use crate::api::{ PeregrineCoreBase };
 
-async fn get_sticks(all_backends: &AllBackends, channel_registry: &ChannelRegistry, stick_id: &StickId) -> Result<Vec<Stick>,Error> {
+const PERSISTENT_NAMESPACE : &str = "stick";
+
+/* Sticks are persisted as (size,topology,tags) under their id */
+async fn get_persistent_stick(persistent: &PersistentCache, stick_id: &StickId) -> Option<Stick> {
+    let bytes = persistent.get(PERSISTENT_NAMESPACE,stick_id.get_id()).await?;
+    let (size,topology,tags) : (u64,u8,Vec<String>) = serde_json::from_slice(&bytes).ok()?;
+    Some(Stick::new(stick_id,size,StickTopology::from_number(topology).ok()?,&tags))
+}
+
+async fn put_persistent_stick(persistent: &PersistentCache, stick: &Stick) {
+    let value = (stick.size(),stick.topology().to_number(),stick.tags().iter().collect::<Vec<_>>());
+    if let Ok(bytes) = serde_json::to_vec(&value) {
+        persistent.put(PERSISTENT_NAMESPACE,stick.get_id().get_id(),&bytes,Some(DATA_TTL)).await;
+    }
+}
+
+async fn get_sticks(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>, stick_id: &StickId) -> Result<Vec<Stick>,Error> {
+    if let Some(persistent) = persistent {
+        if let Some(stick) = get_persistent_stick(persistent,stick_id).await {
+            return Ok(vec![stick]);
+        }
+    }
     let mut sticks = vec![];
     for backend_namespace in &channel_registry.all() {
         let backend = all_backends.backend(backend_namespace)?;
         let stick = backend.stick(stick_id).await?;
         if let Some(stick) = stick {
+            if let Some(persistent) = persistent {
+                put_persistent_stick(persistent,&stick).await;
+            }
             sticks.push(stick);
         }
     }
     Ok(sticks)
 }
 
-async fn query_stick(all_backends: &AllBackends, channel_registry: &ChannelRegistry, stick_cache: Memoized<StickId,Result<Arc<Stick>,Error>>, stick_id: StickId) -> Result<Arc<Stick>,Error> {
+async fn query_stick(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>, stick_cache: Memoized<StickId,Result<Arc<Stick>,Error>>, stick_id: StickId) -> Result<Arc<Stick>,Error> {
     let stick_cache = stick_cache.clone();
-    let mut sticks = get_sticks(all_backends,channel_registry,&stick_id).await?;
-    let mut out = Err(Error::operr(&format!("no such stick: {}",stick_id.get_id())));
+    let mut sticks = get_sticks(all_backends,channel_registry,persistent,&stick_id).await?;
+    let mut out = Err(Error::nosuch(&format!("no such stick: {}",stick_id.get_id())));
     for stick in sticks.drain(..) {
         let stick = Arc::new(stick);
         if *stick.get_id() == stick_id {
@@ -31,15 +56,17 @@ async fn query_stick(all_backends: &AllBackends, channel_registry: &ChannelRegis
     out
 }
 
-fn make_stick_cache(all_backends: &AllBackends, channel_registry: &ChannelRegistry) -> Memoized<StickId,Result<Arc<Stick>,Error>> {
+fn make_stick_cache(all_backends: &AllBackends, channel_registry: &ChannelRegistry, persistent: &Option<PersistentCache>) -> Memoized<StickId,Result<Arc<Stick>,Error>> {
     let all_backends = all_backends.clone();
     let channel_registry = channel_registry.clone();
-    Memoized::new(MemoizedType::Store,move |stick_cache,stick_id: &StickId| {
+    let persistent = persistent.clone();
+    Memoized::new_with_failures(MemoizedType::Store,&LOOKUP_FAILURES,move |stick_cache,stick_id: &StickId| {
         let all_backends = all_backends.clone();
         let channel_registry = channel_registry.clone();    
+        let persistent = persistent.clone();
         let stick_id = stick_id.clone();
         let stick_cache = stick_cache.clone();
-        Box::pin(async move { query_stick(&all_backends,&channel_registry,stick_cache.clone(),stick_id.clone()).await })
+        Box::pin(async move { query_stick(&all_backends,&channel_registry,&persistent,stick_cache.clone(),stick_id.clone()).await })
     })   
 }
 
@@ -52,7 +79,7 @@ pub struct StickStore {
 impl StickStore {
     pub fn new(base: &PeregrineCoreBase) -> StickStore {
         StickStore {
-            sticks: make_stick_cache(&base.all_backends,&base.channel_registry),
+            sticks: make_stick_cache(&base.all_backends,&base.channel_registry,&base.persistent),
             base: base.clone()
         }
     }
@@ -100,6 +100,7 @@ mod core {
     pub(crate) mod version;
     mod viewport;
     pub(crate) mod data;
+    pub(crate) mod lazydata;
     pub(crate) mod coordsystem;
 
     pub use self::config::{ PgdPeregrineConfig, ConfigKey };
@@ -148,6 +149,8 @@ mod request {
     pub(crate) mod core {
         pub(crate) mod backend;
         pub(crate) mod backoff;
+        pub(crate) mod backoffpolicy;
+        mod coalescer;
         pub(crate) mod manager;
         pub(crate) mod maxirequest;
         pub(crate) mod maxiresponse;
@@ -159,6 +162,7 @@ mod request {
         mod attemptmatch;
         pub(crate) mod sidecars;
         pub(crate) mod packetpriority;
+        mod relevance;
         mod trafficcontrol;
     }
 
@@ -192,8 +196,10 @@ mod request {
 }
 
 mod run {
+    pub mod nativecommander;
     pub mod pgcommander;
     pub mod pgdauphin;
+    pub use self::nativecommander::NativeCommander;
     pub use self::pgcommander::Commander;
     pub use self::pgcommander::{ PgCommander, PgCommanderTaskSpec, add_task, complete_task, async_complete_task };
     pub use self::pgdauphin::{ PgDauphin };
@@ -204,20 +210,22 @@ mod shape {
     pub(crate) mod originstats;
     mod core;
     pub mod emptyshape;
-    mod imageshape;
+    pub(crate) mod imageshape;
     pub(crate) mod metadata;
     pub mod rectangleshape;
     pub mod polygonshape;
     pub(crate) mod textshape;
     pub(crate) mod shape;
+    mod palette;
     mod programshapes;
     mod settingmode;
-    mod wiggleshape;
+    pub(crate) mod wiggleshape;
 
     pub use self::core::{ 
-        Patina, Pen, Colour, DirectColour, Plotter, DrawnType, HotspotPatina, PenGeometry,
+        Patina, Pen, Colour, DirectColour, PlainColour, Plotter, DrawnType, BlendMode, HotspotPatina, PenGeometry,
         AttachmentPoint
     };
+    pub use self::palette::{ Palette, Theme };
     pub use self::settingmode::SettingMode;
     pub use self::shape::{ ShapeDemerge, Shape };
     pub use self::requestedshapescontainer::RequestedShapesContainer;
@@ -278,6 +286,9 @@ mod util {
     pub mod memoized;
     pub mod message;
     pub mod miscpromises;
+    pub mod persistentcache;
+    #[cfg(not(target_arch="wasm32"))]
+    pub mod diskstore;
     pub mod vecutils;
 
     pub use self::builder::Builder;
@@ -301,7 +312,7 @@ pub use self::core::channel::channelintegration::{ ChannelIntegration, ChannelSe
 pub use self::index::{ StickStore, SmallValuesStore };
 pub use self::core::program::programbundle::{ SuppliedBundle, UnpackedSuppliedBundle };
 pub use self::shapeload::{ Region, ShapeStore, DataStore, ShapeRequest, LoadMode, RunReport };
-pub use self::run::{ PgCommander, PgCommanderTaskSpec, PgDauphin, Commander, add_task, complete_task, async_complete_task };
+pub use self::run::{ PgCommander, PgCommanderTaskSpec, PgDauphin, Commander, NativeCommander, add_task, complete_task, async_complete_task };
 pub use self::request::core::maxirequest::{ MaxiRequest };
 pub use self::request::core::maxiresponse::{ MaxiResponse, MaxiResponseDeserialize };
 pub use self::request::core::minirequest::{ MiniRequest, MiniRequestAttempt };
@@ -310,16 +321,22 @@ pub use self::request::core::packetpriority::PacketPriority;
 pub use self::request::core::backend::{ AllBackends, Backend };
 pub use self::shape::shape::DrawingShape;
 pub use self::shape::{ 
-    Patina, Colour, DirectColour, DrawnType, Shape, HotspotPatina, PenGeometry,
-    Pen, Plotter, ShapeDemerge, SettingMode,
+    Patina, Colour, DirectColour, PlainColour, DrawnType, BlendMode, Shape, HotspotPatina, PenGeometry,
+    Pen, Plotter, ShapeDemerge, SettingMode, Palette, Theme,
     ProgramShapesBuilder, RequestedShapesContainer, AttachmentPoint
 };
 pub use self::core::coordsystem::{ CoordinateSystem };
 pub use self::switch::switches::{ Switches };
 pub use self::switch::track::Track;
+pub use self::switch::trackconfig::TrackConfig;
+pub use self::shapeload::programname::ProgramName;
+pub use self::core::pixelsize::PixelSize;
 pub use self::train::{ DrawingCarriage, CarriageExtent };
 pub use self::util::{ CountingPromise, DataMessage, Builder };
 pub use self::util::vecutils::expand_by_repeating;
+pub use self::util::persistentcache::{ PersistentCache, PersistentStore, PersistentFuture };
+#[cfg(not(target_arch="wasm32"))]
+pub use self::util::diskstore::DiskStore;
 pub use self::spacebase::{ 
     reactive, HollowEdge2, SpaceBase, SpaceBaseArea, PartialSpaceBase,
     SpaceBasePoint, SpaceBasePointRef
@@ -327,8 +344,11 @@ pub use self::spacebase::{
 pub use self::shape::polygonshape::PolygonShape;
 pub use self::shape::rectangleshape::RectangleShape;
 pub use self::shape::textshape::TextShape;
+pub use self::shape::imageshape::ImageShape;
+pub use self::shape::wiggleshape::WiggleShape;
 pub use self::core::data::ReceivedData;
 pub use self::request::core::manager::RequestManager;
+pub use self::request::core::backoffpolicy::BackoffPolicy;
 pub use self::request::tracks::trackmodel::{ TrackMapping, TrackModel, TrackModelDeserialize };
 pub use self::request::tracks::expansionmodel::ExpansionModel;
 pub use self::request::minirequests::failureres::FailureRes;
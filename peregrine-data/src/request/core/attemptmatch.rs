@@ -27,14 +27,19 @@ impl AttemptMatch {
         lock!(self.pending).insert(id,response.clone());
     }
 
-    pub(super) fn make_attempt(&self, request: &Rc<MiniRequest>) -> (MiniRequestAttempt,CommanderStream<MiniResponseAttempt>) {
+    pub(super) fn make_attempt(&self, request: &Rc<MiniRequest>, relevance: Option<u64>) -> (MiniRequestAttempt,CommanderStream<MiniResponseAttempt>) {
         let id = self.next_id();
-        let request = MiniRequestAttempt::new(id,request);
+        let request = MiniRequestAttempt::new(id,request,relevance);
         let stream = request.response().clone();
         self.add_pending(id,&stream);
         (request,stream)
     }
 
+    /* For attempts whose responses are never matched directly (see Coalescer) */
+    pub(super) fn make_unmatched_attempt(&self, request: &Rc<MiniRequest>, relevance: Option<u64>) -> MiniRequestAttempt {
+        MiniRequestAttempt::new(self.next_id(),request,relevance)
+    }
+
     pub(super) fn retrieve_callback_by_response(&self, response: &MiniResponseAttempt) -> Option<CommanderStream<MiniResponseAttempt>> {
         lock!(self.pending).remove(&response.message_id())
     }
@@ -1,5 +1,5 @@
 use std::rc::Rc;
-use commander::cdr_timer;
+use commander::{ cdr_current_time, cdr_timer };
 use peregrine_toolkit::error::Error;
 use super::{manager::{LowLevelRequestManager}, minirequest::MiniRequest, queue::QueueKey, miniresponse::{MiniResponseAttempt, MiniResponseError}};
 
@@ -22,18 +22,57 @@ impl Backoff {
         self.key.name.clone().map(|x| x.to_string()).unwrap_or_else(|| "*anon*".to_string())
     }
 
+    fn report_breaker(&self, opened: bool, closed: bool) {
+        if opened {
+            self.manager.message(Error::tmp(&format!("backend degraded: {}",self.errname())));
+        }
+        if closed {
+            self.manager.message(Error::tmp(&format!("backend recovered: {}",self.errname())));
+        }
+    }
+
     pub(crate) async fn backoff<F,T>(&mut self, req: &Rc<MiniRequest>, cb: F) -> Result<T,Error>
             where F: Fn(MiniResponseAttempt) -> Result<T,MiniResponseError> {
+        let policy = self.manager.backoff_policy(&self.key.name);
+        let start = cdr_current_time();
+        let relevance = self.manager.relevance().tag(&self.key.priority);
         let mut last_error = None;
-        for _ in 0..self.repeats {
-            let resp = self.manager.execute(&self.key,req)?.get().await;
+        for retry in 0..self.repeats {
+            if retry > 0 {
+                if self.manager.relevance().is_stale(relevance) {
+                    return Err(Error::operr(&format!("not retrying as the viewport moved on: {}",self.errname())));
+                }
+                let delay = policy.delay(retry as u32-1,self.manager.with_breaker(&self.key.name,|b| b.random()));
+                if policy.give_up(cdr_current_time()+delay-start) { break; }
+                self.manager.message(Error::tmp(&format!("temporary backend failure: {}",self.errname())));
+                cdr_timer(delay).await;
+            }
+            if !self.manager.with_breaker(&self.key.name,|b| b.permit(&policy,cdr_current_time())) {
+                return Err(Error::tmp(&format!("backend degraded, not sending: {}",self.errname())));
+            }
+            let resp = self.manager.execute(&self.key,req,relevance)?.get().await;
             match cb(resp) {
-                Ok(r) => { return Ok(r); },
-                Err(MiniResponseError::Retry(e)) => { last_error = Some(e); },
-                Err(MiniResponseError::NoRetry(e)) => { last_error = Some(e); break; },
+                Ok(r) => {
+                    let closed = self.manager.with_breaker(&self.key.name,|b| b.success());
+                    self.report_breaker(false,closed);
+                    return Ok(r);
+                },
+                Err(MiniResponseError::Retry(e)) => {
+                    let opened = self.manager.with_breaker(&self.key.name,|b| b.failure(&policy,cdr_current_time()));
+                    self.report_breaker(opened,false);
+                    /* the backend may yet recover, so it's worth asking again later */
+                    last_error = Some(Error::tmp(&e.message));
+                },
+                Err(MiniResponseError::NoRetry(e)) => {
+                    self.manager.with_breaker(&self.key.name,|b| b.inconclusive(cdr_current_time()));
+                    last_error = Some(e);
+                    break;
+                },
+                Err(MiniResponseError::Stale(e)) => {
+                    self.manager.with_breaker(&self.key.name,|b| b.inconclusive(cdr_current_time()));
+                    return Err(e);
+                }
             }
-            self.manager.message(Error::tmp(&format!("temporary backend failure: {}",self.errname())));
-            cdr_timer(500.).await;
         }
         self.manager.message(Error::operr(&format!("permanent backend failure: {}",self.errname())));
         Err(last_error.unwrap_or_else(|| Error::fatal("unexpected downcast error in backoff")))
@@ -0,0 +1,212 @@
+/* A BackoffPolicy says how to retry failed requests to a backend: exponentially increasing
+ * delays, each randomised by a jitter fraction so that many clients don't retry in lockstep,
+ * giving up after a maximum elapsed time.
+ *
+ * A CircuitBreaker per backend counts consecutive failed attempts. Once there are too many it
+ * "opens" and requests fail immediately rather than hammering a backend which is down. Every so
+ * often a single request is let through as a probe: if it succeeds the breaker closes again, if
+ * not it stays open until the next probe. A probe which never reports back (eg its task was
+ * dropped) is given up on after the probe interval and another let through.
+ */
+
+/* Times in ms */
+#[derive(Clone)]
+pub struct BackoffPolicy {
+    pub initial_delay: f64,       /* delay before first retry */
+    pub multiplier: f64,          /* factor by which each delay exceeds the last */
+    pub max_delay: f64,           /* largest delay between retries */
+    pub jitter: f64,              /* fraction (0-1) of each delay which is random */
+    pub max_elapsed: Option<f64>, /* give up retrying after this long, if set */
+    pub breaker_failures: usize,  /* consecutive failed attempts before breaker opens (0 = never) */
+    pub breaker_probe: f64        /* time between probes while breaker is open */
+}
+
+impl BackoffPolicy {
+    pub fn new() -> BackoffPolicy {
+        BackoffPolicy {
+            initial_delay: 500.,
+            multiplier: 2.,
+            max_delay: 10000.,
+            jitter: 0.5,
+            max_elapsed: Some(60000.),
+            breaker_failures: 10,
+            breaker_probe: 15000.
+        }
+    }
+
+    /* random is in [0,1) */
+    pub(crate) fn delay(&self, retry: u32, random: f64) -> f64 {
+        let delay = (self.initial_delay * self.multiplier.powi(retry as i32)).min(self.max_delay);
+        let jitter = self.jitter.max(0.).min(1.);
+        delay * (1. - jitter) + delay * jitter * random
+    }
+
+    /* Would a retry after this long since the first attempt be too late? */
+    pub(crate) fn give_up(&self, elapsed: f64) -> bool {
+        self.max_elapsed.map(|max_elapsed| elapsed > max_elapsed).unwrap_or(false)
+    }
+}
+
+enum BreakerState {
+    Closed,
+    Open(f64),
+    Probing(f64)
+}
+
+pub(crate) struct CircuitBreaker {
+    failures: usize,
+    state: BreakerState,
+    rng: u64
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(seed: u64) -> CircuitBreaker {
+        CircuitBreaker { failures: 0, state: BreakerState::Closed, rng: seed }
+    }
+
+    /* splitmix64, for jitter */
+    pub(crate) fn random(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        ((z ^ (z >> 31)) >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /* May we send a request now? */
+    pub(crate) fn permit(&mut self, policy: &BackoffPolicy, now: f64) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            BreakerState::Open(until) | BreakerState::Probing(until) if now >= until => {
+                self.state = BreakerState::Probing(now+policy.breaker_probe);
+                true
+            },
+            _ => false
+        }
+    }
+
+    /* Returns true if this closes an open breaker */
+    pub(crate) fn success(&mut self) -> bool {
+        self.failures = 0;
+        let was_open = !matches!(self.state,BreakerState::Closed);
+        self.state = BreakerState::Closed;
+        was_open
+    }
+
+    /* Returns true if this opens a closed breaker */
+    pub(crate) fn failure(&mut self, policy: &BackoffPolicy, now: f64) -> bool {
+        self.failures += 1;
+        match self.state {
+            BreakerState::Closed if policy.breaker_failures > 0 && self.failures >= policy.breaker_failures => {
+                self.state = BreakerState::Open(now+policy.breaker_probe);
+                true
+            },
+            BreakerState::Probing(_) => {
+                self.state = BreakerState::Open(now+policy.breaker_probe);
+                false
+            },
+            _ => false
+        }
+    }
+
+    /* An attempt which says nothing about the backend's health (eg dropped unsent) */
+    pub(crate) fn inconclusive(&mut self, now: f64) {
+        if let BreakerState::Probing(_) = self.state {
+            self.state = BreakerState::Open(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy() -> BackoffPolicy {
+        BackoffPolicy {
+            breaker_failures: 3,
+            breaker_probe: 1000.,
+            ..BackoffPolicy::new()
+        }
+    }
+
+    fn open(breaker: &mut CircuitBreaker, policy: &BackoffPolicy, now: f64) {
+        assert!(!breaker.failure(policy,now));
+        assert!(!breaker.failure(policy,now));
+        assert!(breaker.failure(policy,now));
+    }
+
+    #[test]
+    fn test_breaker_recovers() {
+        let policy = policy();
+        let mut breaker = CircuitBreaker::new(1);
+        assert!(breaker.permit(&policy,0.));
+        open(&mut breaker,&policy,0.);
+        assert!(!breaker.permit(&policy,999.));
+        /* one probe only */
+        assert!(breaker.permit(&policy,1000.));
+        assert!(!breaker.permit(&policy,1001.));
+        assert!(breaker.success());
+        assert!(breaker.permit(&policy,1002.));
+        assert!(!breaker.success());
+    }
+
+    #[test]
+    fn test_breaker_probe_fails() {
+        let policy = policy();
+        let mut breaker = CircuitBreaker::new(1);
+        open(&mut breaker,&policy,0.);
+        assert!(breaker.permit(&policy,1000.));
+        assert!(!breaker.failure(&policy,1500.));
+        assert!(!breaker.permit(&policy,2000.));
+        assert!(breaker.permit(&policy,2500.));
+    }
+
+    #[test]
+    fn test_breaker_probe_lost() {
+        let policy = policy();
+        let mut breaker = CircuitBreaker::new(1);
+        open(&mut breaker,&policy,0.);
+        assert!(breaker.permit(&policy,1000.));
+        /* the probe never reports back */
+        assert!(!breaker.permit(&policy,1999.));
+        assert!(breaker.permit(&policy,2000.));
+        breaker.inconclusive(2100.);
+        assert!(breaker.permit(&policy,2100.));
+    }
+
+    #[test]
+    fn test_breaker_disabled() {
+        let policy = BackoffPolicy { breaker_failures: 0, ..policy() };
+        let mut breaker = CircuitBreaker::new(1);
+        for _ in 0..100 {
+            assert!(!breaker.failure(&policy,0.));
+        }
+        assert!(breaker.permit(&policy,0.));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = policy();
+        let mut breaker = CircuitBreaker::new(42);
+        for retry in 0..10 {
+            let base = (500. * 2_f64.powi(retry)).min(10000.);
+            for _ in 0..100 {
+                let random = breaker.random();
+                assert!(random >= 0. && random < 1.);
+                let delay = policy.delay(retry as u32,random);
+                assert!(delay >= base*0.5 && delay <= base);
+            }
+        }
+        let steady = BackoffPolicy { jitter: 0., ..policy };
+        assert_eq!(2000.,steady.delay(2,0.99));
+    }
+
+    #[test]
+    fn test_max_elapsed() {
+        let policy = policy();
+        assert!(!policy.give_up(60000.));
+        assert!(policy.give_up(60001.));
+        let forever = BackoffPolicy { max_elapsed: None, ..policy };
+        assert!(!forever.give_up(1e12));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::{ HashMap, HashSet };
+use std::rc::Rc;
+use std::sync::{ Arc, Mutex };
+use peregrine_toolkit::lock;
+use crate::BackendNamespace;
+use crate::request::minirequests::datareq::DataRequest;
+use super::attemptmatch::AttemptMatch;
+use super::minirequest::{ MiniRequest, MiniRequestAttempt };
+use super::miniresponse::{ MiniResponse, MiniResponseAttempt };
+
+/* Neighbouring carriages and anticipation often want the same track at the same scale for
+ * adjacent carriages. If the backend advertises the data-span feature when booting, requests in
+ * a packet which differ only by consecutive carriage index are sent as a single request with a
+ * span. The response to that has streams for each carriage prefixed "i:" (see DataRes::split)
+ * which we split back into responses to each original request.
+ *
+ * The coalesced attempt isn't registered with the AttemptMatch: its response is split before
+ * matching and the pieces matched to the original attempts.
+ */
+
+const DATA_SPAN_FEATURE : &str = "data-span";
+const MAX_SPAN : usize = 8;
+
+#[derive(Clone)]
+pub(crate) struct Coalescer {
+    supported: Arc<Mutex<HashSet<BackendNamespace>>>
+}
+
+/* coalesced message id -> original attempts in carriage order */
+pub(crate) struct CoalescedRequests(HashMap<u64,Vec<MiniRequestAttempt>>);
+
+struct Candidate {
+    index: u64,
+    request: DataRequest,
+    attempt: MiniRequestAttempt
+}
+
+impl Coalescer {
+    pub(crate) fn new() -> Coalescer {
+        Coalescer { supported: Arc::new(Mutex::new(HashSet::new())) }
+    }
+
+    pub(crate) fn set_features(&self, backend: &BackendNamespace, features: &[String]) {
+        let mut supported = lock!(self.supported);
+        if features.iter().any(|x| x == DATA_SPAN_FEATURE) {
+            supported.insert(backend.clone());
+        } else {
+            supported.remove(backend);
+        }
+    }
+
+    fn supports(&self, backend: &Option<BackendNamespace>) -> bool {
+        backend.as_ref().map(|backend| lock!(self.supported).contains(backend)).unwrap_or(false)
+    }
+
+    fn flush(&self, run: &mut Vec<Candidate>, matcher: &AttemptMatch, out: &mut Vec<MiniRequestAttempt>, coalesced: &mut CoalescedRequests) {
+        if run.len() < 2 {
+            out.extend(run.drain(..).map(|c| c.attempt));
+            return;
+        }
+        let wide = run[0].request.with_span(run.len() as u64);
+        let relevance = run.iter().filter_map(|c| c.attempt.relevance()).max();
+        let attempt = matcher.make_unmatched_attempt(&Rc::new(MiniRequest::Data(wide)),relevance);
+        coalesced.0.insert(attempt.msgid(),run.drain(..).map(|c| c.attempt).collect());
+        out.push(attempt);
+    }
+
+    pub(crate) fn coalesce(&self, backend: &Option<BackendNamespace>, matcher: &AttemptMatch, requests: Vec<MiniRequestAttempt>) -> (Vec<MiniRequestAttempt>,CoalescedRequests) {
+        let mut coalesced = CoalescedRequests(HashMap::new());
+        if !self.supports(backend) { return (requests,coalesced); }
+        let mut out = vec![];
+        let mut by_key : HashMap<DataRequest,Vec<Candidate>> = HashMap::new();
+        for attempt in requests {
+            let request = match attempt.request() {
+                MiniRequest::Data(request) if request.span() == 1 => request.clone(),
+                _ => { out.push(attempt); continue; }
+            };
+            by_key.entry(request.coalesce_key()).or_insert_with(|| vec![]).push(Candidate {
+                index: request.region().index(),
+                request, attempt
+            });
+        }
+        for (_,mut candidates) in by_key.drain() {
+            candidates.sort_by_key(|c| c.index);
+            let mut run : Vec<Candidate> = vec![];
+            for candidate in candidates {
+                let contiguous = run.last().map(|last| last.index+1 == candidate.index).unwrap_or(true);
+                if !contiguous || run.len() >= MAX_SPAN {
+                    self.flush(&mut run,matcher,&mut out,&mut coalesced);
+                }
+                run.push(candidate);
+            }
+            self.flush(&mut run,matcher,&mut out,&mut coalesced);
+        }
+        (out,coalesced)
+    }
+}
+
+impl CoalescedRequests {
+    /* Replace responses to coalesced requests with responses to each original */
+    pub(crate) fn split(&mut self, mut responses: Vec<MiniResponseAttempt>) -> Vec<MiniResponseAttempt> {
+        let mut out = vec![];
+        for response in responses.drain(..) {
+            let originals = match self.0.remove(&response.message_id()) {
+                Some(originals) => originals,
+                None => { out.push(response); continue; }
+            };
+            match response.into_variety() {
+                MiniResponse::Data(data) => {
+                    match data.split(originals.len() as u64) {
+                        Ok(parts) => {
+                            for (original,part) in originals.iter().zip(parts.into_iter()) {
+                                out.push(original.make_response_attempt(MiniResponse::Data(part)));
+                            }
+                        },
+                        Err(e) => {
+                            out.extend(originals.iter().map(|x| x.fail(&e.message)));
+                        }
+                    }
+                },
+                MiniResponse::FailureRes(failure) if failure.is_stale() => {
+                    out.extend(originals.iter().map(|x| x.fail_stale()));
+                },
+                MiniResponse::FailureRes(failure) => {
+                    out.extend(originals.iter().map(|x| x.fail(failure.message())));
+                },
+                _ => {
+                    out.extend(originals.iter().map(|x| x.fail("unexpected response to coalesced request")));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Region, StickId, Scale};
+    use crate::core::data::ReceivedData;
+    use crate::request::minirequests::datares::DataRes;
+    use crate::request::minirequests::jumpreq::JumpReq;
+    use super::*;
+
+    fn backend() -> Option<BackendNamespace> { Some(BackendNamespace::new("test","")) }
+
+    fn request(matcher: &AttemptMatch, name: &str, index: u64) -> MiniRequestAttempt {
+        let region = Region::new(&StickId::new("1"),index,&Scale::new(10));
+        let request = DataRequest::new(&backend().unwrap(),name,&region);
+        matcher.make_attempt(&Rc::new(MiniRequest::Data(request)),None).0
+    }
+
+    fn span(attempt: &MiniRequestAttempt) -> Option<(u64,u64)> {
+        match attempt.request() {
+            MiniRequest::Data(request) => Some((request.region().index(),request.span())),
+            _ => None
+        }
+    }
+
+    fn coalescer() -> Coalescer {
+        let coalescer = Coalescer::new();
+        coalescer.set_features(&backend().unwrap(),&[DATA_SPAN_FEATURE.to_string()]);
+        coalescer
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let matcher = AttemptMatch::new();
+        let mut requests = vec![];
+        for index in [3,1,2,5,11,12,13,14,15,16,17,18,19,20] {
+            requests.push(request(&matcher,"a",index));
+        }
+        requests.push(request(&matcher,"b",2));
+        requests.push(matcher.make_attempt(&Rc::new(JumpReq::new("here")),None).0);
+        let (out,coalesced) = coalescer().coalesce(&backend(),&matcher,requests);
+        let mut spans = out.iter().filter_map(|x| span(x)).collect::<Vec<_>>();
+        spans.sort();
+        assert_eq!(vec![(1,3),(2,1),(5,1),(11,8),(19,2)],spans);
+        assert_eq!(6,out.len());
+        assert_eq!(3,coalesced.0.len());
+        /* unsupported backend is untouched */
+        let requests = vec![request(&matcher,"a",1),request(&matcher,"a",2)];
+        let (out,coalesced) = Coalescer::new().coalesce(&backend(),&matcher,requests);
+        assert_eq!(vec![Some((1,1)),Some((2,1))],out.iter().map(|x| span(x)).collect::<Vec<_>>());
+        assert!(coalesced.0.is_empty());
+    }
+
+    fn response(names: &[&str]) -> MiniResponse {
+        let data = names.iter().map(|name| (name.to_string(),ReceivedData::new_bytes(vec![1]))).collect();
+        MiniResponse::Data(DataRes::new(data,false))
+    }
+
+    fn split(names: &[&str]) -> (Vec<u64>,Vec<(u64,bool)>) {
+        let matcher = AttemptMatch::new();
+        let requests = vec![request(&matcher,"a",1),request(&matcher,"a",2)];
+        let originals = requests.iter().map(|x| x.msgid()).collect();
+        let (out,mut coalesced) = coalescer().coalesce(&backend(),&matcher,requests);
+        let passed = MiniResponseAttempt::new(999,response(&["x"]));
+        let responses = coalesced.split(vec![out[0].make_response_attempt(response(names)),passed]);
+        let results = responses.into_iter().map(|x| {
+            (x.message_id(),matches!(x.into_variety(),MiniResponse::Data(_)))
+        }).collect();
+        (originals,results)
+    }
+
+    #[test]
+    fn test_split() {
+        let (originals,results) = split(&["0:a","1:a","b"]);
+        assert_eq!(vec![(originals[0],true),(originals[1],true),(999,true)],results);
+        /* carriage 1 missing */
+        let (originals,results) = split(&["0:a","b"]);
+        assert_eq!(vec![(originals[0],false),(originals[1],false),(999,true)],results);
+    }
+}
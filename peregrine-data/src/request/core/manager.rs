@@ -1,6 +1,6 @@
 use peregrine_toolkit::error::Error;
 use peregrine_toolkit::{ lock };
-use commander::{ CommanderStream };
+use commander::{ CommanderStream, cdr_current_time };
 use peregrine_toolkit::plumbing::oneshot::OneShot;
 use peregrine_toolkit_async::sync::blocker::Blocker;
 use std::collections::HashMap;
@@ -8,9 +8,12 @@ use std::rc::Rc;
 use std::sync::{ Arc, Mutex };
 use super::attemptmatch::{AttemptMatch};
 use super::backoff::Backoff;
+use super::backoffpolicy::{ BackoffPolicy, CircuitBreaker };
+use super::coalescer::Coalescer;
 use super::queue::{RequestQueue, QueueKey};
 use super::minirequest::MiniRequest;
 use super::miniresponse::{MiniResponseAttempt, MiniResponseError};
+use super::relevance::Relevance;
 use super::sidecars::RequestSidecars;
 use crate::core::channel::channelregistry::{ChannelRegistry};
 use crate::core::channel::wrappedchannelsender::WrappedChannelSender;
@@ -27,7 +30,12 @@ pub(crate) struct LowLevelRequestManager {
     shutdown: OneShot,
     matcher: AttemptMatch,
     queues: Arc<Mutex<HashMap<QueueKey,RequestQueue>>>,
-    real_time_lock: Blocker,
+    priority_locks: Vec<Blocker>,
+    default_policy: Arc<Mutex<BackoffPolicy>>,
+    policies: Arc<Mutex<HashMap<BackendNamespace,BackoffPolicy>>>,
+    breakers: Arc<Mutex<HashMap<Option<BackendNamespace>,CircuitBreaker>>>,
+    coalescer: Coalescer,
+    relevance: Relevance,
     messages: MessageSender
 }
 
@@ -39,7 +47,12 @@ impl LowLevelRequestManager {
             shutdown: shutdown.clone(),
             matcher: AttemptMatch::new(),
             queues: Arc::new(Mutex::new(HashMap::new())),
-            real_time_lock: Blocker::new(),
+            priority_locks: (0..PacketPriority::COUNT).map(|_| Blocker::new()).collect(),
+            default_policy: Arc::new(Mutex::new(BackoffPolicy::new())),
+            policies: Arc::new(Mutex::new(HashMap::new())),
+            breakers: Arc::new(Mutex::new(HashMap::new())),
+            coalescer: Coalescer::new(),
+            relevance: Relevance::new(),
             messages: messages.clone(),
             version: version.clone()
         }
@@ -49,8 +62,27 @@ impl LowLevelRequestManager {
         self.messages.send(message);
     }
 
+    pub(crate) fn set_backoff_policy(&self, name: Option<&BackendNamespace>, policy: &BackoffPolicy) {
+        if let Some(name) = name {
+            lock!(self.policies).insert(name.clone(),policy.clone());
+        } else {
+            *lock!(self.default_policy) = policy.clone();
+        }
+    }
+
+    pub(crate) fn backoff_policy(&self, name: &Option<BackendNamespace>) -> BackoffPolicy {
+        name.as_ref().and_then(|name| lock!(self.policies).get(name).cloned())
+            .unwrap_or_else(|| lock!(self.default_policy).clone())
+    }
+
+    pub(crate) fn with_breaker<F,T>(&self, name: &Option<BackendNamespace>, cb: F) -> T where F: FnOnce(&mut CircuitBreaker) -> T {
+        let mut breakers = lock!(self.breakers);
+        let seed = (breakers.len() as u64+1).wrapping_mul(0x2545f4914f6cdd1d) ^ cdr_current_time().to_bits();
+        cb(breakers.entry(name.clone()).or_insert_with(|| CircuitBreaker::new(seed)))
+    }
+
     fn create_queue(&self, key: &QueueKey) -> Result<RequestQueue,Error> {
-        let queue = RequestQueue::new(key,&self.commander,&self.real_time_lock,&self.matcher,&self.sidecars,&self.version,&self.messages)?;
+        let queue = RequestQueue::new(key,&self.commander,&self.priority_locks,&self.coalescer,&self.matcher,&self.relevance,&self.sidecars,&self.version,&self.messages)?;
         let queue2 = queue.clone();
         self.shutdown.add(move || {
             queue2.input_queue().close();
@@ -68,8 +100,10 @@ impl LowLevelRequestManager {
         Ok(queues.get_mut(&key).unwrap().clone()) // safe because of above insert
     }
 
-    pub(crate) fn execute(&mut self, key: &QueueKey, request: &Rc<MiniRequest>) -> Result<CommanderStream<MiniResponseAttempt>,Error> {
-        let (request,stream) = self.matcher.make_attempt(request);
+    pub(crate) fn relevance(&self) -> &Relevance { &self.relevance }
+
+    pub(crate) fn execute(&mut self, key: &QueueKey, request: &Rc<MiniRequest>, relevance: Option<u64>) -> Result<CommanderStream<MiniResponseAttempt>,Error> {
+        let (request,stream) = self.matcher.make_attempt(request,relevance);
         self.get_queue(key)?.input_queue().add(request);
         Ok(stream.clone())
     }
@@ -132,7 +166,7 @@ impl RequestManager {
                 timeout: None,
                 slot: None,
                 task: Box::pin(async move { 
-                    manager.low.execute(&key,&Rc::new(request)).ok().unwrap().get().await;
+                    manager.low.execute(&key,&Rc::new(request),None).ok().unwrap().get().await;
                     Ok(())
                 }),
                 stats: false
@@ -143,4 +177,21 @@ impl RequestManager {
     pub fn message(&self, message: Error) {
         self.low.message(message);
     }
+
+    /* the central carriage has changed: anything anticipating the old position is now stale */
+    pub(crate) fn viewport_moved(&self) {
+        self.low.relevance.moved();
+    }
+
+    pub fn set_backoff_policy(&self, name: &BackendNamespace, policy: &BackoffPolicy) {
+        self.low.set_backoff_policy(Some(name),policy);
+    }
+
+    pub fn set_default_backoff_policy(&self, policy: &BackoffPolicy) {
+        self.low.set_backoff_policy(None,policy);
+    }
+
+    pub(crate) fn set_backend_features(&self, name: &BackendNamespace, features: &[String]) {
+        self.low.coalescer.set_features(name,features);
+    }
 }
@@ -3,14 +3,15 @@ use peregrine_toolkit::error::Error;
 use serde::{Serialize};
 use serde::ser::SerializeMap;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::sync::Arc;
 use crate::core::channel::channelintegration::{ChannelMessageDecoder};
 use crate::core::channel::wrappedchannelsender::WrappedChannelSender;
-use crate::{DataMessage, ChannelSender, BackendNamespace};
+use crate::{DataMessage, ChannelSender, BackendNamespace, PacketPriority};
 use crate::core::version::VersionMetadata;
 use super::maxiresponse::MaxiResponse;
 use super::packet::{RequestPacketFactory, RequestPacketBuilder};
-use super::minirequest::MiniRequestAttempt;
+use super::minirequest::{MiniRequest, MiniRequestAttempt};
 
 #[derive(Clone)]
 pub struct MaxiRequest {
@@ -26,6 +27,18 @@ impl MaxiRequest {
         }
     }
 
+    /* A packet made directly rather than by a RequestManager, for driving a channel natively (eg in
+     * tests). msgids are the positions of the requests.
+     */
+    pub fn new_fixed(channel: &BackendNamespace, requests: Vec<MiniRequest>) -> MaxiRequest {
+        let factory = RequestPacketFactory::new(channel,&PacketPriority::RealTime,&VersionMetadata::new());
+        let mut builder = factory.create();
+        for (msgid,request) in requests.into_iter().enumerate() {
+            builder.add(MiniRequestAttempt::new(msgid as u64,&Rc::new(request),None));
+        }
+        MaxiRequest::new(builder)
+    }
+
     pub fn fail(&self, extra: &str) -> MaxiResponse {
         let mut response = MaxiResponse::empty(&self.factory.channel);
         for r in self.requests.iter() {
@@ -1,6 +1,7 @@
 use peregrine_toolkit::{ serdetools::{st_field, ByteData }, log};
 use serde::de::{Visitor, MapAccess, DeserializeSeed, IgnoredAny};
-use serde::{Deserializer};
+use serde::{Deserializer, Serialize, Serializer};
+use serde::ser::{SerializeMap, Error as _};
 use std::any::Any;
 use std::fmt;
 use std::mem::replace;
@@ -126,6 +127,40 @@ impl<'de> Visitor<'de> for MaxiResponseVisitor {
     }
 }
 
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/* See MiniResponse. Programs and tracks supplied alongside the responses (eg by javascript) can't
+ * be sent again as they have no packed form.
+ */
+impl Serialize for MaxiResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        if self.programs.len() > 0 {
+            return Err(S::Error::custom("cannot send programs"));
+        }
+        for tracks in &self.tracks {
+            match tracks {
+                TrackResult::None => {},
+                TrackResult::Unpacked(tracks,expansions) if tracks.is_empty() && expansions.is_empty() => {},
+                _ => { return Err(S::Error::custom("cannot send tracks")); }
+            }
+        }
+        let no_programs : Vec<()> = vec![];
+        let eardos = self.eardos.iter().map(|(name,data)| (name,Bytes(data))).collect::<Vec<_>>();
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("channel",&self.channel)?;
+        map.serialize_entry("responses",&self.responses)?;
+        map.serialize_entry("programs",&no_programs)?;
+        map.serialize_entry("eardos",&eardos)?;
+        map.end()
+    }
+}
+
 pub struct MaxiResponseDeserialize(pub(crate) WrappedChannelSender,pub(crate) Arc<dyn Any>);
 
 impl<'de> DeserializeSeed<'de> for MaxiResponseDeserialize {
@@ -66,15 +66,17 @@ impl Serialize for MiniRequest {
 #[derive(Clone)]
 pub struct MiniRequestAttempt {
     msgid: u64,
+    relevance: Option<u64>,
     description: String,
     request: Rc<MiniRequest>,
     response: CommanderStream<MiniResponseAttempt>
 }
 
 impl MiniRequestAttempt {
-    pub(crate) fn new(msgid: u64, request: &Rc<MiniRequest>) -> MiniRequestAttempt {
+    pub(crate) fn new(msgid: u64, request: &Rc<MiniRequest>, relevance: Option<u64>) -> MiniRequestAttempt {
         MiniRequestAttempt {
             msgid,
+            relevance,
             request: request.clone(),
             description: request.as_mini().description(),
             response: CommanderStream::new()
@@ -89,6 +91,14 @@ impl MiniRequestAttempt {
         MiniResponseAttempt::new(self.msgid,failure)
     }
 
+    pub(crate) fn fail_stale(&self) -> MiniResponseAttempt {
+        let message = format!("{}: dropped unsent as the viewport moved on",self.description);
+        MiniResponseAttempt::new(self.msgid,MiniResponse::FailureRes(FailureRes::new_stale(&message)))
+    }
+
+    /* viewport epoch when made, if it expires (see Relevance) */
+    pub(crate) fn relevance(&self) -> Option<u64> { self.relevance }
+
     pub fn make_response_attempt(&self, mini: MiniResponse) -> MiniResponseAttempt {
         MiniResponseAttempt::new(self.msgid,mini)
     }
@@ -1,6 +1,6 @@
 use std::{fmt, sync::Arc, any::Any};
 use peregrine_toolkit::{serdetools::st_field, error::Error};
-use serde::{Deserializer, de::{Visitor, DeserializeSeed}};
+use serde::{Deserializer, Serialize, Serializer, de::{Visitor, DeserializeSeed}, ser::SerializeSeq};
 use crate::{request::minirequests::{bootchannelres::BootChannelRes, datares::{DataRes, DataResDeserialize}, failureres::{FailureRes, UnavailableRes, UnavailableReason}, jumpres::JumpRes, programres::ProgramRes, stickres::StickRes, expandres::ExpandRes, smallvaluesres::SmallValuesRes }, core::channel::wrappedchannelsender::WrappedChannelSender};
 
 pub(crate) trait MiniResponseVariety {
@@ -11,7 +11,8 @@ pub(crate) trait MiniResponseVariety {
 
 pub(crate) enum MiniResponseError {
     Retry(Error),
-    NoRetry(Error)
+    NoRetry(Error),
+    Stale(Error)
 }
 
 pub enum MiniResponse {
@@ -54,6 +55,9 @@ impl MiniResponse {
 
     fn bad_response(&self) -> MiniResponseError {
         match self {
+            MiniResponse::FailureRes(g) if g.is_stale() => {
+                MiniResponseError::Stale(Error::operr(g.message()))
+            },
             MiniResponse::FailureRes(g) => {
                 MiniResponseError::Retry(Error::operr(g.message()))
             },
@@ -86,6 +90,28 @@ impl MiniResponse {
     pub(crate) fn total_size(&self) -> usize { self.as_mini().total_size() }
 }
 
+/* The wire format, so that responses which didn't arrive as packets (eg from javascript) can be
+ * recorded. See MiniResponseVisitor.
+ */
+impl Serialize for MiniResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let nothing : Vec<()> = vec![];
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        match self {
+            MiniResponse::BootChannel(x) => { seq.serialize_element(&0)?; seq.serialize_element(x)?; },
+            MiniResponse::FailureRes(x) => { seq.serialize_element(&1)?; seq.serialize_element(x.message())?; },
+            MiniResponse::Program(_) => { seq.serialize_element(&2)?; seq.serialize_element(&nothing)?; },
+            MiniResponse::Stick(x) => { seq.serialize_element(&3)?; seq.serialize_element(x)?; },
+            MiniResponse::Data(x) => { seq.serialize_element(&5)?; seq.serialize_element(x)?; },
+            MiniResponse::Jump(x) => { seq.serialize_element(&6)?; seq.serialize_element(x)?; },
+            MiniResponse::Expand(_) => { seq.serialize_element(&7)?; seq.serialize_element(&nothing)?; },
+            MiniResponse::Unavailable(x) => { seq.serialize_element(&8)?; seq.serialize_element(&[x.reason()])?; },
+            MiniResponse::SmallValues(x) => { seq.serialize_element(&9)?; seq.serialize_element(&[x.small_values()])?; }
+        }
+        seq.end()
+    }
+}
+
 struct MiniResponseVisitor(WrappedChannelSender,Arc<dyn Any>);
 
 impl<'de> Visitor<'de> for MiniResponseVisitor {
@@ -145,6 +171,12 @@ impl MiniResponseAttempt {
     pub(super) fn component_size(&self) -> Vec<(String,usize)> { self.variety.component_size() }
 }
 
+impl Serialize for MiniResponseAttempt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        (self.msg_id,&self.variety).serialize(serializer)
+    }
+}
+
 struct MiniResponseAttemptVisitor(WrappedChannelSender,Arc<dyn Any>);
 
 impl<'de> Visitor<'de> for MiniResponseAttemptVisitor {
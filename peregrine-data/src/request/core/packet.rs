@@ -42,4 +42,6 @@ impl RequestPacketBuilder {
     pub fn add(&mut self, request: MiniRequestAttempt) {
         self.requests.push(request);
     }
+
+    pub fn is_empty(&self) -> bool { self.requests.is_empty() }
 }
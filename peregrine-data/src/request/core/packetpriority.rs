@@ -1,41 +1,87 @@
 use std::fmt::{ self, Display, Formatter };
 use serde_derive::{ Serialize };
 
+/* Most to least urgent:
+ *   RealTime: the user is waiting for it (the carriage under the viewport).
+ *   Visible: on screen or about to be but not blocking interaction (its neighbours).
+ *   Batch: bulk background loading.
+ *   Prefetch: anticipating the next likely move.
+ *   Speculative: anticipating less likely moves.
+ *
+ * Each tier waits for all more urgent tiers to be quiet before sending. Prefetch and speculative
+ * requests are dropped unsent once the viewport moves on from where they were made (see
+ * Relevance).
+ */
 #[cfg_attr(debug_assertions,derive(Debug))]
 #[derive(Clone,PartialEq,Eq,Hash,Serialize)]
 pub enum PacketPriority {
     RealTime,
-    Batch
+    Visible,
+    Batch,
+    Prefetch,
+    Speculative
 }
 
 impl PacketPriority {
+    pub(crate) const COUNT : usize = 5;
+
     pub fn index(&self) -> usize {
         match self {
             PacketPriority::RealTime => 0,
-            PacketPriority::Batch => 1
+            PacketPriority::Visible => 1,
+            PacketPriority::Batch => 2,
+            PacketPriority::Prefetch => 3,
+            PacketPriority::Speculative => 4
         }
     }
 
     pub fn is_high(&self) -> bool {
         match self {
-            PacketPriority::RealTime => true,
-            PacketPriority::Batch => false
+            PacketPriority::RealTime | PacketPriority::Visible => true,
+            _ => false
         }
     }
 
     pub(crate) fn cdr_priority(&self) -> u8 {
         match self {
+            PacketPriority::RealTime => 3,
+            PacketPriority::Visible => 4,
             PacketPriority::Batch => 5,
-            PacketPriority::RealTime => 3
+            PacketPriority::Prefetch => 6,
+            PacketPriority::Speculative => 7
         }
     }
 
-    pub(crate) fn repeats(&self) -> usize { 5 }
+    pub(crate) fn repeats(&self) -> usize {
+        match self {
+            PacketPriority::Speculative => 1,
+            _ => 5
+        }
+    }
 
     pub(crate) fn get_pace(&self) -> &[f64] {
         match self {
-            PacketPriority::Batch => &[0.,5000.,10000.,20000.,20000.,20000.],
-            PacketPriority::RealTime => &[0.,0.,500.,2000.,3000.,10000.]
+            PacketPriority::RealTime => &[0.,0.,500.,2000.,3000.,10000.],
+            PacketPriority::Visible => &[0.,500.,2000.,3000.,5000.,10000.],
+            PacketPriority::Batch | PacketPriority::Prefetch => &[0.,5000.,10000.,20000.,20000.,20000.],
+            PacketPriority::Speculative => &[0.,10000.,20000.,20000.,20000.,20000.]
+        }
+    }
+
+    /* requests at most this many per packet, if limited */
+    pub(crate) fn batch_size(&self) -> Option<usize> {
+        match self {
+            PacketPriority::RealTime | PacketPriority::Visible => None, /* limitless */
+            PacketPriority::Batch | PacketPriority::Prefetch => Some(20),
+            PacketPriority::Speculative => Some(10)
+        }
+    }
+
+    /* dropped unsent when the viewport moves on? */
+    pub(crate) fn expires(&self) -> bool {
+        match self {
+            PacketPriority::Prefetch | PacketPriority::Speculative => true,
+            _ => false
         }
     }
 }
@@ -44,7 +90,33 @@ impl Display for PacketPriority {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             PacketPriority::RealTime => write!(f,"real-time"),
-            PacketPriority::Batch => write!(f,"batch")
+            PacketPriority::Visible => write!(f,"visible"),
+            PacketPriority::Batch => write!(f,"batch"),
+            PacketPriority::Prefetch => write!(f,"prefetch"),
+            PacketPriority::Speculative => write!(f,"speculative")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ALL : [PacketPriority;PacketPriority::COUNT] = [
+        PacketPriority::RealTime, PacketPriority::Visible, PacketPriority::Batch,
+        PacketPriority::Prefetch, PacketPriority::Speculative
+    ];
+
+    #[test]
+    fn test_tier_order() {
+        for (i,priority) in ALL.iter().enumerate() {
+            assert_eq!(i,priority.index());
+        }
+        for pair in ALL.windows(2) {
+            assert!(pair[0].cdr_priority() < pair[1].cdr_priority());
+            assert!(pair[0].get_pace()[1] <= pair[1].get_pace()[1]);
         }
+        assert_eq!(vec![true,true,false,false,false],ALL.iter().map(|x| x.is_high()).collect::<Vec<_>>());
+        assert_eq!(vec![false,false,false,true,true],ALL.iter().map(|x| x.expires()).collect::<Vec<_>>());
     }
 }
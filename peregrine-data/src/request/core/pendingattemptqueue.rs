@@ -1,16 +1,18 @@
 use commander::CommanderStream;
-use super::{minirequest::MiniRequestAttempt, packet::RequestPacketBuilder};
+use super::{minirequest::MiniRequestAttempt, packet::RequestPacketBuilder, relevance::Relevance};
 
 #[derive(Clone)]
 pub(crate) struct PendingAttemptQueue {
     batch_size: Option<usize>,
+    relevance: Relevance,
     pending: CommanderStream<Option<MiniRequestAttempt>>
 }
 
 impl PendingAttemptQueue {
-    pub(crate) fn new(batch_size: Option<usize>) -> PendingAttemptQueue {
+    pub(crate) fn new(batch_size: Option<usize>, relevance: &Relevance) -> PendingAttemptQueue {
         PendingAttemptQueue {
             pending: CommanderStream::new(),
+            relevance: relevance.clone(),
             batch_size
         }
     }
@@ -23,15 +25,55 @@ impl PendingAttemptQueue {
         self.pending.add(None);
     }
 
-    pub(crate) async fn add_to_packet(&self, packet: &mut RequestPacketBuilder) -> bool {
-        let requests = self.pending.get_multi(self.batch_size).await;
-        for item in requests {
-            if let Some(item) = item {
-                packet.add(item);
+    /* Fills the packet, returning requests which the viewport has moved on from so that they can
+     * be failed unsent. None if closed.
+     */
+    pub(crate) async fn add_to_packet(&self, packet: &mut RequestPacketBuilder) -> Option<Vec<MiniRequestAttempt>> {
+        let mut stale = vec![];
+        for item in self.pending.get_multi(self.batch_size).await {
+            let item = item?; // None if close was received
+            if self.relevance.is_stale(item.relevance()) {
+                stale.push(item);
             } else {
-                return false; // close was received
+                packet.add(item);
             }
         }
-        true
+        Some(stale)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use futures::executor::block_on;
+    use crate::{PacketPriority, BackendNamespace};
+    use crate::core::version::VersionMetadata;
+    use crate::request::core::packet::RequestPacketFactory;
+    use crate::request::minirequests::jumpreq::JumpReq;
+    use super::*;
+
+    fn attempt(msgid: u64, relevance: &Relevance, priority: &PacketPriority) -> MiniRequestAttempt {
+        MiniRequestAttempt::new(msgid,&Rc::new(JumpReq::new("here")),relevance.tag(priority))
+    }
+
+    fn msgids(requests: &[MiniRequestAttempt]) -> Vec<u64> {
+        requests.iter().map(|x| x.msgid()).collect()
+    }
+
+    #[test]
+    fn test_stale_dropped() {
+        let relevance = Relevance::new();
+        let factory = RequestPacketFactory::new(&BackendNamespace::or_missing(&None),&PacketPriority::Prefetch,&VersionMetadata::new());
+        let queue = PendingAttemptQueue::new(None,&relevance);
+        queue.add(attempt(1,&relevance,&PacketPriority::Prefetch));
+        queue.add(attempt(2,&relevance,&PacketPriority::Batch));
+        relevance.moved();
+        queue.add(attempt(3,&relevance,&PacketPriority::Speculative));
+        let mut packet = factory.create();
+        let stale = block_on(queue.add_to_packet(&mut packet)).unwrap();
+        assert_eq!(vec![1],msgids(&stale));
+        assert_eq!(vec![2,3],msgids(&packet.requests));
+        queue.close();
+        assert!(block_on(queue.add_to_packet(&mut factory.create())).is_none());
     }
 }
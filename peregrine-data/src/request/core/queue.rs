@@ -8,8 +8,11 @@ use crate::run::{ PgCommander, add_task };
 use crate::run::pgcommander::PgCommanderTaskSpec;
 use crate::util::message::DataMessage;
 use super::attemptmatch::{AttemptMatch};
+use super::coalescer::{ Coalescer, CoalescedRequests };
+use super::minirequest::MiniRequestAttempt;
 use super::packet::{RequestPacketFactory};
 use super::pendingattemptqueue::PendingAttemptQueue;
+use super::relevance::Relevance;
 use super::sidecars::RequestSidecars;
 use super::trafficcontrol::TrafficControl;
 
@@ -38,22 +41,20 @@ pub struct RequestQueue {
     messages: MessageSender,
     pending_send: PendingAttemptQueue,
     packet_factory: RequestPacketFactory,
+    coalescer: Coalescer,
     traffic_control: TrafficControl
 }
 
 impl RequestQueue {
-    pub(crate) fn new(key: &QueueKey, commander: &PgCommander, realtime_lock: &Blocker, matcher: &AttemptMatch, sidecars: &RequestSidecars, version: &VersionMetadata, messages: &MessageSender) -> Result<RequestQueue,Error> {
-        let batch_size = match key.priority {
-            PacketPriority::RealTime => None, /* limitless */
-            PacketPriority::Batch => Some(20) /* no more than 20 at a time */
-        };
+    pub(crate) fn new(key: &QueueKey, commander: &PgCommander, priority_locks: &[Blocker], coalescer: &Coalescer, matcher: &AttemptMatch, relevance: &Relevance, sidecars: &RequestSidecars, version: &VersionMetadata, messages: &MessageSender) -> Result<RequestQueue,Error> {
         let out = RequestQueue {
             key: key.clone(),
             messages: messages.clone(),
-            pending_send: PendingAttemptQueue::new(batch_size),
+            pending_send: PendingAttemptQueue::new(key.priority.batch_size(),relevance),
             name: format!("backend: '{:?}' {}",key.sender.id(),key.priority.to_string()),
+            coalescer: coalescer.clone(),
             packet_factory: RequestPacketFactory::new(&BackendNamespace::or_missing(&key.name),&key.priority,version),
-            traffic_control: TrafficControl::new(realtime_lock,&key.priority,&key.priority.get_pace())
+            traffic_control: TrafficControl::new(priority_locks,&key.priority,&key.priority.get_pace())
         };
         out.start(commander,matcher,sidecars,key.priority.cdr_priority())?;
         Ok(out)
@@ -76,12 +77,27 @@ impl RequestQueue {
         Ok(())
     }
 
-    async fn build_packet(&self) -> Option<MaxiRequest> {
-        let mut packet = self.packet_factory.create();
-        if !self.pending_send.add_to_packet(&mut packet).await {
-            return None; /* queue closed */
+    fn fail_stale(&self, matcher: &AttemptMatch, stale: Vec<MiniRequestAttempt>) {
+        for attempt in stale {
+            let failure = attempt.fail_stale();
+            if let Some(stream) = matcher.retrieve_callback_by_response(&failure) {
+                stream.add(failure);
+            }
+        }
+    }
+
+    async fn build_packet(&self, matcher: &AttemptMatch) -> Option<(MaxiRequest,CoalescedRequests)> {
+        loop {
+            let mut packet = self.packet_factory.create();
+            let stale = self.pending_send.add_to_packet(&mut packet).await?; /* None if queue closed */
+            self.fail_stale(matcher,stale);
+            if !packet.is_empty() {
+                let requests = std::mem::take(&mut packet.requests);
+                let (requests,coalesced) = self.coalescer.coalesce(&self.key.name,matcher,requests);
+                packet.requests = requests;
+                return Some((MaxiRequest::new(packet),coalesced));
+            }
         }
-        Some(MaxiRequest::new(packet))
     }
 
     async fn send_packet(&self, packet: &MaxiRequest) -> Result<MaxiResponse,DataMessage> {
@@ -101,10 +117,10 @@ impl RequestQueue {
         res.ok().unwrap_or_else(|| packet.fail("network/backend failed"))
     }
 
-    async fn process_request(&self, matcher: &AttemptMatch, sidecars: &RequestSidecars, request: &mut MaxiRequest) {
+    async fn process_request(&self, matcher: &AttemptMatch, sidecars: &RequestSidecars, request: &mut MaxiRequest, coalesced: &mut CoalescedRequests) {
         let mut response = self.send_or_fail_packet(request).await;
         sidecars.run(&response,&response.channel(),&self.messages).await;
-        for r in response.take_responses().drain(..) {
+        for r in coalesced.split(response.take_responses()).drain(..) {
             if let Some(stream) = matcher.retrieve_callback_by_response(&r) {
                 stream.add(r);
             }
@@ -113,9 +129,9 @@ impl RequestQueue {
 
     async fn main_loop(self, matcher: AttemptMatch, sidecars: RequestSidecars) -> Result<(),Error> {
         loop {
-            let request = self.build_packet().await;
-            if let Some(mut request) = request {
-                self.process_request(&matcher,&sidecars,&mut request).await;
+            let request = self.build_packet(&matcher).await;
+            if let Some((mut request,mut coalesced)) = request {
+                self.process_request(&matcher,&sidecars,&mut request,&mut coalesced).await;
             } else {
                 break;
             }
@@ -0,0 +1,50 @@
+use std::sync::{ Arc, atomic::{ AtomicU64, Ordering } };
+use crate::PacketPriority;
+
+/* Prefetch and speculative requests anticipate moves from the current viewport and are worth
+ * nothing once the viewport has moved on. Each such request is tagged with the viewport epoch
+ * current when it was made. The epoch is advanced every time the central carriage changes and
+ * requests with an old tag are dropped unsent. Requests at other priorities are never tagged.
+ */
+
+#[derive(Clone)]
+pub(crate) struct Relevance(Arc<AtomicU64>);
+
+impl Relevance {
+    pub(crate) fn new() -> Relevance {
+        Relevance(Arc::new(AtomicU64::new(0)))
+    }
+
+    pub(crate) fn moved(&self) {
+        self.0.fetch_add(1,Ordering::SeqCst);
+    }
+
+    pub(crate) fn tag(&self, priority: &PacketPriority) -> Option<u64> {
+        if priority.expires() { Some(self.0.load(Ordering::SeqCst)) } else { None }
+    }
+
+    pub(crate) fn is_stale(&self, tag: Option<u64>) -> bool {
+        tag.map(|tag| tag != self.0.load(Ordering::SeqCst)).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relevance() {
+        let relevance = Relevance::new();
+        assert_eq!(None,relevance.tag(&PacketPriority::RealTime));
+        assert_eq!(None,relevance.tag(&PacketPriority::Batch));
+        let prefetch = relevance.tag(&PacketPriority::Prefetch);
+        let speculative = relevance.tag(&PacketPriority::Speculative);
+        assert!(prefetch.is_some());
+        assert!(!relevance.is_stale(prefetch));
+        relevance.moved();
+        assert!(relevance.is_stale(prefetch));
+        assert!(relevance.is_stale(speculative));
+        assert!(!relevance.is_stale(None));
+        assert!(!relevance.is_stale(relevance.tag(&PacketPriority::Prefetch)));
+    }
+}
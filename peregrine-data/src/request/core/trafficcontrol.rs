@@ -3,19 +3,24 @@ use peregrine_toolkit_async::sync::{pacer::Pacer, blocker::{Blocker, Lockout}};
 
 use crate::PacketPriority;
 
+/* There is a Blocker per priority tier. While sending, a tier holds its own blocker (unless it is
+ * the least urgent tier, which nothing waits for) and before sending it waits until the blockers
+ * of all more urgent tiers are free.
+ */
 #[derive(Clone)]
 pub(crate) struct TrafficControl {
     pacer: Pacer<f64>,
-    high_priority: bool,
-    realtime_lock: Blocker
+    own_lock: Option<Blocker>,
+    urgent_locks: Vec<Blocker>
 }
 
 impl TrafficControl {
-    pub(crate) fn new(realtime_lock: &Blocker, priority: &PacketPriority, pacing: &[f64]) -> TrafficControl {
+    pub(crate) fn new(priority_locks: &[Blocker], priority: &PacketPriority, pacing: &[f64]) -> TrafficControl {
+        let index = priority.index();
         TrafficControl {
             pacer: Pacer::new(pacing),
-            high_priority: if let PacketPriority::RealTime = priority { true } else { false },
-            realtime_lock: realtime_lock.clone()
+            own_lock: if index+1 < priority_locks.len() { Some(priority_locks[index].clone()) } else { None },
+            urgent_locks: priority_locks[..index].to_vec()
         }
     }
 
@@ -23,14 +28,18 @@ impl TrafficControl {
         self.pacer.report(success);
     }
 
+    fn urgent_blocked(&self) -> bool {
+        self.urgent_locks.iter().any(|lock| lock.clone().is_blocked())
+    }
+
     pub(crate) async fn await_permission(&self) -> Option<Lockout> {
         let wait = self.pacer.get();
         cdr_timer(wait).await;
-        if self.high_priority {
-            Some(self.realtime_lock.lock())
-        } else {
-            self.realtime_lock.wait().await;
-            None
+        while self.urgent_blocked() {
+            for lock in &self.urgent_locks {
+                lock.wait().await;
+            }
         }
+        self.own_lock.as_ref().map(|lock| lock.lock())
     }
 }
@@ -1,24 +1,27 @@
 use std::fmt;
 use peregrine_toolkit::{serdetools::st_field};
-use serde::{Deserialize, Deserializer, de::{Visitor, MapAccess, IgnoredAny}};
+use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{Visitor, MapAccess, IgnoredAny}, ser::{SerializeMap, Error as _}};
 use crate::{Assets, BackendNamespace, request::core::miniresponse::MiniResponseVariety};
 
 pub struct BootChannelRes {
     namespace: BackendNamespace,
     channel_assets: Assets,
     chrome_assets: Assets,
-    supports: Option<Vec<u32>>
+    supports: Option<Vec<u32>>,
+    features: Vec<String>
 }
 
 impl BootChannelRes {
     pub fn new(namespace: BackendNamespace, channel_assets: Assets, chrome_assets: Assets, supports: Option<Vec<u32>>) -> BootChannelRes {
-        BootChannelRes { namespace, channel_assets, chrome_assets, supports }
+        BootChannelRes { namespace, channel_assets, chrome_assets, supports, features: vec![] }
     }
 
     pub(crate) fn channel_assets(&self) -> &Assets { &self.channel_assets }
     pub(crate) fn chrome_assets(&self) -> &Assets { &self.chrome_assets }
     pub(crate) fn namespace(&self) -> &BackendNamespace { &self.namespace }
     pub(crate) fn supports(&self) -> Option<&[u32]> { self.supports.as_ref().map(|x| &x[..]).clone() }
+    pub(crate) fn features(&self) -> &[String] { &self.features }
 }
 
 struct BootChannelVisitor;
@@ -33,12 +36,14 @@ impl<'de> Visitor<'de> for BootChannelVisitor {
     fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
             where M: MapAccess<'de> {
         let mut supports = None;
+        let mut features = None;
         let mut namespace : Option<BackendNamespace> = None;
         let mut assets = None;
         let mut chrome_assets = None;
         while let Some(key) = access.next_key()? {
             match key {
                 "supports" => { supports = Some(access.next_value()?); },
+                "features" => { features = Some(access.next_value()?); },
                 "namespace" => { namespace = Some(access.next_value()?); },
                 "chrome-assets" => { chrome_assets = Some(access.next_value()?); },
                 "assets" => { assets = Some(access.next_value()?); },
@@ -56,7 +61,8 @@ impl<'de> Visitor<'de> for BootChannelVisitor {
             namespace,
             channel_assets: assets,
             chrome_assets,
-            supports
+            supports,
+            features: features.unwrap_or_else(|| vec![])
         })
     }
 }
@@ -68,6 +74,25 @@ impl<'de> Deserialize<'de> for BootChannelRes {
     }
 }
 
+/* Assets can't be sent again, but channels which don't send packets don't have any. */
+impl Serialize for BootChannelRes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        if !self.channel_assets.is_empty() || !self.chrome_assets.is_empty() {
+            return Err(S::Error::custom("cannot send assets"));
+        }
+        let no_assets : HashMap<String,()> = HashMap::new();
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("namespace",&self.namespace)?;
+        map.serialize_entry("assets",&no_assets)?;
+        map.serialize_entry("chrome-assets",&no_assets)?;
+        if let Some(supports) = &self.supports {
+            map.serialize_entry("supports",supports)?;
+        }
+        map.serialize_entry("features",&self.features)?;
+        map.end()
+    }
+}
+
 impl MiniResponseVariety for BootChannelRes {
     fn description(&self) -> &str { "bootstrap" }
 }
@@ -10,7 +10,8 @@ pub struct DataRequest {
     name: String,
     region: Region,
     scope: BTreeMap<String,Vec<String>>,
-    accept: String
+    accept: String,
+    span: u64 /* carriages from region onwards: >1 only when coalesced (see Coalescer) */
 }
 
 impl DataRequest {
@@ -20,7 +21,8 @@ impl DataRequest {
             name: name.to_string(),
             region: region.clone(),
             scope: BTreeMap::new(),
-            accept: "release".to_string()
+            accept: "release".to_string(),
+            span: 1
         }
     }
 
@@ -28,6 +30,20 @@ impl DataRequest {
     pub fn name(&self) -> &str { &self.name }
     pub fn region(&self) -> &Region { &self.region }
     pub fn scope(&self) -> &BTreeMap<String,Vec<String>> { &self.scope }
+    pub(crate) fn span(&self) -> u64 { self.span }
+
+    /* Requests which differ only in carriage index have the same coalesce key */
+    pub(crate) fn coalesce_key(&self) -> DataRequest {
+        let mut out = self.clone();
+        out.region = Region::new(self.region.stick(),0,self.region.scale());
+        out
+    }
+
+    pub(crate) fn with_span(&self, span: u64) -> DataRequest {
+        let mut out = self.clone();
+        out.span = span;
+        out
+    }
 
     pub fn to_invariant(&self) -> DataRequest {
         let mut out = self.clone();
@@ -50,12 +66,15 @@ impl MiniRequestVariety for DataRequest {
 impl Serialize for DataRequest {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where S: serde::Serializer {
-        let mut seq = serializer.serialize_seq(Some(5))?;
+        let mut seq = serializer.serialize_seq(Some(if self.span > 1 { 6 } else { 5 }))?;
         seq.serialize_element(&self.channel)?;
         seq.serialize_element(&self.name)?;
         seq.serialize_element(&self.region)?;
         seq.serialize_element(&self.scope)?;
         seq.serialize_element(&self.accept)?;
+        if self.span > 1 {
+            seq.serialize_element(&self.span)?;
+        }
         seq.end()
     }
 }
@@ -1,7 +1,9 @@
 use anyhow::anyhow as err;
 use peregrine_toolkit::serdetools::{st_field, ByteData };
 use serde::de::{Visitor, MapAccess, DeserializeSeed, self, IgnoredAny, };
-use serde::{Deserializer};
+use serde::{Deserializer, Serialize, Serializer};
+use serde::ser::{SerializeMap, Error as _};
+use std::collections::{ BTreeMap, BTreeSet };
 use std::any::Any;
 use std::fmt;
 use std::sync::Arc;
@@ -12,24 +14,31 @@ use crate::core::channel::wrappedchannelsender::WrappedChannelSender;
 use crate::request::core::miniresponse::MiniResponseVariety;
 use crate::{metric::datastreammetric::PacketDatastreamMetricBuilder};
 use crate::core::data::ReceivedData;
+use crate::core::lazydata::LazyReceivedData;
+use crate::util::persistentcache::{ CacheReader, CacheWriter };
+use peregrine_toolkit::error::Error;
 
 pub struct DataRes {
-    data: HashMap<String,ReceivedData>,
+    data: HashMap<String,LazyReceivedData>,
     invariant: bool
 }
 
 impl DataRes {
-    pub fn new(data: HashMap<String,ReceivedData>, invariant: bool) -> DataRes {
+    pub fn new(mut data: HashMap<String,ReceivedData>, invariant: bool) -> DataRes {
+        let data = data.drain().map(|(k,v)| (k,LazyReceivedData::new_decoded(v))).collect();
         DataRes { data, invariant }
     }
 
+    /* Sizes are only for metrics, so a corrupt stream just counts as empty: it is reported when
+     * it is read.
+     */
     pub(crate) fn account(&self, account_builder: &PacketDatastreamMetricBuilder) {
         for (name,data) in &self.data {
-            account_builder.add(name,data.len());
+            account_builder.add(name,data.len().unwrap_or(0));
         }
     }
 
-    fn get2(&self, name: &str) -> anyhow::Result<&ReceivedData> {
+    fn stream(&self, name: &str) -> anyhow::Result<&LazyReceivedData> {
         self.data.get(name)
             .ok_or_else(|| err!("no such data {}: have {}",
                 name,
@@ -37,7 +46,127 @@ impl DataRes {
             ))
     }
 
+    fn get2(&self, name: &str) -> anyhow::Result<ReceivedData> {
+        self.stream(name)?.get().map_err(|_| err!("corrupt data {}",name))
+    }
+
+    fn len(&self, name: &str) -> anyhow::Result<usize> {
+        self.stream(name)?.len().map_err(|_| err!("corrupt data {}",name))
+    }
+
+    fn get_range(&self, name: &str, start: usize, end: usize) -> anyhow::Result<ReceivedData> {
+        self.stream(name)?.get_range(start,end).map_err(|_| err!("corrupt data {}",name))
+    }
+
     fn is_invariant(&self) -> bool { self.invariant }
+
+    /* A response to a coalesced request has streams named "i:name" for the i-th carriage of
+     * the span. Streams without a prefix are common to all. Every carriage must have every
+     * per-carriage stream.
+     */
+    pub(crate) fn split(self, span: u64) -> Result<Vec<DataRes>,Error> {
+        let mut parts = (0..span).map(|_| HashMap::new()).collect::<Vec<_>>();
+        let mut common = vec![];
+        let mut per_carriage = BTreeSet::new();
+        for (name,data) in self.data {
+            let prefix = name.split_once(':').and_then(|(index,rest)| Some((index.parse::<usize>().ok()?,rest.to_string())));
+            match prefix {
+                Some((index,name)) => {
+                    let part = parts.get_mut(index).ok_or_else(|| Error::operr(&format!("bad index in coalesced data {}",name)))?;
+                    per_carriage.insert(name.clone());
+                    part.insert(name,data);
+                },
+                None => { common.push((name,data)); }
+            }
+        }
+        for (index,part) in parts.iter().enumerate() {
+            if let Some(name) = per_carriage.iter().find(|name| !part.contains_key(*name)) {
+                return Err(Error::operr(&format!("missing data {} for carriage {} of coalesced data",name,index)));
+            }
+        }
+        for (name,data) in common {
+            let data = data.get().map_err(|_| Error::operr(&format!("corrupt data {}",name)))?;
+            for part in parts.iter_mut() {
+                part.insert(name.clone(),LazyReceivedData::new_decoded(data.clone()));
+            }
+        }
+        Ok(parts.drain(..).map(|data| DataRes { data, invariant: self.invariant }).collect())
+    }
+
+    /* For the persistent cache. Streams not yet decoded are stored as they arrived: as the code
+     * and streams of their DataAlgorithm, after any channel-specific decompression.
+     */
+    fn encode_cache(&self) -> Result<Vec<u8>,Error> {
+        let mut out = CacheWriter::new();
+        out.u8(if self.invariant { 1 } else { 0 });
+        out.u32(self.data.len() as u32);
+        for (name,data) in &self.data {
+            let (code,streams) = data.raw().map_err(|_| Error::operr(&format!("cannot store data {}",name)))?;
+            out.string(name);
+            out.string(&code);
+            out.u32(streams.len() as u32);
+            for stream in streams {
+                match stream {
+                    ReceivedData::Bytes(v) => { out.u8(0); out.bytes(&v); },
+                    ReceivedData::Booleans(v) => {
+                        out.u8(1);
+                        out.bytes(&v.iter().map(|x| if *x { 1 } else { 0 }).collect::<Vec<_>>());
+                    },
+                    ReceivedData::Numbers(v) => {
+                        out.u8(2);
+                        out.u32(v.len() as u32);
+                        for x in v.iter() { out.f64(*x); }
+                    },
+                    ReceivedData::Strings(v) => {
+                        out.u8(3);
+                        out.u32(v.len() as u32);
+                        for x in v.iter() { out.string(x); }
+                    },
+                    ReceivedData::Empty => { out.u8(4); }
+                }
+            }
+        }
+        Ok(out.finish())
+    }
+
+    fn decode_cache(bytes: &[u8]) -> Result<DataRes,Error> {
+        let mut reader = CacheReader::new(bytes);
+        let invariant = reader.u8()? != 0;
+        let mut data = HashMap::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let code = reader.string()?;
+            let mut streams = vec![];
+            for _ in 0..reader.u32()? {
+                streams.push(match reader.u8()? {
+                    0 => ReceivedData::new_bytes(reader.bytes()?.to_vec()),
+                    1 => ReceivedData::new_booleans(reader.bytes()?.iter().map(|x| *x != 0).collect()),
+                    2 => ReceivedData::new_numbers((0..reader.u32()?).map(|_| reader.f64()).collect::<Result<_,_>>()?),
+                    3 => ReceivedData::new_strings((0..reader.u32()?).map(|_| reader.string()).collect::<Result<_,_>>()?),
+                    4 => ReceivedData::new_empty(),
+                    _ => { return Err(Error::operr("bad data type in cache entry")); }
+                });
+            }
+            let value = DataAlgorithm::new(&code,&mut streams).ok()
+                .and_then(|algorithm| LazyReceivedData::new_encoded(algorithm).ok())
+                .ok_or_else(|| Error::operr(&format!("bad cached data {}",name)))?;
+            data.insert(name,value);
+        }
+        Ok(DataRes { data, invariant })
+    }
+}
+
+/* In the "values" form, which needs no channel to decode. */
+impl Serialize for DataRes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let values = self.data.keys().map(|name| {
+            Ok((name,self.get2(name).map_err(|e| S::Error::custom(e.to_string()))?))
+        }).collect::<Result<BTreeMap<_,_>,_>>()?;
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("values",&values)?;
+        map.serialize_entry("__invariant",&self.invariant)?;
+        map.end()
+    }
 }
 
 struct DataVisitor(WrappedChannelSender,Arc<dyn Any>);
@@ -68,9 +197,9 @@ impl<'de> Visitor<'de> for DataVisitor {
             }
         }
         let mut data = st_field("data",data)?;
-        let data : HashMap<String,ReceivedData> = data.drain().map(|(k,v)| {
-            Ok((k.clone(),v.to_received_data().map_err(|_e| {
-                de::Error::custom(&format!("wrong type for field: {}",k))
+        let data : HashMap<String,LazyReceivedData> = data.drain().map(|(k,v)| {
+            Ok((k.clone(),LazyReceivedData::new_encoded(v).map_err(|_e| {
+                de::Error::custom(&format!("wrong type or bad header for field: {}",k))
             })?))
         }).collect::<Result<_,_>>()?;
         //debug_log!("data: {:?}",data);
@@ -94,10 +223,10 @@ impl<'de> DeserializeSeed<'de> for DataResDeserialize {
 
 impl MiniResponseVariety for DataRes {
     fn description(&self) -> &str { "data" }
-    fn total_size(&self) -> usize { self.data.values().map(|x| x.len()).sum() }
+    fn total_size(&self) -> usize { self.data.values().map(|x| x.len().unwrap_or(0)).sum() }
 
     fn component_size(&self) -> Vec<(String,usize)> {
-        self.data.iter().map(|(k,v)| (k.to_string(),v.len())).collect::<Vec<_>>()
+        self.data.iter().map(|(k,v)| (k.to_string(),v.len().unwrap_or(0))).collect::<Vec<_>>()
     }
 }
 
@@ -113,8 +242,100 @@ impl DataResponse {
         self.0.account(account_builder);
     }
 
-    pub fn get2(&self, name: &str) -> anyhow::Result<&ReceivedData> { self.0.get2(name) }
+    pub fn get2(&self, name: &str) -> anyhow::Result<ReceivedData> { self.0.get2(name) }
+    pub fn len(&self, name: &str) -> anyhow::Result<usize> { self.0.len(name) }
+    pub fn get_range(&self, name: &str, start: usize, end: usize) -> anyhow::Result<ReceivedData> { self.0.get_range(name,start,end) }
 
     pub(crate) fn is_invariant(&self) -> bool { self.0.is_invariant() }
     pub(crate) fn original_priority(&self) -> PacketPriority { self.1.clone() }
+
+    pub(crate) fn encode_cache(&self) -> Result<Vec<u8>,Error> { self.0.encode_cache() }
+
+    pub(crate) fn decode_cache(bytes: &[u8], priority: PacketPriority) -> Result<DataResponse,Error> {
+        Ok(DataResponse::new(DataRes::decode_cache(bytes)?,priority))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encoded(code: &str, mut streams: Vec<ReceivedData>) -> LazyReceivedData {
+        LazyReceivedData::new_encoded(DataAlgorithm::new(code,&mut streams).unwrap()).unwrap()
+    }
+
+    fn values(res: &DataRes, name: &str) -> String {
+        format!("{:?}",res.get2(name).unwrap())
+    }
+
+    fn coalesced(names: &[&str]) -> DataRes {
+        let data = names.iter().map(|name| {
+            (name.to_string(),ReceivedData::new_bytes(name.as_bytes().to_vec()))
+        }).collect();
+        DataRes::new(data,false)
+    }
+
+    #[test]
+    fn test_split() {
+        let parts = coalesced(&["0:a","1:a","0:b","1:b","c"]).split(2).unwrap();
+        assert_eq!(2,parts.len());
+        for (i,part) in parts.iter().enumerate() {
+            let mut names = part.data.keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(vec!["a","b","c"],names);
+            assert_eq!(format!("{:?}",ReceivedData::new_bytes(format!("{}:a",i).into_bytes())),values(part,"a"));
+            assert_eq!(format!("{:?}",ReceivedData::new_bytes(b"c".to_vec())),values(part,"c"));
+        }
+        assert_eq!(3,coalesced(&["c"]).split(3).unwrap().len());
+    }
+
+    #[test]
+    fn test_split_bad() {
+        /* carriage 1 is missing b */
+        assert!(coalesced(&["0:a","1:a","0:b","c"]).split(2).is_err());
+        /* carriage 2 has nothing */
+        assert!(coalesced(&["0:a","1:a"]).split(3).is_err());
+        assert!(coalesced(&["0:a","2:a"]).split(2).is_err());
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let mut data = HashMap::new();
+        data.insert("names".to_string(),encoded("SZ",vec![ReceivedData::new_bytes(b"a\0bc\0".to_vec())]));
+        data.insert("starts".to_string(),encoded("NDRL",vec![ReceivedData::new_bytes(vec![1,2,3])]));
+        data.insert("runs".to_string(),encoded("NURARA",vec![
+            ReceivedData::new_numbers(vec![4.,-1.]),
+            ReceivedData::new_numbers(vec![2.,1.]),
+            ReceivedData::new_numbers(vec![3.])
+        ]));
+        data.insert("flags".to_string(),LazyReceivedData::new_decoded(ReceivedData::new_booleans(vec![true,false])));
+        data.insert("none".to_string(),LazyReceivedData::new_decoded(ReceivedData::new_empty()));
+        let res = DataRes { data, invariant: true };
+        let bytes = res.encode_cache().unwrap();
+        let again = DataRes::decode_cache(&bytes).unwrap();
+        assert!(again.is_invariant());
+        for name in ["names","starts","runs","flags","none"] {
+            assert_eq!(values(&res,name),values(&again,name));
+        }
+        /* still encoded as received */
+        assert_eq!("SZ",again.stream("names").unwrap().raw().unwrap().0);
+        assert_eq!("NDRL",again.stream("starts").unwrap().raw().unwrap().0);
+        assert!(DataRes::decode_cache(&bytes[..bytes.len()-1]).is_err());
+    }
+
+    #[test]
+    fn test_cache_bad() {
+        let mut data = HashMap::new();
+        data.insert("bytes".to_string(),LazyReceivedData::new_decoded(ReceivedData::new_bytes(vec![1])));
+        assert!(DataRes { data, invariant: false }.encode_cache().is_err());
+        let mut out = CacheWriter::new();
+        out.u8(0);
+        out.u32(1);
+        out.string("x");
+        out.string("BP");
+        out.u32(1);
+        out.u8(0);
+        out.bytes(&[9]);
+        assert!(DataRes::decode_cache(&out.finish()).is_err());
+    }
 }
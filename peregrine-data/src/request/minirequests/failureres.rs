@@ -8,12 +8,18 @@ use crate::request::core::miniresponse::MiniResponseVariety;
 #[derive(serde_derive::Deserialize)]
 #[serde(transparent)]
 pub struct FailureRes {
-    message: String
+    message: String,
+    #[serde(skip)]
+    stale: bool
 }
 
 impl FailureRes {
-    pub fn new(msg: &str) -> FailureRes { FailureRes { message: msg.to_string() } }
+    pub fn new(msg: &str) -> FailureRes { FailureRes { message: msg.to_string(), stale: false } }
+    pub(crate) fn new_stale(msg: &str) -> FailureRes { FailureRes { message: msg.to_string(), stale: true } }
     pub fn message(&self) -> &str { &self.message }
+
+    /* dropped unsent as the viewport moved on: never retry */
+    pub fn is_stale(&self) -> bool { self.stale }
 }
 
 impl MiniResponseVariety for FailureRes {
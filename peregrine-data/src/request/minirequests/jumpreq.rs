@@ -6,7 +6,7 @@ pub struct JumpReq {
 }
 
 impl JumpReq {
-    pub(crate) fn new(location: &str) -> MiniRequest {
+    pub fn new(location: &str) -> MiniRequest {
         MiniRequest::Jump(JumpReq {
             location: location.to_string()
         })
@@ -1,6 +1,6 @@
 use std::fmt;
 use peregrine_toolkit::serdetools::st_field;
-use serde::{Deserializer, Deserialize, de::{Visitor, MapAccess, IgnoredAny}};
+use serde::{Deserializer, Deserialize, Serialize, Serializer, de::{Visitor, MapAccess, IgnoredAny}, ser::SerializeMap};
 use crate::request::core::miniresponse::MiniResponseVariety;
 
 pub struct JumpLocation {
@@ -56,6 +56,23 @@ impl<'de> Deserialize<'de> for JumpRes {
     }
 }
 
+impl Serialize for JumpRes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            JumpRes::Found(location) => {
+                map.serialize_entry("stick",&location.stick)?;
+                map.serialize_entry("left",&location.left)?;
+                map.serialize_entry("right",&location.right)?;
+            },
+            JumpRes::NotFound => {
+                map.serialize_entry("no",&true)?;
+            }
+        }
+        map.end()
+    }
+}
+
 impl MiniResponseVariety for JumpRes {
     fn description(&self) -> &str { "jump" }
 }
@@ -1,6 +1,6 @@
 use std::fmt;
 use peregrine_toolkit::{serdetools::st_field};
-use serde::{Deserialize, Deserializer, de::{MapAccess, Visitor}};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{MapAccess, Visitor}, ser::SerializeMap};
 use crate::{Stick, request::core::miniresponse::MiniResponseVariety};
 
 #[derive(Clone)]
@@ -54,6 +54,26 @@ impl<'de> Deserialize<'de> for StickRes {
     }
 }
 
+impl Serialize for StickRes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            StickRes::Stick(stick) => {
+                let mut tags = stick.tags().iter().collect::<Vec<_>>();
+                tags.sort();
+                map.serialize_entry("id",stick.get_id().get_id())?;
+                map.serialize_entry("size",&stick.size())?;
+                map.serialize_entry("topology",&stick.topology().to_number())?;
+                map.serialize_entry("tags",&tags)?;
+            },
+            StickRes::Unknown(error) => {
+                map.serialize_entry("error",error)?;
+            }
+        }
+        map.end()
+    }
+}
+
 impl MiniResponseVariety for StickRes {
     fn description(&self) -> &str { "stick" }
 }
@@ -37,6 +37,10 @@ impl TrackMapping {
         TrackMapping(Arc::new(builder))
     }
 
+    pub(crate) fn empty() -> TrackMapping {
+        TrackMapping::new(TrackMappingBuilder::new())
+    }
+
     pub(crate) fn apply(&self, switches_data: &SwitchesData) -> (BTreeMap<String,StructValue>,BTreeMap<String,Vec<String>>) {
         let mut values = BTreeMap::new();
         let mut sources = BTreeMap::new();
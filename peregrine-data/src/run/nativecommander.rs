@@ -0,0 +1,45 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::ops::DerefMut;
+use commander::{ Executor, Lock, RunConfig, RunSlot, TaskHandle, cdr_add, cdr_in_agent, cdr_new_agent };
+use crate::run::pgcommander::Commander;
+use crate::util::message::DataMessage;
+
+/* NativeCommander lets native tools (headless rendering, batch export, etc) run the usual
+ * PgCommander tasks under a commander::NativeExecutor rather than in the browser. The executor
+ * thread does the ticking so start() does nothing. As with everything which holds the Executor,
+ * create it on the executor thread, ie inside NativeExecutor::run().
+ */
+
+#[derive(Clone)]
+pub struct NativeCommander {
+    executor: Rc<RefCell<Executor>>
+}
+
+impl NativeCommander {
+    pub fn new(executor: &Rc<RefCell<Executor>>) -> NativeCommander {
+        NativeCommander { executor: executor.clone() }
+    }
+}
+
+impl Commander for NativeCommander {
+    fn start(&self) {}
+
+    fn add_task(&self, name: &str, prio: u8, slot: Option<RunSlot>, timeout: Option<f64>, f: Pin<Box<dyn Future<Output=Result<(),DataMessage>> + 'static>>) -> TaskHandle<Result<(),DataMessage>> {
+        let rc = RunConfig::new(slot,prio,timeout);
+        if cdr_in_agent() {
+            let agent = cdr_new_agent(Some(rc),name);
+            cdr_add(f,agent)
+        } else {
+            let mut exe = self.executor.borrow_mut();
+            let agent = exe.new_agent(&rc,name);
+            exe.add_pin(f,agent)
+        }
+    }
+
+    fn make_lock(&self) -> Lock { self.executor.borrow_mut().make_lock() }
+    fn identity(&self) -> u64 { self.executor.borrow().identity() }
+    fn executor(&self) -> Box<dyn DerefMut<Target=Executor> + '_> { Box::new(self.executor.borrow_mut()) }
+}
@@ -1,4 +1,4 @@
-use std::sync::MutexGuard;
+use std::ops::DerefMut;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::{ Arc, Mutex };
@@ -83,7 +83,7 @@ pub trait Commander {
     fn add_task(&self, name: &str, prio: u8, slot: Option<RunSlot>, timeout: Option<f64>, f: Pin<Box<dyn Future<Output=Result<(),DataMessage>> + 'static>>) -> TaskHandle<Result<(),DataMessage>>;
     fn make_lock(&self) -> Lock;
     fn identity(&self) -> u64;
-    fn executor(&self) -> MutexGuard<Executor>;
+    fn executor(&self) -> Box<dyn DerefMut<Target=Executor> + '_>;
 }
 
 pub struct PgCommanderTaskSpec<T> {
@@ -1,6 +1,6 @@
 use anyhow::{ self };
 use peregrine_toolkit::error::Error;
-use peregrine_toolkit::{lock, log};
+use peregrine_toolkit::{lock, log, warn};
 use std::any::Any;
 use std::collections::{HashMap};
 use std::sync::{ Arc, Mutex };
@@ -11,6 +11,7 @@ use crate::api::MessageSender;
 use crate::core::program::programbundle::SuppliedBundle;
 use peregrine_dauphin_queue::{ PgDauphinQueue, PgDauphinLoadTaskSpec, PgEardoLoadTaskSpec, PgEardoRunTaskSpec };
 use crate::shapeload::programname::{ProgramName};
+use crate::util::persistentcache::PersistentCache;
 use eard_interp::ObjectFile;
 
 pub(crate) struct PgEardoTaskSpec {
@@ -24,9 +25,20 @@ struct PgDauphinData {
     programs_present: HashMap<eard_interp::ProgramName,BackendNamespace>,
     programs: HashMap<ProgramName,Option<ProgramModel>>,
     all_backends: Option<AllBackends>,
+    persistent: Option<PersistentCache>,
     channel_registry: ChannelRegistry
 }
 
+/* In the persistent cache, bundles are kept under their binary name and each program maps to
+ * the bundle containing it, so that a program can be loaded next session without a backend.
+ */
+const PERSISTENT_EARDO : &str = "eardo";
+const PERSISTENT_PROGRAM : &str = "program";
+
+fn persistent_program_key(name: &eard_interp::ProgramName) -> String {
+    format!("{}::{}::{}",name.group,name.name,name.version)
+}
+
 #[derive(Clone)]
 pub struct PgDauphin(Arc<Mutex<PgDauphinData>>,CountingPromise);
 
@@ -37,11 +49,28 @@ impl PgDauphin {
             programs: HashMap::new(),
             programs_present: HashMap::new(),
             all_backends: None,
+            persistent: None,
             channel_registry: channel_registry.clone()
         })),booted.clone()))
     }
 
+    async fn load_persistent_program(&self, program_name: &ProgramName) -> Option<()> {
+        let persistent = lock!(self.0).persistent.clone()?;
+        let location = persistent.get(PERSISTENT_PROGRAM,&persistent_program_key(program_name.to_eard())).await?;
+        let (backend_namespace,bundle_name) : (BackendNamespace,String) = serde_json::from_slice(&location).ok()?;
+        let binary_name = self.binary_name(&backend_namespace,&bundle_name);
+        let data = persistent.get(PERSISTENT_EARDO,&binary_name).await?;
+        if let Err(e) = self.load_eardo(&backend_namespace,&bundle_name,&data).await {
+            warn!("bad cached program bundle {}: {:?}",bundle_name,e);
+            return None;
+        }
+        Some(())
+    }
+
     async fn load_program(&self, program_name: &ProgramName) -> Result<(),Error> {
+        if self.load_persistent_program(program_name).await.is_some() && self.is_present(program_name) {
+            return Ok(());
+        }
         let obj = lock!(self.0);
         let all_backends = obj.all_backends.clone();
         let channel_registry = obj.channel_registry.clone();
@@ -61,22 +90,27 @@ impl PgDauphin {
         lock!(self.0).all_backends = Some(all_backends.clone());
     }
 
+    pub(crate) fn set_persistent(&self, persistent: Option<&PersistentCache>) {
+        lock!(self.0).persistent = persistent.cloned();
+    }
+
     fn binary_name(&self, channel: &BackendNamespace, name_of_bundle: &str) -> String {
         let channel_name = channel.to_string();
         format!("{}-{}-{}",channel_name.len(),channel_name,name_of_bundle)
     }
 
-    fn register_eardo(&self, backend_namespace: &BackendNamespace, data: &[u8]) -> Result<(),Error> {
+    fn register_eardo(&self, backend_namespace: &BackendNamespace, data: &[u8]) -> Result<Vec<eard_interp::ProgramName>,Error> {
         let eardo = ObjectFile::decode(data.to_vec()).map_err(|e| 
              Error::operr(&format!("cannot read file: {}",e))
         )?;
-        for name in eardo.list_programs() {
-            lock!(self.0).programs_present.insert(name,backend_namespace.clone());
+        let names = eardo.list_programs();
+        for name in &names {
+            lock!(self.0).programs_present.insert(name.clone(),backend_namespace.clone());
         }
-        Ok(())
+        Ok(names)
     }
 
-    async fn add_eardo(&self, backend_namespace: &BackendNamespace, name: &str, data: &[u8]) -> Result<(),Error> {
+    async fn load_eardo(&self, backend_namespace: &BackendNamespace, name: &str, data: &[u8]) -> Result<Vec<eard_interp::ProgramName>,Error> {
         let obj = lock!(self.0);
         let pdq = obj.pdq.clone();
         pdq.load_eardo(PgEardoLoadTaskSpec {
@@ -84,7 +118,19 @@ impl PgDauphin {
             data: data.to_vec()
         }).await?;
         drop(obj);
-        self.register_eardo(backend_namespace,data)?;
+        self.register_eardo(backend_namespace,data)
+    }
+
+    async fn add_eardo(&self, backend_namespace: &BackendNamespace, name: &str, data: &[u8]) -> Result<(),Error> {
+        let programs = self.load_eardo(backend_namespace,name,data).await?;
+        let persistent = lock!(self.0).persistent.clone();
+        if let Some(persistent) = persistent {
+            persistent.put(PERSISTENT_EARDO,&self.binary_name(backend_namespace,name),data,None).await;
+            let location = serde_json::to_vec(&(backend_namespace,name)).map_err(|e| Error::operr(&e.to_string()))?;
+            for program in &programs {
+                persistent.put(PERSISTENT_PROGRAM,&persistent_program_key(program),&location,None).await;
+            }
+        }
         Ok(())
     }
 
@@ -5,6 +5,13 @@ use crate::{hotspots::{hotspots::{SpecialClick, HotspotResultVariety}}, SpaceBas
 #[derive(Clone,Debug,PartialEq,Eq,Hash)]
 pub struct DirectColour(pub u8,pub u8,pub u8,pub u8);
 
+/* A single colour, either fixed or named in the palette and resolved when drawn (see palette.rs) */
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub enum PlainColour {
+    Direct(DirectColour),
+    Palette(String)
+}
+
 #[cfg_attr(debug_assertions,derive(Debug))]
 #[derive(Clone)]
 pub enum AttachmentPoint {
@@ -70,13 +77,13 @@ impl PenGeometry {
 #[derive(Clone)]
 pub struct Pen {
     geometry: Arc<PenGeometry>,
-    colours: EachOrEvery<DirectColour>,
-    background: EachOrEvery<DirectColour>,
+    colours: EachOrEvery<PlainColour>,
+    background: EachOrEvery<PlainColour>,
     attachment: AttachmentPoint
 }
 
 impl Pen {
-    pub fn new(name: &str, size: u32, colours: &EachOrEvery<DirectColour>, background: &EachOrEvery<DirectColour>, attachment: &AttachmentPoint) -> Pen {
+    pub fn new(name: &str, size: u32, colours: &EachOrEvery<PlainColour>, background: &EachOrEvery<PlainColour>, attachment: &AttachmentPoint) -> Pen {
         Pen {
             geometry: Arc::new(PenGeometry::new(name,size)),
             colours: colours.clone(),
@@ -86,8 +93,8 @@ impl Pen {
     }
 
     pub fn geometry(&self) -> &PenGeometry { &self.geometry }
-    pub fn colours(&self) -> &EachOrEvery<DirectColour> { &self.colours }
-    pub fn background(&self) -> &EachOrEvery<DirectColour> { &self.background }
+    pub fn colours(&self) -> &EachOrEvery<PlainColour> { &self.colours }
+    pub fn background(&self) -> &EachOrEvery<PlainColour> { &self.background }
     pub fn attachment(&self) -> &AttachmentPoint { &self.attachment }
 
     pub fn filter(&self, filter: &EachOrEveryFilter) -> Pen {
@@ -102,14 +109,37 @@ impl Pen {
 
 #[derive(Clone)]
 #[cfg_attr(debug_assertions,derive(Debug))]
-pub struct Plotter(pub f64, pub DirectColour);
+pub struct Plotter(pub f64, pub PlainColour, pub BlendMode);
 
 #[derive(Clone)]
 #[cfg_attr(debug_assertions,derive(Debug))]
 pub enum Colour {
     Direct(DirectColour),
-    Stripe(DirectColour,DirectColour,(u32,u32),f64),
-    Bar(DirectColour,DirectColour,(u32,u32),f64)
+    Stripe(PlainColour,PlainColour,(u32,u32),f64),
+    Bar(PlainColour,PlainColour,(u32,u32),f64),
+    Palette(String) /* resolved when drawn, see palette.rs */
+}
+
+/* How a drawn shape combines with what's beneath it. Normal is ordinary alpha blending (opaque
+ * unless the colour has alpha), multiply darkens and screen lightens, so that overlaid tracks stay
+ * readable where they overlap.
+ */
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        match name {
+            "normal" => Some(BlendMode::Normal),
+            "multiply" => Some(BlendMode::Multiply),
+            "screen" => Some(BlendMode::Screen),
+            _ => None
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -217,7 +247,7 @@ impl HotspotPatina {
 #[derive(Clone)]
 #[cfg_attr(debug_assertions,derive(Debug))]
 pub enum Patina {
-    Drawn(DrawnType,EachOrEvery<Colour>),
+    Drawn(DrawnType,EachOrEvery<Colour>,BlendMode),
     Hotspot(HotspotPatina,bool),
     Metadata(String,EachOrEvery<(String,StructValue)>)
 }
@@ -225,7 +255,7 @@ pub enum Patina {
 impl Patina {
     pub fn filter(&self, filter: &EachOrEveryFilter) -> Patina {
         match self {
-            Patina::Drawn(drawn_type,colours) => Patina::Drawn(drawn_type.clone(),colours.filter(filter)),
+            Patina::Drawn(drawn_type,colours,blend) => Patina::Drawn(drawn_type.clone(),colours.filter(filter),*blend),
             Patina::Hotspot(hotspot,hover) => Patina::Hotspot(hotspot.filter(filter),*hover),
             Patina::Metadata(key,values) => Patina::Metadata(key.clone(),values.filter(filter))
         }
@@ -233,7 +263,7 @@ impl Patina {
 
     pub fn compatible(&self, len: usize) -> bool {
         match self {
-            Patina::Drawn(_,x) => x.compatible(len),
+            Patina::Drawn(_,x,_) => x.compatible(len),
             Patina::Hotspot(hotspot,_) => { hotspot.compatible(len) },
             Patina::Metadata(_,values) => { values.compatible(len) }
         }
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use super::core::{ Colour, DirectColour, PlainColour };
+
+/* A palette maps colour names to colours, with one set of colours per theme. Style programs can
+ * paint with a name (Colour::Palette, or PlainColour::Palette in pens, stripes and bars) instead of
+ * a fixed colour and the name is only looked up when the shape is drawn, so a theme change means a redraw of the shapes already held, not a reload.
+ *
+ * A name missing from the current theme falls back to its colour in the light theme and a name
+ * unknown there too falls back to "foreground", so an unexpected name is visible rather than lost.
+ *
+ * The deuteranopia-safe theme is built from the Okabe-Ito colours: "positive" and "negative" are
+ * blue and vermillion rather than green and red and also differ in lightness.
+ */
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Theme {
+    Light,
+    Dark,
+    HighContrast,
+    Deuteranopia
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            "high-contrast" => Some(Theme::HighContrast),
+            "deuteranopia" => Some(Theme::Deuteranopia),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high-contrast",
+            Theme::Deuteranopia => "deuteranopia"
+        }
+    }
+}
+
+const THEMES : [Theme;4] = [Theme::Light,Theme::Dark,Theme::HighContrast,Theme::Deuteranopia];
+
+const FALLBACK : &str = "foreground";
+
+/* colours for each of THEMES in order */
+type ThemeColours = [(u8,u8,u8);4];
+
+const BUILTIN : &[(&str,ThemeColours)] = &[
+    ("foreground", [(0,0,0),       (230,230,230), (0,0,0),       (0,0,0)]),
+    ("background", [(255,255,255), (32,32,32),    (255,255,255), (255,255,255)]),
+    ("muted",      [(128,128,128), (150,150,150), (64,64,64),    (128,128,128)]),
+    ("faint",      [(216,216,216), (72,72,72),    (160,160,160), (216,216,216)]),
+    ("accent",     [(43,101,174),  (100,160,230), (0,0,200),     (204,121,167)]),
+    ("highlight",  [(255,200,0),   (255,210,60),  (255,255,0),   (240,228,66)]),
+    ("positive",   [(46,160,67),   (90,200,110),  (0,120,0),     (86,180,233)]),
+    ("negative",   [(200,40,40),   (240,90,90),   (200,0,0),     (213,94,0)])
+];
+
+#[derive(Clone)]
+pub struct Palette {
+    theme: Theme,
+    colours: HashMap<Theme,HashMap<String,DirectColour>>
+}
+
+impl Palette {
+    pub fn new() -> Palette {
+        let mut out = Palette { theme: Theme::Light, colours: HashMap::new() };
+        for (name,colours) in BUILTIN {
+            for (theme,(r,g,b)) in THEMES.iter().zip(colours.iter()) {
+                out.set_colour(theme,name,&DirectColour(*r,*g,*b,255));
+            }
+        }
+        out
+    }
+
+    pub fn theme(&self) -> &Theme { &self.theme }
+    pub fn set_theme(&mut self, theme: &Theme) { self.theme = *theme; }
+
+    pub fn set_colour(&mut self, theme: &Theme, name: &str, colour: &DirectColour) {
+        self.colours.entry(*theme).or_default().insert(name.to_string(),colour.clone());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DirectColour> {
+        self.colours.get(&self.theme).and_then(|x| x.get(name))
+            .or_else(|| self.colours.get(&Theme::Light).and_then(|x| x.get(name)))
+    }
+
+    pub fn lookup(&self, name: &str) -> DirectColour {
+        self.get(name).or_else(|| self.get(FALLBACK)).cloned().unwrap_or(DirectColour(0,0,0,255))
+    }
+
+    pub fn plain(&self, colour: &PlainColour) -> DirectColour {
+        match colour {
+            PlainColour::Direct(c) => c.clone(),
+            PlainColour::Palette(name) => self.lookup(name)
+        }
+    }
+
+    fn resolve_plain(&self, colour: &PlainColour) -> PlainColour {
+        PlainColour::Direct(self.plain(colour))
+    }
+
+    pub fn resolve(&self, colour: &Colour) -> Colour {
+        match colour {
+            Colour::Direct(c) => Colour::Direct(c.clone()),
+            Colour::Stripe(a,b,size,prop) => Colour::Stripe(self.resolve_plain(a),self.resolve_plain(b),*size,*prop),
+            Colour::Bar(a,b,size,prop) => Colour::Bar(self.resolve_plain(a),self.resolve_plain(b),*size,*prop),
+            Colour::Palette(name) => Colour::Direct(self.lookup(name))
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette { Palette::new() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn named(name: &str) -> PlainColour { PlainColour::Palette(name.to_string()) }
+
+    #[test]
+    fn test_theme_colour() {
+        let mut palette = Palette::default();
+        assert_eq!(Theme::Light,*palette.theme());
+        assert_eq!(DirectColour(200,40,40,255),palette.lookup("negative"));
+        palette.set_theme(&Theme::Deuteranopia);
+        assert_eq!(DirectColour(213,94,0,255),palette.lookup("negative"));
+        palette.set_colour(&Theme::Deuteranopia,"negative",&DirectColour(1,2,3,4));
+        assert_eq!(DirectColour(1,2,3,4),palette.lookup("negative"));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let mut palette = Palette::new();
+        palette.set_colour(&Theme::Light,"exon",&DirectColour(10,20,30,255));
+        palette.set_theme(&Theme::Dark);
+        /* missing from dark: the light colour */
+        assert_eq!(Some(&DirectColour(10,20,30,255)),palette.get("exon"));
+        assert_eq!(DirectColour(10,20,30,255),palette.lookup("exon"));
+        /* unknown anywhere: the theme's foreground */
+        assert_eq!(None,palette.get("intron"));
+        assert_eq!(DirectColour(230,230,230,255),palette.lookup("intron"));
+        palette.set_theme(&Theme::Light);
+        assert_eq!(DirectColour(0,0,0,255),palette.lookup("intron"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut palette = Palette::new();
+        palette.set_theme(&Theme::Dark);
+        let fgd = DirectColour(230,230,230,255);
+        let red = DirectColour(255,0,0,255);
+        assert_eq!(fgd,palette.plain(&named("foreground")));
+        assert_eq!(red,palette.plain(&PlainColour::Direct(red.clone())));
+        assert!(matches!(palette.resolve(&Colour::Palette("foreground".to_string())),Colour::Direct(c) if c == fgd));
+        let stripe = Colour::Stripe(PlainColour::Direct(red.clone()),named("foreground"),(2,3),0.5);
+        match palette.resolve(&stripe) {
+            Colour::Stripe(PlainColour::Direct(a),PlainColour::Direct(b),(2,3),p) => {
+                assert_eq!((red.clone(),fgd.clone(),0.5),(a,b,p));
+            },
+            _ => panic!("stripe not resolved")
+        }
+        let bar = Colour::Bar(named("background"),named("foreground"),(4,4),0.25);
+        match palette.resolve(&bar) {
+            Colour::Bar(PlainColour::Direct(a),PlainColour::Direct(b),(4,4),p) => {
+                assert_eq!((DirectColour(32,32,32,255),fgd,0.25),(a,b,p));
+            },
+            _ => panic!("bar not resolved")
+        }
+    }
+}
@@ -86,7 +86,7 @@ impl<A> Clone for RectangleShape<A> where A: Clone {
 impl RectangleShape<AuxLeaf> {
     pub fn demerge<T: Hash + Clone + Eq,D>(self, cat: &D) -> Vec<(T,RectangleShape<AuxLeaf>)> where D: ShapeDemerge<X=T> {
         let demerge = match &self.patina {
-            Patina::Drawn(drawn_type,colours) => {
+            Patina::Drawn(drawn_type,colours,_) => {
                 let allotments_and_colours = self.area.top_left().allotments().zip(&colours,|x,y| (x.clone(),y.clone()));
                 allotments_and_colours.demerge(self.area.len(),|(a,c)| 
                     cat.categorise_with_colour(&a.coord_system,a.depth,drawn_type,c)
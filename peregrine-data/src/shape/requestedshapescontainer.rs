@@ -41,6 +41,8 @@ impl RequestedShapesContainer {
         }
     }
 
+    pub fn shapes(&self) -> &[Shape<LeafRequest>] { &self.shapes }
+
     pub fn filter(&self, min_value: f64, max_value: f64) -> RequestedShapesContainer {
         RequestedShapesContainer {
             shapes: self.shapes.iter().map(|shape| shape.base_filter(min_value,max_value)).collect(),
@@ -1,7 +1,7 @@
 use std::{sync::{Arc, Mutex}};
 use commander::{CommanderStream, cdr_timer};
 use peregrine_toolkit::{log_extra, error::Error, debug_log};
-use crate::{DataMessage, ShapeStore, PeregrineCoreBase, PgCommanderTaskSpec, Scale, add_task, core::{Layout, pixelsize::PixelSize}, shapeload::loadshapes::LoadMode, switch::trackconfiglist::TrainTrackConfigList, CarriageExtent, train::model::trainextent::TrainExtent, PeregrineApiQueue };
+use crate::{DataMessage, ShapeStore, PeregrineCoreBase, PgCommanderTaskSpec, Scale, add_task, core::{Layout, pixelsize::PixelSize}, shapeload::loadshapes::LoadMode, switch::trackconfiglist::TrainTrackConfigList, CarriageExtent, train::model::trainextent::TrainExtent, PeregrineApiQueue, RequestManager };
 use crate::shapeload::carriagebuilder::CarriageBuilder;
 
 const LIGHTWEIGHT_SCHEDULE : &[(i64,i64,bool)] = &[
@@ -88,6 +88,7 @@ fn run_anticipator(base: &PeregrineCoreBase, result_store: &ShapeStore, stream:
 
 pub struct Anticipate {
     api_queue: PeregrineApiQueue,
+    manager: RequestManager,
     extent: Arc<Mutex<Option<CarriageExtent>>>,
     stream: CommanderStream<AnticipateTask>
 }
@@ -98,6 +99,7 @@ impl Anticipate {
         run_anticipator(&base,&result_store,&stream);
         Anticipate {
             api_queue: base.queue.clone(),
+            manager: base.manager.clone(),
             extent: Arc::new(Mutex::new(None)),
             stream
         }
@@ -148,6 +150,7 @@ impl Anticipate {
             if extent == old_extent { return Ok(()); }
         }
         self.stream.clear();
+        self.manager.viewport_moved();
         if self.enabled() {
             let schedule = if lightweight() {
                 LIGHTWEIGHT_SCHEDULE
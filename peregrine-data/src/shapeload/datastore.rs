@@ -1,15 +1,37 @@
 use commander::cdr_current_time;
 use peregrine_toolkit::error::Error;
-use peregrine_toolkit::{lock};
+use peregrine_toolkit::{lock, warn};
+use std::collections::HashMap;
 use std::sync::{ Arc, Mutex };
 use crate::PacketPriority;
 use crate::api::{ PeregrineCoreBase };
 use crate::request::minirequests::datareq::DataRequest;
 use crate::request::minirequests::datares::{DataResponse};
 use crate::util::lrucache::Cache;
-use crate::util::memoized::{ Memoized, MemoizedType };
-
-// TODO Memoized errors with retry semantics
+use crate::util::memoized::{ Memoized, MemoizedFailures, MemoizedType };
+use crate::util::persistentcache::DATA_TTL;
+
+/* Backoff has already retried each request a few times so we only retry once more here. Failed
+ * regions are then remembered for a few seconds to stop scrolling hammering a failing backend.
+ */
+const DATA_FAILURES : MemoizedFailures = MemoizedFailures {
+    attempts: 2,
+    retry_delay: 2000.,
+    max_retry_delay: 2000.,
+    transient_ttl: 5000.,
+    permanent_ttl: Some(60000.)
+};
+
+/* Prefetch and speculative failures are mostly requests dropped as the viewport moved on. They
+ * say nothing about the region, so they are never retried or remembered.
+ */
+const ANTICIPATE_FAILURES : MemoizedFailures = MemoizedFailures {
+    attempts: 1,
+    retry_delay: 0.,
+    max_retry_delay: 0.,
+    transient_ttl: 0.,
+    permanent_ttl: Some(0.)
+};
 
 #[cfg(debug_data_requests)]
 fn debug_data_requests(request: &DataRequest){
@@ -22,15 +44,34 @@ fn debug_data_requests(request: &DataRequest){
 #[allow(unused)]
 fn debug_data_requests(request: &DataRequest) {}
 
+const PERSISTENT_NAMESPACE : &str = "data";
+
 async fn run(base: PeregrineCoreBase, request: DataRequest, priority: PacketPriority) -> Result<DataResponse,Error> {
     debug_data_requests(&request);
+    /* try the persistent cache before going to the backend */
+    let persistent_key = serde_json::to_string(&request).ok();
+    if let (Some(persistent),Some(key)) = (&base.persistent,&persistent_key) {
+        if let Some(bytes) = persistent.get(PERSISTENT_NAMESPACE,key).await {
+            match DataResponse::decode_cache(&bytes,priority.clone()) {
+                Ok(response) => { return Ok(response); },
+                Err(e) => { warn!("bad cached data for {}: {:?}",request.name(),e); }
+            }
+        }
+    }
     let backend = base.all_backends.backend(request.channel())?;
-    backend.data(&request,&priority).await
+    let response = backend.data(&request,&priority).await?;
+    if let (Some(persistent),Some(key)) = (&base.persistent,&persistent_key) {
+        if let Ok(bytes) = response.encode_cache() {
+            persistent.put(PERSISTENT_NAMESPACE,key,&bytes,Some(DATA_TTL)).await;
+        }
+    }
+    Ok(response)
 }
 
 fn make_data_cache(cache_size: usize, base: &PeregrineCoreBase, prio: PacketPriority) -> Memoized<DataRequest,Result<DataResponse,Error>> {
     let base = base.clone();
-    Memoized::new(MemoizedType::Cache(cache_size),move |_,k: &DataRequest|{
+    let failures = if prio.expires() { &ANTICIPATE_FAILURES } else { &DATA_FAILURES };
+    Memoized::new_with_failures(MemoizedType::Cache(cache_size),failures,move |_,k: &DataRequest|{
         let base = base.clone();
         let prio = prio.clone();
         let k = k.clone();
@@ -38,6 +79,22 @@ fn make_data_cache(cache_size: usize, base: &PeregrineCoreBase, prio: PacketPrio
     })
 }
 
+/* Fixed responses, for running programs natively without any backends. Missing requests fail. */
+fn make_fixed_cache(responses: &Arc<HashMap<DataRequest,DataResponse>>) -> Memoized<DataRequest,Result<DataResponse,Error>> {
+    let responses = responses.clone();
+    Memoized::new(MemoizedType::None,move |_,k: &DataRequest|{
+        let response = responses.get(k).cloned().ok_or_else(|| {
+            Error::operr(&format!("no fixed response for {}",k.name()))
+        });
+        Box::pin(async move { response })
+    })
+}
+
+const ALL_PRIORITIES : [PacketPriority;PacketPriority::COUNT] = [
+    PacketPriority::RealTime, PacketPriority::Visible, PacketPriority::Batch,
+    PacketPriority::Prefetch, PacketPriority::Speculative
+];
+
 const STAT_WINDOW_SIZE : usize = 20;
 
 #[cfg(debug_canvasstore)]
@@ -74,8 +131,8 @@ impl Stats {
 #[derive(Clone)]
 pub struct DataStore {
     invariant_cache: Arc<Mutex<Cache<DataRequest,Result<DataResponse,Error>>>>,
-    cache: Memoized<DataRequest,Result<DataResponse,Error>>,
-    batch_cache: Memoized<DataRequest,Result<DataResponse,Error>>,
+    /* one per PacketPriority, by index: the RealTime cache is the main one */
+    caches: Vec<Memoized<DataRequest,Result<DataResponse,Error>>>,
     stats: Arc<Mutex<Stats>>
 }
 
@@ -83,8 +140,16 @@ impl DataStore {
     pub fn new(cache_size: usize, base: &PeregrineCoreBase) -> DataStore {
         DataStore { 
             invariant_cache: Arc::new(Mutex::new(Cache::new(cache_size))),
-            cache: make_data_cache(cache_size,base,PacketPriority::RealTime),
-            batch_cache: make_data_cache(cache_size,base,PacketPriority::Batch),
+            caches: ALL_PRIORITIES.iter().map(|prio| make_data_cache(cache_size,base,prio.clone())).collect(),
+            stats: Arc::new(Mutex::new(Stats::new()))
+        }
+    }
+
+    pub fn new_fixed(responses: HashMap<DataRequest,DataResponse>) -> DataStore {
+        let responses = Arc::new(responses);
+        DataStore {
+            invariant_cache: Arc::new(Mutex::new(Cache::new(1))),
+            caches: ALL_PRIORITIES.iter().map(|_| make_fixed_cache(&responses)).collect(),
             stats: Arc::new(Mutex::new(Stats::new()))
         }
     }
@@ -97,20 +162,19 @@ impl DataStore {
             return Ok((response,took_ms));
         }
         /* needs full lookup or new request */
-        let response = match priority {
-            PacketPriority::RealTime => {
-                self.cache.get(&request).await.as_ref().clone()
-            },
-            PacketPriority::Batch => {
-                if let Some(value) = self.cache.try_underway(&request).await {
-                    /* Already in main cache, don't pull again */
-                    value.as_ref().clone()
-                } else {
-                    let data = self.batch_cache.get(&request).await;
-                    self.cache.warm(&request,data.as_ref().clone());
-                    data.as_ref().clone()
-                }
+        let main_cache = &self.caches[PacketPriority::RealTime.index()];
+        let response = if let PacketPriority::RealTime = priority {
+            main_cache.get(&request).await.as_ref().clone()
+        } else if let Some(value) = main_cache.try_underway(&request).await {
+            /* Already in main cache, don't pull again */
+            value.as_ref().clone()
+        } else {
+            let data = self.caches[priority.index()].get(&request).await.as_ref().clone();
+            /* failures stay with their own tier: a stale speculative request says nothing about real-time */
+            if data.is_ok() {
+                main_cache.warm(&request,data.clone());
             }
+            data
         };
         if let Ok(response) = &response{
             if response.is_invariant() {
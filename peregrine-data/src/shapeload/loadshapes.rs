@@ -1,11 +1,15 @@
 use peregrine_toolkit::error::Error;
-use crate::{ ShapeStore, PeregrineCoreBase, PgCommanderTaskSpec, add_task, api::MessageSender,  shape::{RequestedShapesContainer}, allotment::core::floatingcarriage::FloatingCarriage, CarriageExtent };
+use crate::{ ShapeStore, PeregrineCoreBase, PacketPriority, PgCommanderTaskSpec, add_task, api::MessageSender,  shape::{RequestedShapesContainer}, allotment::core::floatingcarriage::FloatingCarriage, CarriageExtent };
 
 use super::shaperequestgroup::ShapeRequestGroup;
 
+/* RealTime is the carriage under the viewport and Visible its neighbours in the train. Batch
+ * and Network anticipate moves: Batch builds the shapes, Network only warms the caches.
+ */
 #[derive(Clone)]
 pub enum LoadMode {
     RealTime,
+    Visible,
     Batch,
     Network
 }
@@ -20,10 +24,19 @@ impl LoadMode {
 
     pub fn high_priority(&self) -> bool {
         match self {
-            LoadMode::RealTime => true,
+            LoadMode::RealTime | LoadMode::Visible => true,
             _ => false
         }
     }
+
+    pub fn priority(&self) -> PacketPriority {
+        match self {
+            LoadMode::RealTime => PacketPriority::RealTime,
+            LoadMode::Visible => PacketPriority::Visible,
+            LoadMode::Batch => PacketPriority::Prefetch,
+            LoadMode::Network => PacketPriority::Speculative
+        }
+    }
 }
 
 pub(crate) async fn load_carriage_shape_list(base: &PeregrineCoreBase, result_store: &ShapeStore, messages: Option<&MessageSender>, shape_requests: ShapeRequestGroup, extent: Option<&CarriageExtent>, mode: &LoadMode) -> Result<FloatingCarriage,Vec<Error>> {
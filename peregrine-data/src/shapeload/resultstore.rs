@@ -100,6 +100,7 @@ fn make_filtered_cache(kind: MemoizedType, unfiltered_shapes_cache: Memoized<Sha
 #[derive(Clone)]
 pub struct ShapeStore {
     realtime: Memoized<ShapeRequest,Result<Arc<RequestedShapesContainer>,Error>>,
+    visible: Memoized<ShapeRequest,Result<Arc<RequestedShapesContainer>,Error>>,
     batch: Memoized<ShapeRequest,Result<Arc<RequestedShapesContainer>,Error>>,
     network: Memoized<ShapeRequest,Result<Arc<RequestedShapesContainer>,Error>>,
     stats: Arc<Mutex<OriginStats>>
@@ -110,12 +111,15 @@ impl ShapeStore {
         // XXX both caches separate sizes
         let unfiltered_cache = make_unfiltered_cache(MemoizedType::Cache(cache_size),base,LoadMode::RealTime,false);
         let filtered_cache = make_filtered_cache(MemoizedType::Cache(cache_size),unfiltered_cache);
+        let visible_unfiltered_cache = make_unfiltered_cache(MemoizedType::Cache(cache_size),base,LoadMode::Visible,false);
+        let visible_filtered_cache = make_filtered_cache(MemoizedType::None,visible_unfiltered_cache);
         let batch_unfiltered_cache = make_unfiltered_cache(MemoizedType::Cache(cache_size),base,LoadMode::Batch,false);
         let batch_filtered_cache = make_filtered_cache(MemoizedType::None,batch_unfiltered_cache);
         let network_unfiltered_cache = make_unfiltered_cache(MemoizedType::Cache(cache_size),base,LoadMode::Network,true);
         let network_filtered_cache = make_filtered_cache(MemoizedType::None,network_unfiltered_cache);
         ShapeStore {
             realtime: filtered_cache,
+            visible: visible_filtered_cache,
             batch: batch_filtered_cache,
             network: network_filtered_cache,
             stats: Arc::new(Mutex::new(OriginStats::empty()))
@@ -128,6 +132,16 @@ impl ShapeStore {
             LoadMode::RealTime => {
                 self.realtime.get(lane).await
             },
+            /* really get the shapes, they're on screen but not where the user is looking */
+            LoadMode::Visible => {
+                if let Some(value) = self.realtime.try_get(lane) {
+                    value
+                } else {
+                    let value = self.visible.get(lane).await;
+                    self.realtime.warm(lane,value.as_ref().clone());
+                    value
+                }
+            },
             /* really get the shapes, we may need them in the future */
             LoadMode::Batch => {
                 if let Some(value) = self.realtime.try_get(lane) {
@@ -149,7 +163,7 @@ impl ShapeStore {
                 }
             }
         };
-        if let LoadMode::RealTime = mode {
+        if mode.high_priority() {
             /* genuine request, so include in stats */
             if let Ok(shapes) = shapes.as_ref() {
                 let mut stats = lock!(self.stats);
@@ -36,9 +36,9 @@ impl Track {
         })
     }
 
-    pub(crate) fn program(&self) -> &ProgramModel { &self.program }
+    pub fn program(&self) -> &ProgramModel { &self.program }
     pub(crate) fn mapping(&self) -> &TrackMapping { &self.mapping }
-    pub(crate) fn track_base(&self) -> &BackendNamespace { &self.track_base }
+    pub fn track_base(&self) -> &BackendNamespace { &self.track_base }
     pub fn id(&self) -> u64 { self.id }
     pub fn scale(&self) -> (u64,u64) { (self.min_scale,self.max_scale) }
     pub fn max_scale_jump(&self) -> u64 { self.scale_jump }
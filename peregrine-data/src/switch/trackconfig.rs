@@ -3,6 +3,9 @@ use std::fmt;
 use std::sync::{ Arc };
 use std::collections::HashMap;
 use eachorevery::eoestruct::StructValue;
+use peregrine_toolkit::error::Error;
+use crate::{BackendNamespace, ProgramModel};
+use crate::request::tracks::trackmodel::TrackMapping;
 
 use super::{track::Track};
 
@@ -37,6 +40,16 @@ impl TrackConfig {
         }
     }
 
+    /* A track with settings given directly rather than via switches, for running its program
+     * natively (eg in tests). It's available at all scales. As in the browser, settings not given
+     * take the program's defaults.
+     */
+    pub fn new_fixed(program: &ProgramModel, track_base: &BackendNamespace, mut settings: BTreeMap<String,StructValue>) -> Result<TrackConfig,Error> {
+        let track = Track::new(program,track_base,&TrackMapping::empty(),0,100,1,"")?;
+        program.apply_defaults(&mut settings);
+        Ok(TrackConfig::new(&track,settings,&BTreeMap::new()))
+    }
+
     pub fn track(&self) -> &Track { &self.track }
     pub fn value(&self, name: &str) -> Option<&StructValue> { self.values.get(name) }
     pub fn underlying_switch(&self, setting: &str) -> Option<&Vec<String>> { self.underlying_switch.get(setting) }
@@ -67,3 +80,26 @@ impl PartialEq for TrackConfig {
 }
 
 impl Eq for TrackConfig {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ProgramName, ProgramSetting};
+
+    #[test]
+    fn test_fixed_defaults() {
+        let name = ProgramName::new("test","program",1);
+        let program = ProgramModel::new_fixed(&name,&[
+            ProgramSetting::new("given",StructValue::new_boolean(false)),
+            ProgramSetting::new("missing",StructValue::new_boolean(true))
+        ]);
+        let mut settings = BTreeMap::new();
+        settings.insert("given".to_string(),StructValue::new_boolean(true));
+        settings.insert("extra".to_string(),StructValue::new_null());
+        let config = TrackConfig::new_fixed(&program,&BackendNamespace::new("test","base"),settings).unwrap();
+        assert!(config.value("given") == Some(&StructValue::new_boolean(true)));
+        assert!(config.value("missing") == Some(&StructValue::new_boolean(true)));
+        assert!(config.value("extra") == Some(&StructValue::new_null()));
+        assert_eq!(&name,config.track().program().name());
+    }
+}
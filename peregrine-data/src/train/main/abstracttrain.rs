@@ -1,6 +1,6 @@
 use std::{sync::{Arc, Mutex}, cmp::max};
 use peregrine_toolkit::{puzzle::AnswerAllocator };
-use crate::{shapeload::carriagebuilder::CarriageBuilder, allotment::core::{floatingcarriage::FloatingCarriage}, switch::trackconfiglist::TrainTrackConfigList, api::MessageSender, CarriageExtent, train::{model::trainextent::TrainExtent, graphics::Graphics, core::party::{PartyActions, Party, PartyState}}, PeregrineApiQueue, globals::trainstate::{TrainStateSpec, TrainState}};
+use crate::{shapeload::{carriagebuilder::CarriageBuilder, loadshapes::LoadMode}, allotment::core::{floatingcarriage::FloatingCarriage}, switch::trackconfiglist::TrainTrackConfigList, api::MessageSender, CarriageExtent, train::{model::trainextent::TrainExtent, graphics::Graphics, core::party::{PartyActions, Party, PartyState}}, PeregrineApiQueue, globals::trainstate::{TrainStateSpec, TrainState}};
 
 #[cfg(debug_trains)]
 use peregrine_toolkit::{ log, debug_log };
@@ -41,6 +41,7 @@ pub(crate) struct AbstractTrainActions {
     ready: bool,
     mute: bool,
     active: bool,
+    centre: Option<u64>,
     carriage_factory: FloatingCarriageFactory,
     train_state_spec: TrainStateSpec,
     graphics: Graphics
@@ -55,6 +56,7 @@ impl AbstractTrainActions {
             ready: false,
             mute: false,
             active: false,
+            centre: None,
             carriage_factory,
             graphics: graphics.clone(),
             train_state_spec: TrainStateSpec::new(answer_allocator)
@@ -83,13 +85,16 @@ impl AbstractTrainActions {
     }
 
     pub(crate) fn state(&self) -> TrainState { self.train_state_spec.spec() }
+    pub(crate) fn set_centre(&mut self, centre: u64) { self.centre = Some(centre); }
 }
 
 impl PartyActions<u64,CarriageBuilder,FloatingCarriage> for AbstractTrainActions {
     fn ctor(&mut self, index: &u64) -> CarriageBuilder {
         let new_carriage = self.carriage_factory.new_unloaded_carriage(*index);
         #[cfg(debug_trains)] log!("CP ctor ({})",new_carriage.extent().compact());
-        self.data_api.load_carriage(&new_carriage);
+        /* the carriage under the viewport is most urgent, its flanks are only on screen */
+        let mode = if self.centre == Some(*index) { LoadMode::RealTime } else { LoadMode::Visible };
+        self.data_api.load_carriage(&new_carriage,mode);
         new_carriage
     }
 
@@ -160,6 +165,7 @@ impl AbstractTrain {
     pub(crate) fn update_centre(&mut self, centre: u64) {
         let start = max((centre as i64)-(self.flank as i64),0) as u64;
         let wanted = start..(start+self.flank*2+1);    
+        self.party.inner_mut().set_centre(centre);
         self.party.set(wanted);
     }
 }
@@ -7,21 +7,22 @@ use peregrine_toolkit::log;
 
 use super::train::StickData;
 
-async fn load_one_carriage(base: &mut PeregrineCoreBase, shape_store: &ShapeStore, mut carriage: CarriageBuilder) -> Result<(),Error> {
-    carriage.load(base,&shape_store,LoadMode::RealTime).await
+async fn load_one_carriage(base: &mut PeregrineCoreBase, shape_store: &ShapeStore, mut carriage: CarriageBuilder, mode: LoadMode) -> Result<(),Error> {
+    carriage.load(base,&shape_store,mode).await
 }
 
-pub(crate) fn load_carriage(base: &mut PeregrineCoreBase, shape_store: &ShapeStore, builder: &CarriageBuilder) {
+pub(crate) fn load_carriage(base: &mut PeregrineCoreBase, shape_store: &ShapeStore, builder: &CarriageBuilder, mode: &LoadMode) {
     let mut base2 = base.clone();
     let shape_store = shape_store.clone();
     let builder = builder.clone();
+    let mode = mode.clone();
     let handle = add_task(&base.commander,PgCommanderTaskSpec {
         name: format!("carriage loader"),
         prio: 1,
         slot: None,
         timeout: None,
         task: Box::pin(async move {
-            let result = load_one_carriage(&mut base2,&shape_store,builder.clone()).await;
+            let result = load_one_carriage(&mut base2,&shape_store,builder.clone(),mode).await;
             if let Err(e) = result {
                 base2.messages.send(e.clone());
             }
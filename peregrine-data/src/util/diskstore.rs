@@ -0,0 +1,130 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+use peregrine_toolkit::error::Error;
+use crate::util::persistentcache::{ PersistentFuture, PersistentStore };
+
+/* A PersistentStore for native use which keeps each entry as a file in a directory. Keys
+ * become filenames with '/' replaced by '~' (our keys never contain '~'). Files are listed
+ * oldest-written first. Writes go to a temporary file which is then renamed so that a crash
+ * never leaves a half-written entry under a real key (though the integrity check in
+ * PersistentCache would catch it anyway).
+ */
+
+const TMP_SUFFIX : &str = ".tmp";
+
+/* A PersistentStore keeping entries as files in a directory. */
+pub struct DiskStore {
+    dir: PathBuf,
+    budget: usize
+}
+
+fn io_err(path: &Path, e: std::io::Error) -> Error {
+    Error::operr(&format!("{}: {}",path.display(),e))
+}
+
+impl DiskStore {
+    /* Use (creating if needed) the given directory, storing at most budget bytes. */
+    pub fn new(dir: &Path, budget: usize) -> Result<DiskStore,Error> {
+        fs::create_dir_all(dir).map_err(|e| io_err(dir,e))?;
+        Ok(DiskStore { dir: dir.to_path_buf(), budget })
+    }
+
+    fn path(&self, key: &str) -> PathBuf { self.dir.join(key.replace('/',"~")) }
+
+    fn do_get(&self, key: &str) -> Result<Option<Vec<u8>>,Error> {
+        let path = self.path(key);
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_err(&path,e))
+        }
+    }
+
+    fn do_put(&self, key: &str, value: &[u8]) -> Result<(),Error> {
+        let path = self.path(key);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(TMP_SUFFIX);
+        fs::write(&tmp,value).map_err(|e| io_err(&path,e))?;
+        fs::rename(&tmp,&path).map_err(|e| io_err(&path,e))
+    }
+
+    fn do_remove(&self, key: &str) -> Result<(),Error> {
+        let path = self.path(key);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_err(&path,e)),
+            _ => Ok(())
+        }
+    }
+
+    fn do_list(&self) -> Result<Vec<(String,usize)>,Error> {
+        let mut out = vec![];
+        for entry in fs::read_dir(&self.dir).map_err(|e| io_err(&self.dir,e))? {
+            let entry = entry.map_err(|e| io_err(&self.dir,e))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.ends_with(TMP_SUFFIX) { continue; }
+            let metadata = entry.metadata().map_err(|e| io_err(&entry.path(),e))?;
+            if !metadata.is_file() { continue; }
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            out.push((modified,name.replace('~',"/"),metadata.len() as usize));
+        }
+        out.sort();
+        Ok(out.drain(..).map(|(_,key,size)| (key,size)).collect())
+    }
+}
+
+impl PersistentStore for DiskStore {
+    fn get(&self, key: &str) -> PersistentFuture<Option<Vec<u8>>> {
+        let out = self.do_get(key);
+        Box::pin(async move { out })
+    }
+
+    fn put(&self, key: &str, value: Vec<u8>) -> PersistentFuture<()> {
+        let out = self.do_put(key,&value);
+        Box::pin(async move { out })
+    }
+
+    fn remove(&self, key: &str) -> PersistentFuture<()> {
+        let out = self.do_remove(key);
+        Box::pin(async move { out })
+    }
+
+    fn list(&self) -> PersistentFuture<Vec<(String,usize)>> {
+        let out = self.do_list();
+        Box::pin(async move { out })
+    }
+
+    fn budget(&self) -> usize { self.budget }
+
+    fn now(&self) -> f64 {
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs_f64()*1000.).unwrap_or(0.)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_disk_store() {
+        let dir = std::env::temp_dir().join(format!("peregrine-diskstore-{}",std::process::id()));
+        let store = DiskStore::new(&dir,1000).unwrap();
+        assert_eq!(None,block_on(store.get("v1-1/ns/a")).unwrap());
+        block_on(store.put("v1-1/ns/a",vec![1,2,3])).unwrap();
+        block_on(store.put("v1-1/ns/b",vec![4])).unwrap();
+        block_on(store.put("v1-1/ns/a",vec![5,6])).unwrap();
+        assert_eq!(Some(vec![5,6]),block_on(store.get("v1-1/ns/a")).unwrap());
+        fs::write(dir.join("stray.tmp"),b"x").unwrap();
+        let mut keys = block_on(store.list()).unwrap();
+        keys.sort();
+        assert_eq!(vec![("v1-1/ns/a".to_string(),2),("v1-1/ns/b".to_string(),1)],keys);
+        block_on(store.remove("v1-1/ns/a")).unwrap();
+        block_on(store.remove("v1-1/ns/a")).unwrap();
+        assert_eq!(None,block_on(store.get("v1-1/ns/a")).unwrap());
+        assert_eq!(1,block_on(store.list()).unwrap().len());
+        assert!(store.now() > 0.);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
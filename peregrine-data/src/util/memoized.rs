@@ -3,10 +3,57 @@ use std::future::Future;
 use std::sync::{ Arc, Mutex };
 use std::hash::Hash;
 use std::pin::Pin;
-use commander::{ FusePromise, PromiseFuture };
+use commander::{ FusePromise, PromiseFuture, cdr_current_time, cdr_in_agent, cdr_timer };
 use super::lrucache::Cache;
+use peregrine_toolkit::error::Error;
 use peregrine_toolkit::lock;
 
+/* By default a Memoized remembers whatever its resolver returns, failure or not. A failure policy
+ * changes that for failures. Transient failures (see Error::is_transient) are retried a few times
+ * with exponential backoff and then remembered for a short while, so that a later request tries
+ * again. Permanent failures are remembered for their own ttl (or, if None, like any other value).
+ * Times are in ms. Remembered failures are kept apart from values so that they expire. Outside
+ * an agent there's no clock (or timer), so failures aren't retried and those with a ttl aren't
+ * remembered at all.
+ */
+
+#[derive(Clone)]
+pub struct MemoizedFailures {
+    pub attempts: u32,
+    pub retry_delay: f64,
+    pub max_retry_delay: f64,
+    pub transient_ttl: f64,
+    pub permanent_ttl: Option<f64>
+}
+
+/* For lookups such as jumps and sticks: a missing answer is missing for good, but a failure to ask
+ * is worth another go later.
+ */
+pub const LOOKUP_FAILURES : MemoizedFailures = MemoizedFailures {
+    attempts: 3,
+    retry_delay: 1000.,
+    max_retry_delay: 4000.,
+    transient_ttl: 2000.,
+    permanent_ttl: None
+};
+
+impl MemoizedFailures {
+    fn retry_delay(&self, attempt: u32) -> f64 {
+        (self.retry_delay * 2_f64.powi(attempt as i32)).min(self.max_retry_delay)
+    }
+
+    fn ttl(&self, transient: bool) -> Option<f64> {
+        if transient { Some(self.transient_ttl) } else { self.permanent_ttl }
+    }
+}
+
+/* Some(transient) if the value is a failure */
+type FailureClassifier<V> = Arc<dyn Fn(&V) -> Option<bool>>;
+
+fn now() -> Option<f64> {
+    if cdr_in_agent() { Some(cdr_current_time()) } else { None }
+}
+
 pub enum MemoizedType {
     Store,
     Cache(usize),
@@ -47,6 +94,7 @@ impl<K,V> MemoizedStore<K,V> where K: Clone+Eq+Hash {
 
 struct MemoizedState<K: Clone+Eq+Hash,V> {
     known: MemoizedStore<K,V>,
+    failed: HashMap<K,(Arc<V>,f64)>,
     pending: HashMap<K,FusePromise<Arc<V>>>
 }
 
@@ -54,23 +102,48 @@ impl<K: Clone+Eq+Hash,V> MemoizedState<K,V> {
     fn new(kind: MemoizedType) -> MemoizedState<K,V> {
         MemoizedState {
             known: MemoizedStore::new(kind),
+            failed: HashMap::new(),
             pending: HashMap::new()
         }
     }
 
     pub fn insert(&mut self, key: &K, value: Arc<V>) {
+        self.failed.remove(key);
         self.known.insert(key.clone(),value)
     }
 
+    /* A failure with a ttl is not pending any more: the next request after expiry runs afresh */
+    fn insert_failure(&mut self, key: &K, value: Arc<V>, ttl: Option<f64>) {
+        match ttl {
+            None => { self.known.insert(key.clone(),value); },
+            Some(ttl) => {
+                self.pending.remove(key);
+                if let (true,Some(now)) = (ttl > 0.,now()) {
+                    self.failed.insert(key.clone(),(value,now+ttl));
+                }
+            }
+        }
+    }
+
+    fn get_failed(&mut self, key: &K) -> Option<Arc<V>> {
+        let expiry = self.failed.get(key)?.1;
+        if now().map(|now| expiry > now).unwrap_or(false) {
+            self.failed.get(key).map(|(value,_)| value.clone())
+        } else {
+            self.failed.remove(key);
+            None
+        }
+    }
+
     fn try_get(&mut self, key: &K) -> Option<Arc<V>> {
-        self.known.get(key).cloned()
+        self.known.get(key).cloned().or_else(|| self.get_failed(key))
     }
 
     fn get_promise(&mut self, key: &K) -> (PromiseFuture<Arc<V>>,Option<FusePromise<Arc<V>>>) {
         let p = PromiseFuture::new();
-        let fuse = if let Some(value) = self.known.get(key) {
+        let fuse = if let Some(value) = self.try_get(key) {
             /* already known: satisfy immediately; don't run future */
-            p.satisfy(value.clone());
+            p.satisfy(value);
             None
         } else if let Some(fuse) = self.pending.get_mut(key) {
             /* already pending: add to list; don't run future */
@@ -90,9 +163,9 @@ impl<K: Clone+Eq+Hash,V> MemoizedState<K,V> {
 impl<K: Clone+Eq+Hash,V: 'static> MemoizedState<K,V> {
     fn try_underway(&mut self, key: &K) -> PromiseFuture<Option<Arc<V>>> {
         let p = PromiseFuture::new();
-        if let Some(value) = self.known.get(key) {
+        if let Some(value) = self.try_get(key) {
             /* already known: satisfy immediately; don't run future */
-            p.satisfy(Some(value.clone()));
+            p.satisfy(Some(value));
         } else if let Some(fuse) = self.pending.get_mut(key) {
             /* already pending: add to list; don't run future */
             let fuse2 = FusePromise::new();
@@ -109,6 +182,7 @@ impl<K: Clone+Eq+Hash,V: 'static> MemoizedState<K,V> {
 #[derive(Clone)]
 pub struct Memoized<K: Clone+Hash+Eq,V> {
     resolver: Arc<Box<dyn (Fn(&Memoized<K,V>,&K) -> Pin<Box<dyn Future<Output=V>>>) + 'static>>,
+    failures: Option<(MemoizedFailures,FailureClassifier<V>)>,
     state: Arc<Mutex<MemoizedState<K,V>>>
 }
 
@@ -116,10 +190,26 @@ impl<K: Clone+Hash+Eq,V> Memoized<K,V> {
     pub fn new<F>(kind: MemoizedType, cb: F) -> Memoized<K,V> where F: Fn(&Memoized<K,V>,&K) -> Pin<Box<dyn Future<Output=V>>> + 'static {
         Memoized {
             state: Arc::new(Mutex::new(MemoizedState::new(kind))),
+            failures: None,
             resolver: Arc::new(Box::new(cb))
         }
     }
 
+    async fn resolve(&self, key: &K) -> (Arc<V>,Option<bool>) {
+        let mut attempt = 0;
+        loop {
+            let value = Arc::new((self.resolver)(self,key).await);
+            let failure = self.failures.as_ref().and_then(|(_,classify)| classify(&value));
+            match (&self.failures,failure) {
+                (Some((policy,_)),Some(true)) if attempt+1 < policy.attempts && cdr_in_agent() => {
+                    cdr_timer(policy.retry_delay(attempt)).await;
+                    attempt += 1;
+                },
+                _ => { return (value,failure); }
+            }
+        }
+    }
+
     pub fn warm(&self, key: &K, value: V) {
         lock!(self.state).insert(key,Arc::new(value));
     }
@@ -129,9 +219,13 @@ impl<K: Clone+Hash+Eq,V> Memoized<K,V> {
         let (promise,fuse) = state.get_promise(key);
         drop(state);
         if let Some(fuse) = fuse {
-            let twin = self.clone();
-            let value = Arc::new((self.resolver)(twin,key).await);
-            lock!(self.state).insert(key,value.clone());
+            let (value,failure) = self.resolve(key).await;
+            let mut state = lock!(self.state);
+            match (&self.failures,failure) {
+                (Some((policy,_)),Some(transient)) => { state.insert_failure(key,value.clone(),policy.ttl(transient)); },
+                _ => { state.insert(key,value.clone()); }
+            }
+            drop(state);
             fuse.fuse(value);
         }
         promise.await
@@ -142,9 +236,112 @@ impl<K: Clone+Hash+Eq,V> Memoized<K,V> {
     }
 }
 
+impl<K: Clone+Hash+Eq,T: 'static> Memoized<K,Result<T,Error>> {
+    pub fn new_with_failures<F>(kind: MemoizedType, failures: &MemoizedFailures, cb: F) -> Memoized<K,Result<T,Error>>
+            where F: Fn(&Memoized<K,Result<T,Error>>,&K) -> Pin<Box<dyn Future<Output=Result<T,Error>>>> + 'static {
+        let mut out = Memoized::new(kind,cb);
+        out.failures = Some((failures.clone(),Arc::new(|value: &Result<T,Error>| {
+            value.as_ref().err().map(|e| e.is_transient())
+        })));
+        out
+    }
+}
+
 impl<K: Clone+Hash+Eq,V: 'static> Memoized<K,V> {
     pub async fn try_underway(&self, key: &K) -> Option<Arc<V>> {
         let p = lock!(self.state).try_underway(key);
         p.await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use commander::{ RunConfig, Simulation };
+    use futures::executor::block_on;
+
+    const FAILURES : MemoizedFailures = MemoizedFailures {
+        attempts: 3,
+        retry_delay: 100.,
+        max_retry_delay: 150.,
+        transient_ttl: 1000.,
+        permanent_ttl: Some(5000.)
+    };
+
+    /* Answers with each result in turn, recording when it was asked */
+    fn make_memoized(results: Vec<Result<u32,Error>>) -> (Memoized<u32,Result<u32,Error>>,Arc<Mutex<Vec<f64>>>) {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let calls2 = calls.clone();
+        let results = Arc::new(Mutex::new(results));
+        let memoized = Memoized::new_with_failures(MemoizedType::Store,&FAILURES,move |_,_| {
+            lock!(calls2).push(now().unwrap_or(-1.));
+            let result = lock!(results).remove(0);
+            Box::pin(async move { result })
+        });
+        (memoized,calls)
+    }
+
+    fn value(result: Arc<Result<u32,Error>>) -> Option<u32> { result.as_ref().as_ref().ok().cloned() }
+
+    /* Runs test inside an agent, in virtual time */
+    fn simulate<F>(test: F) where F: FnOnce() -> Pin<Box<dyn Future<Output=()>>> {
+        let mut simulation = Simulation::new(0);
+        let agent = simulation.executor().new_agent(&RunConfig::new(None,3,None),"test");
+        let done = Arc::new(Mutex::new(false));
+        let done2 = done.clone();
+        let future = test();
+        simulation.executor().add(async move { future.await; *lock!(done2) = true; },agent);
+        assert!(simulation.run(1000));
+        assert!(*lock!(done));
+    }
+
+    #[test]
+    fn test_transient() {
+        let (memoized,calls) = make_memoized(vec![
+            Err(Error::tmp("a")),Err(Error::tmp("b")),Err(Error::tmp("c")),
+            Err(Error::tmp("d")),Ok(4)
+        ]);
+        simulate(move || Box::pin(async move {
+            /* retried with backoff, then remembered for a while */
+            assert_eq!(None,value(memoized.get(&1).await));
+            let times = lock!(calls).clone();
+            assert_eq!(3,times.len());
+            assert!(times[1]-times[0] >= 100. && times[1]-times[0] < 150.);
+            assert!(times[2]-times[1] >= 150. && times[2]-times[1] < 200.);
+            cdr_timer(900.).await;
+            assert!(memoized.try_get(&1).is_some());
+            cdr_timer(200.).await;
+            assert!(memoized.try_get(&1).is_none());
+            assert_eq!(Some(4),value(memoized.get(&1).await));
+            assert_eq!(5,lock!(calls).len());
+            assert_eq!(Some(4),value(memoized.get(&1).await));
+            assert_eq!(5,lock!(calls).len());
+        }));
+    }
+
+    #[test]
+    fn test_permanent() {
+        let (memoized,calls) = make_memoized(vec![Err(Error::operr("a")),Ok(2)]);
+        simulate(move || Box::pin(async move {
+            /* not retried, but remembered for longer */
+            assert_eq!(None,value(memoized.get(&1).await));
+            assert_eq!(1,lock!(calls).len());
+            cdr_timer(4000.).await;
+            assert_eq!(None,value(memoized.get(&1).await));
+            assert_eq!(1,lock!(calls).len());
+            cdr_timer(1100.).await;
+            assert_eq!(Some(2),value(memoized.get(&1).await));
+            assert_eq!(2,lock!(calls).len());
+        }));
+    }
+
+    #[test]
+    fn test_outside_agent() {
+        let (memoized,calls) = make_memoized(vec![Err(Error::tmp("a")),Err(Error::operr("b")),Ok(3)]);
+        assert_eq!(None,value(block_on(memoized.get(&1))));
+        assert_eq!(None,value(block_on(memoized.get(&1))));
+        assert_eq!(Some(3),value(block_on(memoized.get(&1))));
+        assert_eq!(Some(3),value(block_on(memoized.get(&1))));
+        assert_eq!(vec![-1.,-1.,-1.],*lock!(calls));
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex };
+use peregrine_toolkit::error::Error;
+use peregrine_toolkit::{ lock, warn };
+use crate::core::version::VersionMetadata;
+
+/* A persistent tier beneath the in-memory caches so that a repeat visit doesn't refetch
+ * everything. The integration supplies a PersistentStore (disk natively, IndexedDB in the
+ * browser) which only needs to keep bytes under string keys. PersistentCache adds everything
+ * else:
+ *
+ * versioning: keys are prefixed with a version made from the cache format and the backend
+ *   version in VersionMetadata, so upgrading the backend protocol invalidates the lot. Entries
+ *   from other versions are left alone (another tab may still be using them) and are simply the
+ *   first to be evicted.
+ * expiry: data from the backend changes with each release, which the protocol version doesn't
+ *   capture, so entries can be given a time to live when stored. Expired entries are removed and
+ *   treated as a miss. Programs are named by version and don't need one.
+ * integrity: each entry is stored with its full key and a checksum of its value. Anything
+ *   truncated, corrupt, or (via a hash collision) for a different key is removed and treated
+ *   as a miss.
+ * eviction: the total size is kept within the store's budget by removing the least recently
+ *   used entries. Recency is tracked in-session: entries from previous sessions are oldest.
+ *
+ * The cache is only ever an optimisation: errors from the store are logged and treated as
+ * misses, never passed on.
+ */
+
+const FORMAT_VERSION : u32 = 1;
+const MAGIC : &[u8] = b"PGC1";
+
+/* A future returned by a PersistentStore. */
+pub type PersistentFuture<T> = Pin<Box<dyn Future<Output=Result<T,Error>>>>;
+
+/* Data from the backend is refetched after a day. */
+pub(crate) const DATA_TTL : f64 = 24.*60.*60.*1000.;
+
+/* Somewhere to keep bytes between sessions, eg on disk or in IndexedDB. */
+pub trait PersistentStore {
+    /* Value under key, if any. */
+    fn get(&self, key: &str) -> PersistentFuture<Option<Vec<u8>>>;
+    /* Store value under key, replacing any existing value. */
+    fn put(&self, key: &str, value: Vec<u8>) -> PersistentFuture<()>;
+    /* Remove any value under key. */
+    fn remove(&self, key: &str) -> PersistentFuture<()>;
+    /* All keys present, with the size of their values, least recently used first if known. */
+    fn list(&self) -> PersistentFuture<Vec<(String,usize)>>;
+    /* Total bytes of values which may be stored. */
+    fn budget(&self) -> usize;
+    /* Milliseconds since the epoch, by a clock which carries on between sessions. */
+    fn now(&self) -> f64;
+}
+
+/* FNV-1a: stable across builds and sessions, unlike DefaultHasher */
+fn fnv(data: &[u8]) -> u64 {
+    let mut out : u64 = 0xcbf29ce484222325;
+    for byte in data {
+        out ^= *byte as u64;
+        out = out.wrapping_mul(0x100000001b3);
+    }
+    out
+}
+
+/* Simple binary encoding for cache entries */
+pub(crate) struct CacheWriter(Vec<u8>);
+
+impl CacheWriter {
+    pub(crate) fn new() -> CacheWriter { CacheWriter(vec![]) }
+    pub(crate) fn u8(&mut self, value: u8) { self.0.push(value); }
+    pub(crate) fn u32(&mut self, value: u32) { self.0.extend_from_slice(&value.to_le_bytes()); }
+    pub(crate) fn u64(&mut self, value: u64) { self.0.extend_from_slice(&value.to_le_bytes()); }
+    pub(crate) fn f64(&mut self, value: f64) { self.0.extend_from_slice(&value.to_le_bytes()); }
+
+    pub(crate) fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value);
+    }
+
+    pub(crate) fn string(&mut self, value: &str) { self.bytes(value.as_bytes()); }
+    pub(crate) fn finish(self) -> Vec<u8> { self.0 }
+}
+
+pub(crate) struct CacheReader<'t>(&'t [u8]);
+
+impl<'t> CacheReader<'t> {
+    pub(crate) fn new(data: &'t [u8]) -> CacheReader<'t> { CacheReader(data) }
+
+    fn take(&mut self, len: usize) -> Result<&'t [u8],Error> {
+        if self.0.len() < len {
+            return Err(Error::operr("truncated cache entry"));
+        }
+        let (out,rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(out)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8,Error> { Ok(self.take(1)?[0]) }
+
+    pub(crate) fn u32(&mut self) -> Result<u32,Error> {
+        let mut out = [0;4];
+        out.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(out))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64,Error> {
+        let mut out = [0;8];
+        out.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(out))
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64,Error> { Ok(f64::from_bits(self.u64()?)) }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'t [u8],Error> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String,Error> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| Error::operr("bad string in cache entry"))
+    }
+
+    pub(crate) fn rest(self) -> &'t [u8] { self.0 }
+}
+
+fn wrap(key: &str, expiry: f64, value: &[u8]) -> Vec<u8> {
+    let mut out = CacheWriter::new();
+    for byte in MAGIC { out.u8(*byte); }
+    out.string(key);
+    out.f64(expiry);
+    out.u64(fnv(value));
+    let mut out = out.finish();
+    out.extend_from_slice(value);
+    out
+}
+
+/* Value, if intact and for this key, and its expiry time */
+fn unwrap(key: &str, data: &[u8]) -> Result<(Vec<u8>,f64),Error> {
+    let mut reader = CacheReader::new(data);
+    for byte in MAGIC {
+        if reader.u8()? != *byte {
+            return Err(Error::operr("bad cache entry header"));
+        }
+    }
+    if reader.string()? != key {
+        return Err(Error::operr("cache entry for wrong key"));
+    }
+    let expiry = reader.f64()?;
+    let checksum = reader.u64()?;
+    let value = reader.rest();
+    if fnv(value) != checksum {
+        return Err(Error::operr("cache entry checksum mismatch"));
+    }
+    Ok((value.to_vec(),expiry))
+}
+
+struct PersistentIndex {
+    sizes: HashMap<String,(usize,u64)>,
+    total: usize,
+    next_use: u64
+}
+
+impl PersistentIndex {
+    fn new() -> PersistentIndex {
+        PersistentIndex { sizes: HashMap::new(), total: 0, next_use: 0 }
+    }
+
+    fn touch(&mut self, key: &str) {
+        self.next_use += 1;
+        let next_use = self.next_use;
+        if let Some((_,used)) = self.sizes.get_mut(key) {
+            *used = next_use;
+        }
+    }
+
+    fn set(&mut self, key: &str, size: usize) {
+        self.remove(key);
+        self.next_use += 1;
+        self.sizes.insert(key.to_string(),(size,self.next_use));
+        self.total += size;
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((size,_)) = self.sizes.remove(key) {
+            self.total -= size;
+        }
+    }
+
+    fn victims(&mut self, budget: usize) -> Vec<String> {
+        let mut by_age = self.sizes.iter().map(|(k,(_,used))| (*used,k.clone())).collect::<Vec<_>>();
+        by_age.sort();
+        let mut out = vec![];
+        for (_,key) in by_age {
+            if self.total <= budget { break; }
+            self.remove(&key);
+            out.push(key);
+        }
+        out
+    }
+}
+
+/* A versioned, checked, size-limited cache over a PersistentStore. */
+#[derive(Clone)]
+pub struct PersistentCache {
+    store: Arc<dyn PersistentStore>,
+    prefix: String,
+    index: Arc<Mutex<Option<PersistentIndex>>>
+}
+
+impl PersistentCache {
+    pub fn new(store: Arc<dyn PersistentStore>, version: &VersionMetadata) -> PersistentCache {
+        Self::new_prefixed(store,&format!("v{}-{}/",FORMAT_VERSION,version.backend_version()))
+    }
+
+    fn new_prefixed(store: Arc<dyn PersistentStore>, prefix: &str) -> PersistentCache {
+        PersistentCache {
+            store,
+            prefix: prefix.to_string(),
+            index: Arc::new(Mutex::new(None))
+        }
+    }
+
+    fn store_key(&self, namespace: &str, key: &str) -> String {
+        format!("{}{}/{:016x}",self.prefix,namespace,fnv(key.as_bytes()))
+    }
+
+    fn report<T>(&self, result: Result<T,Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("persistent cache: {:?}",e);
+                None
+            }
+        }
+    }
+
+    /* Build the index on first use. Entries from other versions count towards the budget and
+     * are indexed first so that they are the first to go.
+     */
+    async fn ensure_index(&self) {
+        if lock!(self.index).is_some() { return; }
+        let mut index = PersistentIndex::new();
+        let entries = self.report(self.store.list().await).unwrap_or_else(|| vec![]);
+        let (ours,others) : (Vec<_>,Vec<_>) = entries.iter().partition(|(key,_)| key.starts_with(&self.prefix));
+        for (key,size) in others.iter().chain(ours.iter()) {
+            index.set(key,*size);
+        }
+        let mut ours = lock!(self.index);
+        if ours.is_none() {
+            *ours = Some(index);
+        }
+    }
+
+    async fn remove_key(&self, store_key: &str) {
+        if let Some(index) = lock!(self.index).as_mut() {
+            index.remove(store_key);
+        }
+        self.report(self.store.remove(store_key).await);
+    }
+
+    /* Value for key in namespace, if present and intact. */
+    pub async fn get(&self, namespace: &str, key: &str) -> Option<Vec<u8>> {
+        self.ensure_index().await;
+        let store_key = self.store_key(namespace,key);
+        let data = self.report(self.store.get(&store_key).await)??;
+        match unwrap(key,&data) {
+            Ok((_,expiry)) if expiry < self.store.now() => {
+                self.remove_key(&store_key).await;
+                None
+            },
+            Ok((value,_)) => {
+                if let Some(index) = lock!(self.index).as_mut() {
+                    index.touch(&store_key);
+                }
+                Some(value)
+            },
+            Err(e) => {
+                warn!("persistent cache: discarding {}: {:?}",store_key,e);
+                self.remove_key(&store_key).await;
+                None
+            }
+        }
+    }
+
+    /* Store value for key in namespace, evicting old entries if over budget. The entry expires
+     * after ttl milliseconds, if given.
+     */
+    pub async fn put(&self, namespace: &str, key: &str, value: &[u8], ttl: Option<f64>) {
+        self.ensure_index().await;
+        let store_key = self.store_key(namespace,key);
+        let expiry = ttl.map(|ttl| self.store.now()+ttl).unwrap_or(f64::INFINITY);
+        let data = wrap(key,expiry,value);
+        let budget = self.store.budget();
+        if data.len() > budget { return; }
+        let victims = {
+            let mut index = lock!(self.index);
+            let index = index.as_mut().unwrap();
+            index.set(&store_key,data.len());
+            index.victims(budget)
+        };
+        for victim in victims {
+            self.report(self.store.remove(&victim).await);
+        }
+        self.report(self.store.put(&store_key,data).await);
+    }
+
+    /* Bytes currently stored, including by other versions of the cache. */
+    pub fn size(&self) -> usize {
+        lock!(self.index).as_ref().map(|index| index.total).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor::block_on;
+
+    /* Entries in order of writing */
+    struct MemoryStore {
+        entries: Mutex<Vec<(String,Vec<u8>)>>,
+        budget: usize,
+        now: Mutex<f64>
+    }
+
+    impl MemoryStore {
+        fn new(budget: usize) -> Arc<MemoryStore> {
+            Arc::new(MemoryStore { entries: Mutex::new(vec![]), budget, now: Mutex::new(0.) })
+        }
+
+        fn keys(&self) -> Vec<String> {
+            lock!(self.entries).iter().map(|(k,_)| k.clone()).collect()
+        }
+    }
+
+    impl PersistentStore for MemoryStore {
+        fn get(&self, key: &str) -> PersistentFuture<Option<Vec<u8>>> {
+            let out = lock!(self.entries).iter().find(|(k,_)| k == key).map(|(_,v)| v.clone());
+            Box::pin(async move { Ok(out) })
+        }
+
+        fn put(&self, key: &str, value: Vec<u8>) -> PersistentFuture<()> {
+            let mut entries = lock!(self.entries);
+            entries.retain(|(k,_)| k != key);
+            entries.push((key.to_string(),value));
+            Box::pin(async move { Ok(()) })
+        }
+
+        fn remove(&self, key: &str) -> PersistentFuture<()> {
+            lock!(self.entries).retain(|(k,_)| k != key);
+            Box::pin(async move { Ok(()) })
+        }
+
+        fn list(&self) -> PersistentFuture<Vec<(String,usize)>> {
+            let out = lock!(self.entries).iter().map(|(k,v)| (k.clone(),v.len())).collect();
+            Box::pin(async move { Ok(out) })
+        }
+
+        fn budget(&self) -> usize { self.budget }
+        fn now(&self) -> f64 { *lock!(self.now) }
+    }
+
+    #[test]
+    fn test_wrap() {
+        let data = wrap("key",12.,b"value");
+        assert_eq!((b"value".to_vec(),12.),unwrap("key",&data).unwrap());
+        assert!(unwrap("other",&data).is_err());
+        assert!(unwrap("key",&data[..data.len()-1]).is_err());
+        let mut bad = data.clone();
+        *bad.last_mut().unwrap() ^= 1;
+        assert!(unwrap("key",&bad).is_err());
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert!(unwrap("key",&bad).is_err());
+        assert!(unwrap("key",&[]).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_removed() {
+        let store = MemoryStore::new(1000);
+        let cache = PersistentCache::new_prefixed(store.clone(),"v1-1/");
+        block_on(cache.put("ns","a",b"hello",None));
+        let store_key = cache.store_key("ns","a");
+        for (_,value) in lock!(store.entries).iter_mut() { *value.last_mut().unwrap() ^= 1; }
+        assert_eq!(None,block_on(cache.get("ns","a")));
+        assert!(!store.keys().contains(&store_key));
+        assert_eq!(0,cache.size());
+    }
+
+    #[test]
+    fn test_eviction() {
+        let entry = wrap("a",f64::INFINITY,&[0;10]).len();
+        let store = MemoryStore::new(entry*2);
+        let cache = PersistentCache::new_prefixed(store.clone(),"v1-1/");
+        block_on(cache.put("ns","a",&[0;10],None));
+        block_on(cache.put("ns","b",&[1;10],None));
+        assert_eq!(entry*2,cache.size());
+        /* a is now more recently used than b */
+        assert!(block_on(cache.get("ns","a")).is_some());
+        block_on(cache.put("ns","c",&[2;10],None));
+        assert_eq!(Some(vec![0;10]),block_on(cache.get("ns","a")));
+        assert_eq!(None,block_on(cache.get("ns","b")));
+        assert_eq!(Some(vec![2;10]),block_on(cache.get("ns","c")));
+        assert_eq!(entry*2,cache.size());
+        assert_eq!(2,store.keys().len());
+        /* never stored if bigger than the whole budget */
+        block_on(cache.put("ns","d",&[3;100],None));
+        assert_eq!(None,block_on(cache.get("ns","d")));
+        assert_eq!(2,store.keys().len());
+    }
+
+    #[test]
+    fn test_versions() {
+        let entry = wrap("a",f64::INFINITY,&[0;10]).len();
+        let store = MemoryStore::new(entry*2);
+        let old = PersistentCache::new_prefixed(store.clone(),"v1-1/");
+        block_on(old.put("ns","a",&[0;10],None));
+        let new = PersistentCache::new_prefixed(store.clone(),"v1-2/");
+        assert_eq!(None,block_on(new.get("ns","a")));
+        /* other versions are left alone until space is needed */
+        assert_eq!(Some(vec![0;10]),block_on(old.get("ns","a")));
+        block_on(new.put("ns","b",&[1;10],None));
+        assert_eq!(2,store.keys().len());
+        block_on(new.put("ns","c",&[2;10],None));
+        assert_eq!(vec![new.store_key("ns","b"),new.store_key("ns","c")],store.keys());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let store = MemoryStore::new(1000);
+        let cache = PersistentCache::new_prefixed(store.clone(),"v1-1/");
+        block_on(cache.put("ns","a",b"a",Some(1000.)));
+        block_on(cache.put("ns","b",b"b",None));
+        *lock!(store.now) = 999.;
+        assert_eq!(Some(b"a".to_vec()),block_on(cache.get("ns","a")));
+        *lock!(store.now) = 1001.;
+        assert_eq!(None,block_on(cache.get("ns","a")));
+        assert_eq!(vec![cache.store_key("ns","b")],store.keys());
+        assert_eq!(Some(b"b".to_vec()),block_on(cache.get("ns","b")));
+    }
+}
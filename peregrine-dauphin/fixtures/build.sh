@@ -0,0 +1,7 @@
+#! /bin/bash
+
+# Compiles the harness test fixtures. Rerun (and commit the .eardo) after changing a .eard file.
+
+cd "$(dirname "$0")"
+
+eard-compiler -c draw.eard -o draw.eardo
@@ -0,0 +1,226 @@
+use std::any::Any;
+use std::collections::{ HashMap, HashSet };
+use std::sync::{ Arc, Mutex };
+use commander::{ Executor, RunConfig, TaskResult, TestIntegration };
+use eard_interp::{ Interpreter, LibcoreBuilder, ProgramName, RunContext, prepare_libcore };
+use eard_libeoe::{ LibEoEBuilder, prepare_libeoe };
+use eard_libperegrine::{ LibPeregrineBuilder, prepare_libperegrine };
+use peregrine_data::{
+    AccessorResolver, Assets, DataRequest, DataResponse, DataStore, LoadMode, ProgramShapesBuilder,
+    RequestedShapesContainer, RunReport, ShapeRequest, SmallValuesStore
+};
+use peregrine_toolkit::error::Error;
+use peregrine_toolkit::lock;
+use crate::{ LibcoreBrowser, check_not_refused, eard_interp, load_checked_eardo };
+
+/* The harness runs style programs natively, outside any browser or PeregrineCore, so that their
+ * output can be tested. Programs are loaded from .eardo files and run under a commander Executor
+ * using TestIntegration. All data comes from fixtures: any request a program makes which doesn't
+ * have a fixed response fails. Channels are resolved without a registry, so only self() and
+ * source() work, both of which are taken to be the track base of the ShapeRequest. Settings come
+ * from the track config in the ShapeRequest, as in the browser.
+ */
+
+const MAX_TICKS : u64 = 10000;
+
+pub struct EardHarness {
+    interp: Interpreter,
+    refused: HashSet<ProgramName>,
+    libcore_builder: LibcoreBuilder,
+    libperegrine_builder: LibPeregrineBuilder,
+    libeoe_builder: LibEoEBuilder,
+    data_store: DataStore,
+    small_values_store: SmallValuesStore,
+    assets: Assets
+}
+
+impl EardHarness {
+    pub fn new() -> Result<EardHarness,Error> {
+        let (interp,libcore_builder,libperegrine_builder,libeoe_builder) = eard_interp().map_err(|e| Error::operr(&e))?;
+        Ok(EardHarness {
+            interp, libcore_builder, libperegrine_builder, libeoe_builder,
+            refused: HashSet::new(),
+            data_store: DataStore::new_fixed(HashMap::new()),
+            small_values_store: SmallValuesStore::new_fixed(HashMap::new()),
+            assets: Assets::empty()
+        })
+    }
+
+    pub fn load(&mut self, eardo: &[u8]) -> Result<(),Error> {
+        load_checked_eardo(&mut self.interp,&mut self.refused,eardo)
+    }
+
+    pub fn load_file(&mut self, path: &str) -> Result<(),Error> {
+        let eardo = std::fs::read(path).map_err(|e| Error::operr(&format!("Cannot read {}: {}",path,e)))?;
+        self.load(&eardo)
+    }
+
+    pub fn set_data(&mut self, responses: HashMap<DataRequest,DataResponse>) {
+        self.data_store = DataStore::new_fixed(responses);
+    }
+
+    /* keyed by (namespace,column) */
+    pub fn set_small_values(&mut self, values: HashMap<(String,String),HashMap<String,String>>) {
+        self.small_values_store = SmallValuesStore::new_fixed(values);
+    }
+
+    pub fn set_assets(&mut self, assets: &Assets) {
+        self.assets = assets.clone();
+    }
+
+    fn payloads(&self, request: &ShapeRequest, mode: &LoadMode, shapes: &Arc<Mutex<Option<ProgramShapesBuilder>>>) -> HashMap<String,Box<dyn Any>> {
+        let track_base = request.track().track().track_base();
+        let mut payloads = HashMap::new();
+        payloads.insert("request".to_string(),Box::new(request.clone()) as Box<dyn Any>);
+        payloads.insert("out".to_string(),Box::new(shapes.clone()) as Box<dyn Any>);
+        payloads.insert("report".to_string(),Box::new(Arc::new(Mutex::new(RunReport { net_ms: 0. }))) as Box<dyn Any>);
+        payloads.insert("mode".to_string(),Box::new(mode.clone()) as Box<dyn Any>);
+        payloads.insert("channel-resolver".to_string(),Box::new(AccessorResolver::new_fixed(track_base,track_base)) as Box<dyn Any>);
+        payloads
+    }
+
+    /* Runs the program for the track in the request to completion */
+    pub fn run(&self, request: &ShapeRequest, mode: &LoadMode) -> Result<RequestedShapesContainer,Error> {
+        let name = request.track().track().program().name();
+        let name = ProgramName::new(name.group(),name.name(),name.version());
+        check_not_refused(&self.refused,&name).map_err(|e| Error::operr(&format!("running eardo: {}",e)))?;
+        let program = self.interp.get(&name,"main").map_err(|e| Error::operr(&format!("running eardo: {}",e)))?.clone();
+        let shapes = Arc::new(Mutex::new(Some(ProgramShapesBuilder::new(&self.assets,mode))));
+        let mut context = RunContext::new();
+        prepare_libcore(&mut context,&self.libcore_builder,LibcoreBrowser::new());
+        prepare_libperegrine(
+            &mut context,&self.libperegrine_builder,&self.data_store,&self.small_values_store,
+            self.payloads(request,mode,&shapes)
+        ).map_err(|e| Error::operr(&format!("running eardo: {}",e)))?;
+        prepare_libeoe(&mut context,&self.libeoe_builder).map_err(|e| Error::operr(&format!("running eardo: {}",e)))?;
+        let mut integration = TestIntegration::new();
+        let mut executor = Executor::new(integration.clone());
+        let agent = executor.new_agent(&RunConfig::new(None,2,None),"eard harness");
+        let handle = executor.add(async move {
+            program.run(context).await.map_err(|e| Error::operr(&format!("running eardo: {}",e)))
+        },agent);
+        /* time advances one unit per tick so that any timers eventually fire */
+        for tick in 0..MAX_TICKS {
+            if handle.task_state() != TaskResult::Ongoing { break; }
+            integration.set_time(tick as f64);
+            executor.tick(f64::INFINITY);
+        }
+        match handle.task_state() {
+            TaskResult::Done => {},
+            TaskResult::Ongoing => { return Err(Error::operr("program did not complete")); },
+            TaskResult::Killed(_) => { return Err(Error::operr("program was killed")); }
+        }
+        handle.take_result().unwrap_or_else(|| Err(Error::operr("program gave no result")))?;
+        let shapes = lock!(shapes).take().ok_or_else(|| Error::operr("program took its output"))?;
+        Ok(shapes.to_abstract_shapes_container())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use peregrine_data::{
+        BackendNamespace, Colour, DataRes, DirectColour, Patina, PacketPriority, PixelSize, ProgramModel,
+        ProgramName, ProgramSetting, ReceivedData, Region, Scale, Shape, StickId, TrackConfig
+    };
+    use eachorevery::eoestruct::StructValue;
+    use super::*;
+
+    /* compiled from fixtures/draw.eard by fixtures/build.sh */
+    const DRAW_EARDO : &str = concat!(env!("CARGO_MANIFEST_DIR"),"/fixtures/draw.eardo");
+
+    fn region() -> Region { Region::new(&StickId::new("1"),0,&Scale::new(10)) }
+
+    fn request(program: &str, settings: &[(&str,StructValue)]) -> ShapeRequest {
+        let program = ProgramModel::new_fixed(&ProgramName::new("test",program,1),&[
+            ProgramSetting::new("on",StructValue::new_boolean(true)),
+            ProgramSetting::new("label-column",StructValue::new_string("id"))
+        ]);
+        let settings = settings.iter().map(|(k,v)| (k.to_string(),v.clone())).collect::<BTreeMap<_,_>>();
+        let track = TrackConfig::new_fixed(&program,&BackendNamespace::new("test","base"),settings).unwrap();
+        assert!(track.value("on") == Some(&StructValue::new_boolean(true)));
+        ShapeRequest::new(&region(),&track,&PixelSize::new(1000),false)
+    }
+
+    fn features() -> HashMap<DataRequest,DataResponse> {
+        let mut data = HashMap::new();
+        data.insert("start".to_string(),ReceivedData::new_numbers(vec![100.,300.]));
+        data.insert("end".to_string(),ReceivedData::new_numbers(vec![200.,450.]));
+        data.insert("id".to_string(),ReceivedData::new_strings(vec!["a".to_string(),"b".to_string()]));
+        let request = DataRequest::new(&BackendNamespace::new("test","base"),"test-features",&region());
+        let mut out = HashMap::new();
+        out.insert(request,DataResponse::new(DataRes::new(data,false),PacketPriority::RealTime));
+        out
+    }
+
+    fn labels() -> HashMap<(String,String),HashMap<String,String>> {
+        let mut names = HashMap::new();
+        names.insert("a".to_string(),"Alpha".to_string());
+        names.insert("b".to_string(),"Beta".to_string());
+        let mut out = HashMap::new();
+        out.insert(("test".to_string(),"name".to_string()),names);
+        out
+    }
+
+    fn harness() -> EardHarness {
+        let mut harness = EardHarness::new().unwrap();
+        harness.load_file(DRAW_EARDO).unwrap();
+        harness.set_data(features());
+        harness.set_small_values(labels());
+        harness
+    }
+
+    fn texts(shapes: &RequestedShapesContainer) -> Vec<String> {
+        shapes.shapes().iter().filter_map(|shape| match shape {
+            Shape::Text(text) => Some(text.iter_texts().cloned().collect::<Vec<_>>()),
+            _ => None
+        }).flatten().collect()
+    }
+
+    #[test]
+    fn test_harness_draw() {
+        let shapes = harness().run(&request("draw",&[("label-column",StructValue::new_string("name"))]),&LoadMode::RealTime).unwrap();
+        assert_eq!(2,shapes.shapes().len());
+        let rectangle = shapes.shapes().iter().find_map(|shape| match shape {
+            Shape::Rectangle(rectangle) => Some(rectangle),
+            _ => None
+        }).expect("no rectangle");
+        let extents = rectangle.area().iter().map(|(a,b)| (*a.base,*b.base)).collect::<Vec<_>>();
+        assert_eq!(vec![(100.,200.),(300.,450.)],extents);
+        match rectangle.patina() {
+            Patina::Drawn(_,colours,_) => {
+                assert!(colours.iter(2).unwrap().all(|c| matches!(c,Colour::Direct(DirectColour(255,0,0,255)))));
+            },
+            _ => panic!("rectangle not drawn")
+        }
+        assert_eq!(vec!["Alpha","Beta"],texts(&shapes));
+    }
+
+    #[test]
+    fn test_harness_settings() {
+        /* the default column has no small values, so labels are the ids themselves */
+        let shapes = harness().run(&request("draw",&[]),&LoadMode::RealTime).unwrap();
+        assert_eq!(vec!["a","b"],texts(&shapes));
+    }
+
+    #[test]
+    fn test_harness_missing_data() {
+        let mut harness = harness();
+        harness.set_data(HashMap::new());
+        assert!(harness.run(&request("draw",&[]),&LoadMode::RealTime).is_err());
+    }
+
+    #[test]
+    fn test_harness_bad_eardo() {
+        let mut harness = EardHarness::new().unwrap();
+        assert!(harness.load(b"not an eardo").is_err());
+        assert!(harness.load_file("/nonexistent/program.eardo").is_err());
+    }
+
+    #[test]
+    fn test_harness_missing_program() {
+        let harness = EardHarness::new().unwrap();
+        let error = harness.run(&request("missing",&[]),&LoadMode::RealTime).err().unwrap();
+        assert!(format!("{:?}",error).contains("running eardo"));
+    }
+}
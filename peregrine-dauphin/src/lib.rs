@@ -1,22 +1,83 @@
 use commander::{ CommanderStream, cdr_tick };
-use eard_interp::{LibcoreTemplate, InterpreterBuilder, build_libcore, Interpreter, LibcoreBuilder, RunContext, prepare_libcore};
+use eard_interp::{LibcoreTemplate, InterpreterBuilder, build_libcore, Interpreter, LibcoreBuilder, RunContext, prepare_libcore, ObjectFile, ProgramName};
 use eard_libeoe::{ build_libeoe, LibEoEBuilder, prepare_libeoe };
 use peregrine_data::{ 
     PgCommander, PgCommanderTaskSpec, PeregrineCore, add_task, DataStore, SmallValuesStore
 };
 use peregrine_dauphin_queue::{ PgDauphinTaskSpec, PgEardoLoadTaskSpec, PgEardoRunTaskSpec };
-use eard_libperegrine::{build_libperegrine, prepare_libperegrine, LibPeregrineBuilder};
+use eard_libperegrine::{build_libperegrine, prepare_libperegrine, LibPeregrineBuilder, check_operations};
 use peregrine_toolkit::error::Error;
 use peregrine_toolkit::time::now;
 use peregrine_toolkit::{log_extra, log, lock};
+use std::collections::HashSet;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 
-fn load_eardo(interp: &mut Interpreter, spec: PgEardoLoadTaskSpec, stream: CommanderStream<Result<(),Error>>) {
-    stream.add(interp.load(&spec.data).map_err(|e|
+mod harness;
+
+pub use crate::harness::EardHarness;
+
+/* Fetching each program now means that one needing operations which this client lacks (ie it was
+ * compiled against a newer libperegrine) is refused with the eardo rather than failing later when
+ * a track using it is drawn. Each instruction is checked against the libperegrine operation table
+ * before anything is loaded, so the error can say which operations are missing and which are used
+ * with the wrong number of registers. Linking each program once loaded then catches anything
+ * missing from the other libraries. The interpreter can't unload a program, so those which fail to
+ * link are remembered as refused and never run.
+ */
+fn check_eardo_operations(eardo: &ObjectFile) -> Result<(),Error> {
+    let mut failures = vec![];
+    for code in &eardo.code {
+        let name = &code.name;
+        let uses = code.code.values().flat_map(|block| {
+            block.program.iter().map(|(opcode,registers)| (*opcode as u64,registers.len()))
+        }).collect::<Vec<_>>();
+        if let Err(e) = check_operations(&uses) {
+            failures.push(format!("{}/{}/{}: {}",name.group,name.name,name.version,e));
+        }
+    }
+    if failures.len() > 0 {
+        return Err(Error::operr(&format!("Cannot load eardo: {}",failures.join("; "))));
+    }
+    Ok(())
+}
+
+fn check_eardo_links(interp: &Interpreter, eardo: &ObjectFile, refused: &mut HashSet<ProgramName>) -> Result<(),Error> {
+    let mut failures = vec![];
+    for code in &eardo.code {
+        let name = &code.name;
+        if let Err(e) = interp.get(name,"main") {
+            failures.push(format!("{}/{}/{}: {}",name.group,name.name,name.version,e));
+            refused.insert(name.clone());
+        } else {
+            refused.remove(name);
+        }
+    }
+    if failures.len() > 0 {
+        return Err(Error::operr(&format!("Cannot load eardo: {}",failures.join("; "))));
+    }
+    Ok(())
+}
+
+fn load_checked_eardo(interp: &mut Interpreter, refused: &mut HashSet<ProgramName>, data: &[u8]) -> Result<(),Error> {
+    let eardo = ObjectFile::decode(data.to_vec()).map_err(|e|
         Error::operr(&format!("Cannot load eardo: {}",e))
-    ));
+    )?;
+    check_eardo_operations(&eardo)?;
+    interp.load(data).map_err(|e| Error::operr(&format!("Cannot load eardo: {}",e)))?;
+    check_eardo_links(interp,&eardo,refused)
+}
+
+fn check_not_refused(refused: &HashSet<ProgramName>, name: &ProgramName) -> Result<(),String> {
+    if refused.contains(name) {
+        return Err(format!("{}/{}/{} was refused when loaded",name.group,name.name,name.version));
+    }
+    Ok(())
+}
+
+fn load_eardo(interp: &mut Interpreter, refused: &mut HashSet<ProgramName>, spec: PgEardoLoadTaskSpec, stream: CommanderStream<Result<(),Error>>) {
+    stream.add(load_checked_eardo(interp,refused,&spec.data));
 }
 
 macro_rules! result {
@@ -31,11 +92,12 @@ macro_rules! result {
     };
 }
 
-fn run_eardo(interp: &mut Interpreter, data_store: &DataStore, small_values_store: &SmallValuesStore, 
+fn run_eardo(interp: &mut Interpreter, refused: &HashSet<ProgramName>, data_store: &DataStore, small_values_store: &SmallValuesStore, 
     libcore_builder: &LibcoreBuilder, libperegrine_builder: &LibPeregrineBuilder, libeoe_builder: &LibEoEBuilder,
     commander: &PgCommander, spec: PgEardoRunTaskSpec, stream: CommanderStream<Result<(),Error>>) {
     /* run */
     let stream = stream.clone();
+    result!(check_not_refused(refused,&spec.name),stream,());
     let program = result!(interp.get(&spec.name,"main"),stream,()).clone();
     let libcore_builder = libcore_builder.clone();
     let libperegrine_builder = libperegrine_builder.clone();
@@ -111,11 +173,12 @@ async fn main_loop(core: PeregrineCore) -> Result<(),Error> {
     let data_store = core.agent_store.data_store.clone();
     let small_values_store = core.agent_store.small_values_store.clone();
     let (mut interp,libcore_builder,libperegrine_builder,libeoe_builder) = eard_interp().map_err(|e| Error::operr(&e))?;
+    let mut refused = HashSet::new();
     loop {
         let e = core.base.dauphin_queue.get().await;
         match e.task {
-            PgDauphinTaskSpec::LoadEardo(p) => load_eardo(&mut interp,p,e.channel),
-            PgDauphinTaskSpec::RunEardo(r) => run_eardo(&mut interp,&data_store,&small_values_store,&libcore_builder,&libperegrine_builder,&libeoe_builder,&core.base.commander,r,e.channel),
+            PgDauphinTaskSpec::LoadEardo(p) => load_eardo(&mut interp,&mut refused,p,e.channel),
+            PgDauphinTaskSpec::RunEardo(r) => run_eardo(&mut interp,&refused,&data_store,&small_values_store,&libcore_builder,&libperegrine_builder,&libeoe_builder,&core.base.commander,r,e.channel),
             PgDauphinTaskSpec::Quit => { break; }
         }
     }
@@ -1,10 +1,11 @@
+use std::ops::DerefMut;
 use std::pin::Pin;
-use std::sync::{ Arc, Mutex, MutexGuard, Weak };
+use std::sync::{ Arc, Mutex, Weak };
 use std::future::Future;
 use commander::{ Executor, Integration, Lock, RunConfig, RunSlot, SleepQuantity, TaskHandle, cdr_new_agent, cdr_add, cdr_in_agent };
 use peregrine_toolkit::js::raf::Raf;
 use peregrine_toolkit::js::timer::Timer;
-use peregrine_toolkit::lock;
+use peregrine_toolkit::{ lock, warn };
 use js_sys::Date;
 use super::bell::{ BellReceiver, make_bell, BellSender };
 use peregrine_data::{ Commander, DataMessage };
@@ -38,6 +39,14 @@ use crate::util::message::{ message, Message };
 
 const MS_PER_TICK : f64 = 7.;
 
+/* Anticipation (priority 9) gets a little of every tick however busy we are, but never more than
+ * half so that there is time left in the frame for scrolling.
+ */
+const ANTICIPATE_PRIO : u8 = 9;
+const ANTICIPATE_GUARANTEED : f64 = 0.1;
+const ANTICIPATE_LIMIT : f64 = 0.5;
+const STARVATION_TICKS : u64 = 100;
+
 pub fn js_panic(e: Result<(),Message>) {
     match e {
         Ok(_) => (),
@@ -139,9 +148,12 @@ impl PgCommanderWeb {
             quantity,
             bell_sender
         };
+        let mut executor = Executor::new(integration);
+        executor.set_priority_budget(ANTICIPATE_PRIO,ANTICIPATE_GUARANTEED,ANTICIPATE_LIMIT);
+        executor.set_starvation_ticks(Some(STARVATION_TICKS));
         let state = CommanderState {
             sleep_state,
-            executor: Arc::new(Mutex::new(Executor::new(integration)))
+            executor: Arc::new(Mutex::new(executor))
         };
         let weak_state = state.downgrade();
         lock!(state.sleep_state).raf = Some(Raf::new( move || {
@@ -197,7 +209,7 @@ impl Commander for PgCommanderWeb {
     fn make_lock(&self) -> Lock { self.state.make_lock() }
     fn identity(&self) -> u64 { self.state.identity() }
 
-    fn executor(&self) -> MutexGuard<Executor> { self.state.executor.lock().unwrap() }
+    fn executor(&self) -> Box<dyn DerefMut<Target=Executor> + '_> { Box::new(self.state.executor.lock().unwrap()) }
 }
 
 #[derive(Clone)]
@@ -215,4 +227,8 @@ impl Integration for PeregrineCommanderIntegration {
         *self.quantity.lock().unwrap() = amount;
        self.bell_sender.ring();
     }
+
+    fn starved(&self, priority: u8, ticks: u64) {
+        warn!("tasks at priority {} have not run for {} ticks",priority,ticks);
+    }
 }
@@ -8,7 +8,7 @@ use peregrine_toolkit_async::sync::needed::Needed;
 pub use url::Url;
 use wasm_bindgen::JsValue;
 pub use web_sys::{ console, WebGlRenderingContext, Element };
-use peregrine_data::{ StickId, Commander };
+use peregrine_data::{ StickId, Commander, DirectColour, Theme };
 use super::buildconfig::{ GIT_TAG, GIT_BUILD_DATE };
 use super::mousemove::run_mouse_move;
 use commander::CommanderStream;
@@ -64,6 +64,8 @@ enum DrawMessage {
     DebugAction(u8),
     SetArtificial(String,bool),
     Jump(String),
+    SetTheme(Theme),
+    SetPaletteColour(Theme,String,DirectColour),
     Sync(),
     AddJsapiChannel(String,JsValue)
 }
@@ -80,6 +82,8 @@ impl std::fmt::Debug for DrawMessage {
             DrawMessage::DebugAction(index)  => write!(f,"DebugAction({:?})",index),
             DrawMessage::SetArtificial(name,start) => write!(f,"SetArtificial({:?},{:?})",name,start),
             DrawMessage::Jump(location) => write!(f,"Jump({})",location),
+            DrawMessage::SetTheme(theme) => write!(f,"SetTheme({})",theme.name()),
+            DrawMessage::SetPaletteColour(theme,name,colour) => write!(f,"SetPaletteColour({},{:?},{:?})",theme.name(),name,colour),
             DrawMessage::Sync() => write!(f,"Sync"),
             DrawMessage::AddJsapiChannel(name,channel) => write!(f,"AddJsapiChannel({},...)",name)
         }
@@ -137,6 +141,12 @@ impl DrawMessage {
             DrawMessage::Jump(location) => {
                 draw.jump(&location);
             },
+            DrawMessage::SetTheme(theme) => {
+                draw.set_theme(&theme);
+            },
+            DrawMessage::SetPaletteColour(theme,name,colour) => {
+                draw.set_palette_colour(&theme,&name,&colour);
+            },
             DrawMessage::AddJsapiChannel(name,payload) => {
                 draw.add_jsapi_channel(&name,payload);
             }
@@ -214,6 +224,14 @@ impl PeregrineAPI {
         self.queue.add(Some(DrawMessage::SetArtificial(name.to_string(),start)));
     }
 
+    pub fn set_theme(&self, theme: &Theme) {
+        self.queue.add(Some(DrawMessage::SetTheme(*theme)));
+    }
+
+    pub fn set_palette_colour(&self, theme: &Theme, name: &str, colour: &DirectColour) {
+        self.queue.add(Some(DrawMessage::SetPaletteColour(*theme,name.to_string(),colour.clone())));
+    }
+
     async fn step(&self, mut draw: PeregrineInnerAPI) -> Result<(),Message> {
         log_important!("version {} {} {}.",GIT_TAG,GIT_BUILD_DATE,env!("BUILD_TIME"));
         #[cfg(debug_assertions)]
@@ -19,7 +19,7 @@ fn draw_objects_and_spectres(lweb: &mut LockedPeregrineInnerAPI, read_stage: &Re
         session.begin(&mut *lock!(gl))?;
         lweb.trainset.draw_animate_tick(read_stage,&gl,&mut session)?;
         lweb.spectre_manager.draw(&gl,&assets,read_stage,&mut session)?;
-        session.finish(lweb.data_api)?;
+        session.finish(&mut *lock!(gl),lweb.data_api)?;
     }
     Ok(())
 }
@@ -9,7 +9,7 @@ use std::sync::{ Mutex, Arc };
 use crate::util::message::{ Message, message_register_callback, routed_message, message_register_default };
 use crate::input::translate::targetreporter::TargetReporter;
 use eachorevery::eoestruct::StructValue;
-use peregrine_data::{Assets, Commander, PeregrineCore, PeregrineApiQueue, BackendNamespace, ChannelIntegration, DataMessage};
+use peregrine_data::{Assets, Commander, PeregrineCore, PeregrineApiQueue, BackendNamespace, ChannelIntegration, DataMessage, DirectColour, Theme};
 use peregrine_dauphin::peregrine_dauphin;
 use peregrine_febe_javascript::JavascriptIntegration;
 use peregrine_febe_network::NetworkChannel;
@@ -269,6 +269,17 @@ impl PeregrineInnerAPI {
         self.target_reporter.set_stick(stick.get_id());
     }
 
+    /* Palette changes only need the shapes we already have redrawn, not a reload */
+    pub(super) fn set_theme(&mut self, theme: &Theme) {
+        lock!(self.webgl).palette_mut().set_theme(theme);
+        self.trainset.redraw_carriages();
+    }
+
+    pub(super) fn set_palette_colour(&mut self, theme: &Theme, name: &str, colour: &DirectColour) {
+        lock!(self.webgl).palette_mut().set_colour(theme,name,colour);
+        self.trainset.redraw_carriages();
+    }
+
     pub(crate) fn add_jsapi_channel(&mut self, name: &str, payload: JsValue) {
         err_web_drop(self.jsapi.add_channel(name,payload));
         self.data_api.add_backend(&format!("jsapi:{}",name));
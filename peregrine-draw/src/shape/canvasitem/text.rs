@@ -82,13 +82,13 @@ impl DrawingText {
 
 pub(crate) fn prepare_text(out: &mut Vec<GLShape>, tools: &mut DrawingToolsBuilder, shape: &TextShape<AuxLeaf>, draw_group: &DrawGroup) -> Result<(),Error> {
     let depth = shape.position().allotments().map(|x| x.depth);
-    let drawing_text = tools.text();
-    let background = shape.pen().background();
     let texts = shape.iter_texts().collect::<Vec<_>>();
-    let colours_iter = shape.pen().colours().iter(texts.len()).unwrap();
-    let bgd_iter = shape.pen().background().iter(texts.len()).unwrap();
+    let palette = tools.palette();
+    let colours = shape.pen().colours().iter(texts.len()).unwrap().map(|c| palette.plain(c)).collect::<Vec<_>>();
+    let backgrounds = shape.pen().background().iter(texts.len()).unwrap().map(|c| palette.plain(c)).collect::<Vec<_>>();
+    let drawing_text = tools.text();
     let mut all_texts = vec![];
-    for (text,(colour,background)) in texts.iter().zip(colours_iter.zip(bgd_iter)) {
+    for (text,(colour,background)) in texts.iter().zip(colours.iter().zip(backgrounds.iter())) {
         let item = drawing_text.make(&shape.pen().geometry(),&text,colour,background);
         all_texts.push(item);
     }
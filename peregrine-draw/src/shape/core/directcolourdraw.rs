@@ -1,4 +1,4 @@
-use crate::{shape::{layers::{patina::{PatinaProcessName, PatinaFactory}}, util::eoethrow::{eoe_throw2}}, webgl::{ AttribHandle, ProcessStanzaAddable, ProgramBuilder, ProcessBuilder }};
+use crate::{shape::{layers::{patina::{PatinaProcessName, PatinaFactory, Blending}}, util::eoethrow::{eoe_throw2}}, webgl::{ AttribHandle, ProcessStanzaAddable, ProgramBuilder, ProcessBuilder }};
 use eachorevery::EachOrEvery;
 use peregrine_data::{DirectColour};
 use peregrine_toolkit::error::Error;
@@ -60,11 +60,13 @@ impl DirectColourDraw {
     }
 }
 
-pub(crate) struct ColourFragment;
+pub(crate) struct ColourFragment {
+    blending: Blending
+}
 
 impl ColourFragment {
-    pub(crate) fn new() -> ColourFragment {
-        ColourFragment
+    pub(crate) fn new(blending: &Blending) -> ColourFragment {
+        ColourFragment { blending: blending.clone() }
     }
 
     pub(crate) fn make(&self, builder: &mut ProcessBuilder) -> Result<DirectColourDraw,Error> {
@@ -73,5 +75,5 @@ impl ColourFragment {
 }
 
 impl PatinaFactory for ColourFragment {
-    fn patina_name(&self) -> PatinaProcessName { PatinaProcessName::Direct }
+    fn patina_name(&self) -> PatinaProcessName { PatinaProcessName::Direct(self.blending.clone()) }
 }
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use eachorevery::{EachOrEveryFilterBuilder, EachOrEvery};
 use peregrine_data::reactive::Observable;
-use peregrine_data::{ Colour, DirectColour, DrawnType, Patina, Plotter, SpaceBaseArea, HollowEdge2, SpaceBase, AuxLeaf, HotspotPatina, AttachmentPoint, PartialSpaceBase, SpaceBasePoint };
+use peregrine_data::{ BlendMode, Colour, DirectColour, DrawnType, Palette, Patina, Plotter, SpaceBaseArea, HollowEdge2, SpaceBase, AuxLeaf, HotspotPatina, AttachmentPoint, PartialSpaceBase, SpaceBasePoint };
 use peregrine_toolkit::error::Error;
 use super::directcolourdraw::{DirectColourDraw, ColourFragment};
 use super::super::layers::layer::{ Layer };
@@ -12,7 +12,7 @@ use crate::shape::canvasitem::heraldry::{HeraldryHandle, HeraldryCanvas, Heraldr
 use crate::shape::canvasitem::text::draw_text;
 use crate::shape::layers::drawing::DynamicShape;
 use crate::shape::layers::drawingtools::{DrawingToolsBuilder, CanvasType};
-use crate::shape::layers::patina::Freedom;
+use crate::shape::layers::patina::{Blending, Freedom};
 use crate::shape::triangles::polygon::{SolidPolygonDataFactory, SolidPolygon};
 use crate::shape::triangles::rectangles::{Rectangles, RectanglesDataFactory };
 use crate::shape::triangles::drawgroup::DrawGroup;
@@ -61,28 +61,29 @@ impl GLAttachmentPoint {
 
 #[cfg_attr(debug_assertions,derive(Debug))]
 pub(crate) enum SimpleShapePatina {
-    Solid(EachOrEvery<DirectColour>),
-    Hollow(EachOrEvery<DirectColour>,f64),
+    Solid(EachOrEvery<DirectColour>,BlendMode),
+    Hollow(EachOrEvery<DirectColour>,f64,BlendMode),
     Hotspot(HotspotPatina,bool),
     None
 }
 
-fn simplify_colours(colours: &EachOrEvery<Colour>) -> Result<EachOrEvery<DirectColour>,Error> {
+fn simplify_colours(colours: &EachOrEvery<Colour>, palette: &Palette) -> Result<EachOrEvery<DirectColour>,Error> {
     Ok(colours.map_results(|colour| {
         match colour {
             Colour::Direct(d) => Ok(d.clone()),
+            Colour::Palette(name) => Ok(palette.lookup(name)),
             _ => Err(Error::fatal("attempt to simplify pattern to colour"))
         }
     })?)
 }
 
 impl SimpleShapePatina {
-    pub(crate) fn from_patina(patina: &Patina) -> Result<SimpleShapePatina,Error> {
+    pub(crate) fn from_patina(patina: &Patina, palette: &Palette) -> Result<SimpleShapePatina,Error> {
         Ok(match patina {
-            Patina::Drawn(drawn_variety,colours) => {
+            Patina::Drawn(drawn_variety,colours,blend) => {
                 match drawn_variety {
-                    DrawnType::Stroke(width) => SimpleShapePatina::Hollow(simplify_colours(colours)?,*width),
-                    DrawnType::Fill => SimpleShapePatina::Solid(simplify_colours(colours)?),
+                    DrawnType::Stroke(width) => SimpleShapePatina::Hollow(simplify_colours(colours,palette)?,*width,*blend),
+                    DrawnType::Fill => SimpleShapePatina::Solid(simplify_colours(colours,palette)?,*blend),
                 }
             },
             Patina::Hotspot(hotspot,hover) => { SimpleShapePatina::Hotspot(hotspot.clone(),*hover) }
@@ -104,8 +105,8 @@ fn add_colour(addable: &mut ProcessStanzaElements, patina: &SimpleShapePatina, d
     let points_per_shape = addable.points_per_shape();
     let number_of_shapes = addable.number_of_shapes();
     match patina {
-        SimpleShapePatina::Solid(colours) |
-        SimpleShapePatina::Hollow(colours,_) => {
+        SimpleShapePatina::Solid(colours,_) |
+        SimpleShapePatina::Hollow(colours,_,_) => {
             draw.direct(addable,&colours,points_per_shape,number_of_shapes)?;
         },
         _ => {}
@@ -184,14 +185,15 @@ fn run_to_spacebase(run: Option<EachOrEvery<f64>>) -> Option<SpaceBase<f64,()>>
 pub(crate) fn add_shape_to_layer(layer: &mut Layer, left: f64, gl: &mut WebGlGlobal, tools: &mut DrawingToolsBuilder, shape: GLShape) -> Result<ShapeToAdd,Error> {
     let bitmap_multiplier = gl.refs().canvas_source.bitmap_multiplier() as f64;
     match shape {
-        GLShape::Wiggle((start,end),yy,Plotter(_,colour),depth) => {
+        GLShape::Wiggle((start,end),yy,Plotter(_,colour,blend),depth) => {
+            let colour = EachOrEvery::every(tools.palette().plain(&colour));
             let wiggle_factory = WiggleAdderFactory::new();
-            let fragment_factory = ColourFragment::new();
+            let fragment_factory = ColourFragment::new(&Blending::new(&blend,&colour,1,depth)?);
             let process = layer.get_process_builder(&wiggle_factory,&fragment_factory)?;
             let adder = wiggle_factory.make(process)?;
             let draw = fragment_factory.make(process)?;
             let (mut array,count) = adder.add_wiggle(process,start,end,&yy,left,depth)?;
-            draw.direct(&mut array,&EachOrEvery::every(colour),1,count)?;
+            draw.direct(&mut array,&colour,1,count)?;
             array.close()?;
             Ok(ShapeToAdd::None)
         },
@@ -219,10 +221,11 @@ pub(crate) fn add_shape_to_layer(layer: &mut Layer, left: f64, gl: &mut WebGlGlo
         },
         GLShape::Rectangle(area,run,simple_shape_patina,depth,draw_group,wobble) => {
             match simple_shape_patina {
-                SimpleShapePatina::Solid(_) | SimpleShapePatina::Hollow(_,_) => {
-                    let hollow = match simple_shape_patina { SimpleShapePatina::Hollow(_,w) => Some(w), _ => None };
+                SimpleShapePatina::Solid(ref colours,blend) | SimpleShapePatina::Hollow(ref colours,_,blend) => {
+                    let hollow = match simple_shape_patina { SimpleShapePatina::Hollow(_,w,_) => Some(w), _ => None };
+                    let blending = Blending::new(&blend,colours,area.len(),draw_group.depth())?;
                     let vertex_factory = RectanglesDataFactory::new(&draw_group);
-                    let fragment_factory = ColourFragment::new();
+                    let fragment_factory = ColourFragment::new(&blending);
                     let builder = layer.get_process_builder(&vertex_factory,&fragment_factory)?;
                     let run = run_to_spacebase(run);
                     let mut rectangles = vertex_factory.make_area(builder,&area,&run,&depth,left,hollow,&None,wobble)?;
@@ -242,9 +245,10 @@ pub(crate) fn add_shape_to_layer(layer: &mut Layer, left: f64, gl: &mut WebGlGlo
         },
         GLShape::Polygon(centre,radius,depth,points,angle,patina,group,wobble) => {
             match patina {
-                SimpleShapePatina::Solid(_) => {
+                SimpleShapePatina::Solid(ref colours,blend) => {
+                    let blending = Blending::new(&blend,colours,centre.len(),depth)?;
                     let vertex_factory = SolidPolygonDataFactory::new(&group);
-                    let fragment_factory = ColourFragment::new();
+                    let fragment_factory = ColourFragment::new(&blending);
                     let builder = layer.get_process_builder(&vertex_factory,&fragment_factory)?;
                     let mut polygons = vertex_factory.make(builder,&centre,&radius,points,angle,depth,left,&group,wobble)?;
                     let draw = fragment_factory.make(builder)?;
@@ -253,7 +257,7 @@ pub(crate) fn add_shape_to_layer(layer: &mut Layer, left: f64, gl: &mut WebGlGlo
                     campaign.close()?;
                     Ok(ShapeToAdd::Dynamic(Box::new(SolidPolygon::new(polygons,&gl))))
                 },
-                SimpleShapePatina::Hollow(_,_) => {
+                SimpleShapePatina::Hollow(_,_,_) => {
                     todo!()
                 }
                 SimpleShapePatina::Hotspot(hotspot,hover) => {
@@ -1,5 +1,5 @@
 use eachorevery::EachOrEvery;
-use peregrine_data::{ Colour, DrawnType, Patina, RectangleShape, Shape, ShapeDemerge, HollowEdge2, AuxLeaf, DrawingShape, CoordinateSystem, PolygonShape };
+use peregrine_data::{ Colour, DrawnType, Palette, Patina, RectangleShape, Shape, ShapeDemerge, HollowEdge2, AuxLeaf, DrawingShape, CoordinateSystem, PolygonShape };
 use peregrine_toolkit::error::Error;
 use crate::shape::canvasitem::heraldry::{HeraldryCanvasesUsed, Heraldry};
 use crate::shape::canvasitem::text::prepare_text;
@@ -13,23 +13,23 @@ fn split_spacebaserect(tools: &mut DrawingToolsBuilder, shape: &RectangleShape<A
     let depth = shape.area().top_left().allotments().map(|x| x.depth);
     let wobble = shape.wobble().clone();
     match shape.patina() {
-        Patina::Drawn(drawn_variety,_) => {
+        Patina::Drawn(drawn_variety,_,_) => {
             let width = match drawn_variety {
                 DrawnType::Stroke(w) => Some(*w),
                 DrawnType::Fill => None
             };
             match draw_group.shape_category() {
                 ShapeCategory::SolidColour | ShapeCategory::Other => {
-                    out.push(GLShape::Rectangle(shape.area().clone(),shape.run().clone(),SimpleShapePatina::from_patina(shape.patina())?,depth,draw_group.clone(),wobble));
+                    out.push(GLShape::Rectangle(shape.area().clone(),shape.run().clone(),SimpleShapePatina::from_patina(shape.patina(),tools.palette())?,depth,draw_group.clone(),wobble));
                 },
                 ShapeCategory::Heraldry(HeraldryCanvasesUsed::Solid(heraldry_canvas),scale) => {
-                    let heraldry = make_heraldry(shape.patina())?;
+                    let heraldry = make_heraldry(shape.patina(),tools.palette())?;
                     let handles = heraldry.map_results(|x| x.add(tools))?;
                     out.push(GLShape::Heraldry(shape.area().clone(),shape.run().clone(),handles,depth,draw_group.clone(),heraldry_canvas.clone(),scale.clone(),None,wobble));
                 },
                 ShapeCategory::Heraldry(HeraldryCanvasesUsed::Hollow(heraldry_canvas_h,heraldry_canvas_v),scale) => {
                     let width = width.unwrap_or(0.);
-                    let heraldry = make_heraldry(shape.patina())?;
+                    let heraldry = make_heraldry(shape.patina(),tools.palette())?;
                     let handles = heraldry.map_results(|x| x.add(tools))?;
                     // XXX too much cloning, at least Arc them
                     let area = shape.area();
@@ -49,12 +49,12 @@ fn split_spacebaserect(tools: &mut DrawingToolsBuilder, shape: &RectangleShape<A
     Ok(out)
 }
 
-fn split_polygon(shape: &PolygonShape<AuxLeaf>, draw_group: &DrawGroup) -> Result<Vec<GLShape>,Error> {
+fn split_polygon(shape: &PolygonShape<AuxLeaf>, draw_group: &DrawGroup, palette: &Palette) -> Result<Vec<GLShape>,Error> {
     let mut out = vec![];
     let wobble = shape.wobble().clone();
     match shape.patina() {
-        Patina::Drawn(_,_) => {
-            out.push(GLShape::Polygon(shape.position().clone(),shape.radius().clone(),draw_group.depth(),shape.points(),shape.angle(),SimpleShapePatina::from_patina(shape.patina())?,draw_group.clone(),wobble));
+        Patina::Drawn(_,_,_) => {
+            out.push(GLShape::Polygon(shape.position().clone(),shape.radius().clone(),draw_group.depth(),shape.points(),shape.angle(),SimpleShapePatina::from_patina(shape.patina(),palette)?,draw_group.clone(),wobble));
         },
         Patina::Hotspot(hotspot,hover) => {
             out.push(GLShape::Polygon(shape.position().clone(),shape.radius().clone(),draw_group.depth(),shape.points(),shape.angle(),SimpleShapePatina::Hotspot(hotspot.clone(),*hover),draw_group.clone(),None));
@@ -64,33 +64,33 @@ fn split_polygon(shape: &PolygonShape<AuxLeaf>, draw_group: &DrawGroup) -> Resul
     Ok(out)
 }
 
-fn colour_to_heraldry(colour: &Colour, _hollow: bool) -> Option<Heraldry> {
+fn colour_to_heraldry(colour: &Colour, palette: &Palette, _hollow: bool) -> Option<Heraldry> {
     match colour {
         Colour::Stripe(a,b,c,_prop) => {
-            Some(Heraldry::Stripe(a.clone(),b.clone(),50,*c))
+            Some(Heraldry::Stripe(palette.plain(a),palette.plain(b),50,*c))
         },
         Colour::Bar(a,b,c,prop) => {
-            Some(Heraldry::new_dots(a,b,(prop*100.) as u32,*c,false))
+            Some(Heraldry::new_dots(&palette.plain(a),&palette.plain(b),(prop*100.) as u32,*c,false))
         },
         _ => None
     }
 }
 
-fn make_heraldry(patina: &Patina) -> Result<EachOrEvery<Heraldry>,Error> {
+fn make_heraldry(patina: &Patina, palette: &Palette) -> Result<EachOrEvery<Heraldry>,Error> {
     let (colours,hollow) = match patina {
-        Patina::Drawn(DrawnType::Fill,c) => (c,false),
-        Patina::Drawn(DrawnType::Stroke(_),c) => (c,true),
+        Patina::Drawn(DrawnType::Fill,c,_) => (c,false),
+        Patina::Drawn(DrawnType::Stroke(_),c,_) => (c,true),
         _ => Err(Error::fatal("heraldry attempted on non filled/hollow"))?
     };
     colours.map_results(|colour| {
-        colour_to_heraldry(colour,hollow)
+        colour_to_heraldry(colour,palette,hollow)
             .ok_or_else(|| Error::fatal("heraldry attempted on non-heraldic colour"))
     })
 }
 
-pub struct GLCategoriser();
+pub struct GLCategoriser<'a>(&'a Palette);
 
-impl ShapeDemerge for GLCategoriser {
+impl<'a> ShapeDemerge for GLCategoriser<'a> {
     type X = DrawGroup;
 
     fn categorise(&self, coord_system: &CoordinateSystem, depth: i8) -> Self::X {
@@ -102,7 +102,7 @@ impl ShapeDemerge for GLCategoriser {
             DrawnType::Fill => false,
             DrawnType::Stroke(_) => true
         };
-        let category = if let Some(heraldry) = colour_to_heraldry(colour,is_fill) {
+        let category = if let Some(heraldry) = colour_to_heraldry(colour,self.0,is_fill) {
             ShapeCategory::Heraldry(heraldry.canvases_used(),heraldry.scale())                                
         } else {
             ShapeCategory::SolidColour
@@ -113,7 +113,7 @@ impl ShapeDemerge for GLCategoriser {
 
 pub(crate) fn prepare_shape_in_layer(tools: &mut DrawingToolsBuilder, shape: DrawingShape) -> Result<Vec<GLShape>,Error> {
     let mut out = vec![];
-    let demerge = shape.demerge(&GLCategoriser());
+    let demerge = shape.demerge(&GLCategoriser(tools.palette()));
     for (draw_group,shape) in demerge {
         if draw_group.coord_system().is_dustbin() { continue; }
         match shape {
@@ -137,7 +137,7 @@ pub(crate) fn prepare_shape_in_layer(tools: &mut DrawingToolsBuilder, shape: Dra
                 out.append(&mut split_spacebaserect(tools,&shape,&draw_group)?);
             },
             Shape::Polygon(shape) => {
-                out.append(&mut split_polygon(&shape,&draw_group)?);
+                out.append(&mut split_polygon(&shape,&draw_group,tools.palette())?);
             }
         }
     }
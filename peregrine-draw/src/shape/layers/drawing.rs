@@ -37,7 +37,7 @@ impl DrawingBuilder {
         let bitmap_multiplier = gl_ref.canvas_source.bitmap_multiplier() as f64;
         Ok(DrawingBuilder {
             main_layer: Layer::new(gl_ref.program_store,left)?,
-            tools: DrawingToolsBuilder::new(gl_ref.fonts,assets,gl_ref.image_cache,scale,left,bitmap_multiplier),
+            tools: DrawingToolsBuilder::new(gl_ref.fonts,assets,gl_ref.image_cache,scale,left,bitmap_multiplier,gl_ref.palette),
             dynamic_shapes: vec![],
             left
         })
@@ -1,4 +1,4 @@
-use peregrine_data::{Assets, Scale};
+use peregrine_data::{Assets, Palette, Scale};
 use peregrine_toolkit::{error::Error};
 use crate::{webgl::{global::WebGlGlobal, canvas::{composition::compositionbuilder::CompositionBuilder, binding::weave::CanvasWeave}}, util::fonts::Fonts, hotspots::drawinghotspots::{DrawingHotspots, DrawingHotspotsBuilder}, shape::canvasitem::{text::DrawingText, bitmap::DrawingBitmap, imagecache::ImageCache}};
 
@@ -46,16 +46,18 @@ pub(crate) struct DrawingToolsBuilder {
     text: DrawingText,
     bitmap: DrawingBitmap,
     manager: Vec<CompositionBuilder>,
-    hotspots: DrawingHotspotsBuilder
+    hotspots: DrawingHotspotsBuilder,
+    palette: Palette
 }
 
 impl DrawingToolsBuilder {
-    pub(super) fn new(fonts: &Fonts, assets: &Assets, image_cache: &ImageCache, scale: Option<&Scale>, left: f64, bitmap_multiplier: f64) -> DrawingToolsBuilder {
+    pub(super) fn new(fonts: &Fonts, assets: &Assets, image_cache: &ImageCache, scale: Option<&Scale>, left: f64, bitmap_multiplier: f64, palette: &Palette) -> DrawingToolsBuilder {
         DrawingToolsBuilder {
             manager: (0..CANVAS_TYPE_LEN).map(|x| CompositionBuilder::new(&CanvasType::from_index(x).to_weave())).collect(),
             text: DrawingText::new(fonts,bitmap_multiplier),
             bitmap: DrawingBitmap::new(assets,image_cache),
-            hotspots: DrawingHotspotsBuilder::new(scale, left)
+            hotspots: DrawingHotspotsBuilder::new(scale, left),
+            palette: palette.clone()
         }
     }
 
@@ -63,6 +65,7 @@ impl DrawingToolsBuilder {
     pub(crate) fn text(&mut self) -> &mut DrawingText { &mut self.text }
     pub(crate) fn bitmap(&mut self) -> &mut DrawingBitmap { &mut self.bitmap }
     pub(crate) fn hotspots(&mut self) -> &mut DrawingHotspotsBuilder { &mut self.hotspots }
+    pub(crate) fn palette(&self) -> &Palette { &self.palette }
 
     pub(crate) fn build(self) -> Result<DrawingTools,Error> {
         Ok(DrawingTools {
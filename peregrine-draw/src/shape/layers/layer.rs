@@ -33,7 +33,7 @@ impl ProgramCharacter {
     }
 
     pub(crate) fn order(&self) -> (usize,usize,String) {
-        (self.1.order(),0,self.key())
+        (self.1.order(),self.1.depth_order(),self.key())
     }
 }
 
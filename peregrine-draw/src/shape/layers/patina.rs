@@ -1,11 +1,20 @@
 use enum_iterator::Sequence;
+use eachorevery::EachOrEvery;
+use peregrine_data::{ BlendMode, DirectColour };
+use peregrine_toolkit::error::Error;
+use web_sys::WebGlRenderingContext;
+use crate::shape::util::eoethrow::eoe_throw2;
 use crate::webgl::canvas::htmlcanvas::canvasinuse::CanvasInUse;
 use crate::webgl::{SetFlag};
 use crate::webgl::{ SourceInstrs, UniformProto, AttributeProto, GLArity, Varying, Statement, TextureProto };
 use super::consts::{ PR_LOW, PR_DEF };
 
+/* Multiply and screen need the fragment colour premultiplied, see Blending::apply */
+#[derive(Clone,Copy,Debug,Hash,PartialEq,Eq,Sequence)]
+pub(crate) enum BlendProgram { Normal, Multiply, Screen }
+
 #[derive(Clone,Debug,Hash,PartialEq,Eq,Sequence)]
-pub(crate) enum PatinaProgramName { Direct, Texture, FreeTexture }
+pub(crate) enum PatinaProgramName { Direct(BlendProgram), Texture, FreeTexture }
 
 pub(crate) trait PatinaFactory {
     fn patina_name(&self) -> PatinaProcessName;
@@ -19,13 +28,25 @@ impl PatinaProgramName {
     pub fn get_source(&self) -> SourceInstrs {
         SourceInstrs::new(
             match self {
-                PatinaProgramName::Direct => vec![
-                    AttributeProto::new(PR_DEF,GLArity::Vec4,"aVertexColour"),
-                    Varying::new(PR_LOW,GLArity::Vec4,"vColour"),
-                    Statement::new_vertex("vColour = aVertexColour"),
-                    Statement::new_fragment("gl_FragColor = vColour"),
-                    Statement::new_fragment("gl_FragColor.a = gl_FragColor.a * uOpacity")
-                ],
+                PatinaProgramName::Direct(blend) => {
+                    let mut out = vec![
+                        AttributeProto::new(PR_DEF,GLArity::Vec4,"aVertexColour"),
+                        Varying::new(PR_LOW,GLArity::Vec4,"vColour"),
+                        Statement::new_vertex("vColour = aVertexColour"),
+                        Statement::new_fragment("gl_FragColor = vColour"),
+                        Statement::new_fragment("gl_FragColor.a = gl_FragColor.a * uOpacity")
+                    ];
+                    match blend {
+                        BlendProgram::Normal => {},
+                        BlendProgram::Multiply => {
+                            out.push(Statement::new_fragment("gl_FragColor = vec4(mix(vec3(1.0),gl_FragColor.rgb,gl_FragColor.a),1.0)"));
+                        },
+                        BlendProgram::Screen => {
+                            out.push(Statement::new_fragment("gl_FragColor = vec4(gl_FragColor.rgb*gl_FragColor.a,1.0)"));
+                        }
+                    }
+                    out
+                },
                 PatinaProgramName::Texture => vec![
                     TextureProto::new("uSampler","uSamplerSize","uSamplerScale"),
                     AttributeProto::new(PR_DEF,GLArity::Vec2,"aTextureCoord"),
@@ -79,16 +100,73 @@ impl Freedom {
     }
 }
 
+/* Opaque shapes are drawn first, in any order, and write the depth buffer so that the nearest
+ * wins. Shapes which show what's beneath them (translucent colours or a multiply/screen blend
+ * mode) are drawn afterwards, one process per depth, back to front. They are depth tested against
+ * the opaque shapes but don't write the depth buffer, so they don't hide each other.
+ */
+#[derive(Clone,Debug,Hash,PartialEq,Eq)]
+pub(crate) enum Blending {
+    Opaque,
+    Alpha(i8),
+    Multiply(i8),
+    Screen(i8)
+}
+
+impl Blending {
+    pub(crate) fn new(mode: &BlendMode, colours: &EachOrEvery<DirectColour>, count: usize, depth: i8) -> Result<Blending,Error> {
+        Ok(match mode {
+            BlendMode::Normal => {
+                let mut colours = eoe_throw2("blending colours",colours.iter(count))?;
+                if colours.any(|c| c.3 < 255) { Blending::Alpha(depth) } else { Blending::Opaque }
+            },
+            BlendMode::Multiply => Blending::Multiply(depth),
+            BlendMode::Screen => Blending::Screen(depth)
+        })
+    }
+
+    fn program(&self) -> BlendProgram {
+        match self {
+            Blending::Opaque | Blending::Alpha(_) => BlendProgram::Normal,
+            Blending::Multiply(_) => BlendProgram::Multiply,
+            Blending::Screen(_) => BlendProgram::Screen
+        }
+    }
+
+    /* greater depth is nearer, so ascending is back to front */
+    fn depth_order(&self) -> usize {
+        match self {
+            Blending::Opaque => 0,
+            Blending::Alpha(d) | Blending::Multiply(d) | Blending::Screen(d) => (*d as i16 + 128) as usize
+        }
+    }
+
+    pub(crate) fn apply(&self, context: &WebGlRenderingContext) {
+        context.depth_mask(*self == Blending::Opaque);
+        match self {
+            Blending::Opaque | Blending::Alpha(_) => {
+                context.blend_func_separate(WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA, WebGlRenderingContext::ONE, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA);
+            },
+            Blending::Multiply(_) => {
+                context.blend_func_separate(WebGlRenderingContext::DST_COLOR, WebGlRenderingContext::ZERO, WebGlRenderingContext::ZERO, WebGlRenderingContext::ONE);
+            },
+            Blending::Screen(_) => {
+                context.blend_func_separate(WebGlRenderingContext::ONE_MINUS_DST_COLOR, WebGlRenderingContext::ONE, WebGlRenderingContext::ZERO, WebGlRenderingContext::ONE);
+            }
+        }
+    }
+}
+
 // TODO texture types
 
 #[derive(Clone,PartialEq,Eq,Hash)]
 #[cfg_attr(debug_assertions,derive(Debug))]
-pub(crate) enum PatinaProcessName { Direct, Texture(CanvasInUse), FreeTexture(CanvasInUse,Freedom) }
+pub(crate) enum PatinaProcessName { Direct(Blending), Texture(CanvasInUse), FreeTexture(CanvasInUse,Freedom) }
 
 impl PatinaProcessName {
     pub(super) fn get_program_name(&self) -> PatinaProgramName {
         match self {
-            PatinaProcessName::Direct => PatinaProgramName::Direct,
+            PatinaProcessName::Direct(blending) => PatinaProgramName::Direct(blending.program()),
             PatinaProcessName::Texture(_) => PatinaProgramName::Texture,
             PatinaProcessName::FreeTexture(_,_) => PatinaProgramName::FreeTexture
         }
@@ -96,17 +174,79 @@ impl PatinaProcessName {
 
     pub(crate) fn canvas_name(&self) -> Option<&CanvasInUse> {
         match self {
-            PatinaProcessName::Direct => None,
+            PatinaProcessName::Direct(_) => None,
             PatinaProcessName::Texture(c) => Some(c),
             PatinaProcessName::FreeTexture(c, _) => Some(c),
         }
     }
 
+    /* Translucent shapes go before textures as the transparent parts of text and images would
+     * otherwise write depth and hide any translucent shape behind them.
+     */
     pub(super) fn order(&self) -> usize {
         match self {
-            PatinaProcessName::Direct => 0,
-            PatinaProcessName::Texture(_) => 1,
-            PatinaProcessName::FreeTexture(_,_) => 2
+            PatinaProcessName::Direct(Blending::Opaque) => 0,
+            PatinaProcessName::Direct(_) => 1,
+            PatinaProcessName::Texture(_) => 2,
+            PatinaProcessName::FreeTexture(_,_) => 3
         }
     }
+
+    pub(super) fn depth_order(&self) -> usize {
+        self.blending().depth_order()
+    }
+
+    pub(crate) fn blending(&self) -> &Blending {
+        match self {
+            PatinaProcessName::Direct(blending) => blending,
+            _ => &Blending::Opaque
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOLID : DirectColour = DirectColour(255,0,0,255);
+    const CLEAR : DirectColour = DirectColour(0,0,255,128);
+
+    fn normal(colours: &EachOrEvery<DirectColour>, count: usize) -> Blending {
+        Blending::new(&BlendMode::Normal,colours,count,3).unwrap()
+    }
+
+    #[test]
+    fn test_blending_classified() {
+        assert_eq!(Blending::Opaque,normal(&EachOrEvery::every(SOLID),4));
+        assert_eq!(Blending::Opaque,normal(&EachOrEvery::each(vec![SOLID,SOLID]),2));
+        assert_eq!(Blending::Alpha(3),normal(&EachOrEvery::every(CLEAR),4));
+        /* one translucent colour is enough to need blending */
+        assert_eq!(Blending::Alpha(3),normal(&EachOrEvery::each(vec![SOLID,CLEAR,SOLID]),3));
+        /* blend modes always show what's beneath, whatever the colour */
+        let solid = EachOrEvery::every(SOLID);
+        assert_eq!(Blending::Multiply(-2),Blending::new(&BlendMode::Multiply,&solid,1,-2).unwrap());
+        assert_eq!(Blending::Screen(-2),Blending::new(&BlendMode::Screen,&solid,1,-2).unwrap());
+        assert!(Blending::new(&BlendMode::Normal,&EachOrEvery::each(vec![SOLID,CLEAR]),3,0).is_err());
+    }
+
+    #[test]
+    fn test_blending_order() {
+        let mut processes = vec![
+            Blending::Screen(127),
+            Blending::Alpha(0),
+            Blending::Opaque,
+            Blending::Multiply(-128),
+            Blending::Alpha(127),
+            Blending::Alpha(-128)
+        ].into_iter().map(PatinaProcessName::Direct).collect::<Vec<_>>();
+        processes.sort_by_key(|p| (p.order(),p.depth_order()));
+        let blendings = processes.iter().map(|p| p.blending().clone()).collect::<Vec<_>>();
+        assert_eq!(Blending::Opaque,blendings[0]);
+        /* then back to front, with the whole range of depths kept distinct */
+        let depths = blendings[1..].iter().map(|b| b.depth_order()).collect::<Vec<_>>();
+        assert_eq!(vec![0,0,128,255,255],depths);
+        assert_eq!(0,Blending::Alpha(-128).depth_order());
+        assert_eq!(255,Blending::Alpha(127).depth_order());
+        assert!(Blending::Alpha(-128).depth_order() < Blending::Alpha(-127).depth_order());
+    }
 }
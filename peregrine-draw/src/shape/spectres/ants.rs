@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::{Arc, Mutex}};
 use eachorevery::EachOrEvery;
-use peregrine_data::{Colour, DirectColour, DrawnType, Patina, SpaceBase, SpaceBaseArea, PartialSpaceBase, reactive::{Observable}, ProgramShapesBuilder};
+use peregrine_data::{BlendMode, Colour, DirectColour, PlainColour, DrawnType, Patina, SpaceBase, SpaceBaseArea, PartialSpaceBase, reactive::{Observable}, ProgramShapesBuilder};
 use peregrine_toolkit::{lock};
 use crate::{Message, run::{PgConfigKey, PgPeregrineConfig}, shape::{util::eoethrow::eoe_throw}};
 use super::{spectre::{AreaVariables, Spectre}, spectremanager::{SpectreConfigKey, SpectreManager}};
@@ -68,7 +68,8 @@ impl Spectre for MarchingAnts {
         let obs = SpaceBaseArea::new(top_left_obs,bottom_right_obs).unwrap();
         shapes.add_rectangle(area,Patina::Drawn(
             DrawnType::Stroke(self.width),
-            EachOrEvery::every(Colour::Bar(DirectColour(255,255,255,0),self.colour.clone(),(self.length,self.length),self.prop))
+            EachOrEvery::every(Colour::Bar(PlainColour::Direct(DirectColour(255,255,255,0)),PlainColour::Direct(self.colour.clone()),(self.length,self.length),self.prop)),
+            BlendMode::Normal
         ),Some(obs)).map_err(|x| Message::DataError(x))?;
         Ok(())
     }
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::{Arc, Mutex}};
 use eachorevery::EachOrEvery;
-use peregrine_data::{Colour, DirectColour, DrawnType, Patina, SpaceBase, SpaceBaseArea, PartialSpaceBase, reactive::{Observable}, ProgramShapesBuilder, LeafRequest, SpecialClick};
+use peregrine_data::{BlendMode, Colour, DirectColour, PlainColour, DrawnType, Patina, SpaceBase, SpaceBaseArea, PartialSpaceBase, reactive::{Observable}, ProgramShapesBuilder, LeafRequest, SpecialClick};
 use peregrine_toolkit::{lock};
 use crate::{Message, run::{PgConfigKey, PgPeregrineConfig}, shape::{util::eoethrow::eoe_throw}};
 use super::{spectre::{AreaVariables, Spectre}, spectremanager::{SpectreConfigKey, SpectreManager}};
@@ -65,7 +65,8 @@ impl Maypole {
         let obs = SpaceBaseArea::new(top_left_obs,bottom_right_obs).unwrap();
         shapes.add_rectangle(area,Patina::Drawn(
             DrawnType::Stroke(self.width),
-            EachOrEvery::every(Colour::Bar(DirectColour(255,255,255,0),self.colour.clone(),(self.length,self.length),self.prop))
+            EachOrEvery::every(Colour::Bar(PlainColour::Direct(DirectColour(255,255,255,0)),PlainColour::Direct(self.colour.clone()),(self.length,self.length),self.prop)),
+            BlendMode::Normal
         ),Some(obs)).map_err(|x| Message::DataError(x))?;
         Ok(())
     }
@@ -86,7 +87,8 @@ impl Maypole {
         ).unwrap();
         let patina = Patina::Drawn(
             DrawnType::Fill,
-            EachOrEvery::every(Colour::Direct(DirectColour(255,0,0,255))));
+            EachOrEvery::every(Colour::Direct(DirectColour(255,0,0,255))),
+            BlendMode::Normal);
         shapes.add_polygon(centre,EachOrEvery::every(7.),11,0.,patina,Some(obs)).map_err(|x| Message::DataError(x))?;
         Ok(())
     }
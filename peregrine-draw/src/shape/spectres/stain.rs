@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::{Arc, Mutex}};
 use eachorevery::EachOrEvery;
-use peregrine_data::{Colour, DirectColour, DrawnType, Patina, SpaceBase, SpaceBaseArea, PartialSpaceBase, reactive::{Observable}, ProgramShapesBuilder, LeafRequest};
+use peregrine_data::{BlendMode, Colour, DirectColour, DrawnType, Patina, SpaceBase, SpaceBaseArea, PartialSpaceBase, reactive::{Observable}, ProgramShapesBuilder, LeafRequest};
 use peregrine_toolkit::{lock};
 use crate::{Message, run::{PgConfigKey, PgPeregrineConfig}, shape::{util::eoethrow::eoe_throw}};
 use peregrine_data::reactive;
@@ -89,7 +89,7 @@ impl Spectre for Stain {
                             make_stain_rect_wobble(Some(&pos2.0),Some(&pos2.1),Some(&pos2.2),Some(&pos2.3),0.)?));
         }
         for (area,wobble) in rectangles.drain(..) {
-            shapes.add_rectangle(area,Patina::Drawn(DrawnType::Fill,EachOrEvery::every(Colour::Direct(self.colour.clone()))),Some(wobble))
+            shapes.add_rectangle(area,Patina::Drawn(DrawnType::Fill,EachOrEvery::every(Colour::Direct(self.colour.clone())),BlendMode::Normal),Some(wobble))
                 .map_err(|e| Message::DataError(e))?;
         }
         Ok(())
@@ -13,12 +13,31 @@ use crate::util::message::Message;
 struct GLCarriageData {
     data_api: PeregrineApiQueue,
     commander: PgCommanderWeb,
+    carriage: DrawingCarriage,
+    gl: Arc<Mutex<WebGlGlobal>>,
+    assets: Assets,
+    redraw_needed: Needed,
     extent: CarriageExtent,
     opacity: Mutex<f64>,
     drawing: AsyncOnce<Result<Option<Drawing>,Message>>,
+    generation: u64,
     preflight_done: bool
 }
 
+fn make_drawing(carriage: &DrawingCarriage, gl: &Arc<Mutex<WebGlGlobal>>, assets: &Assets, redraw_needed: &Needed) -> AsyncOnce<Result<Option<Drawing>,Message>> {
+    let carriage = carriage.clone();
+    let gl = gl.clone();
+    let assets = assets.clone();
+    let redraw_needed = redraw_needed.clone();
+    AsyncOnce::new(async move {
+        let scale = carriage.extent().scale();
+        let shapes = carriage.shapes().clone();
+        let drawing = Drawing::new(Some(scale),shapes,&gl,carriage.extent().left_right().0,&assets,&carriage.relevancy()).await;
+        redraw_needed.set();
+        drawing.map_err(|e| Message::DataError(DataMessage::XXXTransitional(e) ))
+    })
+}
+
 fn get_drawing(data: &GLCarriageData) -> Result<Option<Drawing>,Message> {
     let current = data.drawing.peek();
     let result = if let Some(x) = current { x } else { return Ok(None); };
@@ -42,24 +61,18 @@ pub(crate) struct GLCarriage(Arc<Mutex<GLCarriageData>>);
 
 impl GLCarriage {
     pub fn new(data_api: &PeregrineApiQueue, redraw_needed: &Needed, commander: &PgCommanderWeb, carriage: &DrawingCarriage, gl: &Arc<Mutex<WebGlGlobal>>, assets: &Assets) -> Result<GLCarriage,Message> {
-        let carriage2 = carriage.clone();
-        let gl = gl.clone();
-        let assets = assets.clone();
-        let redraw_needed = redraw_needed.clone();
         let our_carriage = GLCarriage(Arc::new(Mutex::new(GLCarriageData {
             commander: commander.clone(),
             data_api: data_api.clone(),
+            carriage: carriage.clone(),
+            gl: gl.clone(),
+            assets: assets.clone(),
+            redraw_needed: redraw_needed.clone(),
             extent: carriage.extent().clone(),
             opacity: Mutex::new(1.),
             preflight_done: false,
-            drawing: AsyncOnce::new(async move {
-                let carriage = carriage2;
-                let scale = carriage.extent().scale();
-                let shapes = carriage.shapes().clone();
-                let drawing = Drawing::new(Some(scale),shapes,&gl,carriage.extent().left_right().0,&assets,&carriage.relevancy()).await;
-                redraw_needed.set();
-                drawing.map_err(|e| Message::DataError(DataMessage::XXXTransitional(e) ))
-            })
+            generation: 0,
+            drawing: make_drawing(carriage,gl,assets,redraw_needed)
         })));
         our_carriage.preflight_freewheel(carriage);
         Ok(our_carriage)
@@ -89,6 +102,32 @@ impl GLCarriage {
         }));
     }
 
+    /* Rebuild the drawing from the shapes we already have, eg after a theme change. The old
+     * drawing is shown until the new one is ready, and kept if the new one fails. Should redraws
+     * overlap, only the latest is kept.
+     */
+    pub(super) fn redraw(&self) {
+        let mut state = lock!(self.0);
+        state.generation += 1;
+        let generation = state.generation;
+        let drawing = make_drawing(&state.carriage,&state.gl,&state.assets,&state.redraw_needed);
+        let commander = state.commander.clone();
+        drop(state);
+        let self2 = self.clone();
+        commander.add::<Message>("redraw", 2, None, None, Box::pin(async move {
+            if let Err(e) = drawing.get().await {
+                error!("{}",e);
+                return Ok(());
+            }
+            let mut state = lock!(self2.0);
+            if state.generation == generation {
+                state.drawing = drawing;
+                state.redraw_needed.set();
+            }
+            Ok(())
+        }));
+    }
+
     pub fn extent(&self) -> CarriageExtent { lock!(self.0).extent.clone() }
 
     pub(super) fn set_opacity(&self, amount: f64) {
@@ -101,6 +101,12 @@ impl GlRailwayData {
         Ok(())
     }
 
+    fn redraw_carriages(&mut self) {
+        for carriage in self.carriages.values() {
+            carriage.redraw();
+        }
+    }
+
     fn drop_carriage(&mut self, carriage: &DrawingCarriage) { 
         self.carriages.remove(carriage);
     }
@@ -280,6 +286,7 @@ impl GlRailway {
     }
 
     pub(crate) fn drop_carriage(&mut self, carriage: &DrawingCarriage) { lock!(self.data).drop_carriage(carriage); }
+    pub(crate) fn redraw_carriages(&mut self) { lock!(self.data).redraw_carriages(); }
 
     pub fn transition_animate_tick(&mut self, api: &PeregrineCore, gl: &mut WebGlGlobal, newly_elapsed: f64) -> Result<(),Message> {
         if lock!(self.data).transition_animate_tick(gl,newly_elapsed)? {
@@ -1,5 +1,5 @@
 use peregrine_toolkit::error::Error;
-use crate::{webgl::{GPUSpec, canvas::composition::{areabuilder::CanvasItemAreaBuilder, packer::{allocate_areas, allocate_linear}}}};
+use crate::{webgl::{GPUSpec, canvas::composition::{areabuilder::CanvasItemAreaBuilder, packer::{allocate_areas, allocate_linear, PackingMetric}}}};
 
 #[allow(dead_code)]
 #[derive(Clone,PartialEq,Eq,Hash,Debug)]
@@ -12,11 +12,11 @@ pub(crate) enum CanvasWeave {
 }
 
 impl CanvasWeave {
-    pub(crate) fn tessellate(&self, items: &mut [&mut CanvasItemAreaBuilder], gpu_spec: &GPUSpec) -> Result<Option<(u32,u32)>,Error> {
+    pub(crate) fn tessellate(&self, items: &mut [&mut CanvasItemAreaBuilder], gpu_spec: &GPUSpec, metric: &mut PackingMetric) -> Result<Option<(u32,u32)>,Error> {
         Ok(match self {
             CanvasWeave::HorizStack => allocate_linear(items,gpu_spec,true)?,
             CanvasWeave::VertStack => allocate_linear(items,gpu_spec,false)?,
-            _ =>  allocate_areas(items,gpu_spec)?
+            _ =>  allocate_areas(items,gpu_spec,metric)?
         })
     }
 
@@ -60,7 +60,8 @@ impl CompositionBuilder {
         for handle in &mut self.texts {
             items.push(handle.in_progress.as_mut().unwrap());
         }
-        let (width,height) = ubail!(self.weave.tessellate(&mut items,gl.gpu_spec())?,Ok(None));
+        let refs = gl.refs();
+        let (width,height) = ubail!(self.weave.tessellate(&mut items,refs.gpuspec,refs.packing)?,Ok(None));
         let canvas_id = gl.canvas_source().make(&self.weave,(width,height))?;
         let (force_width,force_height) = self.weave.force_size(width,height);
         for item in items {
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use peregrine_toolkit::{error::Error};
+use peregrine_toolkit::packing::maxrects::{ MaxRects, PackingStats };
 use crate::webgl::GPUSpec;
 
 use super::areabuilder::CanvasItemAreaBuilder;
@@ -124,8 +125,6 @@ impl Bin {
     fn height(&self) -> u32 { self.height_watermark }
 }
 
-// TODO test this algorithm
-
 fn tallest_first(sizes: &[(u32,u32)]) -> Vec<usize> {
     let mut sorted = sizes.iter().enumerate().collect::<Vec<_>>();
     sorted.sort_by_key(|(_,(w,h))| (*h,*w));
@@ -149,26 +148,153 @@ fn attempt_at_width(order: &[usize], sizes: &[(u32,u32)], texture_width: u64) ->
     (out,bin.height())
 }
 
-pub(crate) fn allocate_areas(items: &mut [&mut CanvasItemAreaBuilder], gpu_spec: &GPUSpec) -> Result<Option<(u32,u32)>,Error> {
-    let sizes = items.iter().map(|x| x.size()).collect::<Vec<_>>();
+/* Shelves are fast but waste space when sizes vary a lot (eg dense text in many lengths) and
+ * sometimes then need a texture wider than the GPU allows. In those cases MaxRects can do better
+ * but is O(n^2)-ish, so we only try it where shelf packing has done badly and there aren't too
+ * many items.
+ */
+const MIN_SHELF_OCCUPANCY : f64 = 0.5;
+const MAXRECTS_LIMIT : usize = 1000;
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum PackingStrategy {
+    Shelf,
+    MaxRects
+}
+
+fn attempt_maxrects_at_width(order: &[usize], sizes: &[(u32,u32)], texture_width: u64, max_size: u64) -> Option<(Vec<(u32,u32)>,u32)> {
+    let mut out = vec![(0,0);order.len()];
+    let mut bin = MaxRects::new(texture_width as u32,max_size as u32);
+    let mut height = 0;
+    for index in order {
+        let area = &sizes[*index];
+        let origin = bin.allocate(area.0+1,area.1+1)?;
+        height = height.max(origin.1+area.1+1);
+        out[*index] = origin;
+    }
+    Some((out,height))
+}
+
+fn padded_area(sizes: &[(u32,u32)]) -> u64 {
+    sizes.iter().map(|(w,h)| (*w as u64+1)*(*h as u64+1)).sum()
+}
+
+fn layout_stats(size: (u32,u32), sizes: &[(u32,u32)], origins: &[(u32,u32)]) -> PackingStats {
+    let mut bin = MaxRects::new(size.0,size.1);
+    for (origin,area) in origins.iter().zip(sizes.iter()) {
+        bin.reserve(*origin,(area.0+1,area.1+1));
+    }
+    bin.stats()
+}
+
+/* Replaying a layout into MaxRects is as slow as packing with it, so for very many items we only
+ * count the free strip below the lowest item, which is a lower bound on the largest free area.
+ */
+fn packing_stats(size: (u32,u32), sizes: &[(u32,u32)], origins: &[(u32,u32)]) -> PackingStats {
+    if sizes.len() <= MAXRECTS_LIMIT {
+        return layout_stats(size,sizes,origins);
+    }
+    let bottom = origins.iter().zip(sizes.iter()).map(|(origin,area)| origin.1+area.1+1).max().unwrap_or(0);
+    PackingStats {
+        size,
+        used_area: padded_area(sizes),
+        largest_free_area: size.0 as u64 * size.1.saturating_sub(bottom) as u64
+    }
+}
+
+/* Stats of compositions packed since last taken, for the gb-packing metric */
+pub(crate) struct PackingMetric {
+    compositions: usize,
+    maxrects: usize,
+    used_area: u64,
+    total_area: u64,
+    fragmentation: f64
+}
+
+impl PackingMetric {
+    pub(crate) fn new() -> PackingMetric {
+        PackingMetric { compositions: 0, maxrects: 0, used_area: 0, total_area: 0, fragmentation: 0. }
+    }
+
+    fn add(&mut self, strategy: PackingStrategy, stats: &PackingStats) {
+        self.compositions += 1;
+        if strategy == PackingStrategy::MaxRects { self.maxrects += 1; }
+        self.used_area += stats.used_area;
+        self.total_area += stats.total_area();
+        self.fragmentation += stats.fragmentation();
+    }
+
+    pub(crate) fn take(&mut self) -> Option<Vec<(String,f64)>> {
+        if self.compositions == 0 { return None; }
+        let out = vec![
+            ("compositions".to_string(),self.compositions as f64),
+            ("maxrects".to_string(),self.maxrects as f64),
+            ("occupancy".to_string(),self.used_area as f64 / self.total_area.max(1) as f64),
+            ("fragmentation".to_string(),self.fragmentation / self.compositions as f64),
+            ("texture-area".to_string(),self.total_area as f64)
+        ];
+        *self = PackingMetric::new();
+        Some(out)
+    }
+}
+
+#[cfg(debug_canvasstore)]
+fn report(strategy: PackingStrategy, size: (u32,u32), sizes: &[(u32,u32)], stats: &PackingStats) {
+    use peregrine_toolkit::log;
+
+    log!("packed {} items into {}x{} by {:?}: {:.0}% occupied, {:.0}% of free space fragmented",
+        sizes.len(),size.0,size.1,strategy,stats.occupancy()*100.,stats.fragmentation()*100.);
+}
+
+#[cfg(not(debug_canvasstore))]
+fn report(_strategy: PackingStrategy, _size: (u32,u32), _sizes: &[(u32,u32)], _stats: &PackingStats) {}
+
+/* returns texture size and origins (1-to-1 with sizes) and strategy used */
+fn pack_areas(sizes: &[(u32,u32)], max_size: u64) -> Result<Option<((u32,u32),Vec<(u32,u32)>,PackingStrategy)>,Error> {
     if sizes.len() == 0 { return Ok(None); }
-    let order = tallest_first(&sizes);
-    let max_size = gpu_spec.max_texture_size() as u64;
-    let mut texture_width = initial_width(&sizes);
-    loop {
-        if texture_width > max_size {
-            return Err(Error::fatal("cannot pack rectangles: all attempts failed"));
-        }
-        let (out,texture_height) = attempt_at_width(&order,&sizes,texture_width);
+    let order = tallest_first(sizes);
+    let used_area = padded_area(sizes);
+    let mut texture_width = initial_width(sizes);
+    while texture_width <= max_size {
+        let (out,texture_height) = attempt_at_width(&order,sizes,texture_width);
         let texture_height = texture_height.next_power_of_two() as u64;
+        let mut best = None;
         if texture_height <= max_size {
-            for (i,origin) in out.iter().enumerate() {
-                items[i].set_origin(*origin);
+            let occupancy = used_area as f64 / (texture_width * texture_height) as f64;
+            if occupancy >= MIN_SHELF_OCCUPANCY || sizes.len() > MAXRECTS_LIMIT {
+                return Ok(Some(((texture_width as u32,texture_height as u32),out,PackingStrategy::Shelf)));
+            }
+            best = Some((out,texture_height,PackingStrategy::Shelf));
+        }
+        if sizes.len() <= MAXRECTS_LIMIT {
+            if let Some((out,height)) = attempt_maxrects_at_width(&order,sizes,texture_width,max_size) {
+                let height = (height as u64).next_power_of_two();
+                let better = best.as_ref().map(|(_,best_height,_)| height < *best_height).unwrap_or(true);
+                if height <= max_size && better {
+                    best = Some((out,height,PackingStrategy::MaxRects));
+                }
             }
-            return Ok(Some((texture_width as u32,texture_height as u32)));
+        }
+        if let Some((out,height,strategy)) = best {
+            return Ok(Some(((texture_width as u32,height as u32),out,strategy)));
         }
         texture_width *= 2;
     }
+    Err(Error::fatal("cannot pack rectangles: all attempts failed"))
+}
+
+pub(crate) fn allocate_areas(items: &mut [&mut CanvasItemAreaBuilder], gpu_spec: &GPUSpec, metric: &mut PackingMetric) -> Result<Option<(u32,u32)>,Error> {
+    let sizes = items.iter().map(|x| x.size()).collect::<Vec<_>>();
+    let (size,origins,strategy) = if let Some(packed) = pack_areas(&sizes,gpu_spec.max_texture_size() as u64)? { packed } else {
+        return Ok(None);
+    };
+    for (i,origin) in origins.iter().enumerate() {
+        items[i].set_origin(*origin);
+    }
+    let stats = packing_stats(size,&sizes,&origins);
+    report(strategy,size,&sizes,&stats);
+    metric.add(strategy,&stats);
+    Ok(Some(size))
 }
 
 pub(crate) fn allocate_linear(items: &mut [&mut CanvasItemAreaBuilder], gpu_spec: &GPUSpec, horizontal: bool) -> Result<Option<(u32,u32)>,Error> {
@@ -192,3 +318,71 @@ pub(crate) fn allocate_linear(items: &mut [&mut CanvasItemAreaBuilder], gpu_spec
     size.1 = size.1.next_power_of_two();
     Ok(Some(size))
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use super::*;
+
+    fn disjoint(sizes: &[(u32,u32)], origins: &[(u32,u32)]) -> bool {
+        for i in 0..sizes.len() {
+            for j in (i+1)..sizes.len() {
+                let (a,b) = ((origins[i],sizes[i]),(origins[j],sizes[j]));
+                if a.0.0 < b.0.0+b.1.0 && b.0.0 < a.0.0+a.1.0 && a.0.1 < b.0.1+b.1.1 && b.0.1 < a.0.1+a.1.1 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_shelf() {
+        let sizes = vec![(10,10);16];
+        let (size,origins,strategy) = pack_areas(&sizes,4096).unwrap().unwrap();
+        assert_eq!(PackingStrategy::Shelf,strategy);
+        assert_eq!((64,64),size);
+        assert!(disjoint(&sizes,&origins));
+        assert_eq!(padded_area(&sizes),layout_stats(size,&sizes,&origins).used_area);
+    }
+
+    #[test]
+    fn test_varied_sizes() {
+        /* one tall item then lots of short wide ones: shelves waste the space beside the tall one */
+        let mut sizes = vec![(8,200)];
+        for i in 0..40 {
+            sizes.push((20+(i*7)%90,6+(i%3)));
+        }
+        let (size,origins,strategy) = pack_areas(&sizes,4096).unwrap().unwrap();
+        assert_eq!(PackingStrategy::MaxRects,strategy);
+        assert!(disjoint(&sizes,&origins));
+        for (origin,area) in origins.iter().zip(sizes.iter()) {
+            assert!(origin.0+area.0 < size.0 && origin.1+area.1 < size.1);
+        }
+    }
+
+    #[test]
+    fn test_metric() {
+        let mut metric = PackingMetric::new();
+        assert!(metric.take().is_none());
+        let sizes = vec![(10,10);16];
+        let (size,origins,strategy) = pack_areas(&sizes,4096).unwrap().unwrap();
+        metric.add(strategy,&packing_stats(size,&sizes,&origins));
+        let values = metric.take().unwrap().into_iter().collect::<HashMap<_,_>>();
+        assert_eq!(1.,values["compositions"]);
+        assert_eq!(0.,values["maxrects"]);
+        assert_eq!(padded_area(&sizes) as f64/4096.,values["occupancy"]);
+        assert!(metric.take().is_none());
+        /* too many to replay: only the strip below is known to be free */
+        let sizes = vec![(1,1);MAXRECTS_LIMIT+1];
+        let origins = (0..sizes.len()).map(|i| ((i as u32%32)*2,(i as u32/32)*2)).collect::<Vec<_>>();
+        let stats = packing_stats((64,128),&sizes,&origins);
+        assert_eq!(64*(128-64),stats.largest_free_area);
+    }
+
+    #[test]
+    fn test_too_big() {
+        assert!(pack_areas(&[(100,10)],64).is_err());
+        assert!(pack_areas(&[],64).unwrap().is_none());
+    }
+}
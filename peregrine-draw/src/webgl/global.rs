@@ -2,8 +2,9 @@ use crate::{run::{ PgPeregrineConfig }, shape::{layers::programstore::ProgramSto
 pub use url::Url;
 pub use web_sys::{ console, WebGlRenderingContext };
 use crate::util::message::Message;
+use peregrine_data::Palette;
 use wasm_bindgen::JsCast;
-use super::{GPUSpec, glbufferstore::GLBufferStore, canvas::{binding::texturebinding::TextureBinding, htmlcanvas::{canvassource::CanvasSource, scratchcanvases::ScratchCanvasAllocator}}};
+use super::{GPUSpec, glbufferstore::GLBufferStore, canvas::{binding::texturebinding::TextureBinding, composition::packer::PackingMetric, htmlcanvas::{canvassource::CanvasSource, scratchcanvases::ScratchCanvasAllocator}}};
 
 pub struct WebGlGlobal {
     program_store: ProgramStore,
@@ -16,7 +17,9 @@ pub struct WebGlGlobal {
     gpuspec: GPUSpec,
     fonts: Fonts,
     dpr: f32,
-    buffer_store: GLBufferStore
+    buffer_store: GLBufferStore,
+    palette: Palette,
+    packing: PackingMetric
 }
 
 pub(crate) struct WebGlGlobalRefs<'a> {
@@ -29,7 +32,9 @@ pub(crate) struct WebGlGlobalRefs<'a> {
     pub canvas_size: &'a mut Option<(u32,u32)>,
     pub gpuspec: &'a GPUSpec,
     pub fonts: &'a Fonts,
-    pub buffer_store: &'a GLBufferStore
+    pub buffer_store: &'a GLBufferStore,
+    pub palette: &'a Palette,
+    pub packing: &'a mut PackingMetric
 }
 
 impl WebGlGlobal {
@@ -56,13 +61,16 @@ impl WebGlGlobal {
             gpuspec,
             fonts,
             dpr: dom.device_pixel_ratio(),
-            buffer_store: GLBufferStore::new(&context)
+            buffer_store: GLBufferStore::new(&context),
+            palette: Palette::new(),
+            packing: PackingMetric::new()
         })
     }
 
     pub fn device_pixel_ratio(&self) -> f32 { self.dpr }
     pub(crate) fn gpu_spec(&self) -> &GPUSpec { &self.gpuspec }
     pub fn canvas_source(&self) -> &CanvasSource { &self.canvas_source }
+    pub(crate) fn palette_mut(&mut self) -> &mut Palette { &mut self.palette }
 
     pub(crate) fn refs<'a>(&'a mut self) -> WebGlGlobalRefs<'a> {
         WebGlGlobalRefs {
@@ -75,7 +83,9 @@ impl WebGlGlobal {
             canvas_size: &mut self.canvas_size,
             gpuspec: &self.gpuspec,
             fonts: &self.fonts,
-            buffer_store: &self.buffer_store 
+            buffer_store: &self.buffer_store,
+            palette: &self.palette,
+            packing: &mut self.packing
         }
     }
 }
@@ -124,6 +124,7 @@ impl Process {
         let program_stage = self.program_stage.clone();
         program_stage.apply(stage,self.left,opacity,dpr,self).map_err(|e| Error::fatal(&format!("XXX transition {:?}",e)))?;
         self.program.select_program(gl.context)?;
+        self.character.1.blending().apply(gl.context);
         for stanza in self.stanzas.iter() {
             stanza.activate()?;
             for entry in self.textures.values_mut() {
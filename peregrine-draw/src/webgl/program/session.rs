@@ -93,8 +93,11 @@ impl DrawingSession {
         Ok(())
     }
 
-    pub(crate) fn finish(&self, core: &PeregrineCore) -> Result<(),Message> {
+    pub(crate) fn finish(&self, gl: &mut WebGlGlobal, core: &PeregrineCore) -> Result<(),Message> {
         self.metric.send_metric(core);
+        if let Some(values) = gl.refs().packing.take() {
+            core.general_metric("gb-packing",vec![],values);
+        }
         Ok(())
     }
 }
@@ -0,0 +1,65 @@
+use peregrine_data::ReceivedData;
+use peregrine_toolkit::error::Error;
+use crate::column::EncodedColumn;
+
+/* Packed is a bitset, see peregrine-data's dataalgorithm.rs */
+#[derive(Clone,Debug,PartialEq)]
+pub enum BooleanEncoding {
+    Array,
+    Bytes,
+    Packed
+}
+
+fn pack(values: &[bool]) -> Vec<u8> {
+    let mut out = vec![((8 - values.len()%8) % 8) as u8];
+    for chunk in values.chunks(8) {
+        out.push(chunk.iter().enumerate().fold(0,|acc,(i,v)| if *v { acc | (1<<i) } else { acc }));
+    }
+    out
+}
+
+pub fn encode_booleans(values: &[bool], encoding: &BooleanEncoding) -> Result<EncodedColumn,Error> {
+    let mut out = EncodedColumn::new();
+    out.push_code('B');
+    match encoding {
+        BooleanEncoding::Array => {
+            out.push_code('A');
+            out.push_data(ReceivedData::new_booleans(values.to_vec()));
+        },
+        BooleanEncoding::Bytes => {
+            out.push_code('B');
+            out.push_data(ReceivedData::new_bytes(values.iter().map(|v| if *v { 1 } else { 0 }).collect()));
+        },
+        BooleanEncoding::Packed => {
+            out.push_code('P');
+            out.push_data(ReceivedData::new_bytes(pack(values)));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peregrine_toolkit::lcg::Lcg;
+    use crate::testutil::decode;
+
+    #[test]
+    fn test_booleans_round_trip() {
+        let mut random = Lcg::new(2);
+        for _ in 0..100 {
+            let values = (0..random.below(60)).map(|_| random.below(2) == 1).collect::<Vec<_>>();
+            for encoding in &[BooleanEncoding::Array,BooleanEncoding::Bytes,BooleanEncoding::Packed] {
+                let column = encode_booleans(&values,encoding).expect("encode");
+                assert_eq!(values,*decode(&column).data_as_booleans().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(vec![0],pack(&[]));
+        assert_eq!(vec![7,1],pack(&[true]));
+        assert_eq!(vec![0,0b10000001],pack(&[true,false,false,false,false,false,false,true]));
+    }
+}
@@ -0,0 +1,65 @@
+use peregrine_data::ReceivedData;
+use serde::{ Serialize, Serializer };
+use serde::ser::SerializeSeq;
+
+/* The algorithm code and its data, in the order the decoder consumes them. */
+pub struct EncodedColumn {
+    code: String,
+    data: Vec<ReceivedData>
+}
+
+impl EncodedColumn {
+    pub(crate) fn new() -> EncodedColumn {
+        EncodedColumn { code: String::new(), data: vec![] }
+    }
+
+    pub(crate) fn push_code(&mut self, code: char) { self.code.push(code); }
+    pub(crate) fn push_data(&mut self, data: ReceivedData) { self.data.push(data); }
+
+    pub fn code(&self) -> &str { &self.code }
+    pub fn data(&self) -> &[ReceivedData] { &self.data }
+}
+
+pub fn encode_empty() -> EncodedColumn {
+    let mut out = EncodedColumn::new();
+    out.push_code('E');
+    out
+}
+
+struct SerializeReceivedData<'a>(&'a ReceivedData);
+
+impl<'a> Serialize for SerializeReceivedData<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        match self.0 {
+            ReceivedData::Bytes(x) => serializer.serialize_bytes(x),
+            ReceivedData::Booleans(x) => x.serialize(serializer),
+            ReceivedData::Numbers(x) => x.serialize(serializer),
+            ReceivedData::Strings(x) => x.serialize(serializer),
+            ReceivedData::Empty => serializer.serialize_unit()
+        }
+    }
+}
+
+impl Serialize for EncodedColumn {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut seq = serializer.serialize_seq(Some(self.data.len()+1))?;
+        seq.serialize_element(&self.code)?;
+        for data in &self.data {
+            seq.serialize_element(&SerializeReceivedData(data))?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil::decode;
+
+    #[test]
+    fn test_empty() {
+        let column = encode_empty();
+        assert_eq!("E",column.code());
+        assert_eq!(0,decode(&column).len());
+    }
+}
@@ -0,0 +1,22 @@
+/* peregrine-encoder is the inverse of the DataAlgorithm decoder in peregrine-data. It takes columns
+ * of numbers, strings and booleans and, given a choice of encoding, produces the algorithm code and
+ * the data which the decoder expects. An EncodedColumn serializes to exactly the wire format, so it
+ * can be used to write data producers in rust as well as to test the decoder.
+ *
+ * The best_* functions choose the smallest encoding for a column automatically.
+ */
+
+mod booleans;
+mod column;
+mod numbers;
+mod optimise;
+mod strings;
+
+#[cfg(test)]
+mod testutil;
+
+pub use booleans::{ BooleanEncoding, encode_booleans };
+pub use column::{ EncodedColumn, encode_empty };
+pub use numbers::{ NumberEncoding, NumberSource, encode_numbers };
+pub use optimise::{ EncodingChoice, best_booleans, best_numbers, best_strings };
+pub use strings::{ StringEncoding, encode_strings };
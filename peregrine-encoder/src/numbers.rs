@@ -0,0 +1,198 @@
+use peregrine_data::ReceivedData;
+use peregrine_toolkit::error::Error;
+use peregrine_toolkit::lesqlite2::lesqlite2_encode;
+use crate::column::EncodedColumn;
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum NumberSource {
+    Array,
+    Lesqlite2
+}
+
+/* Transforms are applied outermost first, so Delta(Zigzag(Raw(Lesqlite2))) is the usual choice for
+ * sorted positions. RunLength encodes the value of each run with the first encoding and the length
+ * with the second, followed by the total number of values.
+ */
+#[derive(Clone,Debug,PartialEq)]
+pub enum NumberEncoding {
+    Raw(NumberSource),
+    Zigzag(Box<NumberEncoding>),
+    Delta(Box<NumberEncoding>),
+    RunLength(Box<NumberEncoding>,Box<NumberEncoding>)
+}
+
+fn is_integer(value: f64) -> bool { value.is_finite() && value.fract() == 0. && value.abs() < (1_u64<<53) as f64 }
+
+fn zigzag(values: &[f64]) -> Result<Vec<f64>,Error> {
+    values.iter().map(|v| {
+        if !is_integer(*v) {
+            return Err(Error::operr(&format!("cannot zigzag non-integer {}",v)));
+        }
+        Ok(if *v < 0. { -2.*v-1. } else { 2.*v })
+    }).collect()
+}
+
+/* The decoder sums in floating point so make sure the differences sum back exactly */
+fn delta(values: &[f64]) -> Result<Vec<f64>,Error> {
+    let mut prev = 0.;
+    let mut out = vec![];
+    for value in values {
+        let diff = value - prev;
+        if prev + diff != *value {
+            return Err(Error::operr(&format!("cannot delta encode {} exactly",value)));
+        }
+        out.push(diff);
+        prev = *value;
+    }
+    Ok(out)
+}
+
+/* NaN != NaN, so compare bits to keep runs of missing values together */
+fn runs(values: &[f64]) -> (Vec<f64>,Vec<f64>) {
+    let mut run_values : Vec<f64> = vec![];
+    let mut run_lengths = vec![];
+    for value in values {
+        if run_values.last().map(|v| v.to_bits() == value.to_bits()).unwrap_or(false) {
+            *run_lengths.last_mut().unwrap() += 1.;
+        } else {
+            run_values.push(*value);
+            run_lengths.push(1.);
+        }
+    }
+    (run_values,run_lengths)
+}
+
+impl NumberEncoding {
+    pub(crate) fn encode(&self, values: &[f64], out: &mut EncodedColumn) -> Result<(),Error> {
+        match self {
+            NumberEncoding::Raw(NumberSource::Array) => {
+                out.push_code('R');
+                out.push_code('A');
+                out.push_data(ReceivedData::new_numbers(values.to_vec()));
+            },
+            NumberEncoding::Raw(NumberSource::Lesqlite2) => {
+                out.push_code('R');
+                out.push_code('L');
+                let bytes = lesqlite2_encode(values)?;
+                out.push_data(ReceivedData::new_bytes(bytes));
+            },
+            NumberEncoding::Zigzag(inner) => {
+                out.push_code('Z');
+                inner.encode(&zigzag(values)?,out)?;
+            },
+            NumberEncoding::Delta(inner) => {
+                out.push_code('D');
+                inner.encode(&delta(values)?,out)?;
+            },
+            NumberEncoding::RunLength(values_encoding,lengths_encoding) => {
+                out.push_code('U');
+                let (run_values,run_lengths) = runs(values);
+                values_encoding.encode(&run_values,out)?;
+                lengths_encoding.encode(&run_lengths,out)?;
+                out.push_data(ReceivedData::new_numbers(vec![values.len() as f64]));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn encode_numbers(values: &[f64], encoding: &NumberEncoding) -> Result<EncodedColumn,Error> {
+    let mut out = EncodedColumn::new();
+    out.push_code('N');
+    encoding.encode(values,&mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peregrine_toolkit::lcg::Lcg;
+    use crate::testutil::decode;
+
+    /* lesqlite2 needs everything non-negative, so always zigzag last */
+    fn all_encodings() -> Vec<NumberEncoding> {
+        let array = NumberEncoding::Raw(NumberSource::Array);
+        let lesqlite2 = NumberEncoding::Raw(NumberSource::Lesqlite2);
+        let zigzag = |x: &NumberEncoding| NumberEncoding::Zigzag(Box::new(x.clone()));
+        let delta = |x: &NumberEncoding| NumberEncoding::Delta(Box::new(x.clone()));
+        let run_length = NumberEncoding::RunLength(Box::new(zigzag(&lesqlite2)),Box::new(lesqlite2.clone()));
+        vec![
+            array.clone(), zigzag(&array), delta(&array), delta(&zigzag(&array)),
+            zigzag(&lesqlite2), delta(&zigzag(&lesqlite2)), run_length
+        ]
+    }
+
+    #[test]
+    fn test_codes() {
+        let encoding = NumberEncoding::Delta(Box::new(NumberEncoding::Zigzag(Box::new(NumberEncoding::Raw(NumberSource::Lesqlite2)))));
+        assert_eq!("NDZRL",encode_numbers(&[1.,2.],&encoding).unwrap().code());
+        assert_eq!("NRA",encode_numbers(&[1.5],&NumberEncoding::Raw(NumberSource::Array)).unwrap().code());
+    }
+
+    #[test]
+    fn test_numbers_round_trip() {
+        let mut random = Lcg::new(1);
+        for _ in 0..200 {
+            let len = random.below(40);
+            let values = (0..len).map(|_| {
+                let v = random.integer(40) as f64;
+                if random.below(2) == 0 { -v } else { v }
+            }).collect::<Vec<_>>();
+            for encoding in all_encodings() {
+                let column = encode_numbers(&values,&encoding).expect("encode");
+                assert_eq!(values,*decode(&column).data_as_numbers().unwrap(),"{:?}",encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_length() {
+        let (values,lengths) = runs(&[1.,1.,1.,2.,1.,1.]);
+        assert_eq!(vec![1.,2.,1.],values);
+        assert_eq!(vec![3.,1.,2.],lengths);
+        let mut random = Lcg::new(6);
+        let encoding = NumberEncoding::RunLength(Box::new(NumberEncoding::Raw(NumberSource::Array)),Box::new(NumberEncoding::Raw(NumberSource::Lesqlite2)));
+        for _ in 0..100 {
+            let mut values = vec![];
+            for _ in 0..random.below(20) {
+                let value = random.integer(8) as f64 / 4.;
+                values.extend(std::iter::repeat(value).take(random.below(30)));
+            }
+            let column = encode_numbers(&values,&encoding).expect("encode");
+            assert_eq!("NURARL",column.code());
+            assert_eq!(values,*decode(&column).data_as_numbers().unwrap());
+        }
+    }
+
+    fn run_length_column(lengths: &[f64], total: f64) -> EncodedColumn {
+        let mut out = EncodedColumn::new();
+        for code in "NURARA".chars() { out.push_code(code); }
+        out.push_data(ReceivedData::new_numbers(vec![5.;lengths.len()]));
+        out.push_data(ReceivedData::new_numbers(lengths.to_vec()));
+        out.push_data(ReceivedData::new_numbers(vec![total]));
+        out
+    }
+
+    #[test]
+    fn test_run_length_total() {
+        let decode_result = |column: &EncodedColumn| {
+            let bytes = serde_cbor::to_vec(column).unwrap();
+            let algorithm : peregrine_data::DataAlgorithm = serde_cbor::from_slice(&bytes).unwrap();
+            algorithm.to_received_data().map(|x| x.data_as_numbers().unwrap().to_vec())
+        };
+        assert_eq!(Ok(vec![5.,5.,5.]),decode_result(&run_length_column(&[2.,1.],3.)));
+        /* a corrupt run must not be believed */
+        assert!(decode_result(&run_length_column(&[2.,1e15],3.)).is_err());
+        assert!(decode_result(&run_length_column(&[2.,1.],4.)).is_err());
+    }
+
+    #[test]
+    fn test_numbers_bad() {
+        let lesqlite2 = NumberEncoding::Raw(NumberSource::Lesqlite2);
+        assert!(encode_numbers(&[-1.],&lesqlite2).is_err());
+        assert!(encode_numbers(&[0.5],&NumberEncoding::Zigzag(Box::new(lesqlite2))).is_err());
+        let values = [0.25,-7.5,1e10];
+        let column = encode_numbers(&values,&NumberEncoding::Raw(NumberSource::Array)).unwrap();
+        assert_eq!(values.to_vec(),*decode(&column).data_as_numbers().unwrap());
+    }
+}
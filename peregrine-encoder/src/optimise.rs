@@ -0,0 +1,177 @@
+use std::fmt;
+use peregrine_toolkit::error::Error;
+use crate::booleans::{ BooleanEncoding, encode_booleans };
+use crate::column::EncodedColumn;
+use crate::numbers::{ NumberEncoding, NumberSource, encode_numbers };
+use crate::strings::{ StringEncoding, encode_strings };
+
+/* The optimiser simply tries every sensible encoding and keeps the one with the smallest serialized
+ * form. Encodings which can't represent the column (eg lesqlite2 of negative numbers) are skipped.
+ * Sizes are of the CBOR serialization, before any compression of the whole response.
+ */
+
+pub struct EncodingChoice {
+    column: EncodedColumn,
+    size: usize,
+    baseline: usize
+}
+
+impl EncodingChoice {
+    pub fn column(&self) -> &EncodedColumn { &self.column }
+    pub fn into_column(self) -> EncodedColumn { self.column }
+    pub fn size(&self) -> usize { self.size }
+
+    /* size as a plain array, ie with no encoding at all */
+    pub fn baseline_size(&self) -> usize { self.baseline }
+    pub fn saving(&self) -> usize { self.baseline.saturating_sub(self.size) }
+
+    pub fn saving_ratio(&self) -> f64 {
+        if self.baseline == 0 { 0. } else { self.saving() as f64 / self.baseline as f64 }
+    }
+}
+
+impl fmt::Display for EncodingChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,"{}: {} bytes (array {} bytes, saved {:.0}%)",self.column.code(),self.size,self.baseline,self.saving_ratio()*100.)
+    }
+}
+
+pub(crate) fn serialized_size(column: &EncodedColumn) -> Result<usize,Error> {
+    Ok(Error::oper_r(serde_cbor::to_vec(column),"cannot serialize column")?.len())
+}
+
+fn choose<F>(candidates: Vec<F>, baseline: Result<EncodedColumn,Error>) -> Result<EncodingChoice,Error>
+        where F: FnOnce() -> Result<EncodedColumn,Error> {
+    let baseline = baseline?;
+    let baseline_size = serialized_size(&baseline)?;
+    let mut best = EncodingChoice { column: baseline, size: baseline_size, baseline: baseline_size };
+    for candidate in candidates {
+        if let Ok(column) = candidate() {
+            let size = serialized_size(&column)?;
+            if size < best.size {
+                best = EncodingChoice { column, size, baseline: baseline_size };
+            }
+        }
+    }
+    Ok(best)
+}
+
+/* Up to two transforms over each source. Two deltas suit evenly spaced positions. */
+pub(crate) fn number_candidates() -> Vec<NumberEncoding> {
+    let transforms : &[&[char]] = &[&[],&['Z'],&['D'],&['D','Z'],&['Z','D'],&['D','D'],&['D','D','Z']];
+    let mut out = vec![];
+    for source in &[NumberSource::Array,NumberSource::Lesqlite2] {
+        for transform in transforms {
+            let mut encoding = NumberEncoding::Raw(source.clone());
+            for t in transform.iter().rev() {
+                encoding = match t {
+                    'Z' => NumberEncoding::Zigzag(Box::new(encoding)),
+                    _ => NumberEncoding::Delta(Box::new(encoding))
+                };
+            }
+            out.push(encoding);
+        }
+    }
+    out
+}
+
+/* Runs are only worth trying at the top level: lengths are always positive integers */
+fn run_length_candidates() -> Vec<NumberEncoding> {
+    let lengths = NumberEncoding::Raw(NumberSource::Lesqlite2);
+    number_candidates().into_iter().map(|values| {
+        NumberEncoding::RunLength(Box::new(values),Box::new(lengths.clone()))
+    }).collect()
+}
+
+fn string_candidates() -> Vec<StringEncoding> {
+    let simple = vec![StringEncoding::Array,StringEncoding::CharacterSplit,StringEncoding::ZeroSplit];
+    let mut out = simple.clone();
+    for index in number_candidates() {
+        for keys in &simple {
+            out.push(StringEncoding::Classify(index.clone(),Box::new(keys.clone())));
+        }
+    }
+    out
+}
+
+pub fn best_numbers(values: &[f64]) -> Result<EncodingChoice,Error> {
+    let candidates = number_candidates().into_iter().chain(run_length_candidates().into_iter()).map(|encoding| {
+        move || encode_numbers(values,&encoding)
+    }).collect();
+    choose(candidates,encode_numbers(values,&NumberEncoding::Raw(NumberSource::Array)))
+}
+
+pub fn best_strings(values: &[String]) -> Result<EncodingChoice,Error> {
+    let candidates = string_candidates().into_iter().map(|encoding| {
+        move || encode_strings(values,&encoding)
+    }).collect();
+    choose(candidates,encode_strings(values,&StringEncoding::Array))
+}
+
+pub fn best_booleans(values: &[bool]) -> Result<EncodingChoice,Error> {
+    let candidates = vec![BooleanEncoding::Bytes,BooleanEncoding::Packed].into_iter().map(|encoding| {
+        move || encode_booleans(values,&encoding)
+    }).collect();
+    choose(candidates,encode_booleans(values,&BooleanEncoding::Array))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peregrine_toolkit::lcg::Lcg;
+    use crate::testutil::decode;
+
+    #[test]
+    fn test_positions() {
+        let values = (0..1000).map(|i| (1_000_000 + i*150) as f64).collect::<Vec<_>>();
+        let choice = best_numbers(&values).unwrap();
+        assert!(choice.column().code().starts_with("ND"));
+        assert!(choice.size() * 10 < choice.baseline_size());
+        assert_eq!(values,*decode(choice.column()).data_as_numbers().unwrap());
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut values = vec![];
+        for i in 0..40 {
+            values.extend(std::iter::repeat((i%7) as f64 * 0.5).take(100));
+        }
+        let choice = best_numbers(&values).unwrap();
+        assert!(choice.column().code().starts_with("NU"));
+        assert_eq!(values,*decode(choice.column()).data_as_numbers().unwrap());
+        let flags = (0..1000).map(|i| i%3 == 0).collect::<Vec<_>>();
+        let choice = best_booleans(&flags).unwrap();
+        assert_eq!("BP",choice.column().code());
+    }
+
+    #[test]
+    fn test_fractions() {
+        let values = vec![0.5,-1.25,3.75];
+        let choice = best_numbers(&values).unwrap();
+        assert_eq!(values,*decode(choice.column()).data_as_numbers().unwrap());
+    }
+
+    #[test]
+    fn test_biotypes() {
+        let values = (0..500).map(|i| ["protein_coding","lncRNA","pseudogene"][i%3].to_string()).collect::<Vec<_>>();
+        let choice = best_strings(&values).unwrap();
+        assert!(choice.column().code().starts_with("SY"));
+        assert!(choice.saving_ratio() > 0.5);
+        assert_eq!(values,*decode(choice.column()).data_as_strings().unwrap());
+    }
+
+    #[test]
+    fn test_never_worse() {
+        let mut random = Lcg::new(5);
+        for _ in 0..20 {
+            let values = (0..random.below(50)).map(|_| random.integer(30) as f64).collect::<Vec<_>>();
+            let choice = best_numbers(&values).unwrap();
+            assert!(choice.size() <= choice.baseline_size());
+            assert_eq!(choice.size(),serialized_size(choice.column()).unwrap());
+            assert_eq!(values,*decode(choice.column()).data_as_numbers().unwrap());
+            let flags = values.iter().map(|x| *x > 1000.).collect::<Vec<_>>();
+            let choice = best_booleans(&flags).unwrap();
+            assert_eq!(flags,*decode(choice.column()).data_as_booleans().unwrap());
+        }
+    }
+}
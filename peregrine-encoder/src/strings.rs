@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use peregrine_data::ReceivedData;
+use peregrine_toolkit::error::Error;
+use crate::column::EncodedColumn;
+use crate::numbers::NumberEncoding;
+
+/* Classify stores each distinct value once, in order of first appearance, plus an index per row. */
+#[derive(Clone,Debug,PartialEq)]
+pub enum StringEncoding {
+    Array,
+    CharacterSplit,
+    ZeroSplit,
+    Classify(NumberEncoding,Box<StringEncoding>)
+}
+
+fn classify(values: &[String]) -> (Vec<f64>,Vec<String>) {
+    let mut positions = HashMap::new();
+    let mut keys = vec![];
+    let index = values.iter().map(|value| {
+        *positions.entry(value.as_str()).or_insert_with(|| {
+            keys.push(value.to_string());
+            keys.len()-1
+        }) as f64
+    }).collect();
+    (index,keys)
+}
+
+impl StringEncoding {
+    pub(crate) fn encode(&self, values: &[String], out: &mut EncodedColumn) -> Result<(),Error> {
+        match self {
+            StringEncoding::Array => {
+                out.push_code('A');
+                out.push_data(ReceivedData::new_strings(values.to_vec()));
+            },
+            StringEncoding::CharacterSplit => {
+                if let Some(bad) = values.iter().find(|v| v.chars().count() != 1) {
+                    return Err(Error::operr(&format!("cannot character split '{}'",bad)));
+                }
+                out.push_code('C');
+                out.push_data(ReceivedData::new_bytes(values.concat().into_bytes()));
+            },
+            StringEncoding::ZeroSplit => {
+                if let Some(bad) = values.iter().find(|v| v.contains('\0')) {
+                    return Err(Error::operr(&format!("cannot zero split '{}'",bad.escape_default())));
+                }
+                out.push_code('Z');
+                let mut bytes = vec![];
+                for value in values {
+                    bytes.extend_from_slice(value.as_bytes());
+                    bytes.push(0);
+                }
+                out.push_data(ReceivedData::new_bytes(bytes));
+            },
+            StringEncoding::Classify(index_encoding,keys_encoding) => {
+                out.push_code('Y');
+                let (index,keys) = classify(values);
+                index_encoding.encode(&index,out)?;
+                keys_encoding.encode(&keys,out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn encode_strings(values: &[String], encoding: &StringEncoding) -> Result<EncodedColumn,Error> {
+    let mut out = EncodedColumn::new();
+    out.push_code('S');
+    encoding.encode(values,&mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::numbers::NumberSource;
+    use peregrine_toolkit::lcg::Lcg;
+    use crate::testutil::decode;
+
+    const ALPHABET : &[&str] = &["A","C","G","T","é","-","\u{1F9EC}"];
+
+    fn random_string(random: &mut Lcg, max_len: usize) -> String {
+        (0..random.below(max_len+1)).map(|_| ALPHABET[random.below(ALPHABET.len())]).collect()
+    }
+
+    fn lesqlite2() -> NumberEncoding { NumberEncoding::Raw(NumberSource::Lesqlite2) }
+
+    #[test]
+    fn test_strings_round_trip() {
+        let mut random = Lcg::new(3);
+        let encodings = vec![
+            StringEncoding::Array,
+            StringEncoding::ZeroSplit,
+            StringEncoding::Classify(lesqlite2(),Box::new(StringEncoding::ZeroSplit)),
+            StringEncoding::Classify(lesqlite2(),Box::new(StringEncoding::Classify(lesqlite2(),Box::new(StringEncoding::Array))))
+        ];
+        for _ in 0..100 {
+            let values = (0..random.below(30)).map(|_| random_string(&mut random,3)).collect::<Vec<_>>();
+            for encoding in &encodings {
+                let column = encode_strings(&values,encoding).expect("encode");
+                assert_eq!(values,*decode(&column).data_as_strings().unwrap(),"{:?}",encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn test_character_split() {
+        let mut random = Lcg::new(4);
+        for _ in 0..100 {
+            let values = (0..random.below(30)).map(|_| ALPHABET[random.below(ALPHABET.len())].to_string()).collect::<Vec<_>>();
+            let column = encode_strings(&values,&StringEncoding::CharacterSplit).expect("encode");
+            assert_eq!(values,*decode(&column).data_as_strings().unwrap());
+        }
+        assert!(encode_strings(&["AC".to_string()],&StringEncoding::CharacterSplit).is_err());
+        assert!(encode_strings(&["a\0b".to_string()],&StringEncoding::ZeroSplit).is_err());
+    }
+
+    #[test]
+    fn test_classify() {
+        let values = ["x","y","x","z","y"].iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let (index,keys) = classify(&values);
+        assert_eq!(vec![0.,1.,0.,2.,1.],index);
+        assert_eq!(vec!["x","y","z"],keys);
+        let column = encode_strings(&values,&StringEncoding::Classify(lesqlite2(),Box::new(StringEncoding::Array))).unwrap();
+        assert_eq!("SYRLA",column.code());
+    }
+}
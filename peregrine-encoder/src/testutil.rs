@@ -0,0 +1,9 @@
+use peregrine_data::{ DataAlgorithm, ReceivedData };
+use crate::column::EncodedColumn;
+
+/* Sends a column through the real decoder, via the wire format. */
+pub(crate) fn decode(column: &EncodedColumn) -> ReceivedData {
+    let bytes = serde_cbor::to_vec(column).expect("serialize");
+    let algorithm : DataAlgorithm = serde_cbor::from_slice(&bytes).expect("deserialize");
+    algorithm.to_received_data().expect("decode")
+}
@@ -6,7 +6,7 @@ use js_sys::{ Array, JSON };
 use serde_wasm_bindgen::from_value;
 use wasm_bindgen::{prelude::*, JsCast};
 use peregrine_draw::{Endstop, Message, PeregrineAPI, PeregrineConfig, PgCommanderWeb};
-use peregrine_data::{StickId, DataMessage };
+use peregrine_data::{StickId, DataMessage, Theme };
 use peregrine_message::{MessageKind, PeregrineMessage};
 use peregrine_toolkit::{ warn, log, error, js::jstojsonvalue::js_to_json, error::{ErrorType,CallToAction }};
 use web_sys::{ Element };
@@ -197,6 +197,14 @@ impl GenomeBrowser {
     pub fn set_artificial(&self, name: &str, start: bool) {
         self.api.set_artificial(name,start);
     }
+
+    /* light, dark, high-contrast or deuteranopia */
+    pub fn set_theme(&self, theme: &str) {
+        match Theme::from_name(theme) {
+            Some(theme) => { self.api.set_theme(&theme); },
+            None => { warn!("unknown theme {}",theme); }
+        }
+    }
     
     pub fn goto(&self, left: f64, right: f64) {
         /* convert ensembl co-ordinates to regular ones */
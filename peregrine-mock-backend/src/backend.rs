@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use serde_cbor::Value;
+use crate::fixture::{ DirectorySource, FixtureSource };
+
+/* A MockBackend answers request packets, exactly as serialized by MaxiRequest, with response
+ * packets which deserialize as a MaxiResponse. It knows nothing of the individual request types:
+ * each [msgid,opcode,request] in the packet is answered from its fixture.
+ *
+ * Each fixture is a CBOR map containing the key "response" which is exactly what the backend
+ * sends for this request in the "responses" list ie [opcode,data], but without the msgid. A fixture
+ * may also contain "programs", "eardos" and "tracks-packed" which, if present, are added to the
+ * corresponding lists of the packet. Requests without a fixture get a failure response, as from a
+ * real backend, rather than failing the whole packet.
+ */
+
+const FAILURE_OPCODE : i128 = 1;
+
+pub(crate) fn key(name: &str) -> Value { Value::Text(name.to_string()) }
+
+fn failure(msgid: &Value, message: &str) -> Value {
+    Value::Array(vec![msgid.clone(),Value::Array(vec![Value::Integer(FAILURE_OPCODE),Value::Text(message.to_string())])])
+}
+
+/* Recorded fixtures from the same packet all carry its programs, etc, so drop repeats */
+fn extend_list(packet: &mut BTreeMap<Value,Value>, name: &str, fixture: &mut BTreeMap<Value,Value>) {
+    if let Some(Value::Array(values)) = fixture.remove(&key(name)) {
+        let existing = packet.entry(key(name)).or_insert_with(|| Value::Array(vec![]));
+        if let Value::Array(existing) = existing {
+            for value in values {
+                if !existing.contains(&value) {
+                    existing.push(value);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MockBackend {
+    source: Arc<dyn FixtureSource>
+}
+
+impl MockBackend {
+    pub fn new<S>(source: S) -> MockBackend where S: FixtureSource + 'static {
+        MockBackend { source: Arc::new(source) }
+    }
+
+    pub fn from_directory(root: &Path) -> MockBackend {
+        MockBackend::new(DirectorySource::new(root))
+    }
+
+    fn fixture(&self, opcode: u8, request: &Value) -> Result<BTreeMap<Value,Value>,String> {
+        let bytes = self.source.fixture(opcode,request)?;
+        match serde_cbor::from_slice(&bytes) {
+            Ok(Value::Map(map)) => Ok(map),
+            Ok(_) => Err("fixture is not a map".to_string()),
+            Err(e) => Err(format!("corrupt fixture: {}",e))
+        }
+    }
+
+    fn respond_one(&self, packet: &mut BTreeMap<Value,Value>, request: &Value) -> Result<Value,String> {
+        let (msgid,opcode,request) = match request {
+            Value::Array(parts) if parts.len() == 3 => (&parts[0],&parts[1],&parts[2]),
+            _ => { return Err("bad request in packet".to_string()); }
+        };
+        let opcode = match opcode {
+            Value::Integer(opcode) if *opcode >= 0 && *opcode < 256 => *opcode as u8,
+            _ => { return Err("bad opcode in packet".to_string()); }
+        };
+        let mut fixture = match self.fixture(opcode,request) {
+            Ok(fixture) => fixture,
+            Err(e) => { return Ok(failure(msgid,&e)); }
+        };
+        let response = match fixture.remove(&key("response")) {
+            Some(response) => response,
+            None => { return Ok(failure(msgid,"fixture has no response")); }
+        };
+        extend_list(packet,"programs",&mut fixture);
+        extend_list(packet,"eardos",&mut fixture);
+        extend_list(packet,"tracks-packed",&mut fixture);
+        Ok(Value::Array(vec![msgid.clone(),response]))
+    }
+
+    /* Err only if the request packet itself is malformed */
+    pub fn respond(&self, request: &[u8]) -> Result<Vec<u8>,String> {
+        let mut request = match serde_cbor::from_slice(request) {
+            Ok(Value::Map(map)) => map,
+            Ok(_) => { return Err("request packet is not a map".to_string()); },
+            Err(e) => { return Err(format!("corrupt request packet: {}",e)); }
+        };
+        let channel = request.remove(&key("channel")).ok_or_else(|| "request packet has no channel".to_string())?;
+        let requests = match request.remove(&key("requests")) {
+            Some(Value::Array(requests)) => requests,
+            _ => { return Err("request packet has no requests".to_string()); }
+        };
+        let mut packet = BTreeMap::new();
+        packet.insert(key("channel"),channel);
+        packet.insert(key("programs"),Value::Array(vec![]));
+        packet.insert(key("eardos"),Value::Array(vec![]));
+        let mut responses = vec![];
+        for request in &requests {
+            responses.push(self.respond_one(&mut packet,request)?);
+        }
+        packet.insert(key("responses"),Value::Array(responses));
+        serde_cbor::to_vec(&Value::Map(packet)).map_err(|e| format!("cannot serialize response: {}",e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::MemorySource;
+
+    fn fixture(response: Value, programs: Vec<i128>) -> Vec<u8> {
+        let mut fixture = BTreeMap::new();
+        fixture.insert(key("response"),response);
+        fixture.insert(key("programs"),Value::Array(programs.iter().map(|x| Value::Integer(*x)).collect()));
+        serde_cbor::to_vec(&Value::Map(fixture)).unwrap()
+    }
+
+    fn request(requests: Vec<(i128,i128,Value)>) -> Vec<u8> {
+        let mut packet = BTreeMap::new();
+        packet.insert(key("channel"),Value::Array(vec![key("ensembl"),key("main")]));
+        packet.insert(key("requests"),Value::Array(requests.into_iter().map(|(msgid,opcode,request)| {
+            Value::Array(vec![Value::Integer(msgid),Value::Integer(opcode),request])
+        }).collect()));
+        packet.insert(key("version"),Value::Map(BTreeMap::new()));
+        serde_cbor::to_vec(&Value::Map(packet)).unwrap()
+    }
+
+    fn response(bytes: &[u8]) -> BTreeMap<Value,Value> {
+        match serde_cbor::from_slice(bytes).unwrap() {
+            Value::Map(map) => map,
+            _ => panic!("not a map")
+        }
+    }
+
+    #[test]
+    fn test_respond() {
+        let mut source = MemorySource::new();
+        let jump = Value::Array(vec![key("gene:BRCA2")]);
+        let stick = Value::Array(vec![key("homo_sapiens:13")]);
+        source.insert(5,&jump,fixture(Value::Array(vec![Value::Integer(6),key("jump")]),vec![1,2]));
+        source.insert(2,&stick,fixture(Value::Array(vec![Value::Integer(3),key("stick")]),vec![2,3]));
+        let backend = MockBackend::new(source);
+        let packet = request(vec![(7,5,jump),(8,2,stick),(9,2,key("missing"))]);
+        let packet = response(&backend.respond(&packet).unwrap());
+        assert_eq!(Some(&Value::Array(vec![key("ensembl"),key("main")])),packet.get(&key("channel")));
+        assert_eq!(Some(&Value::Array(vec![Value::Integer(1),Value::Integer(2),Value::Integer(3)])),packet.get(&key("programs")));
+        let responses = match packet.get(&key("responses")) {
+            Some(Value::Array(responses)) => responses.clone(),
+            _ => panic!("no responses")
+        };
+        assert_eq!(3,responses.len());
+        assert_eq!(Value::Array(vec![Value::Integer(7),Value::Array(vec![Value::Integer(6),key("jump")])]),responses[0]);
+        match &responses[2] {
+            Value::Array(parts) => {
+                assert_eq!(Value::Integer(9),parts[0]);
+                assert!(matches!(&parts[1],Value::Array(failure) if failure[0] == Value::Integer(1)));
+            },
+            _ => panic!("bad failure")
+        }
+    }
+
+    #[test]
+    fn test_bad_packet() {
+        let backend = MockBackend::new(MemorySource::new());
+        assert!(backend.respond(b"not cbor").is_err());
+        assert!(backend.respond(&serde_cbor::to_vec(&Value::Integer(1)).unwrap()).is_err());
+        assert!(backend.respond(&request(vec![])).is_ok());
+    }
+}
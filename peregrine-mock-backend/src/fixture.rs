@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+use serde_cbor::Value;
+
+/* Fixtures are stored one file per mini-request. The path is derived from the serialized form of
+ * the request, ie exactly what a backend sees, so that any request can be mapped without knowing
+ * the internals of each request type. The first component is the request type. Then come the
+ * leaves of the request in order (maps flattened as key then value), the last of which becomes
+ * the filename with ".cbor" added. For example,
+ *
+ * boot.cbor
+ * program/ensembl-webteam/core/gene/1.cbor
+ * data/ensembl/main/gene/homo_sapiens_GCA_000001405_28:1/12/345/release.cbor
+ *
+ * Anything other than alphanumerics and "-_.:" is %-encoded, as is an initial ".". An empty string
+ * is a bare "%".
+ *
+ * This is the layout used by febe-filesystem too, so fixtures recorded there can be served here.
+ */
+
+pub fn opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        0 => "boot",
+        1 => "program",
+        2 => "stick",
+        4 => "data",
+        5 => "jump",
+        6 => "metric",
+        7 => "expand",
+        8 => "small-values",
+        _ => "unknown"
+    }
+}
+
+fn sanitise(segment: &str) -> String {
+    if segment.is_empty() { return "%".to_string(); }
+    let mut out = String::new();
+    for (i,b) in segment.bytes().enumerate() {
+        let safe = b.is_ascii_alphanumeric() || b"-_:".contains(&b) || (b == b'.' && i > 0);
+        if safe {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}",b));
+        }
+    }
+    out
+}
+
+pub fn flatten(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::Text(s) => { out.push(sanitise(s)); },
+        Value::Integer(n) => { out.push(n.to_string()); },
+        Value::Float(f) => { out.push(sanitise(&f.to_string())); },
+        Value::Bool(b) => { out.push(b.to_string()); },
+        Value::Array(values) => {
+            for value in values { flatten(value,out); }
+        },
+        Value::Map(values) => {
+            for (key,value) in values {
+                flatten(key,out);
+                flatten(value,out);
+            }
+        },
+        _ => {}
+    }
+}
+
+/* request is the third member of a [msgid,opcode,request] triple in a request packet */
+pub fn fixture_segments(opcode: u8, request: &Value) -> Vec<String> {
+    let mut segments = vec![opcode_name(opcode).to_string()];
+    flatten(request,&mut segments);
+    if let Some(last) = segments.last_mut() {
+        last.push_str(".cbor");
+    }
+    segments
+}
+
+pub fn fixture_path(root: &Path, opcode: u8, request: &Value) -> PathBuf {
+    let mut path = root.to_path_buf();
+    for segment in fixture_segments(opcode,request) {
+        path.push(segment);
+    }
+    path
+}
+
+/* Where a MockBackend gets its fixtures: a directory or, for tests, memory. */
+pub trait FixtureSource : Send + Sync {
+    fn fixture(&self, opcode: u8, request: &Value) -> Result<Vec<u8>,String>;
+}
+
+pub struct DirectorySource(PathBuf);
+
+impl DirectorySource {
+    pub fn new(root: &Path) -> DirectorySource { DirectorySource(root.to_path_buf()) }
+}
+
+impl FixtureSource for DirectorySource {
+    fn fixture(&self, opcode: u8, request: &Value) -> Result<Vec<u8>,String> {
+        let path = fixture_path(&self.0,opcode,request);
+        std::fs::read(&path).map_err(|e| format!("no fixture {}: {}",path.display(),e))
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySource(HashMap<Vec<String>,Vec<u8>>);
+
+impl MemorySource {
+    pub fn new() -> MemorySource { MemorySource(HashMap::new()) }
+
+    /* fixture is the serialized fixture map, as it would be stored in a file */
+    pub fn insert(&mut self, opcode: u8, request: &Value, fixture: Vec<u8>) {
+        self.0.insert(fixture_segments(opcode,request),fixture);
+    }
+}
+
+impl FixtureSource for MemorySource {
+    fn fixture(&self, opcode: u8, request: &Value) -> Result<Vec<u8>,String> {
+        let segments = fixture_segments(opcode,request);
+        self.0.get(&segments).cloned().ok_or_else(|| format!("no fixture {}",segments.join("/")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sanitise() {
+        assert_eq!("homo_sapiens:1",sanitise("homo_sapiens:1"));
+        assert_eq!("a%2Fb",sanitise("a/b"));
+        assert_eq!("%2E.",sanitise(".."));
+        assert_eq!("x.y",sanitise("x.y"));
+        assert_eq!("%",sanitise(""));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let value = serde_cbor::value::to_value((("ensembl","main"),"gene",vec![1,2],"release")).unwrap();
+        let mut out = vec![];
+        flatten(&value,&mut out);
+        assert_eq!(vec!["ensembl","main","gene","1","2","release"],out);
+    }
+
+    #[test]
+    fn test_fixture_path() {
+        let request = serde_cbor::value::to_value((("ensembl-webteam","core"),"gene",1)).unwrap();
+        let path = fixture_path(Path::new("/fixtures"),1,&request);
+        assert_eq!(PathBuf::from("/fixtures/program/ensembl-webteam/core/gene/1.cbor"),path);
+        assert_eq!(vec!["boot.cbor"],fixture_segments(0,&Value::Null));
+    }
+}
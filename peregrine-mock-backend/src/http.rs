@@ -0,0 +1,142 @@
+use std::io::{ self, BufRead, BufReader, Read, Write };
+use std::net::{ SocketAddr, TcpListener, TcpStream, ToSocketAddrs };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread::{ self, JoinHandle };
+use crate::backend::MockBackend;
+
+/* Just enough HTTP/1.1 for febe-network to talk to a MockBackend. febe-network POSTs the request
+ * packet to its URL with "/hi" or "/lo" added and a "stamp" query parameter. As there's only one
+ * backend per server the path is ignored. Each connection carries a single request. CORS headers
+ * are sent so that a browser page from elsewhere can use the server.
+ */
+
+struct HttpRequest {
+    method: String,
+    body: Vec<u8>
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let method = line.split_whitespace().next().unwrap_or("").to_string();
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 { break; }
+        let header = line.trim_end();
+        if header.is_empty() { break; }
+        if let Some((name,value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData,"bad content-length"))?;
+            }
+        }
+    }
+    let mut body = vec![0;length];
+    reader.read_exact(&mut body)?;
+    Ok(HttpRequest { method, body })
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(stream,"HTTP/1.1 {}\r\n",status)?;
+    write!(stream,"Access-Control-Allow-Origin: *\r\n")?;
+    write!(stream,"Access-Control-Allow-Methods: POST, OPTIONS\r\n")?;
+    write!(stream,"Access-Control-Allow-Headers: Content-Type\r\n")?;
+    write!(stream,"Content-Type: {}\r\n",content_type)?;
+    write!(stream,"Content-Length: {}\r\n",body.len())?;
+    write!(stream,"Connection: close\r\n\r\n")?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn handle(backend: &MockBackend, mut stream: TcpStream) -> io::Result<()> {
+    let request = read_request(&mut stream)?;
+    match request.method.as_str() {
+        "POST" => {
+            match backend.respond(&request.body) {
+                Ok(body) => write_response(&mut stream,"200 OK","application/cbor",&body),
+                Err(e) => write_response(&mut stream,"400 Bad Request","text/plain",e.as_bytes())
+            }
+        },
+        "OPTIONS" => write_response(&mut stream,"204 No Content","text/plain",&[]),
+        _ => write_response(&mut stream,"405 Method Not Allowed","text/plain",&[])
+    }
+}
+
+/* A MockBackend listening for HTTP on a background thread until dropped. */
+pub struct MockServer {
+    addr: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl MockServer {
+    /* Use port 0 to have one chosen: addr() says which. */
+    pub fn start<A: ToSocketAddrs>(backend: &MockBackend, addr: A) -> io::Result<MockServer> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let stopping = Arc::new(AtomicBool::new(false));
+        let backend = backend.clone();
+        let stopping2 = stopping.clone();
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopping2.load(Ordering::SeqCst) { break; }
+                if let Ok(stream) = stream {
+                    let backend = backend.clone();
+                    thread::spawn(move || { handle(&backend,stream).ok(); });
+                }
+            }
+        });
+        Ok(MockServer { addr, stopping, thread: Some(thread) })
+    }
+
+    pub fn addr(&self) -> &SocketAddr { &self.addr }
+
+    /* A channel name for febe-network */
+    pub fn url(&self) -> String { format!("http://{}/api/data",self.addr) }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopping.store(true,Ordering::SeqCst);
+        /* wake the listener so that it sees we're stopping */
+        TcpStream::connect(self.addr).ok();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+    use serde_cbor::Value;
+    use crate::backend::key;
+    use crate::fixture::MemorySource;
+
+    fn post(server: &MockServer, method: &str, body: &[u8]) -> String {
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        write!(stream,"{} /api/data/hi?stamp=0 HTTP/1.1\r\nContent-Length: {}\r\n\r\n",method,body.len()).unwrap();
+        stream.write_all(body).unwrap();
+        let mut out = vec![];
+        stream.read_to_end(&mut out).unwrap();
+        String::from_utf8_lossy(&out).to_string()
+    }
+
+    #[test]
+    fn test_server() {
+        let server = MockServer::start(&MockBackend::new(MemorySource::new()),"127.0.0.1:0").unwrap();
+        assert!(server.url().ends_with("/api/data"));
+        let mut packet = BTreeMap::new();
+        packet.insert(key("channel"),Value::Array(vec![key("ensembl"),key("main")]));
+        packet.insert(key("requests"),Value::Array(vec![]));
+        let response = post(&server,"POST",&serde_cbor::to_vec(&Value::Map(packet)).unwrap());
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/cbor\r\n"));
+        assert!(post(&server,"POST",b"junk").starts_with("HTTP/1.1 400 "));
+        assert!(post(&server,"OPTIONS",&[]).starts_with("HTTP/1.1 204 "));
+        assert!(post(&server,"GET",&[]).starts_with("HTTP/1.1 405 "));
+    }
+}
@@ -0,0 +1,16 @@
+/* peregrine-mock-backend is a stand-in for backend-server for integration tests. It speaks the
+ * CBOR packet protocol of MaxiRequest and MaxiResponse, answering each request from a fixture, and
+ * can be used in-process (MockBackend::respond) or over HTTP (MockServer) with febe-network. The
+ * binary serves a fixture directory over HTTP.
+ *
+ * It deliberately doesn't depend on peregrine-data: requests are handled as CBOR values so that the
+ * server checks the wire format rather than sharing the client's idea of it.
+ */
+
+mod backend;
+mod fixture;
+mod http;
+
+pub use backend::MockBackend;
+pub use fixture::{ DirectorySource, FixtureSource, MemorySource, fixture_path, fixture_segments, flatten, opcode_name };
+pub use http::MockServer;
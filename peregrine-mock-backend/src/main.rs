@@ -0,0 +1,27 @@
+use std::path::Path;
+use peregrine_mock_backend::{ MockBackend, MockServer };
+
+/* peregrine_mock_backend <fixture-dir> [<addr>] */
+
+const DEFAULT_ADDR : &str = "127.0.0.1:3333";
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} <fixture-dir> [<addr>]",args[0]);
+        std::process::exit(2);
+    }
+    let backend = MockBackend::from_directory(Path::new(&args[1]));
+    let addr = args.get(2).map(|x| x.as_str()).unwrap_or(DEFAULT_ADDR);
+    let server = match MockServer::start(&backend,addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("cannot listen on {}: {}",addr,e);
+            std::process::exit(1);
+        }
+    };
+    println!("serving {} at {}",args[1],server.url());
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use peregrine_data::{ BlendMode, Colour, DirectColour, Palette, PlainColour };
+
+pub(crate) fn escape(input: &str) -> String {
+    let mut out = String::new();
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c)
+        }
+    }
+    out
+}
+
+fn rgb(colour: &DirectColour) -> String {
+    format!("rgb({},{},{})",colour.0,colour.1,colour.2)
+}
+
+/* Returns the colour and opacity attributes for a fill or stroke */
+pub(crate) fn direct_colour(attr: &str, colour: &DirectColour) -> String {
+    if colour.3 == 255 {
+        format!("{}=\"{}\"",attr,rgb(colour))
+    } else {
+        format!("{}=\"{}\" {}-opacity=\"{:.3}\"",attr,rgb(colour),attr,colour.3 as f64/255.)
+    }
+}
+
+/* Extra attribute for an element drawn with a blend mode, empty for normal */
+pub(crate) fn blend_mode(blend: &BlendMode) -> &'static str {
+    match blend {
+        BlendMode::Normal => "",
+        BlendMode::Multiply => " style=\"mix-blend-mode: multiply\"",
+        BlendMode::Screen => " style=\"mix-blend-mode: screen\""
+    }
+}
+
+/* Elements are collected with their depth and only sorted into paint order when the document is
+ * finished, as the carriages and shapes arrive in no particular order. The sort is stable so that
+ * within a depth, shapes are painted in the order they were generated, as in peregrine-draw.
+ */
+pub(crate) struct SvgDocument {
+    width: f64,
+    height: f64,
+    elements: Vec<(i8,String)>,
+    defs: Vec<String>,
+    patterns: HashMap<String,String>,
+    palette: Palette
+}
+
+impl SvgDocument {
+    pub(crate) fn new(width: f64, height: f64, palette: &Palette) -> SvgDocument {
+        SvgDocument {
+            width, height,
+            elements: vec![],
+            defs: vec![],
+            patterns: HashMap::new(),
+            palette: palette.clone()
+        }
+    }
+
+    pub(crate) fn add(&mut self, depth: i8, element: String) {
+        self.elements.push((depth,element));
+    }
+
+    fn pattern(&mut self, key: String, make: impl FnOnce(&str) -> String) -> String {
+        let next = self.patterns.len();
+        let defs = &mut self.defs;
+        let id = self.patterns.entry(key).or_insert_with(|| {
+            let id = format!("pg-pattern-{}",next);
+            defs.push(make(&id));
+            id
+        });
+        format!("url(#{})",id)
+    }
+
+    pub(crate) fn plain(&self, colour: &PlainColour) -> DirectColour {
+        self.palette.plain(colour)
+    }
+
+    /* Heraldry is approximated with SVG patterns. Stripes are diagonal, bars horizontal. */
+    pub(crate) fn colour(&mut self, attr: &str, colour: &Colour) -> String {
+        match colour {
+            Colour::Direct(c) => direct_colour(attr,c),
+            Colour::Palette(name) => direct_colour(attr,&self.palette.lookup(name)),
+            Colour::Stripe(a,b,(x,y),prop) => {
+                let (a,b) = (self.plain(a),self.plain(b));
+                let (x,y) = ((*x).max(1) as f64*4.,(*y).max(1) as f64*4.);
+                let key = format!("stripe {:?} {:?} {} {} {}",a,b,x,y,prop);
+                let prop = prop.max(0.).min(1.);
+                let url = self.pattern(key,|id| {
+                    format!("<pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" height=\"{}\" patternTransform=\"rotate(45)\"><rect width=\"{}\" height=\"{}\" {}/><rect width=\"{}\" height=\"{}\" {}/></pattern>",
+                        id,x,y,x,y,direct_colour("fill",&a),x*prop,y,direct_colour("fill",&b))
+                });
+                format!("{}=\"{}\"",attr,url)
+            },
+            Colour::Bar(a,b,(x,y),prop) => {
+                let (a,b) = (self.plain(a),self.plain(b));
+                let (x,y) = ((*x).max(1) as f64*4.,(*y).max(1) as f64*4.);
+                let key = format!("bar {:?} {:?} {} {} {}",a,b,x,y,prop);
+                let prop = prop.max(0.).min(1.);
+                let url = self.pattern(key,|id| {
+                    format!("<pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" height=\"{}\"><rect width=\"{}\" height=\"{}\" {}/><rect y=\"{}\" width=\"{}\" height=\"{}\" {}/></pattern>",
+                        id,x,y,x,y,direct_colour("fill",&a),y*(1.-prop)/2.,x,y*prop,direct_colour("fill",&b))
+                });
+                format!("{}=\"{}\"",attr,url)
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        self.elements.sort_by_key(|(depth,_)| *depth);
+        let mut out = String::new();
+        write!(out,"<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            self.width,self.height,self.width,self.height).ok();
+        if self.defs.len() > 0 {
+            out.push_str("<defs>\n");
+            for def in &self.defs {
+                out.push_str(def);
+                out.push('\n');
+            }
+            out.push_str("</defs>\n");
+        }
+        for (_,element) in &self.elements {
+            out.push_str(element);
+            out.push('\n');
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use peregrine_data::Theme;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("a&lt;b&gt; &amp; &quot;c&apos;",escape("a<b> & \"c'"));
+    }
+
+    #[test]
+    fn test_depth_order() {
+        let mut doc = SvgDocument::new(10.,10.,&Palette::new());
+        doc.add(2,"<c/>".to_string());
+        doc.add(-1,"<a/>".to_string());
+        doc.add(2,"<d/>".to_string());
+        doc.add(0,"<b/>".to_string());
+        let out = doc.finish();
+        let a = out.find("<a/>").unwrap();
+        let b = out.find("<b/>").unwrap();
+        let c = out.find("<c/>").unwrap();
+        let d = out.find("<d/>").unwrap();
+        assert!(a < b && b < c && c < d);
+    }
+
+    #[test]
+    fn test_patterns_shared() {
+        let mut doc = SvgDocument::new(10.,10.,&Palette::new());
+        let red = DirectColour(255,0,0,255);
+        let blue = DirectColour(0,0,255,128);
+        let (pred,pblue) = (PlainColour::Direct(red.clone()),PlainColour::Direct(blue.clone()));
+        let a = doc.colour("fill",&Colour::Stripe(pred.clone(),pblue.clone(),(2,2),0.5));
+        let b = doc.colour("fill",&Colour::Stripe(pred.clone(),pblue.clone(),(2,2),0.5));
+        let c = doc.colour("fill",&Colour::Bar(pred.clone(),pblue.clone(),(2,2),0.5));
+        assert_eq!(a,b);
+        assert_ne!(a,c);
+        assert_eq!("fill=\"rgb(255,0,0)\"",doc.colour("fill",&Colour::Direct(red)));
+        assert_eq!("fill=\"rgb(0,0,255)\" fill-opacity=\"0.502\"",doc.colour("fill",&Colour::Direct(blue)));
+    }
+
+    #[test]
+    fn test_palette_colour() {
+        let mut palette = Palette::new();
+        let mut doc = SvgDocument::new(10.,10.,&palette);
+        assert_eq!("fill=\"rgb(0,0,0)\"",doc.colour("fill",&Colour::Palette("foreground".to_string())));
+        palette.set_theme(&Theme::Dark);
+        let mut doc = SvgDocument::new(10.,10.,&palette);
+        assert_eq!("fill=\"rgb(230,230,230)\"",doc.colour("fill",&Colour::Palette("foreground".to_string())));
+        assert_eq!("fill=\"rgb(230,230,230)\"",doc.colour("fill",&Colour::Palette("no-such-colour".to_string())));
+        let named = |name: &str| PlainColour::Palette(name.to_string());
+        doc.colour("fill",&Colour::Stripe(named("foreground"),named("background"),(1,1),0.5));
+        assert!(doc.defs[0].contains("rgb(230,230,230)") && doc.defs[0].contains("rgb(32,32,32)"));
+    }
+
+    #[test]
+    fn test_blend_mode() {
+        assert_eq!("",blend_mode(&BlendMode::Normal));
+        assert_eq!(" style=\"mix-blend-mode: multiply\"",blend_mode(&BlendMode::Multiply));
+    }
+}
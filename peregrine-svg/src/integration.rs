@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex };
+use peregrine_data::{
+    Assets, CarriageSpeed, DataMessage, DrawingCarriage, GlobalAllotmentMetadata, InstanceInformation,
+    Palette, PeregrineApiQueue, PeregrineIntegration, PlayingField, TrainIdentity, Viewport
+};
+use peregrine_toolkit::error::Error;
+use peregrine_toolkit::{ lock, log };
+use crate::document::SvgDocument;
+use crate::shapes::add_shape;
+use crate::stage::SvgStage;
+
+pub struct SvgOptions {
+    pub width: f64,
+    pub height: Option<f64>, /* None = height of playing field */
+    pub palette: Palette /* for named colours, light theme by default */
+}
+
+impl SvgOptions {
+    pub fn new(width: f64) -> SvgOptions {
+        SvgOptions { width, height: None, palette: Palette::new() }
+    }
+}
+
+struct SvgState {
+    queue: PeregrineApiQueue,
+    assets: Assets,
+    trains: HashMap<TrainIdentity,Vec<DrawingCarriage>>,
+    current: Option<TrainIdentity>,
+    viewport: Option<Viewport>,
+    playing_field: Option<PlayingField>,
+    paused: bool
+}
+
+/* There's nothing to prepare for a carriage and no animation so carriages are ready as soon as they
+ * are created and transitions complete immediately. The railway then behaves as it does in the
+ * browser once everything has settled.
+ */
+#[derive(Clone)]
+pub struct SvgIntegration(Arc<Mutex<SvgState>>);
+
+impl SvgIntegration {
+    pub fn new(queue: &PeregrineApiQueue) -> SvgIntegration {
+        SvgIntegration(Arc::new(Mutex::new(SvgState {
+            queue: queue.clone(),
+            assets: Assets::empty(),
+            trains: HashMap::new(),
+            current: None,
+            viewport: None,
+            playing_field: None,
+            paused: false
+        })))
+    }
+
+    /* true when there's a train showing and enough information to place it */
+    pub fn is_ready(&self) -> bool {
+        let state = lock!(self.0);
+        state.current.as_ref().map(|train| state.trains.contains_key(train)).unwrap_or(false) &&
+            state.viewport.is_some() && state.playing_field.is_some()
+    }
+
+    pub fn render(&self, options: &SvgOptions) -> Result<String,Error> {
+        let state = lock!(self.0);
+        let (viewport,playing_field) = match (&state.viewport,&state.playing_field) {
+            (Some(v),Some(p)) => (v,p),
+            _ => { return Err(Error::operr("cannot render svg: no viewport")); }
+        };
+        let stage = SvgStage::new(viewport,playing_field,options.width,options.height)?;
+        let mut doc = SvgDocument::new(stage.width(),stage.height(),&options.palette);
+        let carriages = state.current.as_ref().and_then(|train| state.trains.get(train));
+        for carriage in carriages.map(|x| x.as_slice()).unwrap_or(&[]) {
+            for shape in carriage.shapes().iter() {
+                add_shape(&mut doc,&stage,shape,&state.assets);
+            }
+        }
+        Ok(doc.finish())
+    }
+}
+
+impl PeregrineIntegration for SvgIntegration {
+    fn report_instance_information(&self, info: &InstanceInformation) {
+        log!("{}",info);
+    }
+
+    fn set_assets(&mut self, assets: &Assets) {
+        lock!(self.0).assets.add(assets);
+    }
+
+    fn set_pause(&mut self, yn: bool) {
+        lock!(self.0).paused = yn;
+    }
+
+    fn create_train(&mut self, train: &TrainIdentity) {
+        lock!(self.0).trains.insert(train.clone(),vec![]);
+    }
+
+    fn drop_train(&mut self, train: &TrainIdentity) {
+        let mut state = lock!(self.0);
+        state.trains.remove(train);
+        if state.current.as_ref() == Some(train) {
+            state.current = None;
+        }
+    }
+
+    fn create_carriage(&mut self, carriage: &DrawingCarriage) {
+        lock!(self.0).queue.carriage_ready(carriage);
+    }
+
+    fn drop_carriage(&mut self, _carriage: &DrawingCarriage) {}
+
+    fn set_carriages(&mut self, train: &TrainIdentity, carriages: &[DrawingCarriage]) -> Result<(),DataMessage> {
+        lock!(self.0).trains.insert(train.clone(),carriages.to_vec());
+        Ok(())
+    }
+
+    fn start_transition(&mut self, train: &TrainIdentity, _max: u64, _speed: CarriageSpeed) -> Result<(),DataMessage> {
+        let mut state = lock!(self.0);
+        state.current = Some(train.clone());
+        state.queue.transition_complete();
+        Ok(())
+    }
+
+    fn notify_viewport(&mut self, viewport: &Viewport) {
+        let mut state = lock!(self.0);
+        if !state.paused {
+            state.viewport = Some(viewport.clone());
+        }
+    }
+
+    fn notify_allotment_metadata(&mut self, _metadata: &GlobalAllotmentMetadata) {}
+
+    fn set_playing_field(&mut self, playing_field: PlayingField) {
+        lock!(self.0).playing_field = Some(playing_field);
+    }
+}
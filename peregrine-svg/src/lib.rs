@@ -0,0 +1,12 @@
+/* peregrine-svg is a headless alternative to the WebGL code in peregrine-draw. It implements
+ * PeregrineIntegration so that it can sit behind a PeregrineCore just like the browser does, but
+ * instead of building WebGL programs it turns the shapes of the currently displayed train into an
+ * SVG document. It is intended for publication figures and regression snapshots.
+ */
+
+mod document;
+mod integration;
+mod shapes;
+mod stage;
+
+pub use crate::integration::{ SvgIntegration, SvgOptions };
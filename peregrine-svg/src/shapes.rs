@@ -0,0 +1,150 @@
+use std::f64::consts::PI;
+use peregrine_data::{
+    Assets, AttachmentPoint, AuxLeaf, BlendMode, Colour, DrawingShape, DrawnType, ImageShape, Patina, PolygonShape,
+    RectangleShape, Shape, TextShape, WiggleShape
+};
+use crate::document::{ SvgDocument, blend_mode, direct_colour, escape };
+use crate::stage::SvgStage;
+
+fn drawn_attrs(doc: &mut SvgDocument, drawn_type: &DrawnType, colour: &Colour, blend: &BlendMode) -> String {
+    let attrs = match drawn_type {
+        DrawnType::Fill => doc.colour("fill",colour),
+        DrawnType::Stroke(width) => {
+            format!("fill=\"none\" {} stroke-width=\"{}\"",doc.colour("stroke",colour),width.max(1.))
+        }
+    };
+    format!("{}{}",attrs,blend_mode(blend))
+}
+
+fn add_rectangles(doc: &mut SvgDocument, stage: &SvgStage, shape: &RectangleShape<AuxLeaf>) {
+    let (drawn_type,colours,blend) = match shape.patina() {
+        Patina::Drawn(drawn_type,colours,blend) => (drawn_type,colours,blend),
+        _ => { return; } /* hotspots and metadata are invisible */
+    };
+    let colours = if let Some(colours) = colours.iter(shape.area().len()) { colours } else { return; };
+    for ((top_left,bottom_right),colour) in shape.area().iter().zip(colours) {
+        let coord_system = &top_left.allotment.coord_system;
+        let a = stage.point(coord_system,*top_left.base,*top_left.normal,*top_left.tangent);
+        let b = stage.point(coord_system,*bottom_right.base,*bottom_right.normal,*bottom_right.tangent);
+        if let (Some((x0,y0)),Some((x1,y1))) = (a,b) {
+            let attrs = drawn_attrs(doc,drawn_type,colour,blend);
+            doc.add(top_left.allotment.depth,format!("<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+                x0.min(x1),y0.min(y1),(x1-x0).abs(),(y1-y0).abs(),attrs));
+        }
+    }
+}
+
+/* Same layout as peregrine-draw: zeroth point at angle, progressing anticlockwise in the (tangent,normal) plane */
+fn polygon_points(points: usize, angle: f32) -> Vec<(f64,f64)> {
+    let mut out = vec![];
+    let mut theta = (angle as f64) * PI / 180.;
+    let delta_theta = 2. * PI / (points as f64);
+    for _ in 0..points {
+        let (y,x) = theta.sin_cos();
+        out.push((x,y));
+        theta += delta_theta;
+    }
+    out
+}
+
+fn add_polygons(doc: &mut SvgDocument, stage: &SvgStage, shape: &PolygonShape<AuxLeaf>) {
+    let (drawn_type,colours,blend) = match shape.patina() {
+        Patina::Drawn(drawn_type,colours,blend) => (drawn_type,colours,blend),
+        _ => { return; }
+    };
+    let len = shape.position().len();
+    let (colours,radii) = match (colours.iter(len),shape.radius().iter(len)) {
+        (Some(c),Some(r)) => (c,r),
+        _ => { return; }
+    };
+    let deltas = polygon_points(shape.points(),shape.angle());
+    for ((centre,colour),radius) in shape.position().iter().zip(colours).zip(radii) {
+        let coord_system = &centre.allotment.coord_system;
+        let points = deltas.iter().filter_map(|(dt,dn)| {
+            stage.point(coord_system,*centre.base,centre.normal+dn*radius,centre.tangent+dt*radius)
+        }).map(|(x,y)| format!("{:.2},{:.2}",x,y)).collect::<Vec<_>>();
+        if points.len() < deltas.len() { continue; }
+        let attrs = drawn_attrs(doc,drawn_type,colour,blend);
+        doc.add(centre.allotment.depth,format!("<polygon points=\"{}\" {}/>",points.join(" "),attrs));
+    }
+}
+
+fn add_texts(doc: &mut SvgDocument, stage: &SvgStage, shape: &TextShape<AuxLeaf>) {
+    let pen = shape.pen();
+    let len = shape.position().len();
+    let (colours,backgrounds) = match (pen.colours().iter(len),pen.background().iter(len)) {
+        (Some(c),Some(b)) => (c.map(|c| doc.plain(c)).collect::<Vec<_>>(),b.map(|b| doc.plain(b)).collect::<Vec<_>>()),
+        _ => { return; }
+    };
+    let size = pen.geometry().size_in_webgl();
+    let anchor = match pen.attachment() {
+        AttachmentPoint::Left => "start",
+        AttachmentPoint::Right => "end"
+    };
+    let font = escape(&pen.geometry().to_font(1.));
+    for (((position,text),colour),background) in shape.position().iter().zip(shape.iter_texts()).zip(colours).zip(backgrounds) {
+        let coord_system = &position.allotment.coord_system;
+        let (x,y) = if let Some(p) = stage.point(coord_system,*position.base,*position.normal,*position.tangent) { p } else { continue; };
+        let mut element = String::new();
+        if background.3 > 0 {
+            /* same estimate of text width as used when allocating space */
+            let width = size * text.chars().count() as f64 * 0.6;
+            let left = if anchor == "end" { x-width } else { x };
+            element.push_str(&format!("<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",left,y,width,size,direct_colour("fill",&background)));
+        }
+        element.push_str(&format!("<text x=\"{:.2}\" y=\"{:.2}\" style=\"font: {}\" dominant-baseline=\"hanging\" text-anchor=\"{}\" {}>{}</text>",
+            x,y,font,anchor,direct_colour("fill",&colour),escape(text)));
+        doc.add(position.allotment.depth,element);
+    }
+}
+
+fn add_images(doc: &mut SvgDocument, stage: &SvgStage, shape: &ImageShape<AuxLeaf>, assets: &Assets) {
+    for (position,name) in shape.position().iter().zip(shape.iter_names()) {
+        let coord_system = &position.allotment.coord_system;
+        let (x,y) = if let Some(p) = stage.point(coord_system,*position.base,*position.normal,*position.tangent) { p } else { continue; };
+        let asset = if let Some(asset) = assets.get(Some(shape.channel()),name) { asset } else { continue; };
+        let bytes = match asset.bytes().map(|b| b.data_as_bytes()) {
+            Some(Ok(bytes)) => bytes,
+            _ => { continue; }
+        };
+        let width = asset.metadata_u32("width").unwrap_or(0);
+        let height = asset.metadata_u32("height").unwrap_or(0);
+        doc.add(position.allotment.depth,format!("<image x=\"{:.2}\" y=\"{:.2}\" width=\"{}\" height=\"{}\" xlink:href=\"data:image/png;base64,{}\"/>",
+            x,y,width,height,base64::encode(&*bytes)));
+    }
+}
+
+/* A wiggle is a polyline broken wherever there's a missing value. Values are already in pixels. */
+fn add_wiggle(doc: &mut SvgDocument, stage: &SvgStage, shape: &WiggleShape<AuxLeaf>) {
+    let values = shape.values();
+    if values.len() < 2 { return; }
+    let (start,end) = shape.range();
+    let style = shape.get_style();
+    let step = (end-start+1.)/(values.len() as f64);
+    let colour = format!("{}{}",direct_colour("stroke",&doc.plain(&shape.plotter().1)),blend_mode(&shape.plotter().2));
+    let mut runs = vec![];
+    let mut run = vec![];
+    for (i,value) in values.iter().enumerate() {
+        let point = value.and_then(|y| stage.point(&style.coord_system,start+step*(i as f64),y,0.));
+        if let Some((x,y)) = point {
+            run.push(format!("{:.2},{:.2}",x,y));
+        } else if run.len() > 0 {
+            runs.push(std::mem::replace(&mut run,vec![]));
+        }
+    }
+    if run.len() > 0 { runs.push(run); }
+    for run in runs {
+        doc.add(style.depth,format!("<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"1\"/>",run.join(" "),colour));
+    }
+}
+
+pub(crate) fn add_shape(doc: &mut SvgDocument, stage: &SvgStage, shape: &DrawingShape, assets: &Assets) {
+    match shape {
+        Shape::Rectangle(shape) => add_rectangles(doc,stage,shape),
+        Shape::Polygon(shape) => add_polygons(doc,stage,shape),
+        Shape::Text(shape) => add_texts(doc,stage,shape),
+        Shape::Image(shape) => add_images(doc,stage,shape,assets),
+        Shape::Wiggle(shape) => add_wiggle(doc,stage,shape),
+        Shape::Empty(_) => {}
+    }
+}
@@ -0,0 +1,111 @@
+use peregrine_data::{ CoordinateSystem, PlayingField, Viewport };
+use peregrine_toolkit::error::Error;
+
+/* An SvgStage is the equivalent of the Stage/ReadStage pair in peregrine-draw: it knows where the
+ * viewport is and how big the picture is and so can convert (base,normal,tangent) triples into
+ * pixels. The calculations mirror those in the vertex shaders of peregrine-draw's geometry.rs and
+ * the squeeze handling of stage.rs so that an exported image matches what is seen on screen.
+ *
+ * The SVG is always rendered with the content scrolled to the top, with the whole playing field
+ * visible, so there is no vertical position to worry about.
+ */
+
+/* Negative normals mean "from the far edge" with -1 the first reversed pixel, ie the far edge itself
+ * (size), as in fix_normal_unpacked in peregrine-draw's rectangles.rs. SidewaysRight additionally
+ * flips the whole coordinate system. Returns distance from the near edge.
+ */
+fn fix_normal(mut n: f64, coord_system: &CoordinateSystem, size: f64) -> f64 {
+    if coord_system.up_from_bottom() {
+        n = -n-1.;
+    }
+    if n < 0. { size+n+1. } else { n }
+}
+
+#[derive(Clone)]
+pub(crate) struct SvgStage {
+    width: f64,
+    height: f64,
+    left_bp: f64,
+    px_per_bp: f64,
+    squeeze: (f64,f64)
+}
+
+impl SvgStage {
+    pub(crate) fn new(viewport: &Viewport, playing_field: &PlayingField, width: f64, height: Option<f64>) -> Result<SvgStage,Error> {
+        let position = viewport.position().map_err(|_| Error::operr("viewport has no position"))?;
+        let bp_per_screen = viewport.bp_per_screen().map_err(|_| Error::operr("viewport has no scale"))?;
+        let squeeze = playing_field.squeeze;
+        let drawable = width - squeeze.0 - squeeze.1;
+        if drawable <= 0. || bp_per_screen <= 0. {
+            return Err(Error::operr(&format!("cannot draw {}bp into {}px",bp_per_screen,drawable)));
+        }
+        Ok(SvgStage {
+            width,
+            height: height.unwrap_or(playing_field.height).max(1.),
+            left_bp: position - bp_per_screen/2.,
+            px_per_bp: drawable / bp_per_screen,
+            squeeze
+        })
+    }
+
+    pub(crate) fn width(&self) -> f64 { self.width }
+    pub(crate) fn height(&self) -> f64 { self.height }
+    pub(crate) fn bp_to_px(&self, bp: f64) -> f64 { self.squeeze.0 + (bp-self.left_bp)*self.px_per_bp }
+
+    /* None means "do not draw" (ie the dustbin) */
+    pub(crate) fn point(&self, coord_system: &CoordinateSystem, base: f64, normal: f64, tangent: f64) -> Option<(f64,f64)> {
+        match coord_system {
+            CoordinateSystem::Dustbin => None,
+            CoordinateSystem::Tracking => {
+                Some((self.bp_to_px(base)+tangent,normal))
+            },
+            CoordinateSystem::TrackingSpecial | CoordinateSystem::TrackingWindow => {
+                Some((self.bp_to_px(base)+tangent,fix_normal(normal,coord_system,self.height)))
+            },
+            CoordinateSystem::Window | CoordinateSystem::Content => {
+                Some((base*self.width+tangent,fix_normal(normal,coord_system,self.height)))
+            },
+            CoordinateSystem::SidewaysLeft | CoordinateSystem::SidewaysRight => {
+                Some((fix_normal(normal,coord_system,self.width),base*self.height+tangent))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stage() -> SvgStage {
+        SvgStage {
+            width: 1000.,
+            height: 200.,
+            left_bp: 1000.,
+            px_per_bp: 0.5,
+            squeeze: (100.,0.)
+        }
+    }
+
+    #[test]
+    fn test_tracking() {
+        let stage = stage();
+        assert_eq!(Some((100.,10.)),stage.point(&CoordinateSystem::Tracking,1000.,10.,0.));
+        assert_eq!(Some((155.,10.)),stage.point(&CoordinateSystem::Tracking,1100.,10.,5.));
+        assert_eq!(Some((100.,200.)),stage.point(&CoordinateSystem::TrackingWindow,1000.,-1.,0.));
+    }
+
+    #[test]
+    fn test_window() {
+        let stage = stage();
+        assert_eq!(Some((500.,0.)),stage.point(&CoordinateSystem::Window,0.5,0.,0.));
+        assert_eq!(Some((990.,191.)),stage.point(&CoordinateSystem::Window,1.,-10.,-10.));
+        assert_eq!(None,stage.point(&CoordinateSystem::Dustbin,1.,1.,1.));
+    }
+
+    #[test]
+    fn test_sideways() {
+        let stage = stage();
+        assert_eq!(Some((10.,100.)),stage.point(&CoordinateSystem::SidewaysLeft,0.5,10.,0.));
+        assert_eq!(Some((990.,100.)),stage.point(&CoordinateSystem::SidewaysRight,0.5,10.,0.));
+    }
+}
@@ -52,6 +52,13 @@ impl Error {
         }
     }
 
+    /* Might the same operation succeed if tried again later? Only if the error says so: an
+     * operation error is as likely to be bad data, which will be just as bad next time.
+     */
+    pub fn is_transient(&self) -> bool {
+        matches!(self.error_type,ErrorType::Temporary)
+    }
+
     pub fn context(&self, context: &str) -> Error {
         Error {
             error_type: self.error_type.clone(),
@@ -85,3 +92,18 @@ macro_rules! pg_unwrap {
         })
     };
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_transient() {
+        assert!(Error::tmp("x").is_transient());
+        assert!(!Error::operr("x").is_transient());
+        assert!(!Error::nosuch("x").is_transient());
+        assert!(!Error::bad_version("x").is_transient());
+        assert!(!Error::fatal("x").is_transient());
+        assert!(Error::tmp("x").context("y").is_transient());
+    }
+}
@@ -0,0 +1,22 @@
+/* A small, deterministic pseudo-random generator (a 64-bit LCG) for property tests, here and in
+ * other crates, so that failures can be reproduced from the seed. Not for anything else.
+ */
+
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Lcg { Lcg(seed) }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 11
+    }
+
+    /* biased towards small values, as real data is */
+    pub fn integer(&mut self, max_bits: u32) -> u64 {
+        let bits = (self.next_u64() % (max_bits as u64 + 1)) as u32;
+        if bits == 0 { 0 } else { self.next_u64() & (u64::MAX >> (64-bits)) }
+    }
+
+    pub fn below(&mut self, n: usize) -> usize { (self.next_u64() % (n as u64)) as usize }
+}
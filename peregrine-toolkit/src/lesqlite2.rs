@@ -1,3 +1,5 @@
+use crate::error::Error;
+
 fn need_bytes(data: &[u8], i: &mut usize, n : usize) -> Result<(),()> {
     if *i+n > data.len() {
         return Err(())
@@ -6,37 +8,168 @@ fn need_bytes(data: &[u8], i: &mut usize, n : usize) -> Result<(),()> {
     Ok(())
 }
 
-pub fn lesqlite2_decode(data: &[u8]) -> Result<Vec<f64>,()> {
-    let mut out = vec![];
-    let mut i = 0;
-    while i < data.len() {
-        if data[i] < 178 {
-            need_bytes(data,&mut i,1)?;
-            out.push(data[i-1] as f64);
-        } else if data[i] < 242 {
-            need_bytes(data,&mut i,2)?;
-            out.push((((data[i-2] as u64-178)<<8) + (data[i-1] as u64) + 178_u64) as f64);
-        } else if data[i] < 250 {
-            need_bytes(data,&mut i,3)?;
+/* Decodes one value at a time, so that a long column can be consumed without being materialised.
+ * After an error, iteration stops.
+ */
+pub struct Lesqlite2Iter<T: AsRef<[u8]>> {
+    data: T,
+    i: usize
+}
+
+impl<T: AsRef<[u8]>> Lesqlite2Iter<T> {
+    pub fn new(data: T) -> Lesqlite2Iter<T> {
+        Lesqlite2Iter { data, i: 0 }
+    }
+
+    fn next_value(&mut self) -> Result<f64,()> {
+        let data = self.data.as_ref();
+        let i = &mut self.i;
+        if data[*i] < 178 {
+            need_bytes(data,i,1)?;
+            Ok(data[*i-1] as f64)
+        } else if data[*i] < 242 {
+            need_bytes(data,i,2)?;
+            Ok((((data[*i-2] as u64-178)<<8) + (data[*i-1] as u64) + 178_u64) as f64)
+        } else if data[*i] < 250 {
+            need_bytes(data,i,3)?;
             let v : u64 = 
-                ((data[i-3] as u64-242_u64)<<16_u64) +
-                ((data[i-1] as u64) << 8_u64) +
-                (data[i-2] as u64) +
+                ((data[*i-3] as u64-242_u64)<<16_u64) +
+                ((data[*i-1] as u64) << 8_u64) +
+                (data[*i-2] as u64) +
                 16562_u64
             ;
-            out.push(v as f64);
+            Ok(v as f64)
         } else {
-            need_bytes(data,&mut i,1)?;
-            let n = (data[i-1] - 247) as usize;
-            need_bytes(data,&mut i,n)?;
+            need_bytes(data,i,1)?;
+            let n = (data[*i-1] - 247) as usize;
+            need_bytes(data,i,n)?;
             let mut v = 0;
             let mut m = 0;
             for j in 0..n {
-                v += (data[i-n+j] as u64) << m;
+                v += (data[*i-n+j] as u64) << m;
                 m += 8;
             }
-            out.push(v as f64);
+            Ok(v as f64)
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Iterator for Lesqlite2Iter<T> {
+    type Item = Result<f64,()>;
+
+    fn next(&mut self) -> Option<Result<f64,()>> {
+        if self.i >= self.data.as_ref().len() { return None; }
+        let out = self.next_value();
+        if out.is_err() {
+            self.i = self.data.as_ref().len();
+        }
+        Some(out)
+    }
+}
+
+pub fn lesqlite2_decode(data: &[u8]) -> Result<Vec<f64>,()> {
+    Lesqlite2Iter::new(data).collect()
+}
+
+/* Number of values, looking only at the first byte of each. */
+pub fn lesqlite2_count(data: &[u8]) -> Result<usize,()> {
+    let mut i = 0;
+    let mut count = 0;
+    while i < data.len() {
+        i += match data[i] {
+            0..=177 => 1,
+            178..=241 => 2,
+            242..=249 => 3,
+            n => 1 + (n - 247) as usize
+        };
+        count += 1;
+    }
+    if i == data.len() { Ok(count) } else { Err(()) }
+}
+
+/* Inverse of lesqlite2_decode. Values must be non-negative integers: use zigzag for signed values. */
+pub fn lesqlite2_encode(data: &[f64]) -> Result<Vec<u8>,Error> {
+    let mut out = vec![];
+    for value in data {
+        if !value.is_finite() || *value < 0. || value.fract() != 0. || *value >= u64::MAX as f64 {
+            return Err(Error::operr(&format!("lesqlite2 needs non-negative integers, not {}",value)));
+        }
+        let v = *value as u64;
+        if v < 178 {
+            out.push(v as u8);
+        } else if v < 16562 {
+            let w = v - 178;
+            out.push((178 + (w >> 8)) as u8);
+            out.push((w & 0xFF) as u8);
+        } else if v < 16562 + (8 << 16) {
+            let w = v - 16562;
+            out.push((242 + (w >> 16)) as u8);
+            out.push((w & 0xFF) as u8);
+            out.push(((w >> 8) & 0xFF) as u8);
+        } else {
+            let mut n = 3;
+            while n < 8 && (v >> (n*8)) != 0 {
+                n += 1;
+            }
+            out.push((247 + n) as u8);
+            for j in 0..n {
+                out.push(((v >> (j*8)) & 0xFF) as u8);
+            }
         }
     }
     Ok(out)
 }
+
+#[cfg(test)]
+mod test {
+    use crate::lcg::Lcg;
+    use super::*;
+
+    #[test]
+    fn test_lesqlite2_boundaries() {
+        let values = [
+            0., 1., 177., 178., 179., 16561., 16562., 16563., 540849., 540850., 
+            16777215., 16777216., 4294967296., 9007199254740992.
+        ];
+        let encoded = lesqlite2_encode(&values).expect("encode");
+        assert_eq!(values.to_vec(),lesqlite2_decode(&encoded).expect("decode"));
+        assert_eq!(vec![177],lesqlite2_encode(&[177.]).unwrap());
+        assert_eq!(vec![178,0],lesqlite2_encode(&[178.]).unwrap());
+        assert_eq!(vec![242,0,0],lesqlite2_encode(&[16562.]).unwrap());
+        assert_eq!(vec![250,0xB2,0x40,0x08],lesqlite2_encode(&[540850.]).unwrap());
+    }
+
+    #[test]
+    fn test_lesqlite2_round_trip() {
+        let mut random = Lcg::new(12345);
+        for _ in 0..100 {
+            let values = (0..50).map(|_| random.integer(53) as f64).collect::<Vec<_>>();
+            let encoded = lesqlite2_encode(&values).expect("encode");
+            assert_eq!(values,lesqlite2_decode(&encoded).expect("decode"));
+            assert_eq!(Ok(values.len()),lesqlite2_count(&encoded));
+        }
+    }
+
+    #[test]
+    fn test_lesqlite2_bad() {
+        assert!(lesqlite2_encode(&[-1.]).is_err());
+        assert!(lesqlite2_encode(&[0.5]).is_err());
+        assert!(lesqlite2_encode(&[f64::NAN]).is_err());
+        assert!(lesqlite2_decode(&[178]).is_err());
+        assert!(lesqlite2_decode(&[252,1]).is_err());
+        assert!(lesqlite2_count(&[178]).is_err());
+        assert!(lesqlite2_count(&[252,1]).is_err());
+        let mut iter = Lesqlite2Iter::new(vec![5,178]);
+        assert_eq!(Some(Ok(5.)),iter.next());
+        assert_eq!(Some(Err(())),iter.next());
+        assert_eq!(None,iter.next());
+    }
+
+    #[test]
+    fn test_lesqlite2_iter_partial() {
+        let values = (0..1000).map(|x| (x*x) as f64).collect::<Vec<_>>();
+        let encoded = lesqlite2_encode(&values).unwrap();
+        let middle = Lesqlite2Iter::new(&encoded).skip(500).take(3).collect::<Result<Vec<_>,_>>().unwrap();
+        assert_eq!(vec![250000.,251001.,252004.],middle);
+    }
+}
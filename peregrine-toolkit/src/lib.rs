@@ -10,6 +10,10 @@ pub mod js {
     pub mod raf;
 }
 
+pub mod packing {
+    pub mod maxrects;
+}
+
 pub mod plumbing {
     pub mod distributor;
     pub mod onchange;
@@ -59,6 +63,7 @@ pub mod identitynumber;
 pub mod itertools;
 #[macro_use]
 pub mod lang;
+pub mod lcg;
 pub mod lesqlite2;
 pub mod rate;
 pub mod refs;
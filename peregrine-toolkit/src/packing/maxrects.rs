@@ -0,0 +1,263 @@
+/* MaxRects is a rectangle packer which keeps a list of maximal free rectangles: every free area
+ * of the bin is covered by at least one of them and none is contained in another, though they may
+ * overlap. A request is placed at the corner of the free rectangle which it fits most snugly
+ * ("best short side fit") and then every free rectangle which the placement overlaps is cut into
+ * the up-to-four maximal rectangles around it.
+ *
+ * Unlike the shelf packer it copes with rectangles arriving in any order and, because allocations
+ * can be freed, with long-lived bins. A freed area is returned to the free list and merged with
+ * neighbours sharing a whole edge so that it can be reused straight away. It is O(n^2)-ish, though,
+ * so isn't for very large batches.
+ *
+ * Stats are occupancy (fraction of the bin allocated) and fragmentation (fraction of the free area
+ * which is not in the largest free rectangle, ie how far the free space is from being one lump).
+ */
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32
+}
+
+impl Rect {
+    fn area(&self) -> u64 { self.w as u64 * self.h as u64 }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x && other.y >= self.y && other.x+other.w <= self.x+self.w && other.y+other.h <= self.y+self.h
+    }
+
+    fn overlaps(&self, other: &Rect) -> bool {
+        other.x < self.x+self.w && self.x < other.x+other.w && other.y < self.y+self.h && self.y < other.y+other.h
+    }
+
+    /* The maximal rectangles of self not covered by cut */
+    fn subtract(&self, cut: &Rect, out: &mut Vec<Rect>) {
+        if cut.x > self.x {
+            out.push(Rect { x: self.x, y: self.y, w: cut.x-self.x, h: self.h });
+        }
+        if cut.x+cut.w < self.x+self.w {
+            out.push(Rect { x: cut.x+cut.w, y: self.y, w: self.x+self.w-cut.x-cut.w, h: self.h });
+        }
+        if cut.y > self.y {
+            out.push(Rect { x: self.x, y: self.y, w: self.w, h: cut.y-self.y });
+        }
+        if cut.y+cut.h < self.y+self.h {
+            out.push(Rect { x: self.x, y: cut.y+cut.h, w: self.w, h: self.y+self.h-cut.y-cut.h });
+        }
+    }
+
+    /* The union of two rectangles sharing a whole edge */
+    fn merge(&self, other: &Rect) -> Option<Rect> {
+        if self.x == other.x && self.w == other.w {
+            if self.y+self.h == other.y { return Some(Rect { h: self.h+other.h, ..*self }); }
+            if other.y+other.h == self.y { return Some(Rect { h: self.h+other.h, ..*other }); }
+        }
+        if self.y == other.y && self.h == other.h {
+            if self.x+self.w == other.x { return Some(Rect { w: self.w+other.w, ..*self }); }
+            if other.x+other.w == self.x { return Some(Rect { w: self.w+other.w, ..*other }); }
+        }
+        None
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct PackingStats {
+    pub size: (u32,u32),
+    pub used_area: u64,
+    pub largest_free_area: u64
+}
+
+impl PackingStats {
+    pub fn total_area(&self) -> u64 { self.size.0 as u64 * self.size.1 as u64 }
+
+    pub fn occupancy(&self) -> f64 {
+        let total = self.total_area();
+        if total == 0 { 0. } else { self.used_area as f64 / total as f64 }
+    }
+
+    pub fn fragmentation(&self) -> f64 {
+        let free = self.total_area().saturating_sub(self.used_area);
+        if free == 0 { 0. } else { 1. - (self.largest_free_area.min(free) as f64 / free as f64) }
+    }
+}
+
+pub struct MaxRects {
+    width: u32,
+    height: u32,
+    free: Vec<Rect>,
+    used_area: u64
+}
+
+impl MaxRects {
+    pub fn new(width: u32, height: u32) -> MaxRects {
+        let mut out = MaxRects { width, height, free: vec![], used_area: 0 };
+        out.reset();
+        out
+    }
+
+    fn reset(&mut self) {
+        self.free = vec![Rect { x: 0, y: 0, w: self.width, h: self.height }];
+        self.used_area = 0;
+    }
+
+    pub fn size(&self) -> (u32,u32) { (self.width,self.height) }
+
+    fn prune(&mut self) {
+        let mut i = 0;
+        while i < self.free.len() {
+            let rect = self.free[i];
+            let contained = self.free.iter().enumerate().any(|(j,other)| {
+                j != i && other.contains(&rect) && (other != &rect || j < i)
+            });
+            if contained {
+                self.free.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn place(&mut self, placed: &Rect) {
+        let mut out = vec![];
+        for rect in self.free.drain(..) {
+            if rect.overlaps(placed) {
+                rect.subtract(placed,&mut out);
+            } else {
+                out.push(rect);
+            }
+        }
+        self.free = out;
+        self.prune();
+        self.used_area += placed.area();
+    }
+
+    /* returns origin, or None if there's no room */
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<(u32,u32)> {
+        let best = self.free.iter()
+            .filter(|r| r.w >= width && r.h >= height)
+            .min_by_key(|r| {
+                let (dx,dy) = (r.w-width,r.h-height);
+                (dx.min(dy),dx.max(dy),r.y,r.x)
+            })?;
+        let placed = Rect { x: best.x, y: best.y, w: width, h: height };
+        self.place(&placed);
+        Some((placed.x,placed.y))
+    }
+
+    /* Mark an area as in use where it is, eg to measure an existing layout */
+    pub fn reserve(&mut self, origin: (u32,u32), size: (u32,u32)) {
+        self.place(&Rect { x: origin.0, y: origin.1, w: size.0, h: size.1 });
+    }
+
+    /* Return a previous allocation to the free list for immediate reuse */
+    pub fn free(&mut self, origin: (u32,u32), size: (u32,u32)) {
+        let mut rect = Rect { x: origin.0, y: origin.1, w: size.0, h: size.1 };
+        self.used_area = self.used_area.saturating_sub(rect.area());
+        if self.used_area == 0 {
+            self.reset();
+            return;
+        }
+        /* grow the freed area into neighbours which share an edge with it */
+        loop {
+            let merged = self.free.iter().find_map(|other| rect.merge(other));
+            match merged {
+                Some(bigger) if bigger != rect => { rect = bigger; },
+                _ => { break; }
+            }
+        }
+        self.free.push(rect);
+        self.prune();
+    }
+
+    pub fn stats(&self) -> PackingStats {
+        PackingStats {
+            size: (self.width,self.height),
+            used_area: self.used_area,
+            largest_free_area: self.free.iter().map(|r| r.area()).max().unwrap_or(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn disjoint(placed: &[Rect]) -> bool {
+        for (i,a) in placed.iter().enumerate() {
+            for b in &placed[i+1..] {
+                if a.overlaps(b) { return false; }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_allocate() {
+        let mut bin = MaxRects::new(64,64);
+        let mut placed = vec![];
+        for (w,h) in &[(64,10),(30,20),(30,20),(10,30),(4,4),(4,4)] {
+            let (x,y) = bin.allocate(*w,*h).expect("no room");
+            let rect = Rect { x, y, w: *w, h: *h };
+            assert!(Rect { x: 0, y: 0, w: 64, h: 64 }.contains(&rect));
+            placed.push(rect);
+        }
+        assert!(disjoint(&placed));
+        assert_eq!(placed.iter().map(|r| r.area()).sum::<u64>(),bin.stats().used_area);
+        assert_eq!(None,bin.allocate(65,1));
+    }
+
+    #[test]
+    fn test_full() {
+        let mut bin = MaxRects::new(16,16);
+        for _ in 0..16 {
+            assert!(bin.allocate(4,4).is_some());
+        }
+        assert_eq!(None,bin.allocate(1,1));
+        assert_eq!(1.,bin.stats().occupancy());
+        assert_eq!(0.,bin.stats().fragmentation());
+    }
+
+    #[test]
+    fn test_free() {
+        let mut bin = MaxRects::new(16,16);
+        let a = bin.allocate(8,16).unwrap();
+        let b = bin.allocate(8,8).unwrap();
+        let c = bin.allocate(8,8).unwrap();
+        assert_eq!(None,bin.allocate(8,16));
+        bin.free(b,(8,8));
+        bin.free(c,(8,8));
+        /* the two halves merge so the full-height column can be reused */
+        assert!(bin.allocate(8,16).is_some());
+        bin.free(a,(8,16));
+        assert_eq!(0.5,bin.stats().occupancy());
+    }
+
+    #[test]
+    fn test_free_all_resets() {
+        let mut bin = MaxRects::new(16,16);
+        let a = bin.allocate(5,3).unwrap();
+        let b = bin.allocate(7,9).unwrap();
+        bin.free(a,(5,3));
+        bin.free(b,(7,9));
+        assert_eq!(PackingStats { size: (16,16), used_area: 0, largest_free_area: 256 },bin.stats());
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut bin = MaxRects::new(16,16);
+        bin.reserve((4,4),(8,8));
+        let stats = bin.stats();
+        assert_eq!(64,stats.used_area);
+        assert_eq!(64,stats.largest_free_area);
+        assert_eq!(1.-64./192.,stats.fragmentation());
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        let stats = PackingStats { size: (10,10), used_area: 50, largest_free_area: 25 };
+        assert_eq!(0.5,stats.occupancy());
+        assert_eq!(0.5,stats.fragmentation());
+    }
+}